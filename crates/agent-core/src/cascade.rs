@@ -0,0 +1,365 @@
+//! Model Cascade
+//!
+//! `LlmProvider` decorator that answers with the cheapest model first and
+//! escalates to larger models only when a verifier rule rejects the answer:
+//!
+//! - **Invalid tool call** - a ```tool block that is not a valid `ToolCall`
+//! - **Too short** - a final answer below `min_answer_chars`
+//! - **Low confidence** - the model's own `Confidence: <0-1>` line is below
+//!   `min_confidence` (the instruction is injected into the system prompt and
+//!   the line is stripped from the answer)
+//!
+//! The cascade only applies when the requested model is the cascade alias
+//! (`auto` by default); any other model name passes straight through.
+//!
+//! ## Usage
+//!
+//! ```rust,ignore
+//! use agent_core::cascade::{CascadeConfig, CascadeProvider};
+//!
+//! let provider = CascadeProvider::new(
+//!     Arc::new(OllamaProvider::from_env()),
+//!     CascadeConfig::new(["llama3.2:1b", "llama3.2", "llama3.1:70b"]),
+//! );
+//! ```
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+
+use crate::error::{AgentError, Result};
+use crate::message::{Message, Role};
+use crate::provider::{
+    Completion, CompletionStream, Escalation, GenerationOptions, LlmProvider, ModelInfo,
+    ProviderInfo, StreamChunk, TokenUsage,
+};
+use crate::tool::ToolCall;
+
+const CONFIDENCE_INSTRUCTION: &str = "When giving a final answer (not a tool call), end it with a line `Confidence: <number between 0 and 1>` rating how sure you are.";
+
+/// Cascade settings
+#[derive(Clone, Debug)]
+pub struct CascadeConfig {
+    /// Models to try, cheapest first
+    pub models: Vec<String>,
+    
+    /// Requested model name that triggers the cascade
+    pub alias: String,
+    
+    /// Final answers shorter than this (in characters) are escalated
+    pub min_answer_chars: usize,
+    
+    /// Self-reported confidence below this is escalated
+    pub min_confidence: f32,
+}
+
+impl CascadeConfig {
+    pub fn new<I, S>(models: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            models: models.into_iter().map(Into::into).collect(),
+            alias: "auto".into(),
+            min_answer_chars: 20,
+            min_confidence: 0.6,
+        }
+    }
+    
+    pub fn alias(mut self, alias: impl Into<String>) -> Self {
+        self.alias = alias.into();
+        self
+    }
+    
+    pub fn min_answer_chars(mut self, chars: usize) -> Self {
+        self.min_answer_chars = chars;
+        self
+    }
+    
+    pub fn min_confidence(mut self, confidence: f32) -> Self {
+        self.min_confidence = confidence;
+        self
+    }
+}
+
+/// Provider decorator that escalates through a list of models
+pub struct CascadeProvider {
+    inner: Arc<dyn LlmProvider>,
+    config: CascadeConfig,
+}
+
+impl CascadeProvider {
+    pub fn new(inner: Arc<dyn LlmProvider>, config: CascadeConfig) -> Self {
+        Self { inner, config }
+    }
+    
+    /// Get configuration
+    pub fn config(&self) -> &CascadeConfig {
+        &self.config
+    }
+    
+    /// Apply the verifier rules, returning the reason to escalate (if any)
+    /// and the answer with its confidence line removed
+    fn verify(&self, content: &str, structured: bool) -> (Option<String>, String) {
+        if let Some(block) = tool_block(content) {
+            return match serde_json::from_str::<ToolCall>(block) {
+                Ok(_) => (None, content.to_string()),
+                Err(e) => (Some(format!("invalid tool call: {}", e)), content.to_string()),
+            };
+        }
+        
+        // Schema-constrained output is validated by the caller instead
+        if structured {
+            return (None, content.to_string());
+        }
+        
+        let (answer, confidence) = split_confidence(content);
+        
+        if answer.chars().count() < self.config.min_answer_chars {
+            return (Some(format!("answer shorter than {} characters", self.config.min_answer_chars)), answer);
+        }
+        
+        match confidence {
+            Some(c) if c < self.config.min_confidence => {
+                (Some(format!("self-reported confidence {:.2} below {:.2}", c, self.config.min_confidence)), answer)
+            }
+            _ => (None, answer),
+        }
+    }
+}
+
+/// Body of the first ```tool block, if any
+fn tool_block(content: &str) -> Option<&str> {
+    let start = content.find("```tool")? + "```tool".len();
+    let end = content[start..].find("```").map_or(content.len(), |i| start + i);
+    Some(content[start..end].trim())
+}
+
+/// Split a trailing `Confidence: x` line from an answer
+fn split_confidence(content: &str) -> (String, Option<f32>) {
+    let trimmed = content.trim_end();
+    let (body, last) = trimmed.rsplit_once('\n').unwrap_or(("", trimmed));
+    
+    // Tolerate markdown emphasis and percentages ("**Confidence: 80%**")
+    let line = last.trim().trim_matches(|c| c == '*' || c == '_' || c == '`');
+    let value = line
+        .get(..11)
+        .filter(|prefix| prefix.eq_ignore_ascii_case("confidence:"))
+        .and_then(|_| line[11..].trim().trim_matches(|c| c == '*' || c == '_').trim_end_matches('%').parse::<f32>().ok())
+        .map(|v| if v > 1.0 { v / 100.0 } else { v });
+    
+    match value {
+        Some(v) => (body.trim_end().to_string(), Some(v)),
+        None => (trimmed.to_string(), None),
+    }
+}
+
+/// Append the confidence instruction to the system prompt
+fn with_confidence_instruction(messages: &[Message]) -> Vec<Message> {
+    let mut messages = messages.to_vec();
+    match messages.first_mut() {
+        Some(first) if first.role == Role::System => {
+            first.content.push_str("\n\n");
+            first.content.push_str(CONFIDENCE_INSTRUCTION);
+        }
+        _ => messages.insert(0, Message::system(CONFIDENCE_INSTRUCTION)),
+    }
+    messages
+}
+
+#[async_trait]
+impl LlmProvider for CascadeProvider {
+    async fn info(&self) -> Result<ProviderInfo> {
+        self.inner.info().await
+    }
+    
+    async fn health_check(&self) -> Result<bool> {
+        self.inner.health_check().await
+    }
+    
+    async fn complete(
+        &self,
+        messages: &[Message],
+        options: &GenerationOptions,
+    ) -> Result<Completion> {
+        if options.model != self.config.alias || self.config.models.is_empty() {
+            return self.inner.complete(messages, options).await;
+        }
+        
+        let structured = options.response_format.is_some();
+        let messages = if structured { messages.to_vec() } else { with_confidence_instruction(messages) };
+        
+        let mut escalations = Vec::new();
+        let mut usage = TokenUsage::default();
+        let last = self.config.models.len() - 1;
+        
+        for (i, model) in self.config.models.iter().enumerate() {
+            let options = GenerationOptions {
+                model: model.clone(),
+                ..options.clone()
+            };
+            
+            // A failing tier escalates too, unless it is the last one
+            let mut completion = match self.inner.complete(&messages, &options).await {
+                Ok(completion) => completion,
+                Err(e) if i < last => {
+                    escalations.push(Escalation { model: model.clone(), reason: format!("error: {}", e) });
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+            
+            if let Some(step) = completion.usage {
+                usage += step;
+            }
+            
+            let (reason, answer) = self.verify(&completion.content, structured);
+            match reason {
+                Some(reason) if i < last => {
+                    tracing::debug!(model = %model, reason = %reason, "Cascade escalating");
+                    escalations.push(Escalation { model: model.clone(), reason });
+                }
+                _ => {
+                    completion.content = answer;
+                    completion.model = model.clone();
+                    completion.usage = Some(usage);
+                    completion.escalations = escalations;
+                    return Ok(completion);
+                }
+            }
+        }
+        
+        Err(AgentError::Provider("Model cascade exhausted".into()))
+    }
+    
+    /// Cascaded streams are verified before anything is sent, so the answer
+    /// arrives as a single chunk; pinned models stream normally.
+    async fn complete_stream(
+        &self,
+        messages: &[Message],
+        options: &GenerationOptions,
+    ) -> Result<CompletionStream> {
+        if options.model != self.config.alias || self.config.models.is_empty() {
+            return self.inner.complete_stream(messages, options).await;
+        }
+        
+        let chunk = StreamChunk::from(self.complete(messages, options).await?);
+        Ok(Box::pin(futures::stream::once(async move { Ok(chunk) })))
+    }
+    
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+    
+    fn supports_json_schema(&self) -> bool {
+        self.inner.supports_json_schema()
+    }
+    
+    fn estimate_tokens(&self, text: &str) -> u32 {
+        self.inner.estimate_tokens(text)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::FinishReason;
+    use std::collections::HashMap;
+
+    /// Provider answering with a fixed reply per model
+    struct PerModelProvider {
+        replies: HashMap<&'static str, &'static str>,
+    }
+
+    #[async_trait]
+    impl LlmProvider for PerModelProvider {
+        async fn info(&self) -> Result<ProviderInfo> {
+            Err(AgentError::Other("unused".into()))
+        }
+        
+        async fn health_check(&self) -> Result<bool> {
+            Ok(true)
+        }
+        
+        async fn complete(&self, _: &[Message], options: &GenerationOptions) -> Result<Completion> {
+            Ok(Completion {
+                content: self.replies[options.model.as_str()].to_string(),
+                model: options.model.clone(),
+                usage: Some(TokenUsage { prompt_tokens: 10, completion_tokens: 10, total_tokens: 20 }),
+                truncated: false,
+                finish_reason: Some(FinishReason::Stop),
+                escalations: Vec::new(),
+            })
+        }
+        
+        async fn complete_stream(&self, _: &[Message], _: &GenerationOptions) -> Result<CompletionStream> {
+            Err(AgentError::Other("unused".into()))
+        }
+        
+        async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn cascade(replies: &[(&'static str, &'static str)]) -> CascadeProvider {
+        CascadeProvider::new(
+            Arc::new(PerModelProvider { replies: replies.iter().copied().collect() }),
+            CascadeConfig::new(replies.iter().map(|(model, _)| *model)),
+        )
+    }
+
+    fn auto() -> GenerationOptions {
+        GenerationOptions { model: "auto".into(), ..Default::default() }
+    }
+
+    #[tokio::test]
+    async fn test_escalates_on_rules() {
+        let provider = cascade(&[
+            ("tiny", "```tool\n{not json}\n```"),
+            ("small", "Maybe."),
+            ("medium", "BTC is around $60,000 today.\nConfidence: 0.3"),
+            ("large", "BTC trades near $60,000; prices move quickly.\nConfidence: 0.9"),
+        ]);
+        
+        let completion = provider.complete(&[Message::user("BTC price?")], &auto()).await.unwrap();
+        
+        assert_eq!(completion.model, "large");
+        assert_eq!(completion.content, "BTC trades near $60,000; prices move quickly.");
+        assert_eq!(completion.usage.unwrap().total_tokens, 80);
+        let rejected: Vec<_> = completion.escalations.iter().map(|e| e.model.as_str()).collect();
+        assert_eq!(rejected, vec!["tiny", "small", "medium"]);
+    }
+
+    #[tokio::test]
+    async fn test_first_model_accepted_and_pinned_passthrough() {
+        let provider = cascade(&[
+            ("small", "```tool\n{\"tool\": \"price_lookup\", \"arguments\": {\"symbols\": \"BTC\"}}\n```"),
+            ("large", "unused"),
+        ]);
+        
+        let completion = provider.complete(&[Message::user("BTC price?")], &auto()).await.unwrap();
+        assert_eq!(completion.model, "small");
+        assert!(completion.escalations.is_empty());
+        
+        let pinned = GenerationOptions { model: "large".into(), ..Default::default() };
+        let completion = provider.complete(&[Message::user("BTC price?")], &pinned).await.unwrap();
+        assert_eq!(completion.content, "unused");
+    }
+
+    #[tokio::test]
+    async fn test_streamed_run_reports_cascade_model() {
+        let provider = cascade(&[("small", "Maybe."), ("large", "BTC trades near $60,000.")]);
+        let config = crate::reasoning::AgentConfig { generation: auto(), ..Default::default() };
+        let agent = crate::reasoning::Agent::new(Arc::new(provider), Arc::new(crate::tool::ToolRegistry::new()), config);
+        
+        let mut conversation = crate::message::Conversation::new();
+        conversation.push(Message::user("BTC price?"));
+        let (events, _received) = tokio::sync::mpsc::channel(64);
+        let run = agent.run_streaming(&mut conversation, &events).await.unwrap();
+        
+        assert_eq!(run.model, "large");
+        let rejected: Vec<_> = run.trace[0].escalations.iter().map(|e| e.model.as_str()).collect();
+        assert_eq!(rejected, vec!["small"]);
+    }
+}
@@ -0,0 +1,270 @@
+//! Embeddings
+//!
+//! Provider-agnostic text embedding interface and a small vector index that
+//! persists to a JSON file. Sized for per-user memories and modest document
+//! collections, not millions of vectors: search is a linear scan.
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::error::{AgentError, Result};
+
+/// Strategy trait for embedding providers
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Embed a batch of texts, returning one vector per input
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>>;
+    
+    /// Embedding model identifier
+    fn model(&self) -> &str;
+    
+    /// Embed a single text
+    async fn embed_one(&self, input: &str) -> Result<Vec<f32>> {
+        self.embed(&[input.to_string()])
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| AgentError::Provider("Embedding provider returned no vectors".into()))
+    }
+}
+
+/// Cosine similarity between two vectors (0.0 if either is empty or zero)
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() || a.is_empty() {
+        return 0.0;
+    }
+    
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a.sqrt() * norm_b.sqrt())
+    }
+}
+
+/// A stored vector with its source text
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct IndexEntry {
+    /// Unique entry ID
+    pub id: String,
+    
+    /// Partition key (e.g. user ID, collection name)
+    pub namespace: String,
+    
+    /// Original text
+    pub text: String,
+    
+    /// Embedding vector
+    pub embedding: Vec<f32>,
+    
+    /// Creation or last-update timestamp
+    pub updated_at: DateTime<Utc>,
+    
+    /// Custom key-value pairs
+    #[serde(default)]
+    pub metadata: HashMap<String, serde_json::Value>,
+}
+
+impl IndexEntry {
+    pub fn new(namespace: impl Into<String>, text: impl Into<String>, embedding: Vec<f32>) -> Self {
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            namespace: namespace.into(),
+            text: text.into(),
+            embedding,
+            updated_at: Utc::now(),
+            metadata: HashMap::new(),
+        }
+    }
+    
+    pub fn with_metadata(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.metadata.insert(key.into(), value);
+        self
+    }
+}
+
+/// A search hit
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScoredEntry {
+    pub entry: IndexEntry,
+    pub score: f32,
+}
+
+/// Vector index persisted as a JSON file
+#[derive(Debug, Default)]
+pub struct VectorIndex {
+    entries: Vec<IndexEntry>,
+    path: Option<PathBuf>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexFile {
+    version: u32,
+    entries: Vec<IndexEntry>,
+}
+
+/// Copy of an index taken under its lock, to write to disk after releasing it
+pub struct IndexSnapshot {
+    file: IndexFile,
+    path: Option<PathBuf>,
+}
+
+impl IndexSnapshot {
+    /// Write the snapshot to the index's backing file (no-op for in-memory indexes)
+    pub fn write(&self) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        
+        // Write-then-rename so a crash never leaves a truncated index
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec(&self.file)?)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+}
+
+impl VectorIndex {
+    /// Create an in-memory index (nothing is written to disk)
+    pub fn in_memory() -> Self {
+        Self::default()
+    }
+    
+    /// Open an index backed by `path`, loading it if the file exists
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let entries = if path.exists() {
+            let file: IndexFile = serde_json::from_slice(&std::fs::read(&path)?)?;
+            file.entries
+        } else {
+            Vec::new()
+        };
+        
+        Ok(Self {
+            entries,
+            path: Some(path),
+        })
+    }
+    
+    /// Write the index to its backing file (no-op for in-memory indexes)
+    pub fn save(&self) -> Result<()> {
+        self.snapshot().write()
+    }
+    
+    /// Copy the entries for a later `IndexSnapshot::write`
+    pub fn snapshot(&self) -> IndexSnapshot {
+        IndexSnapshot {
+            file: IndexFile {
+                version: 1,
+                entries: self.entries.clone(),
+            },
+            path: self.path.clone(),
+        }
+    }
+    
+    /// Add an entry
+    pub fn insert(&mut self, entry: IndexEntry) {
+        self.entries.push(entry);
+    }
+    
+    /// Replace an entry by ID, returning whether it existed
+    pub fn replace(&mut self, entry: IndexEntry) -> bool {
+        match self.entries.iter_mut().find(|e| e.id == entry.id) {
+            Some(existing) => {
+                *existing = entry;
+                true
+            }
+            None => false,
+        }
+    }
+    
+    /// Remove an entry by ID
+    pub fn remove(&mut self, id: &str) -> bool {
+        let before = self.entries.len();
+        self.entries.retain(|e| e.id != id);
+        self.entries.len() != before
+    }
+    
+    /// Remove every entry in a namespace, returning how many were removed
+    pub fn clear_namespace(&mut self, namespace: &str) -> usize {
+        let before = self.entries.len();
+        self.entries.retain(|e| e.namespace != namespace);
+        before - self.entries.len()
+    }
+    
+    /// Top-k entries in `namespace` by cosine similarity to `query`
+    pub fn search(&self, namespace: &str, query: &[f32], k: usize) -> Vec<ScoredEntry> {
+        let mut hits: Vec<ScoredEntry> = self
+            .entries
+            .iter()
+            .filter(|e| e.namespace == namespace)
+            .map(|e| ScoredEntry {
+                score: cosine_similarity(query, &e.embedding),
+                entry: e.clone(),
+            })
+            .collect();
+        
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score));
+        hits.truncate(k);
+        hits
+    }
+    
+    /// All entries in a namespace
+    pub fn entries(&self, namespace: &str) -> Vec<&IndexEntry> {
+        self.entries.iter().filter(|e| e.namespace == namespace).collect()
+    }
+    
+    /// Total number of entries
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    
+    /// Check if empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cosine_similarity() {
+        assert!((cosine_similarity(&[1.0, 0.0], &[1.0, 0.0]) - 1.0).abs() < 1e-6);
+        assert!(cosine_similarity(&[1.0, 0.0], &[0.0, 1.0]).abs() < 1e-6);
+        assert_eq!(cosine_similarity(&[1.0], &[1.0, 2.0]), 0.0);
+    }
+
+    #[test]
+    fn test_index_search_and_persist() {
+        let path = std::env::temp_dir().join(format!("vector-index-{}.json", uuid::Uuid::new_v4()));
+        
+        let mut index = VectorIndex::open(&path).unwrap();
+        index.insert(IndexEntry::new("alice", "likes BTC", vec![1.0, 0.0]));
+        index.insert(IndexEntry::new("alice", "avoids memecoins", vec![0.0, 1.0]));
+        index.insert(IndexEntry::new("bob", "likes BTC", vec![1.0, 0.0]));
+        index.save().unwrap();
+        
+        let reloaded = VectorIndex::open(&path).unwrap();
+        let hits = reloaded.search("alice", &[0.9, 0.1], 1);
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].entry.text, "likes BTC");
+        assert_eq!(reloaded.entries("bob").len(), 1);
+        
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -56,6 +56,14 @@ pub enum AgentError {
     #[error("Authentication failed: {0}")]
     Auth(String),
     
+    /// Shared token or delegation budget exhausted
+    #[error("Budget exceeded: {0}")]
+    BudgetExceeded(String),
+    
+    /// Content rejected by the safety policy (lists the kinds found)
+    #[error("Content blocked: {0}")]
+    ContentBlocked(String),
+    
     /// Generic IO error
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
@@ -92,6 +100,11 @@ impl AgentError {
             AgentError::ContextOverflow { .. } => "The conversation is too long. Please start a new session.".into(),
             AgentError::RateLimited(_) => "You've made too many requests. Please wait a moment.".into(),
             AgentError::Auth(_) => "Authentication failed. Please check your credentials.".into(),
+            AgentError::BudgetExceeded(_) => "This request used up its processing budget. Please try a narrower question.".into(),
+            AgentError::ContentBlocked(kinds) => format!(
+                "Your message was not sent because it contains sensitive data ({}). Never share seed phrases or private keys.",
+                kinds.replace('_', " ")
+            ),
             _ => "An unexpected error occurred.".into(),
         }
     }
@@ -0,0 +1,288 @@
+//! Session Export
+//!
+//! Portable formats for sessions:
+//!
+//! - **JSON** - the `Session` serde shape plus a `version` field; the only
+//!   format that imports back (`from_json`), with tool messages and
+//!   `MessageMetadata` intact
+//! - **Markdown** - a readable transcript
+//! - **OpenAI JSONL** - one `{"messages": [...]}` line per session, the
+//!   chat fine-tuning format; tool calls become `tool_calls` entries
+//!
+//! `export_user` collects every session of a user, for data-access requests.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use crate::error::{AgentError, Result};
+use crate::message::{Message, Role};
+use crate::session::{Session, SessionStore};
+use crate::tool::ToolCall;
+
+/// Current JSON export format version
+pub const EXPORT_VERSION: u32 = 1;
+
+/// A session in the versioned JSON export format
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct SessionExport {
+    /// Format version (0 = a bare `Session` saved before versioning)
+    #[serde(default)]
+    pub version: u32,
+    
+    #[serde(flatten)]
+    pub session: Session,
+}
+
+impl SessionExport {
+    pub fn new(session: Session) -> Self {
+        Self {
+            version: EXPORT_VERSION,
+            session,
+        }
+    }
+}
+
+/// All sessions of one user
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UserExport {
+    pub version: u32,
+    pub user_id: String,
+    pub exported_at: DateTime<Utc>,
+    pub sessions: Vec<SessionExport>,
+}
+
+/// Serialize a session as versioned JSON
+pub fn to_json(session: &Session) -> Result<String> {
+    Ok(serde_json::to_string_pretty(&SessionExport::new(session.clone()))?)
+}
+
+/// Import a session exported with `to_json` (or a bare serialized `Session`)
+pub fn from_json(json: &str) -> Result<Session> {
+    let export: SessionExport = serde_json::from_str(json)?;
+    if export.version > EXPORT_VERSION {
+        return Err(AgentError::Session(format!(
+            "Unsupported session export version {} (newest supported: {})",
+            export.version, EXPORT_VERSION
+        )));
+    }
+    Ok(export.session)
+}
+
+/// Render a session as a Markdown transcript
+pub fn to_markdown(session: &Session) -> String {
+    let metadata = &session.metadata;
+    let mut out = format!("# {}\n\n", session.title());
+    
+    out.push_str(&format!("- **Session:** `{}`\n", session.id));
+    out.push_str(&format!("- **Created:** {}\n", session.created_at.to_rfc3339()));
+    out.push_str(&format!("- **Updated:** {}\n", session.updated_at.to_rfc3339()));
+    out.push_str(&format!("- **Model:** {}\n", metadata.model));
+    if let Some(user_id) = &metadata.user_id {
+        out.push_str(&format!("- **User:** {}\n", user_id));
+    }
+    if !metadata.tags.is_empty() {
+        out.push_str(&format!("- **Tags:** {}\n", metadata.tags.join(", ")));
+    }
+    if let Some(prompt) = &metadata.prompt_version {
+        out.push_str(&format!("- **Prompt:** {}\n", prompt));
+    }
+    
+    for message in session.conversation.messages() {
+        let heading = match message.role {
+            Role::System => "System",
+            Role::User => "User",
+            Role::Assistant => "Assistant",
+            Role::Tool => "Tool result",
+        };
+        out.push_str(&format!("\n## {} · {}\n\n", heading, message.timestamp.format("%Y-%m-%d %H:%M:%S UTC")));
+        
+        let meta = message.metadata.as_ref();
+        let mut details = Vec::new();
+        if let Some(name) = &message.name {
+            details.push(format!("name: {}", name));
+        }
+        if let Some(model) = meta.and_then(|m| m.model.as_ref()) {
+            details.push(format!("model: {}", model));
+        }
+        if let Some(usage) = meta.and_then(|m| m.usage) {
+            details.push(format!("tokens: {} in / {} out", usage.prompt_tokens, usage.completion_tokens));
+        }
+        if let Some(id) = meta.and_then(|m| m.tool_call_id.as_ref()) {
+            details.push(format!("call: {}", id));
+        }
+        if !details.is_empty() {
+            out.push_str(&format!("_{}_\n\n", details.join(" · ")));
+        }
+        
+        if message.role == Role::Tool {
+            out.push_str(&format!("```\n{}\n```\n", message.content.trim_end()));
+        } else {
+            out.push_str(message.content.trim_end());
+            out.push('\n');
+        }
+    }
+    
+    out
+}
+
+/// Export sessions as OpenAI chat fine-tuning JSONL (one line per session)
+pub fn to_openai_jsonl<'a>(sessions: impl IntoIterator<Item = &'a Session>) -> Result<String> {
+    let mut out = String::new();
+    for session in sessions {
+        out.push_str(&serde_json::to_string(&json!({ "messages": openai_messages(session) }))?);
+        out.push('\n');
+    }
+    Ok(out)
+}
+
+/// A session's messages in the OpenAI chat format
+///
+/// An assistant message holding a tool call becomes a `tool_calls` entry
+/// whose id is shared with the tool result that follows it.
+pub fn openai_messages(session: &Session) -> Vec<Value> {
+    let messages = session.conversation.messages();
+    let mut out = Vec::with_capacity(messages.len());
+    let mut pending_call: Option<String> = None;
+    
+    for (i, message) in messages.iter().enumerate() {
+        let mut entry = match message.role {
+            Role::Assistant => match parse_tool_call(&message.content) {
+                Some((text, call)) => {
+                    let id = call
+                        .id
+                        .clone()
+                        .or_else(|| messages.get(i + 1).filter(|next| next.role == Role::Tool).and_then(tool_call_id))
+                        .unwrap_or_else(|| format!("call_{}", i));
+                    pending_call = Some(id.clone());
+                    json!({
+                        "role": "assistant",
+                        "content": if text.is_empty() { Value::Null } else { Value::String(text) },
+                        "tool_calls": [{
+                            "id": id,
+                            "type": "function",
+                            "function": {
+                                "name": call.name,
+                                "arguments": serde_json::to_string(&call.arguments).unwrap_or_default(),
+                            },
+                        }],
+                    })
+                }
+                None => json!({ "role": "assistant", "content": message.content }),
+            },
+            Role::Tool => {
+                let id = tool_call_id(message).or_else(|| pending_call.take()).unwrap_or_else(|| format!("call_{}", i));
+                json!({ "role": "tool", "tool_call_id": id, "content": message.content })
+            }
+            _ => json!({ "role": message.role.to_string(), "content": message.content }),
+        };
+        
+        if let (Some(name), Some(object)) = (&message.name, entry.as_object_mut()) {
+            object.insert("name".into(), Value::String(name.clone()));
+        }
+        out.push(entry);
+    }
+    
+    out
+}
+
+/// Export every session of a user (newest first)
+pub fn export_user(store: &dyn SessionStore, user_id: &str) -> Result<UserExport> {
+    let sessions = store.list(Some(user_id), usize::MAX)?;
+    Ok(UserExport {
+        version: EXPORT_VERSION,
+        user_id: user_id.to_string(),
+        exported_at: Utc::now(),
+        sessions: sessions.into_iter().map(SessionExport::new).collect(),
+    })
+}
+
+fn tool_call_id(message: &Message) -> Option<String> {
+    message.metadata.as_ref().and_then(|m| m.tool_call_id.clone())
+}
+
+/// Split an assistant message into its text and ```tool call
+fn parse_tool_call(content: &str) -> Option<(String, ToolCall)> {
+    let start = content.find("```tool")?;
+    let body_start = start + "```tool".len();
+    let body_end = content[body_start..].find("```").map(|i| body_start + i)?;
+    let call: ToolCall = serde_json::from_str(content[body_start..body_end].trim()).ok()?;
+    
+    let text: Vec<&str> = [content[..start].trim(), content[body_end + 3..].trim()]
+        .into_iter()
+        .filter(|part| !part.is_empty())
+        .collect();
+    Some((text.join("\n\n"), call))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::TokenUsage;
+    use crate::session::MemorySessionStore;
+
+    fn session() -> Session {
+        let mut session = Session::with_system_prompt("You are an advisor.");
+        session.metadata.user_id = Some("u1".into());
+        let conversation = &mut session.conversation;
+        conversation.push(Message::user("Price of BTC?"));
+        let usage = TokenUsage { prompt_tokens: 12, completion_tokens: 8, total_tokens: 20 };
+        conversation.push(
+            Message::assistant("Let me check.\n```tool\n{\"tool\": \"price_lookup\", \"arguments\": {\"symbols\": \"BTC\"}}\n```")
+                .with_usage("llama3.2", Some(usage)),
+        );
+        conversation.push(Message::tool("BTC: $60,000", Some("abc".into())));
+        conversation.push(Message::assistant("BTC trades at $60,000.").with_usage("llama3.2", Some(usage)));
+        session
+    }
+
+    #[test]
+    fn test_json_round_trip() {
+        let original = session();
+        let json = to_json(&original).unwrap();
+        assert!(json.contains("\"version\": 1"));
+        
+        let imported = from_json(&json).unwrap();
+        assert_eq!(imported.id, original.id);
+        let messages = imported.conversation.messages();
+        assert_eq!(messages.len(), 5);
+        assert_eq!(messages[3].role, Role::Tool);
+        assert_eq!(tool_call_id(&messages[3]).as_deref(), Some("abc"));
+        assert_eq!(messages[2].metadata.as_ref().and_then(|m| m.usage).map(|u| u.total_tokens), Some(20));
+        
+        // Bare sessions import as version 0; future versions are rejected
+        assert!(from_json(&serde_json::to_string(&original).unwrap()).is_ok());
+        assert!(from_json(&json.replace("\"version\": 1", "\"version\": 99")).is_err());
+    }
+
+    #[test]
+    fn test_openai_jsonl_and_markdown() {
+        let jsonl = to_openai_jsonl([&session()]).unwrap();
+        assert_eq!(jsonl.lines().count(), 1);
+        
+        let line: Value = serde_json::from_str(jsonl.trim()).unwrap();
+        let messages = line["messages"].as_array().unwrap();
+        assert_eq!(messages[2]["content"], "Let me check.");
+        assert_eq!(messages[2]["tool_calls"][0]["id"], "abc");
+        assert_eq!(messages[2]["tool_calls"][0]["function"]["name"], "price_lookup");
+        assert_eq!(messages[2]["tool_calls"][0]["function"]["arguments"], "{\"symbols\":\"BTC\"}");
+        assert_eq!(messages[3]["role"], "tool");
+        assert_eq!(messages[3]["tool_call_id"], "abc");
+        
+        let markdown = to_markdown(&session());
+        assert!(markdown.starts_with("# Price of BTC?\n"));
+        assert!(markdown.contains("_model: llama3.2 · tokens: 12 in / 8 out_"));
+        assert!(markdown.contains("```\nBTC: $60,000\n```"));
+    }
+
+    #[test]
+    fn test_export_user() {
+        let store = MemorySessionStore::new();
+        store.save(&session()).unwrap();
+        store.save(&Session::new()).unwrap();
+        
+        let export = export_user(&store, "u1").unwrap();
+        assert_eq!(export.sessions.len(), 1);
+        assert_eq!(export.sessions[0].version, EXPORT_VERSION);
+    }
+}
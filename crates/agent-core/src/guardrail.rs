@@ -0,0 +1,235 @@
+//! Guardrails
+//!
+//! Checks run on an agent's final answer before it is returned. Violations
+//! are fed back to the model for a revision; once `max_revisions` is used up
+//! the answer is returned flagged with the remaining violations.
+//!
+//! Rules implement `Guardrail`. Domain rules live with their domain (see
+//! `crypto_advisor::policy`); `CriticGuardrail` is a generic LLM reviewer
+//! that looks for claims the tool results don't support.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+use crate::error::Result;
+use crate::message::Message;
+use crate::provider::{GenerationOptions, LlmProvider, TokenUsage};
+use crate::tool::ToolResult;
+
+/// A broken rule
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Violation {
+    /// Rule identifier (e.g. `concentration`)
+    pub rule: String,
+    
+    /// What is wrong, phrased as an instruction for the revision
+    pub message: String,
+}
+
+impl Violation {
+    pub fn new(rule: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            rule: rule.into(),
+            message: message.into(),
+        }
+    }
+}
+
+/// What a guardrail gets to see
+#[derive(Clone, Copy, Debug)]
+pub struct GuardrailInput<'a> {
+    /// The user's question
+    pub question: &'a str,
+    
+    /// The candidate answer
+    pub answer: &'a str,
+    
+    /// Tool results the answer was based on
+    pub tool_results: &'a [ToolResult],
+}
+
+impl GuardrailInput<'_> {
+    /// Whether any tool call succeeded
+    pub fn has_tool_evidence(&self) -> bool {
+        self.tool_results.iter().any(|r| r.success)
+    }
+}
+
+/// Result of one guardrail
+#[derive(Clone, Debug, Default)]
+pub struct GuardrailCheck {
+    pub violations: Vec<Violation>,
+    
+    /// Tokens spent checking (LLM-based guardrails)
+    pub usage: Option<TokenUsage>,
+}
+
+impl From<Vec<Violation>> for GuardrailCheck {
+    fn from(violations: Vec<Violation>) -> Self {
+        Self { violations, usage: None }
+    }
+}
+
+/// A check on a final answer
+#[async_trait]
+pub trait Guardrail: Send + Sync {
+    /// Rule name, for logs
+    fn name(&self) -> &str;
+    
+    async fn check(&self, input: &GuardrailInput<'_>) -> Result<GuardrailCheck>;
+}
+
+/// An ordered set of guardrails
+pub struct Guardrails {
+    rules: Vec<Arc<dyn Guardrail>>,
+    max_revisions: usize,
+}
+
+impl Default for Guardrails {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Guardrails {
+    /// No rules, one revision
+    pub fn new() -> Self {
+        Self {
+            rules: Vec::new(),
+            max_revisions: 1,
+        }
+    }
+    
+    pub fn rule<G: Guardrail + 'static>(mut self, rule: G) -> Self {
+        self.rules.push(Arc::new(rule));
+        self
+    }
+    
+    /// Revisions requested before the answer is returned flagged (0 = flag only)
+    pub fn max_revisions(mut self, revisions: usize) -> Self {
+        self.max_revisions = revisions;
+        self
+    }
+    
+    pub fn revisions(&self) -> usize {
+        self.max_revisions
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.rules.is_empty()
+    }
+    
+    /// Run every rule
+    ///
+    /// A rule that errors is skipped with a warning: a broken checker must
+    /// not block answers.
+    pub async fn check(&self, input: &GuardrailInput<'_>) -> GuardrailCheck {
+        let mut result = GuardrailCheck::default();
+        
+        for rule in &self.rules {
+            match rule.check(input).await {
+                Ok(check) => {
+                    result.violations.extend(check.violations);
+                    if let Some(usage) = check.usage {
+                        *result.usage.get_or_insert_with(TokenUsage::default) += usage;
+                    }
+                }
+                Err(e) => tracing::warn!(rule = rule.name(), error = %e, "Guardrail failed"),
+            }
+        }
+        
+        result
+    }
+}
+
+/// Instruction asking the model to fix its answer
+pub fn revision_prompt(violations: &[Violation]) -> String {
+    let mut prompt = String::from("Your answer does not meet our policy. Revise it and respond with the full corrected answer:\n");
+    for violation in violations {
+        prompt.push_str(&format!("- {}\n", violation.message));
+    }
+    prompt.push_str("\nUse tools if you need evidence you do not have yet.");
+    prompt
+}
+
+const CRITIC_PROMPT: &str = r#"You review answers written by an AI assistant. List every factual claim in the ANSWER (prices, percentages, statistics, dates, statements about assets or markets) that is not supported by the TOOL RESULTS or by the QUESTION itself. General advice and well-known definitions are fine.
+
+Respond ONLY with JSON: {"unsupported_claims": ["<claim>", ...]} - an empty list if everything is supported."#;
+
+#[derive(Deserialize)]
+struct CriticResponse {
+    #[serde(default)]
+    unsupported_claims: Vec<String>,
+}
+
+/// LLM reviewer that flags claims not backed by tool results
+pub struct CriticGuardrail {
+    provider: Arc<dyn LlmProvider>,
+    model: String,
+}
+
+impl CriticGuardrail {
+    pub fn new(provider: Arc<dyn LlmProvider>, model: impl Into<String>) -> Self {
+        Self {
+            provider,
+            model: model.into(),
+        }
+    }
+}
+
+#[async_trait]
+impl Guardrail for CriticGuardrail {
+    fn name(&self) -> &str {
+        "critic"
+    }
+    
+    async fn check(&self, input: &GuardrailInput<'_>) -> Result<GuardrailCheck> {
+        let mut evidence = String::new();
+        for result in input.tool_results.iter().filter(|r| r.success) {
+            evidence.push_str(&format!("[{}]\n{}\n\n", result.name, result.output));
+        }
+        if evidence.is_empty() {
+            evidence.push_str("(no tools were used)");
+        }
+        
+        let schema = serde_json::json!({
+            "type": "object",
+            "required": ["unsupported_claims"],
+            "properties": {"unsupported_claims": {"type": "array", "items": {"type": "string"}}}
+        });
+        let options = GenerationOptions {
+            model: self.model.clone(),
+            temperature: 0.0,
+            response_format: Some(schema),
+            ..Default::default()
+        };
+        let messages = [
+            Message::system(CRITIC_PROMPT),
+            Message::user(format!(
+                "QUESTION:\n{}\n\nTOOL RESULTS:\n{}\n\nANSWER:\n{}",
+                input.question,
+                evidence.trim_end(),
+                input.answer
+            )),
+        ];
+        
+        let completion = self.provider.complete(&messages, &options).await?;
+        let claims = crate::schema::extract_json(&completion.content)
+            .and_then(|value| serde_json::from_value::<CriticResponse>(value).ok())
+            .map(|response| response.unsupported_claims)
+            .unwrap_or_default();
+        
+        Ok(GuardrailCheck {
+            violations: claims
+                .into_iter()
+                .filter(|claim| !claim.trim().is_empty())
+                .map(|claim| {
+                    Violation::new("unsupported_claim", format!("Support this claim with a tool result or remove it: {}", claim))
+                })
+                .collect(),
+            usage: completion.usage,
+        })
+    }
+}
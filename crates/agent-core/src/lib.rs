@@ -23,10 +23,31 @@ pub mod reasoning;
 pub mod message;
 pub mod error;
 pub mod session;
+pub mod export;
+pub mod tagging;
+pub mod resilience;
+pub mod cascade;
+pub mod schema;
+pub mod embedding;
+pub mod memory;
+pub mod prompt;
+pub mod orchestration;
+pub mod guardrail;
+pub mod safety;
+pub mod metrics;
 
+pub use embedding::{EmbeddingProvider, VectorIndex};
 pub use error::{AgentError, Result};
+pub use guardrail::{Guardrail, Guardrails, Violation};
+pub use memory::SemanticMemory;
+pub use metrics::{AgentMetrics, MeteredProvider, MeteredTool, Metrics};
 pub use message::{Message, Role};
+pub use orchestration::{AgentSpec, Orchestrator};
+pub use prompt::{PromptRegistry, PromptVars, RenderedPrompt};
 pub use provider::LlmProvider;
-pub use reasoning::Agent;
+pub use reasoning::{Agent, AgentRun, RunEvent, TraceStep};
+pub use safety::{ContentFilter, SafetyPolicy, SafetyProvider};
+pub use resilience::{ResilienceConfig, ResilientProvider};
+pub use cascade::{CascadeConfig, CascadeProvider};
 pub use session::Session;
 pub use tool::{Tool, ToolCall, ToolResult, ToolRegistry};
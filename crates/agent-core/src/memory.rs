@@ -0,0 +1,265 @@
+//! Semantic Memory
+//!
+//! Long-term, per-user memory of salient facts (risk tolerance, holdings,
+//! exclusions, goals). Facts are extracted from user messages, embedded, and
+//! stored in a `VectorIndex` namespaced by user ID. The most relevant facts
+//! are recalled into the system prompt at the start of each run.
+
+use std::sync::{Arc, RwLock};
+
+use crate::embedding::{EmbeddingProvider, IndexEntry, ScoredEntry, VectorIndex};
+use crate::error::{AgentError, Result};
+
+/// Kind of remembered fact
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FactKind {
+    RiskTolerance,
+    Holding,
+    Exclusion,
+    Goal,
+}
+
+impl FactKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FactKind::RiskTolerance => "risk_tolerance",
+            FactKind::Holding => "holding",
+            FactKind::Exclusion => "exclusion",
+            FactKind::Goal => "goal",
+        }
+    }
+    
+    /// Classify a sentence, if it states something worth remembering
+    fn classify(sentence: &str) -> Option<Self> {
+        let s = sentence.to_lowercase();
+        // Whole words, so "economy" or "Miami" don't read as first person
+        let words: Vec<&str> = s.split(|c: char| !c.is_alphanumeric() && c != '\'').filter(|w| !w.is_empty()).collect();
+        let first_person = words.iter().any(|w| matches!(*w, "i" | "i'm" | "my")) || words.first() == Some(&"we");
+        if !first_person {
+            return None;
+        }
+        
+        const EXCLUSION: &[&str] = &["avoid", "don't want", "do not want", "never buy", "exclude", "stay away", "no memecoin"];
+        const RISK: &[&str] = &["risk", "conservative", "aggressive", "moderate", "cautious", "can't afford to lose"];
+        const HOLDING: &[&str] = &["i own", "i hold", "i have", "i bought", "my portfolio", "i'm holding", "my position"];
+        const GOAL: &[&str] = &["saving for", "my goal", "retire", "horizon", "in 5 years", "long term", "long-term"];
+        
+        let has = |words: &[&str]| words.iter().any(|w| s.contains(w));
+        if has(EXCLUSION) {
+            Some(FactKind::Exclusion)
+        } else if has(RISK) {
+            Some(FactKind::RiskTolerance)
+        } else if has(HOLDING) {
+            Some(FactKind::Holding)
+        } else if has(GOAL) {
+            Some(FactKind::Goal)
+        } else {
+            None
+        }
+    }
+}
+
+/// Extract salient facts from a user message
+pub fn extract_facts(message: &str) -> Vec<(FactKind, String)> {
+    message
+        .split(['.', '!', '?', '\n'])
+        .map(str::trim)
+        .filter(|s| s.len() >= 8 && s.len() <= 300)
+        .filter_map(|s| FactKind::classify(s).map(|kind| (kind, s.to_string())))
+        .collect()
+}
+
+/// Memory settings
+#[derive(Clone, Debug)]
+pub struct MemoryConfig {
+    /// Facts recalled into the system prompt
+    pub top_k: usize,
+    
+    /// Minimum similarity for a fact to be recalled
+    pub min_score: f32,
+    
+    /// Similarity above which a new fact replaces an existing one
+    pub dedup_threshold: f32,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            top_k: 5,
+            min_score: 0.3,
+            dedup_threshold: 0.92,
+        }
+    }
+}
+
+/// Per-user semantic memory
+pub struct SemanticMemory {
+    embedder: Arc<dyn EmbeddingProvider>,
+    index: RwLock<VectorIndex>,
+    /// Held while a snapshot is taken and written, so saves land in order
+    saving: tokio::sync::Mutex<()>,
+    config: MemoryConfig,
+}
+
+impl SemanticMemory {
+    pub fn new(embedder: Arc<dyn EmbeddingProvider>, index: VectorIndex, config: MemoryConfig) -> Self {
+        Self {
+            embedder,
+            index: RwLock::new(index),
+            saving: tokio::sync::Mutex::new(()),
+            config,
+        }
+    }
+    
+    /// Store a fact for a user, replacing a near-duplicate if one exists
+    pub async fn remember(&self, user_id: &str, kind: FactKind, fact: &str) -> Result<()> {
+        let embedding = self.embedder.embed_one(fact).await?;
+        
+        {
+            let mut index = self.index.write().unwrap();
+            let duplicate = index
+                .search(user_id, &embedding, 1)
+                .into_iter()
+                .find(|hit| hit.score >= self.config.dedup_threshold);
+            
+            let mut entry = IndexEntry::new(user_id, fact, embedding)
+                .with_metadata("kind", serde_json::json!(kind.as_str()));
+            
+            match duplicate {
+                Some(hit) => {
+                    entry.id = hit.entry.id;
+                    index.replace(entry);
+                }
+                None => index.insert(entry),
+            }
+        }
+        
+        self.persist().await
+    }
+    
+    /// Extract and store the salient facts in a user message
+    pub async fn observe(&self, user_id: &str, message: &str) -> Result<usize> {
+        let facts = extract_facts(message);
+        for (kind, fact) in &facts {
+            self.remember(user_id, *kind, fact).await?;
+        }
+        Ok(facts.len())
+    }
+    
+    /// Recall the facts most relevant to `query`
+    pub async fn recall(&self, user_id: &str, query: &str) -> Result<Vec<ScoredEntry>> {
+        if self.index.read().unwrap().entries(user_id).is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let embedding = self.embedder.embed_one(query).await?;
+        let index = self.index.read().unwrap();
+        
+        Ok(index
+            .search(user_id, &embedding, self.config.top_k)
+            .into_iter()
+            .filter(|hit| hit.score >= self.config.min_score)
+            .collect())
+    }
+    
+    /// Delete everything remembered about a user
+    pub async fn forget(&self, user_id: &str) -> Result<usize> {
+        let removed = self.index.write().unwrap().clear_namespace(user_id);
+        self.persist().await?;
+        Ok(removed)
+    }
+    
+    /// Write the index to disk, retrying a save that failed after a change
+    pub fn flush(&self) -> Result<()> {
+        self.index.read().unwrap().save()
+    }
+    
+    /// Write the index on a blocking thread, off the async path
+    ///
+    /// The snapshot is taken after the previous save finished, so a slow
+    /// older save can never overwrite a newer one.
+    async fn persist(&self) -> Result<()> {
+        let _saving = self.saving.lock().await;
+        let snapshot = self.index.read().unwrap().snapshot();
+        tokio::task::spawn_blocking(move || snapshot.write())
+            .await
+            .map_err(|e| AgentError::Other(format!("Memory save failed: {e}")))?
+    }
+}
+
+/// Format recalled facts as a system prompt section
+pub fn prompt_section(memories: &[ScoredEntry]) -> String {
+    let mut section = String::from("## What You Remember About This User\n\n");
+    for hit in memories {
+        section.push_str(&format!(
+            "- {} (noted {})\n",
+            hit.entry.text,
+            hit.entry.updated_at.format("%Y-%m-%d")
+        ));
+    }
+    section.push_str("\nUse these facts when relevant, but confirm anything that may have changed.");
+    section
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+
+    /// Bag-of-keywords embedder: one dimension per keyword
+    struct KeywordEmbedder;
+
+    #[async_trait]
+    impl EmbeddingProvider for KeywordEmbedder {
+        async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+            const KEYWORDS: &[&str] = &["risk", "conservative", "doge", "avoid", "btc", "hold"];
+            Ok(inputs
+                .iter()
+                .map(|text| {
+                    let text = text.to_lowercase();
+                    KEYWORDS.iter().map(|k| if text.contains(k) { 1.0 } else { 0.0 }).collect()
+                })
+                .collect())
+        }
+        
+        fn model(&self) -> &str {
+            "keywords"
+        }
+    }
+
+    #[test]
+    fn test_extract_facts() {
+        let facts = extract_facts("Hi there. I'm very conservative with risk. I hold 0.5 BTC! I want to avoid DOGE.");
+        let kinds: Vec<_> = facts.iter().map(|(k, _)| *k).collect();
+        assert_eq!(kinds, vec![FactKind::RiskTolerance, FactKind::Holding, FactKind::Exclusion]);
+        
+        assert!(extract_facts("The economy carries a lot of risk").is_empty());
+        assert_eq!(extract_facts("We are long-term holders")[0].0, FactKind::Goal);
+    }
+
+    #[tokio::test]
+    async fn test_remember_and_recall() {
+        let memory = SemanticMemory::new(Arc::new(KeywordEmbedder), VectorIndex::in_memory(), MemoryConfig::default());
+        
+        memory.observe("alice", "I hold 0.5 BTC. I want to avoid DOGE.").await.unwrap();
+        memory.observe("alice", "I want to avoid DOGE entirely.").await.unwrap();
+        
+        let hits = memory.recall("alice", "should I buy doge?").await.unwrap();
+        assert_eq!(hits[0].entry.text, "I want to avoid DOGE entirely");
+        assert!(memory.recall("bob", "doge").await.unwrap().is_empty());
+        assert_eq!(memory.forget("alice").await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_remember_persists() {
+        let path = std::env::temp_dir().join(format!("memory-{}.json", uuid::Uuid::new_v4()));
+        let memory = SemanticMemory::new(Arc::new(KeywordEmbedder), VectorIndex::open(&path).unwrap(), MemoryConfig::default());
+        
+        memory.observe("alice", "I hold 0.5 BTC").await.unwrap();
+        assert_eq!(VectorIndex::open(&path).unwrap().entries("alice").len(), 1);
+        
+        memory.forget("alice").await.unwrap();
+        assert!(VectorIndex::open(&path).unwrap().entries("alice").is_empty());
+        std::fs::remove_file(&path).ok();
+    }
+}
@@ -67,6 +67,10 @@ pub struct MessageMetadata {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
     
+    /// Full token usage of the completion that produced this message
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<crate::provider::TokenUsage>,
+    
     /// Custom key-value pairs
     #[serde(flatten)]
     pub extra: std::collections::HashMap<String, serde_json::Value>,
@@ -111,6 +115,18 @@ impl Message {
         msg
     }
     
+    /// Record the generating model and its token usage (for assistant messages)
+    ///
+    /// `tokens` holds the message's own (completion) token count; the full
+    /// prompt/completion breakdown is kept in `usage`.
+    pub fn with_usage(mut self, model: impl Into<String>, usage: Option<crate::provider::TokenUsage>) -> Self {
+        let metadata = self.metadata.get_or_insert_with(MessageMetadata::default);
+        metadata.model = Some(model.into());
+        metadata.tokens = usage.map(|u| u.completion_tokens);
+        metadata.usage = usage;
+        self
+    }
+    
     /// Add a name to the message
     pub fn with_name(mut self, name: impl Into<String>) -> Self {
         self.name = Some(name.into());
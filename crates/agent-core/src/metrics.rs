@@ -0,0 +1,568 @@
+//! Metrics
+//!
+//! A small registry of labelled counters and histograms, rendered in the
+//! Prometheus text exposition format, and the decorators that record the
+//! agent's own metrics:
+//!
+//! - `MeteredProvider` - completion latency, time to first token and tokens
+//!   by model, with an `llm.complete` / `llm.stream` span per call (models
+//!   outside `with_models` are labelled `other`)
+//! - `MeteredTool` - calls, failures and latency by tool name
+//! - `Agent::with_metrics` - reasoning iterations per run
+//!
+//! ## Usage
+//!
+//! ```rust,ignore
+//! use agent_core::metrics::{AgentMetrics, MeteredProvider, MeteredTool, Metrics};
+//!
+//! let registry = Metrics::new();
+//! let metrics = AgentMetrics::register(&registry);
+//!
+//! let provider = MeteredProvider::new(Arc::new(OllamaProvider::from_env()), metrics.clone());
+//! tools.wrap(|tool| Arc::new(MeteredTool::new(tool, metrics.clone())));
+//!
+//! // GET /metrics
+//! let body = registry.render();
+//! ```
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use tracing::Instrument;
+
+use crate::error::Result;
+use crate::message::Message;
+use crate::provider::{
+    Completion, CompletionStream, GenerationOptions, LlmProvider, ModelInfo, ProviderInfo, TokenUsage,
+};
+use crate::tool::{Tool, ToolCall, ToolResult, ToolSchema};
+
+/// Buckets for latencies in seconds, from 5 ms to 2 minutes
+pub const LATENCY_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0];
+
+/// Buckets for small counts, such as reasoning iterations
+pub const COUNT_BUCKETS: &[f64] = &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 8.0, 10.0, 15.0, 20.0];
+
+// ============================================================================
+// Registry
+// ============================================================================
+
+/// Metric families of one process
+#[derive(Default)]
+pub struct Metrics {
+    families: Mutex<Vec<Arc<Family>>>,
+}
+
+impl Metrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Register a counter (or get the one already registered under `name`)
+    pub fn counter(&self, name: &'static str, help: &'static str, labels: &'static [&'static str]) -> Counter {
+        Counter(self.family(name, help, labels, Kind::Counter))
+    }
+    
+    /// Register a histogram with upper bucket bounds in ascending order
+    pub fn histogram(
+        &self,
+        name: &'static str,
+        help: &'static str,
+        labels: &'static [&'static str],
+        buckets: &'static [f64],
+    ) -> Histogram {
+        Histogram(self.family(name, help, labels, Kind::Histogram(buckets)))
+    }
+    
+    fn family(&self, name: &'static str, help: &'static str, labels: &'static [&'static str], kind: Kind) -> Arc<Family> {
+        let mut families = self.families.lock().unwrap();
+        if let Some(family) = families.iter().find(|f| f.name == name) {
+            return family.clone();
+        }
+        
+        let family = Arc::new(Family { name, help, labels, kind, series: Mutex::new(BTreeMap::new()) });
+        families.push(family.clone());
+        family
+    }
+    
+    /// Every family in the Prometheus text format (version 0.0.4)
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for family in self.families.lock().unwrap().iter() {
+            family.render(&mut out);
+        }
+        out
+    }
+}
+
+/// A monotonically increasing count, per label values
+#[derive(Clone)]
+pub struct Counter(Arc<Family>);
+
+impl Counter {
+    /// Add one; `labels` are values in the order the family was registered with
+    pub fn inc(&self, labels: &[&str]) {
+        self.add(labels, 1.0);
+    }
+    
+    pub fn add(&self, labels: &[&str], value: f64) {
+        self.0.update(labels, |series| {
+            if let Series::Counter(total) = series {
+                *total += value;
+            }
+        });
+    }
+}
+
+/// A distribution of observed values, per label values
+#[derive(Clone)]
+pub struct Histogram(Arc<Family>);
+
+impl Histogram {
+    pub fn observe(&self, labels: &[&str], value: f64) {
+        self.0.update(labels, |series| {
+            if let Series::Histogram { counts, sum, count } = series {
+                for (bucket, bound) in counts.iter_mut().zip(self.0.buckets()) {
+                    if value <= *bound {
+                        *bucket += 1;
+                    }
+                }
+                *sum += value;
+                *count += 1;
+            }
+        });
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Kind {
+    Counter,
+    Histogram(&'static [f64]),
+}
+
+enum Series {
+    Counter(f64),
+    
+    /// Cumulative count per bucket, then the `+Inf` bucket is `count`
+    Histogram { counts: Vec<u64>, sum: f64, count: u64 },
+}
+
+struct Family {
+    name: &'static str,
+    help: &'static str,
+    labels: &'static [&'static str],
+    kind: Kind,
+    series: Mutex<BTreeMap<Vec<String>, Series>>,
+}
+
+impl Family {
+    fn buckets(&self) -> &'static [f64] {
+        match self.kind {
+            Kind::Histogram(buckets) => buckets,
+            Kind::Counter => &[],
+        }
+    }
+    
+    fn update(&self, labels: &[&str], apply: impl FnOnce(&mut Series)) {
+        debug_assert_eq!(labels.len(), self.labels.len(), "label values for {}", self.name);
+        let key = labels.iter().map(|l| l.to_string()).collect();
+        let mut series = self.series.lock().unwrap();
+        let entry = series.entry(key).or_insert_with(|| match self.kind {
+            Kind::Counter => Series::Counter(0.0),
+            Kind::Histogram(buckets) => Series::Histogram { counts: vec![0; buckets.len()], sum: 0.0, count: 0 },
+        });
+        apply(entry);
+    }
+    
+    fn render(&self, out: &mut String) {
+        let kind = match self.kind {
+            Kind::Counter => "counter",
+            Kind::Histogram(_) => "histogram",
+        };
+        let _ = writeln!(out, "# HELP {} {}", self.name, self.help);
+        let _ = writeln!(out, "# TYPE {} {}", self.name, kind);
+        
+        for (values, series) in self.series.lock().unwrap().iter() {
+            let labels = label_pairs(self.labels, values);
+            match series {
+                Series::Counter(total) => {
+                    let _ = writeln!(out, "{}{} {}", self.name, braced(&labels), total);
+                }
+                Series::Histogram { counts, sum, count } => {
+                    for (bound, bucket) in self.buckets().iter().zip(counts) {
+                        let le = with_label(&labels, "le", &bound.to_string());
+                        let _ = writeln!(out, "{}_bucket{} {}", self.name, braced(&le), bucket);
+                    }
+                    let le = with_label(&labels, "le", "+Inf");
+                    let _ = writeln!(out, "{}_bucket{} {}", self.name, braced(&le), count);
+                    let _ = writeln!(out, "{}_sum{} {}", self.name, braced(&labels), sum);
+                    let _ = writeln!(out, "{}_count{} {}", self.name, braced(&labels), count);
+                }
+            }
+        }
+    }
+}
+
+/// `name="value"` pairs, with values escaped
+fn label_pairs(names: &[&str], values: &[String]) -> Vec<String> {
+    names
+        .iter()
+        .zip(values)
+        .map(|(name, value)| {
+            let escaped = value.replace('\\', "\\\\").replace('"', "\\\"").replace('\n', "\\n");
+            format!("{}=\"{}\"", name, escaped)
+        })
+        .collect()
+}
+
+fn with_label(labels: &[String], name: &str, value: &str) -> Vec<String> {
+    let mut labels = labels.to_vec();
+    labels.push(format!("{}=\"{}\"", name, value));
+    labels
+}
+
+fn braced(labels: &[String]) -> String {
+    if labels.is_empty() {
+        String::new()
+    } else {
+        format!("{{{}}}", labels.join(","))
+    }
+}
+
+// ============================================================================
+// Agent Metrics
+// ============================================================================
+
+/// The metrics recorded by agents, providers and tools
+#[derive(Clone)]
+pub struct AgentMetrics {
+    /// Reasoning iterations per run, by outcome (`ok`, `error`)
+    pub iterations: Histogram,
+    
+    /// Tool calls by tool and outcome (`success`, `failure`, `error`)
+    pub tool_calls: Counter,
+    
+    pub tool_duration: Histogram,
+    
+    /// Completion latency by model and outcome (whole stream, for streams)
+    pub completion_duration: Histogram,
+    
+    pub time_to_first_token: Histogram,
+    
+    /// Tokens by model and kind (`prompt`, `completion`)
+    pub tokens: Counter,
+}
+
+impl AgentMetrics {
+    pub fn register(metrics: &Metrics) -> Self {
+        Self {
+            iterations: metrics.histogram(
+                "agent_iterations",
+                "Reasoning iterations per agent run",
+                &["outcome"],
+                COUNT_BUCKETS,
+            ),
+            tool_calls: metrics.counter("agent_tool_calls_total", "Tool calls", &["tool", "outcome"]),
+            tool_duration: metrics.histogram(
+                "agent_tool_duration_seconds",
+                "Tool call latency",
+                &["tool"],
+                LATENCY_BUCKETS,
+            ),
+            completion_duration: metrics.histogram(
+                "llm_completion_duration_seconds",
+                "Provider completion latency",
+                &["model", "outcome"],
+                LATENCY_BUCKETS,
+            ),
+            time_to_first_token: metrics.histogram(
+                "llm_time_to_first_token_seconds",
+                "Time until a streamed completion's first text",
+                &["model"],
+                LATENCY_BUCKETS,
+            ),
+            tokens: metrics.counter("llm_tokens_total", "Tokens processed by the provider", &["model", "kind"]),
+        }
+    }
+    
+    fn record_tokens(&self, model: &str, usage: TokenUsage) {
+        self.tokens.add(&[model, "prompt"], f64::from(usage.prompt_tokens));
+        self.tokens.add(&[model, "completion"], f64::from(usage.completion_tokens));
+    }
+}
+
+/// Model label of calls to models that weren't configured
+pub const OTHER_MODEL: &str = "other";
+
+fn outcome<T>(result: &Result<T>) -> &'static str {
+    if result.is_ok() { "ok" } else { "error" }
+}
+
+// ============================================================================
+// Decorators
+// ============================================================================
+
+/// `LlmProvider` decorator recording latency, time to first token and tokens
+///
+/// Wrap the retry layer (inside any cascade), so each model call is measured
+/// once, retries included, under the model that served it.
+pub struct MeteredProvider {
+    inner: Arc<dyn LlmProvider>,
+    metrics: AgentMetrics,
+    
+    /// Models labelled by name (empty = all)
+    models: HashSet<String>,
+}
+
+impl MeteredProvider {
+    pub fn new(inner: Arc<dyn LlmProvider>, metrics: AgentMetrics) -> Self {
+        Self { inner, metrics, models: HashSet::new() }
+    }
+    
+    /// Label only these models by name and count the rest as `other`
+    ///
+    /// Callers pick the model of a request, so without a list every made-up
+    /// name would add a new series.
+    pub fn with_models(mut self, models: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.models = models.into_iter().map(Into::into).collect();
+        self
+    }
+    
+    /// The label of a model (`llama3.2:1b` counts as `llama3.2` when only that is listed)
+    fn label<'a>(&self, model: &'a str) -> &'a str {
+        let base = model.split_once(':').map_or(model, |(base, _)| base);
+        if self.models.is_empty() || self.models.contains(model) || self.models.contains(base) {
+            model
+        } else {
+            OTHER_MODEL
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for MeteredProvider {
+    async fn info(&self) -> Result<ProviderInfo> {
+        self.inner.info().await
+    }
+    
+    async fn health_check(&self) -> Result<bool> {
+        self.inner.health_check().await
+    }
+    
+    async fn complete(
+        &self,
+        messages: &[Message],
+        options: &GenerationOptions,
+    ) -> Result<Completion> {
+        let span = tracing::info_span!("llm.complete", model = %options.model);
+        let started = Instant::now();
+        let result = self.inner.complete(messages, options).instrument(span).await;
+        
+        let model = self.label(result.as_ref().map_or(options.model.as_str(), |c| c.model.as_str()));
+        self.metrics.completion_duration.observe(&[model, outcome(&result)], started.elapsed().as_secs_f64());
+        if let Ok(Completion { usage: Some(usage), .. }) = &result {
+            self.metrics.record_tokens(model, *usage);
+        }
+        result
+    }
+    
+    async fn complete_stream(
+        &self,
+        messages: &[Message],
+        options: &GenerationOptions,
+    ) -> Result<CompletionStream> {
+        let span = tracing::info_span!("llm.stream", model = %options.model);
+        let started = Instant::now();
+        let metrics = self.metrics.clone();
+        let model = self.label(&options.model).to_string();
+        
+        let stream = match self.inner.complete_stream(messages, options).instrument(span).await {
+            Ok(stream) => stream,
+            Err(e) => {
+                metrics.completion_duration.observe(&[&model, "error"], started.elapsed().as_secs_f64());
+                return Err(e);
+            }
+        };
+        
+        let mut first_token = true;
+        Ok(Box::pin(stream.inspect(move |chunk| match chunk {
+            Ok(chunk) => {
+                if first_token && !chunk.delta.is_empty() {
+                    first_token = false;
+                    metrics.time_to_first_token.observe(&[&model], started.elapsed().as_secs_f64());
+                }
+                if let Some(usage) = chunk.usage {
+                    metrics.record_tokens(&model, usage);
+                }
+                if chunk.done {
+                    metrics.completion_duration.observe(&[&model, "ok"], started.elapsed().as_secs_f64());
+                }
+            }
+            Err(_) => metrics.completion_duration.observe(&[&model, "error"], started.elapsed().as_secs_f64()),
+        })))
+    }
+    
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+    
+    fn supports_json_schema(&self) -> bool {
+        self.inner.supports_json_schema()
+    }
+}
+
+/// `Tool` decorator counting calls and failures and timing them
+pub struct MeteredTool {
+    inner: Arc<dyn Tool>,
+    metrics: AgentMetrics,
+}
+
+impl MeteredTool {
+    pub fn new(inner: Arc<dyn Tool>, metrics: AgentMetrics) -> Self {
+        Self { inner, metrics }
+    }
+}
+
+#[async_trait]
+impl Tool for MeteredTool {
+    fn schema(&self) -> ToolSchema {
+        self.inner.schema()
+    }
+    
+    async fn execute(&self, call: &ToolCall) -> Result<ToolResult> {
+        let started = Instant::now();
+        let result = self.inner.execute(call).await;
+        
+        let outcome = match &result {
+            Ok(result) if result.success => "success",
+            Ok(_) => "failure",
+            Err(_) => "error",
+        };
+        self.metrics.tool_calls.inc(&[&call.name, outcome]);
+        self.metrics.tool_duration.observe(&[&call.name], started.elapsed().as_secs_f64());
+        result
+    }
+    
+    fn validate(&self, call: &ToolCall) -> Result<()> {
+        self.inner.validate(call)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::AgentError;
+    use crate::provider::{FinishReason, StreamChunk};
+    use crate::tool::{CalculatorTool, ToolRegistry};
+
+    struct FixedProvider;
+
+    #[async_trait]
+    impl LlmProvider for FixedProvider {
+        async fn info(&self) -> Result<ProviderInfo> {
+            Err(AgentError::Other("unused".into()))
+        }
+        
+        async fn health_check(&self) -> Result<bool> {
+            Ok(true)
+        }
+        
+        async fn complete(&self, _: &[Message], options: &GenerationOptions) -> Result<Completion> {
+            Ok(Completion {
+                content: "ok".into(),
+                model: options.model.clone(),
+                usage: Some(TokenUsage { prompt_tokens: 10, completion_tokens: 5, total_tokens: 15 }),
+                truncated: false,
+                finish_reason: Some(FinishReason::Stop),
+                escalations: Vec::new(),
+            })
+        }
+        
+        async fn complete_stream(&self, _: &[Message], _: &GenerationOptions) -> Result<CompletionStream> {
+            let usage = TokenUsage { prompt_tokens: 3, completion_tokens: 2, total_tokens: 5 };
+            Ok(Box::pin(futures::stream::iter([
+                Ok(StreamChunk { delta: "he".into(), ..StreamChunk::default() }),
+                Ok(StreamChunk { delta: "llo".into(), done: true, usage: Some(usage), ..StreamChunk::default() }),
+            ])))
+        }
+        
+        async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[test]
+    fn test_render_counter_and_histogram() {
+        let metrics = Metrics::new();
+        let requests = metrics.counter("requests_total", "Requests", &["path"]);
+        requests.inc(&["/a"]);
+        requests.add(&["/a"], 2.0);
+        requests.inc(&["say \"hi\""]);
+        
+        let latency = metrics.histogram("latency_seconds", "Latency", &[], &[0.1, 1.0]);
+        latency.observe(&[], 0.05);
+        latency.observe(&[], 0.5);
+        latency.observe(&[], 5.0);
+        
+        // Registering again returns the same family
+        metrics.counter("requests_total", "Requests", &["path"]).inc(&["/a"]);
+        
+        let text = metrics.render();
+        assert!(text.contains("# TYPE requests_total counter\n"));
+        assert!(text.contains("requests_total{path=\"/a\"} 4\n"));
+        assert!(text.contains("requests_total{path=\"say \\\"hi\\\"\"} 1\n"));
+        assert!(text.contains("# TYPE latency_seconds histogram\n"));
+        assert!(text.contains("latency_seconds_bucket{le=\"0.1\"} 1\n"));
+        assert!(text.contains("latency_seconds_bucket{le=\"1\"} 2\n"));
+        assert!(text.contains("latency_seconds_bucket{le=\"+Inf\"} 3\n"));
+        assert!(text.contains("latency_seconds_sum 5.55\n"));
+        assert!(text.contains("latency_seconds_count 3\n"));
+    }
+
+    #[tokio::test]
+    async fn test_metered_provider_and_tool() {
+        let metrics = Metrics::new();
+        let agent_metrics = AgentMetrics::register(&metrics);
+        let provider = MeteredProvider::new(Arc::new(FixedProvider), agent_metrics.clone());
+        let options = GenerationOptions { model: "small".into(), ..Default::default() };
+        
+        provider.complete(&[], &options).await.unwrap();
+        let chunks: Vec<_> = provider.complete_stream(&[], &options).await.unwrap().collect().await;
+        assert_eq!(chunks.len(), 2);
+        
+        let mut tools = ToolRegistry::new();
+        tools.register(CalculatorTool);
+        tools.wrap(|tool| Arc::new(MeteredTool::new(tool, agent_metrics.clone())));
+        let call: ToolCall = serde_json::from_str(r#"{"name": "calculate", "arguments": {"expression": "2 + 2"}}"#).unwrap();
+        assert!(tools.execute(&call).await.unwrap().success);
+        
+        let text = metrics.render();
+        assert!(text.contains("llm_completion_duration_seconds_count{model=\"small\",outcome=\"ok\"} 2\n"));
+        assert!(text.contains("llm_time_to_first_token_seconds_count{model=\"small\"} 1\n"));
+        assert!(text.contains("llm_tokens_total{model=\"small\",kind=\"prompt\"} 13\n"));
+        assert!(text.contains("llm_tokens_total{model=\"small\",kind=\"completion\"} 7\n"));
+        assert!(text.contains("agent_tool_calls_total{tool=\"calculate\",outcome=\"success\"} 1\n"));
+        assert!(text.contains("agent_tool_duration_seconds_count{tool=\"calculate\"} 1\n"));
+    }
+
+    #[tokio::test]
+    async fn test_unlisted_models_labelled_other() {
+        let metrics = Metrics::new();
+        let provider = MeteredProvider::new(Arc::new(FixedProvider), AgentMetrics::register(&metrics)).with_models(["small"]);
+        for model in ["small:q4", "made-up-1", "made-up-2"] {
+            let options = GenerationOptions { model: model.into(), ..Default::default() };
+            provider.complete(&[], &options).await.unwrap();
+            provider.complete_stream(&[], &options).await.unwrap().collect::<Vec<_>>().await;
+        }
+        
+        let text = metrics.render();
+        assert!(text.contains("llm_completion_duration_seconds_count{model=\"small:q4\",outcome=\"ok\"} 2\n"));
+        assert!(text.contains("llm_completion_duration_seconds_count{model=\"other\",outcome=\"ok\"} 4\n"));
+        assert!(text.contains("llm_tokens_total{model=\"other\",kind=\"prompt\"} 26\n"));
+        assert!(!text.contains("made-up"));
+    }
+}
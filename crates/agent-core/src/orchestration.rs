@@ -0,0 +1,540 @@
+//! Multi-Agent Orchestration
+//!
+//! Agents registered as tools of other agents. A planner delegates tasks to
+//! specialist agents, each with its own prompt and subset of the tool pool,
+//! and combines their answers.
+//!
+//! ```text
+//!             ┌──────────┐
+//!             │ planner  │  depth 0
+//!             └────┬─────┘
+//!        ┌─────────┼──────────┐
+//!   ┌────┴────┐ ┌──┴───┐ ┌────┴─────┐
+//!   │ market  │ │ risk │ │portfolio │  depth 1
+//!   └─────────┘ └──────┘ └──────────┘
+//! ```
+//!
+//! All agents in one run draw from a shared `Budget`, delegation stops at
+//! `max_depth` (so agents may delegate to each other without looping forever),
+//! and every sub-agent run is recorded as an `AgentTrace`.
+//!
+//! ## Usage
+//!
+//! ```rust,ignore
+//! let orchestrator = Arc::new(
+//!     Orchestrator::new(provider, Arc::new(tools))
+//!         .agent(AgentSpec::new("risk", "Assesses portfolio risk", RISK_PROMPT).tools(["risk_analyzer"]))
+//!         .agent(AgentSpec::new("planner", "Plans investments", PLANNER_PROMPT).delegates(["risk"]))
+//!         .max_tokens(50_000),
+//! );
+//! let run = orchestrator.run("planner", "Should I put $1000 into SOL?").await?;
+//! ```
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use serde::Serialize;
+
+use crate::error::{AgentError, Result};
+use crate::guardrail::Guardrails;
+use crate::metrics::AgentMetrics;
+use crate::provider::{GenerationOptions, LlmProvider, TokenUsage};
+use crate::reasoning::{Agent, AgentConfig, AgentRun, TraceStep};
+use crate::tool::{ParameterSchema, Tool, ToolCall, ToolRegistry, ToolResult, ToolSchema};
+
+/// Token and delegation budget shared by every agent in a run
+#[derive(Debug, Default)]
+pub struct Budget {
+    max_tokens: Option<u32>,
+    max_delegations: Option<usize>,
+    used: Mutex<TokenUsage>,
+    delegations: AtomicUsize,
+}
+
+impl Budget {
+    /// Unlimited budget (usage is still accounted)
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    pub fn max_tokens(mut self, tokens: u32) -> Self {
+        self.max_tokens = Some(tokens);
+        self
+    }
+    
+    pub fn max_delegations(mut self, delegations: usize) -> Self {
+        self.max_delegations = Some(delegations);
+        self
+    }
+    
+    /// Fail if the token budget is spent (checked before each completion)
+    pub fn check(&self) -> Result<()> {
+        match self.max_tokens {
+            Some(max) if self.used().total_tokens >= max => {
+                Err(AgentError::BudgetExceeded(format!("{} token budget spent", max)))
+            }
+            _ => Ok(()),
+        }
+    }
+    
+    /// Account for a completion's usage
+    pub fn charge(&self, usage: TokenUsage) {
+        *self.used.lock().unwrap() += usage;
+    }
+    
+    /// Tokens used so far
+    pub fn used(&self) -> TokenUsage {
+        *self.used.lock().unwrap()
+    }
+    
+    /// Tokens left, if limited
+    pub fn remaining_tokens(&self) -> Option<u32> {
+        self.max_tokens.map(|max| max.saturating_sub(self.used().total_tokens))
+    }
+    
+    /// Reserve one delegation
+    fn start_delegation(&self) -> Result<()> {
+        let count = self.delegations.fetch_add(1, Ordering::SeqCst) + 1;
+        match self.max_delegations {
+            Some(max) if count > max => {
+                Err(AgentError::BudgetExceeded(format!("limit of {} delegations reached", max)))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Definition of an agent that can run as a root or be delegated to
+#[derive(Clone, Debug)]
+pub struct AgentSpec {
+    /// Tool name other agents call it by
+    pub name: String,
+    
+    /// What the agent is good at (shown to delegating agents)
+    pub description: String,
+    
+    /// System prompt
+    pub system_prompt: String,
+    
+    /// Tools from the orchestrator's pool (missing tools are skipped)
+    pub tools: Vec<String>,
+    
+    /// Agents this agent may delegate to
+    pub delegates: Vec<String>,
+    
+    /// Model override (defaults to the orchestrator's model)
+    pub model: Option<String>,
+    
+    /// Reasoning iterations allowed
+    pub max_iterations: usize,
+}
+
+impl AgentSpec {
+    pub fn new(name: impl Into<String>, description: impl Into<String>, system_prompt: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            system_prompt: system_prompt.into(),
+            tools: Vec::new(),
+            delegates: Vec::new(),
+            model: None,
+            max_iterations: AgentConfig::default().max_iterations,
+        }
+    }
+    
+    pub fn tools<I, S>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tools = tools.into_iter().map(Into::into).collect();
+        self
+    }
+    
+    pub fn delegates<I, S>(mut self, delegates: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.delegates = delegates.into_iter().map(Into::into).collect();
+        self
+    }
+    
+    pub fn model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+    
+    pub fn max_iterations(mut self, max: usize) -> Self {
+        self.max_iterations = max;
+        self
+    }
+}
+
+/// One sub-agent run
+#[derive(Clone, Debug, Serialize)]
+pub struct AgentTrace {
+    /// Sub-agent name
+    pub agent: String,
+    
+    /// Agent that delegated the task
+    pub parent: String,
+    
+    /// Delegation depth (the root agent is 0)
+    pub depth: usize,
+    
+    /// Task it was given
+    pub task: String,
+    
+    /// Its answer, if it completed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub answer: Option<String>,
+    
+    /// Why it failed, if it did
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    
+    /// Tokens used by this agent alone (its own sub-agents are traced separately)
+    pub usage: TokenUsage,
+    
+    /// Its reasoning steps
+    pub trace: Vec<TraceStep>,
+}
+
+/// Outcome of an orchestrated run
+#[derive(Clone, Debug)]
+pub struct OrchestratedRun {
+    /// The root agent's run
+    pub run: AgentRun,
+    
+    /// Tokens used by all agents
+    pub usage: TokenUsage,
+    
+    /// Sub-agent runs, in order of completion
+    pub agents: Vec<AgentTrace>,
+}
+
+/// State shared by the agents of one run
+struct RunState {
+    budget: Arc<Budget>,
+    traces: Mutex<Vec<AgentTrace>>,
+}
+
+/// Builds agent trees from specs and runs them
+pub struct Orchestrator {
+    provider: Arc<dyn LlmProvider>,
+    tools: Arc<ToolRegistry>,
+    specs: HashMap<String, AgentSpec>,
+    generation: GenerationOptions,
+    max_depth: usize,
+    max_tokens: Option<u32>,
+    max_delegations: Option<usize>,
+    guardrails: Option<Arc<Guardrails>>,
+    metrics: Option<AgentMetrics>,
+}
+
+impl Orchestrator {
+    /// Create with the pool of tools agents may be given
+    pub fn new(provider: Arc<dyn LlmProvider>, tools: Arc<ToolRegistry>) -> Self {
+        Self {
+            provider,
+            tools,
+            specs: HashMap::new(),
+            generation: GenerationOptions::default(),
+            max_depth: 2,
+            max_tokens: None,
+            max_delegations: None,
+            guardrails: None,
+            metrics: None,
+        }
+    }
+    
+    /// Register an agent
+    pub fn agent(mut self, spec: AgentSpec) -> Self {
+        self.specs.insert(spec.name.clone(), spec);
+        self
+    }
+    
+    /// Generation options for every agent (per-agent models override `model`)
+    pub fn generation(mut self, generation: GenerationOptions) -> Self {
+        self.generation = generation;
+        self
+    }
+    
+    /// Deepest delegation level; agents at this depth get no sub-agents
+    pub fn max_depth(mut self, depth: usize) -> Self {
+        self.max_depth = depth;
+        self
+    }
+    
+    /// Tokens all agents in one run may use together
+    pub fn max_tokens(mut self, tokens: u32) -> Self {
+        self.max_tokens = Some(tokens);
+        self
+    }
+    
+    /// Sub-agent runs allowed in one run
+    pub fn max_delegations(mut self, delegations: usize) -> Self {
+        self.max_delegations = Some(delegations);
+        self
+    }
+    
+    /// Guardrails for the root agent's final answer
+    pub fn guardrails(mut self, guardrails: Arc<Guardrails>) -> Self {
+        self.guardrails = Some(guardrails);
+        self
+    }
+    
+    /// Record reasoning iterations of every agent run, sub-agents included
+    pub fn metrics(mut self, metrics: AgentMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+    
+    /// Run `root` on a question with a fresh budget
+    pub async fn run(self: &Arc<Self>, root: &str, question: &str) -> Result<OrchestratedRun> {
+        let mut budget = Budget::new();
+        if let Some(max) = self.max_tokens {
+            budget = budget.max_tokens(max);
+        }
+        if let Some(max) = self.max_delegations {
+            budget = budget.max_delegations(max);
+        }
+        
+        let state = Arc::new(RunState {
+            budget: Arc::new(budget),
+            traces: Mutex::new(Vec::new()),
+        });
+        
+        let run = self.build(root, 0, &state)?.ask_detailed(question).await?;
+        
+        Ok(OrchestratedRun {
+            run,
+            usage: state.budget.used(),
+            agents: std::mem::take(&mut *state.traces.lock().unwrap()),
+        })
+    }
+    
+    /// Build an agent at `depth`, with sub-agents as tools while below `max_depth`
+    fn build(self: &Arc<Self>, name: &str, depth: usize, state: &Arc<RunState>) -> Result<Agent> {
+        let spec = self.spec(name)?;
+        
+        let mut tools = ToolRegistry::new();
+        for tool in spec.tools.iter().filter_map(|t| self.tools.get(t)) {
+            tools.register_boxed(tool);
+        }
+        
+        if depth < self.max_depth {
+            for delegate in &spec.delegates {
+                self.spec(delegate)?;
+                tools.register(AgentTool {
+                    orchestrator: self.clone(),
+                    agent: delegate.clone(),
+                    parent: spec.name.clone(),
+                    depth: depth + 1,
+                    state: state.clone(),
+                });
+            }
+        }
+        
+        let config = AgentConfig {
+            system_prompt: spec.system_prompt.clone(),
+            max_iterations: spec.max_iterations,
+            generation: GenerationOptions {
+                model: spec.model.clone().unwrap_or_else(|| self.generation.model.clone()),
+                ..self.generation.clone()
+            },
+            ..Default::default()
+        };
+        
+        let mut agent = Agent::new(self.provider.clone(), Arc::new(tools), config).with_budget(state.budget.clone());
+        if let (0, Some(guardrails)) = (depth, &self.guardrails) {
+            agent = agent.with_guardrails(guardrails.clone());
+        }
+        if let Some(metrics) = &self.metrics {
+            agent = agent.with_metrics(metrics.clone());
+        }
+        Ok(agent)
+    }
+    
+    fn spec(&self, name: &str) -> Result<&AgentSpec> {
+        self.specs
+            .get(name)
+            .ok_or_else(|| AgentError::Config(format!("Unknown agent: {}", name)))
+    }
+}
+
+/// A sub-agent exposed as a tool
+struct AgentTool {
+    orchestrator: Arc<Orchestrator>,
+    agent: String,
+    parent: String,
+    depth: usize,
+    state: Arc<RunState>,
+}
+
+#[async_trait]
+impl Tool for AgentTool {
+    fn schema(&self) -> ToolSchema {
+        let description = self
+            .orchestrator
+            .specs
+            .get(&self.agent)
+            .map(|spec| spec.description.clone())
+            .unwrap_or_default();
+        
+        ToolSchema {
+            name: self.agent.clone(),
+            description: format!("Delegate to the {} agent: {}", self.agent, description),
+            parameters: vec![ParameterSchema {
+                name: "task".into(),
+                param_type: "string".into(),
+                description: "Self-contained task, including any figures it needs".into(),
+                required: true,
+                default: None,
+                enum_values: None,
+            }],
+            category: Some("agent".into()),
+            has_side_effects: false,
+        }
+    }
+    
+    async fn execute(&self, call: &ToolCall) -> Result<ToolResult> {
+        let task = call.arguments.get("task").and_then(|v| v.as_str()).unwrap_or("").trim();
+        if task.is_empty() {
+            return Ok(ToolResult::failure(&self.agent, "A non-empty 'task' is required"));
+        }
+        
+        if let Err(e) = self.state.budget.start_delegation() {
+            return Ok(ToolResult::failure(&self.agent, e.to_string()));
+        }
+        
+        tracing::debug!(agent = %self.agent, parent = %self.parent, depth = self.depth, "Delegating");
+        let agent = self.orchestrator.build(&self.agent, self.depth, &self.state)?;
+        let outcome = agent.ask_detailed(task).await;
+        
+        let mut trace = AgentTrace {
+            agent: self.agent.clone(),
+            parent: self.parent.clone(),
+            depth: self.depth,
+            task: task.to_string(),
+            answer: None,
+            error: None,
+            usage: TokenUsage::default(),
+            trace: Vec::new(),
+        };
+        
+        let result = match outcome {
+            Ok(run) => {
+                trace.answer = Some(run.content.clone());
+                trace.usage = run.usage;
+                trace.trace = run.trace;
+                ToolResult::success(&self.agent, run.content)
+                    .with_data(serde_json::json!({ "agent": self.agent, "usage": run.usage }))
+            }
+            Err(e) => {
+                trace.error = Some(e.to_string());
+                ToolResult::failure(&self.agent, format!("Agent '{}' failed: {}", self.agent, e))
+            }
+        };
+        
+        self.state.traces.lock().unwrap().push(trace);
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::message::{Message, Role};
+    use crate::provider::{Completion, CompletionStream, FinishReason, ModelInfo, ProviderInfo};
+
+    /// Provider replaying a script per agent, keyed by the agent's system prompt
+    struct TeamProvider {
+        scripts: Mutex<HashMap<&'static str, Vec<&'static str>>>,
+    }
+
+    #[async_trait]
+    impl LlmProvider for TeamProvider {
+        async fn info(&self) -> Result<ProviderInfo> {
+            Err(AgentError::Other("unused".into()))
+        }
+        
+        async fn health_check(&self) -> Result<bool> {
+            Ok(true)
+        }
+        
+        async fn complete(&self, messages: &[Message], options: &GenerationOptions) -> Result<Completion> {
+            let system = messages.iter().find(|m| m.role == Role::System).map(|m| m.content.as_str()).unwrap_or("");
+            let mut scripts = self.scripts.lock().unwrap();
+            let (_, script) = scripts
+                .iter_mut()
+                .find(|(prompt, _)| system.starts_with(**prompt))
+                .ok_or_else(|| AgentError::Provider("no script".into()))?;
+            let content = script.remove(0).to_string();
+            Ok(Completion {
+                content,
+                model: options.model.clone(),
+                usage: Some(TokenUsage { prompt_tokens: 10, completion_tokens: 10, total_tokens: 20 }),
+                truncated: false,
+                finish_reason: Some(FinishReason::Stop),
+                escalations: Vec::new(),
+            })
+        }
+        
+        async fn complete_stream(&self, _: &[Message], _: &GenerationOptions) -> Result<CompletionStream> {
+            Err(AgentError::Other("unused".into()))
+        }
+        
+        async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn team(scripts: &[(&'static str, &[&'static str])]) -> Orchestrator {
+        let provider = TeamProvider {
+            scripts: Mutex::new(scripts.iter().map(|(prompt, script)| (*prompt, script.to_vec())).collect()),
+        };
+        Orchestrator::new(Arc::new(provider), Arc::new(ToolRegistry::new()))
+            .agent(AgentSpec::new("planner", "Plans", "PLANNER").delegates(["risk"]))
+            .agent(AgentSpec::new("risk", "Assesses risk", "RISK").delegates(["planner"]))
+    }
+
+    #[tokio::test]
+    async fn test_planner_delegates_and_traces_sub_agent() {
+        let orchestrator = Arc::new(team(&[
+            ("PLANNER", &["```tool\n{\"tool\": \"risk\", \"arguments\": {\"task\": \"Rate SOL risk\"}}\n```", "SOL is high risk; keep it under 10%."]),
+            ("RISK", &["SOL is high risk."]),
+        ]));
+        
+        let result = orchestrator.run("planner", "Should I buy SOL?").await.unwrap();
+        
+        assert_eq!(result.run.content, "SOL is high risk; keep it under 10%.");
+        assert_eq!(result.usage.total_tokens, 60);
+        assert_eq!(result.agents.len(), 1);
+        assert_eq!(result.agents[0].agent, "risk");
+        assert_eq!(result.agents[0].task, "Rate SOL risk");
+        assert_eq!(result.agents[0].usage.total_tokens, 20);
+    }
+
+    #[tokio::test]
+    async fn test_depth_and_budget_limits() {
+        // At max depth the risk agent has no planner tool, so its call fails as unknown
+        let orchestrator = Arc::new(team(&[
+            ("PLANNER", &["```tool\n{\"tool\": \"risk\", \"arguments\": {\"task\": \"Rate SOL\"}}\n```", "Done."]),
+            ("RISK", &["```tool\n{\"tool\": \"planner\", \"arguments\": {\"task\": \"Loop\"}}\n```", "High."]),
+        ]).max_depth(1));
+        let result = orchestrator.run("planner", "SOL?").await.unwrap();
+        assert_eq!(result.agents[0].answer.as_deref(), Some("High."));
+        
+        // The planner's second completion exceeds the shared budget
+        let orchestrator = Arc::new(team(&[
+            ("PLANNER", &["```tool\n{\"tool\": \"risk\", \"arguments\": {\"task\": \"Rate SOL\"}}\n```", "Done."]),
+            ("RISK", &["High."]),
+        ]).max_tokens(40));
+        let err = orchestrator.run("planner", "SOL?").await.unwrap_err();
+        assert!(matches!(err, AgentError::BudgetExceeded(_)));
+    }
+}
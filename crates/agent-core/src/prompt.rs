@@ -0,0 +1,513 @@
+//! Prompt Templates
+//!
+//! Versioned system prompt templates, rendered per request with variables
+//! (user name, risk profile, date, tool list) and shared partials.
+//!
+//! ## Syntax
+//!
+//! ```text
+//! {{name}}               variable (empty if unset)
+//! {{name|fallback}}      variable with a default
+//! {{#name}}...{{/name}}  section, rendered only if `name` is set
+//! {{> partial}}          another registered partial
+//! ```
+//!
+//! ## Files
+//!
+//! Built-in templates can be overridden or extended from a directory:
+//!
+//! ```text
+//! prompts/
+//!   crypto_advisor/v1.md        template `crypto_advisor`, version `v1`
+//!   crypto_advisor/v2.md
+//!   crypto_advisor/variants.json  {"v1": 50, "v2": 50} - A/B split (optional)
+//!   partials/user_context.md    partial `user_context`
+//! ```
+//!
+//! Without a split, the highest version is active; numbers in version names
+//! compare numerically, so `v10` comes after `v9`. `reload_if_changed`
+//! re-reads the directory when any file was added, removed or modified.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use std::time::SystemTime;
+
+use serde::Serialize;
+
+use crate::error::{AgentError, Result};
+use crate::tool::ToolRegistry;
+
+/// Version id of templates compiled into the binary
+pub const BUILTIN_VERSION: &str = "builtin";
+
+/// Maximum partial nesting depth
+const MAX_DEPTH: usize = 8;
+
+/// Generic tool-using assistant prompt (used when no template is given)
+pub const DEFAULT_TEMPLATE: &str = r#"You are a helpful AI assistant.
+
+{{> user_context}}
+
+When you need to use a tool, respond with a JSON block in this exact format:
+```tool
+{"tool": "tool_name", "arguments": {"arg1": "value1"}}
+```
+
+After receiving tool results, synthesize them into a helpful response.
+If you can answer directly without tools, do so.
+Be concise and accurate."#;
+
+/// Per-request context shared by the built-in templates
+const USER_CONTEXT_PARTIAL: &str = "Today's date is {{date}}.{{#user_name}} You are speaking with {{user_name}}.{{/user_name}}{{#risk_profile}} Their stated risk profile is {{risk_profile}}.{{/risk_profile}}";
+
+/// A versioned prompt template
+#[derive(Clone, Debug)]
+pub struct PromptTemplate {
+    pub name: String,
+    pub version: String,
+    pub body: String,
+}
+
+impl PromptTemplate {
+    pub fn new(name: impl Into<String>, version: impl Into<String>, body: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            version: version.into(),
+            body: body.into(),
+        }
+    }
+}
+
+/// A rendered prompt and the template version that produced it
+#[derive(Clone, Debug, Serialize)]
+pub struct RenderedPrompt {
+    pub name: String,
+    pub version: String,
+    pub text: String,
+}
+
+impl RenderedPrompt {
+    /// `name@version`, recorded on sessions for A/B comparisons
+    pub fn id(&self) -> String {
+        format!("{}@{}", self.name, self.version)
+    }
+}
+
+/// Template variables
+#[derive(Clone, Debug)]
+pub struct PromptVars {
+    values: BTreeMap<String, String>,
+}
+
+impl Default for PromptVars {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PromptVars {
+    /// Variables with `date` set to today (UTC)
+    pub fn new() -> Self {
+        let mut values = BTreeMap::new();
+        values.insert("date".into(), chrono::Utc::now().format("%Y-%m-%d").to_string());
+        Self { values }
+    }
+    
+    /// Set a variable (empty values count as unset)
+    pub fn set(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.values.insert(key.into(), value.into());
+        self
+    }
+    
+    /// Set a variable if a value is given
+    pub fn set_opt(self, key: impl Into<String>, value: Option<impl Into<String>>) -> Self {
+        match value {
+            Some(value) => self.set(key, value),
+            None => self,
+        }
+    }
+    
+    pub fn user_name(self, name: impl Into<String>) -> Self {
+        self.set("user_name", name)
+    }
+    
+    pub fn risk_profile(self, profile: impl Into<String>) -> Self {
+        self.set("risk_profile", profile)
+    }
+    
+    /// Set `tools` to the sorted, comma-separated tool names
+    pub fn tools(self, tools: &ToolRegistry) -> Self {
+        let mut names = tools.names();
+        names.sort_unstable();
+        self.set("tools", names.join(", "))
+    }
+    
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str).filter(|v| !v.is_empty())
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct Templates {
+    /// Versions per template name, in registration order
+    versions: HashMap<String, Vec<PromptTemplate>>,
+    partials: HashMap<String, String>,
+    /// A/B weights per template name
+    splits: HashMap<String, Vec<(String, u32)>>,
+}
+
+impl Templates {
+    fn add(&mut self, template: PromptTemplate) {
+        let versions = self.versions.entry(template.name.clone()).or_default();
+        versions.retain(|t| t.version != template.version);
+        versions.push(template);
+    }
+}
+
+/// Registry of prompt templates and partials
+pub struct PromptRegistry {
+    /// Templates registered in code, kept across reloads
+    builtin: RwLock<Templates>,
+    /// Builtins overlaid with the prompt directory
+    current: RwLock<Templates>,
+    dir: Option<PathBuf>,
+    fingerprint: RwLock<Vec<(PathBuf, SystemTime)>>,
+}
+
+impl Default for PromptRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PromptRegistry {
+    /// Registry with the built-in `default` template and `user_context` partial
+    pub fn new() -> Self {
+        let registry = Self {
+            builtin: RwLock::new(Templates::default()),
+            current: RwLock::new(Templates::default()),
+            dir: None,
+            fingerprint: RwLock::new(Vec::new()),
+        };
+        registry.register(PromptTemplate::new("default", BUILTIN_VERSION, DEFAULT_TEMPLATE));
+        registry.register_partial("user_context", USER_CONTEXT_PARTIAL);
+        registry
+    }
+    
+    /// Load (and later reload) templates from `dir`
+    ///
+    /// Files take precedence over versions registered in code, so register
+    /// built-in templates first.
+    pub fn load_dir(&mut self, dir: impl Into<PathBuf>) -> Result<()> {
+        self.dir = Some(dir.into());
+        self.reload()
+    }
+    
+    /// Register a template version; it becomes the active version unless an A/B split is set
+    pub fn register(&self, template: PromptTemplate) {
+        self.builtin.write().unwrap().add(template.clone());
+        self.current.write().unwrap().add(template);
+    }
+    
+    /// Register a partial
+    pub fn register_partial(&self, name: impl Into<String>, body: impl Into<String>) {
+        let (name, body) = (name.into(), body.into());
+        self.builtin.write().unwrap().partials.insert(name.clone(), body.clone());
+        self.current.write().unwrap().partials.insert(name, body);
+    }
+    
+    /// Split traffic for a template between versions, by weight
+    pub fn set_split(&self, name: impl Into<String>, weights: Vec<(String, u32)>) {
+        let name = name.into();
+        self.builtin.write().unwrap().splits.insert(name.clone(), weights.clone());
+        self.current.write().unwrap().splits.insert(name, weights);
+    }
+    
+    /// Template names
+    pub fn names(&self) -> Vec<String> {
+        self.current.read().unwrap().versions.keys().cloned().collect()
+    }
+    
+    /// Versions of a template, in registration order
+    pub fn versions(&self, name: &str) -> Vec<String> {
+        self.current
+            .read()
+            .unwrap()
+            .versions
+            .get(name)
+            .map(|v| v.iter().map(|t| t.version.clone()).collect())
+            .unwrap_or_default()
+    }
+    
+    /// Render a template for a request
+    ///
+    /// `version` pins a version; otherwise `bucket` (a user or conversation ID)
+    /// picks one from the A/B split, so the same user keeps the same variant.
+    /// Without a split the latest version is used.
+    pub fn render(
+        &self,
+        name: &str,
+        version: Option<&str>,
+        bucket: Option<&str>,
+        vars: &PromptVars,
+    ) -> Result<RenderedPrompt> {
+        let templates = self.current.read().unwrap();
+        let versions = templates
+            .versions
+            .get(name)
+            .filter(|v| !v.is_empty())
+            .ok_or_else(|| AgentError::Config(format!("Unknown prompt template: {}", name)))?;
+        
+        let version = version
+            .map(str::to_string)
+            .or_else(|| templates.splits.get(name).and_then(|split| pick_variant(split, bucket.unwrap_or(""))));
+        
+        let template = match version {
+            Some(version) => versions
+                .iter()
+                .find(|t| t.version == version)
+                .ok_or_else(|| AgentError::Config(format!("Unknown prompt version: {}@{}", name, version)))?,
+            None => versions.last().expect("non-empty"),
+        };
+        
+        Ok(RenderedPrompt {
+            name: template.name.clone(),
+            version: template.version.clone(),
+            text: render_str(&template.body, vars, &templates.partials, 0)?.trim().to_string(),
+        })
+    }
+    
+    /// Re-read the prompt directory, replacing previously loaded files
+    pub fn reload(&self) -> Result<()> {
+        let Some(dir) = &self.dir else {
+            return Ok(());
+        };
+        
+        let mut templates = self.builtin.read().unwrap().clone();
+        let files = scan(dir)?;
+        let mut loaded = Vec::new();
+        
+        for (path, _) in &files {
+            let relative = path.strip_prefix(dir).unwrap_or(path);
+            let parts: Vec<_> = relative.iter().map(|p| p.to_string_lossy().into_owned()).collect();
+            let [folder, file] = parts.as_slice() else {
+                continue;
+            };
+            
+            let read = || std::fs::read_to_string(path).map_err(AgentError::Io);
+            match (folder.as_str(), Path::new(file).extension().and_then(|e| e.to_str())) {
+                ("partials", Some("md" | "txt")) => {
+                    templates.partials.insert(file_stem(file), read()?);
+                }
+                (_, Some("md" | "txt")) => {
+                    loaded.push(PromptTemplate::new(folder.clone(), file_stem(file), read()?));
+                }
+                (_, Some("json")) if file == "variants.json" => {
+                    let weights: BTreeMap<String, u32> = serde_json::from_str(&read()?)
+                        .map_err(|e| AgentError::Config(format!("Invalid {}: {}", path.display(), e)))?;
+                    templates.splits.insert(folder.clone(), weights.into_iter().collect());
+                }
+                _ => {}
+            }
+        }
+        
+        // Added in version order, so the highest one ends up active
+        loaded.sort_by_cached_key(|t| version_key(&t.version));
+        for template in loaded {
+            templates.add(template);
+        }
+        
+        // A split naming a missing version would fail every render; keep the old templates
+        for (name, split) in &templates.splits {
+            let known = templates.versions.get(name);
+            if let Some((version, _)) = split.iter().find(|(v, _)| !known.is_some_and(|k| k.iter().any(|t| &t.version == v))) {
+                return Err(AgentError::Config(format!("Split for {} names unknown version {}", name, version)));
+            }
+        }
+        
+        *self.current.write().unwrap() = templates;
+        *self.fingerprint.write().unwrap() = files;
+        Ok(())
+    }
+    
+    /// Reload if any file in the prompt directory changed
+    ///
+    /// On error the previous templates stay active.
+    pub fn reload_if_changed(&self) -> Result<bool> {
+        let Some(dir) = &self.dir else {
+            return Ok(false);
+        };
+        
+        if scan(dir)? == *self.fingerprint.read().unwrap() {
+            return Ok(false);
+        }
+        
+        self.reload()?;
+        Ok(true)
+    }
+}
+
+/// Files under `dir` (one level of folders) with modification times, sorted by path
+fn scan(dir: &Path) -> Result<Vec<(PathBuf, SystemTime)>> {
+    let mut files = Vec::new();
+    if !dir.is_dir() {
+        return Ok(files);
+    }
+    
+    for folder in std::fs::read_dir(dir)? {
+        let folder = folder?.path();
+        if !folder.is_dir() {
+            continue;
+        }
+        for file in std::fs::read_dir(&folder)? {
+            let file = file?;
+            let metadata = file.metadata()?;
+            if metadata.is_file() {
+                files.push((file.path(), metadata.modified()?));
+            }
+        }
+    }
+    
+    files.sort();
+    Ok(files)
+}
+
+/// Sort key comparing the numbers in a version name numerically (`v2` < `v10`, `v1.2` < `v1.10`)
+fn version_key(version: &str) -> (Vec<u64>, String) {
+    let numbers = version
+        .split(|c: char| !c.is_ascii_digit())
+        .filter(|part| !part.is_empty())
+        .map(|part| part.parse().unwrap_or(u64::MAX))
+        .collect();
+    (numbers, version.to_string())
+}
+
+fn file_stem(file: &str) -> String {
+    Path::new(file).file_stem().map_or_else(|| file.to_string(), |s| s.to_string_lossy().into_owned())
+}
+
+/// Pick a version by weight, stable for a given bucket key
+fn pick_variant(split: &[(String, u32)], bucket: &str) -> Option<String> {
+    let total: u64 = split.iter().map(|(_, w)| u64::from(*w)).sum();
+    if total == 0 {
+        return None;
+    }
+    
+    // FNV-1a, stable across builds unlike `DefaultHasher`
+    let hash = bucket.bytes().fold(0xcbf2_9ce4_8422_2325u64, |h, b| (h ^ u64::from(b)).wrapping_mul(0x0100_0000_01b3));
+    let mut point = hash % total;
+    for (version, weight) in split {
+        if point < u64::from(*weight) {
+            return Some(version.clone());
+        }
+        point -= u64::from(*weight);
+    }
+    None
+}
+
+fn render_str(template: &str, vars: &PromptVars, partials: &HashMap<String, String>, depth: usize) -> Result<String> {
+    if depth > MAX_DEPTH {
+        return Err(AgentError::Config("Prompt partials nested too deeply".into()));
+    }
+    
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| AgentError::Parse("Unclosed '{{' in prompt template".into()))?;
+        let tag = after[..end].trim();
+        rest = &after[end + 2..];
+        
+        if let Some(name) = tag.strip_prefix('>') {
+            let name = name.trim();
+            let partial = partials
+                .get(name)
+                .ok_or_else(|| AgentError::Config(format!("Unknown prompt partial: {}", name)))?;
+            out.push_str(&render_str(partial, vars, partials, depth + 1)?);
+        } else if let Some(name) = tag.strip_prefix('#') {
+            let name = name.trim();
+            let close = format!("{{{{/{}}}}}", name);
+            let body_end = rest
+                .find(&close)
+                .ok_or_else(|| AgentError::Parse(format!("Unclosed section '{}' in prompt template", name)))?;
+            if vars.get(name).is_some() {
+                out.push_str(&render_str(&rest[..body_end], vars, partials, depth + 1)?);
+            }
+            rest = &rest[body_end + close.len()..];
+        } else if tag.starts_with('/') {
+            return Err(AgentError::Parse(format!("Unexpected '{{{{{}}}}}' in prompt template", tag)));
+        } else {
+            let (name, fallback) = tag.split_once('|').unwrap_or((tag, ""));
+            out.push_str(vars.get(name.trim()).unwrap_or(fallback.trim()));
+        }
+    }
+    
+    out.push_str(rest);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_variables_sections_and_partials() {
+        let registry = PromptRegistry::new();
+        registry.register(PromptTemplate::new("advisor", "v1", "{{> user_context}}\nTools: {{tools|none}}"));
+        
+        let vars = PromptVars::new().set("date", "2026-01-02").user_name("Ada");
+        let prompt = registry.render("advisor", None, None, &vars).unwrap();
+        
+        assert_eq!(prompt.id(), "advisor@v1");
+        assert_eq!(prompt.text, "Today's date is 2026-01-02. You are speaking with Ada.\nTools: none");
+        
+        registry.register(PromptTemplate::new("broken", "v1", "{{> missing}}"));
+        assert!(registry.render("broken", None, None, &vars).is_err());
+    }
+
+    #[test]
+    fn test_load_dir_split_and_reload() {
+        let dir = std::env::temp_dir().join(format!("prompts-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("advisor")).unwrap();
+        std::fs::write(dir.join("advisor/v1.md"), "Version one").unwrap();
+        std::fs::write(dir.join("advisor/v2.md"), "Version two").unwrap();
+        
+        let mut registry = PromptRegistry::new();
+        registry.register(PromptTemplate::new("advisor", BUILTIN_VERSION, "Built in"));
+        registry.load_dir(&dir).unwrap();
+        let vars = PromptVars::new();
+        assert_eq!(registry.render("advisor", None, None, &vars).unwrap().version, "v2");
+        assert_eq!(registry.render("advisor", Some("v1"), None, &vars).unwrap().text, "Version one");
+        
+        // A 50/50 split is stable per bucket and serves both versions
+        std::fs::write(dir.join("advisor/variants.json"), r#"{"v1": 50, "v2": 50}"#).unwrap();
+        assert!(registry.reload_if_changed().unwrap());
+        let pick = |user: &str| registry.render("advisor", None, Some(user), &vars).unwrap().version;
+        assert_eq!(pick("user-1"), pick("user-1"));
+        let served: std::collections::HashSet<_> = (0..20).map(|i| pick(&format!("user-{}", i))).collect();
+        assert_eq!(served.len(), 2);
+        
+        assert!(!registry.reload_if_changed().unwrap());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[test]
+    fn test_versions_compare_numerically() {
+        let dir = std::env::temp_dir().join(format!("prompts-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(dir.join("advisor")).unwrap();
+        for version in ["v1", "v2", "v10", "v9"] {
+            std::fs::write(dir.join(format!("advisor/{version}.md")), version).unwrap();
+        }
+        
+        let mut registry = PromptRegistry::new();
+        registry.load_dir(&dir).unwrap();
+        assert_eq!(registry.versions("advisor"), ["v1", "v2", "v9", "v10"]);
+        assert_eq!(registry.render("advisor", None, None, &PromptVars::new()).unwrap().version, "v10");
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
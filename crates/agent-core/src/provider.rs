@@ -48,6 +48,10 @@ pub struct GenerationOptions {
     /// System prompt override (if provider supports it separately)
     #[serde(default)]
     pub system_prompt: Option<String>,
+    
+    /// JSON schema the response must conform to (structured output mode)
+    #[serde(default)]
+    pub response_format: Option<serde_json::Value>,
 }
 
 fn default_temperature() -> f32 { 0.7 }
@@ -63,6 +67,7 @@ impl Default for GenerationOptions {
             top_p: default_top_p(),
             stop_sequences: Vec::new(),
             system_prompt: None,
+            response_format: None,
         }
     }
 }
@@ -84,16 +89,38 @@ pub struct Completion {
     
     /// Finish reason
     pub finish_reason: Option<FinishReason>,
+    
+    /// Attempts rejected before `model` answered (model cascades)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub escalations: Vec<Escalation>,
 }
 
-/// Token usage statistics
+/// A cascade attempt whose answer was rejected by a verifier rule
 #[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Escalation {
+    /// Model whose answer was rejected
+    pub model: String,
+    
+    /// Verifier rule that fired
+    pub reason: String,
+}
+
+/// Token usage statistics
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct TokenUsage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
 }
 
+impl std::ops::AddAssign for TokenUsage {
+    fn add_assign(&mut self, other: Self) {
+        self.prompt_tokens = self.prompt_tokens.saturating_add(other.prompt_tokens);
+        self.completion_tokens = self.completion_tokens.saturating_add(other.completion_tokens);
+        self.total_tokens = self.total_tokens.saturating_add(other.total_tokens);
+    }
+}
+
 /// Reason for completion finishing
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
 #[serde(rename_all = "snake_case")]
@@ -106,7 +133,9 @@ pub enum FinishReason {
 }
 
 /// A chunk from streaming completion
-#[derive(Clone, Debug, Serialize, Deserialize)]
+///
+/// The final chunk also describes the completion as a whole, like `Completion`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
 pub struct StreamChunk {
     /// The text delta
     pub delta: String,
@@ -116,6 +145,37 @@ pub struct StreamChunk {
     
     /// Token usage (typically only on final chunk)
     pub usage: Option<TokenUsage>,
+    
+    /// Model that generated the response (final chunk)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    
+    /// Whether the response was truncated (final chunk)
+    #[serde(default)]
+    pub truncated: bool,
+    
+    /// Finish reason (final chunk)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finish_reason: Option<FinishReason>,
+    
+    /// Attempts rejected before `model` answered (final chunk of a model cascade)
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub escalations: Vec<Escalation>,
+}
+
+impl From<Completion> for StreamChunk {
+    /// The whole completion as a single, final chunk
+    fn from(completion: Completion) -> Self {
+        Self {
+            delta: completion.content,
+            done: true,
+            usage: completion.usage,
+            model: Some(completion.model),
+            truncated: completion.truncated,
+            finish_reason: completion.finish_reason,
+            escalations: completion.escalations,
+        }
+    }
 }
 
 /// Stream type for completion streaming
@@ -178,6 +238,14 @@ pub trait LlmProvider: Send + Sync {
     /// List available models
     async fn list_models(&self) -> Result<Vec<ModelInfo>>;
     
+    /// Whether `GenerationOptions::response_format` is enforced natively
+    ///
+    /// Providers returning `false` still receive the schema, but callers
+    /// must validate the output themselves (see `Agent::ask_structured`).
+    fn supports_json_schema(&self) -> bool {
+        false
+    }
+    
     /// Estimate token count for text (provider-specific tokenization)
     fn estimate_tokens(&self, text: &str) -> u32 {
         // Default: rough estimate of ~4 chars per token
@@ -2,18 +2,34 @@
 //!
 //! Implements the ReAct (Reason + Act) pattern for agent behavior.
 //! The agent observes, thinks, acts (via tools), and responds.
+//!
+//! `run_streaming` sends the answer's text as the model writes it, and each
+//! tool call and result. Each step is streamed until something that may start
+//! a tool call (`{` or a code fence); the rest is held back and sent only if
+//! the step is the answer.
 
 use std::sync::Arc;
 
+use futures::StreamExt;
+use serde::de::DeserializeOwned;
+use tokio::sync::mpsc;
+use tracing::Instrument;
+
+use crate::embedding::ScoredEntry;
 use crate::error::{AgentError, Result};
+use crate::guardrail::{revision_prompt, GuardrailInput, Guardrails, Violation};
+use crate::memory::SemanticMemory;
 use crate::message::{Conversation, Message, Role};
-use crate::provider::{Completion, GenerationOptions, LlmProvider};
+use crate::metrics::AgentMetrics;
+use crate::orchestration::Budget;
+use crate::prompt::{PromptRegistry, PromptVars};
+use crate::provider::{Completion, Escalation, GenerationOptions, LlmProvider, StreamChunk, TokenUsage};
 use crate::tool::{ToolCall, ToolRegistry, ToolResult};
 
 /// Agent configuration
 #[derive(Clone, Debug)]
 pub struct AgentConfig {
-    /// System prompt template
+    /// System prompt (empty: the built-in `default` template, rendered per run)
     pub system_prompt: String,
     
     /// Maximum reasoning iterations before giving up
@@ -24,35 +40,99 @@ pub struct AgentConfig {
     
     /// Whether to append tool descriptions to system prompt
     pub inject_tool_descriptions: bool,
+    
+    /// Re-prompts allowed when structured output fails schema validation
+    pub structured_output_retries: usize,
 }
 
 impl Default for AgentConfig {
     fn default() -> Self {
         Self {
-            system_prompt: DEFAULT_SYSTEM_PROMPT.into(),
+            system_prompt: String::new(),
             max_iterations: 10,
             generation: GenerationOptions::default(),
             inject_tool_descriptions: true,
+            structured_output_retries: 2,
         }
     }
 }
 
-const DEFAULT_SYSTEM_PROMPT: &str = r#"You are a helpful AI assistant.
+/// Outcome of an agent run
+#[derive(Clone, Debug)]
+pub struct AgentRun {
+    /// Final answer
+    pub content: String,
+    
+    /// Token usage summed over every completion in the run
+    pub usage: TokenUsage,
+    
+    /// Model that produced the final answer
+    pub model: String,
+    
+    /// Completions requested (tool calls and re-prompts included)
+    pub iterations: usize,
+    
+    /// One step per completion, in order
+    pub trace: Vec<TraceStep>,
+    
+    /// Guardrail violations left after revisions (the answer is flagged if non-empty)
+    pub violations: Vec<Violation>,
+    
+    /// Revisions requested by guardrails
+    pub revisions: usize,
+}
+
+/// One completion within an agent run
+#[derive(Clone, Debug, serde::Serialize)]
+pub struct TraceStep {
+    /// Model that produced the completion
+    pub model: String,
+    
+    /// Token usage of this completion
+    pub usage: Option<TokenUsage>,
+    
+    /// Cascade attempts rejected before `model` answered
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub escalations: Vec<Escalation>,
+    
+    /// Tool called in this step, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+}
 
-When you need to use a tool, respond with a JSON block in this exact format:
-```tool
-{"tool": "tool_name", "arguments": {"arg1": "value1"}}
-```
+impl TraceStep {
+    fn from_completion(completion: &Completion) -> Self {
+        Self {
+            model: completion.model.clone(),
+            usage: completion.usage,
+            escalations: completion.escalations.clone(),
+            tool: None,
+        }
+    }
+}
 
-After receiving tool results, synthesize them into a helpful response.
-If you can answer directly without tools, do so.
-Be concise and accurate."#;
+/// What `run_streaming` reports as a run goes on
+#[derive(Clone, Debug)]
+pub enum RunEvent {
+    /// Part of the answer's text
+    Delta(String),
+    
+    /// A tool the model called, sent before it runs
+    ToolCall(ToolCall),
+    
+    /// The called tool's result
+    ToolResult(ToolResult),
+}
 
 /// The main Agent struct
 pub struct Agent {
     provider: Arc<dyn LlmProvider>,
     tools: Arc<ToolRegistry>,
     config: AgentConfig,
+    memory: Option<(Arc<SemanticMemory>, String)>,
+    budget: Option<Arc<Budget>>,
+    guardrails: Option<Arc<Guardrails>>,
+    metrics: Option<AgentMetrics>,
 }
 
 impl Agent {
@@ -66,9 +146,44 @@ impl Agent {
             provider,
             tools,
             config,
+            memory: None,
+            budget: None,
+            guardrails: None,
+            metrics: None,
         }
     }
     
+    /// Attach long-term memory for a user
+    ///
+    /// Relevant facts are recalled into the system prompt when a run starts,
+    /// and new facts are extracted from the user's message once it completes.
+    pub fn with_memory(mut self, memory: Arc<SemanticMemory>, user_id: impl Into<String>) -> Self {
+        self.memory = Some((memory, user_id.into()));
+        self
+    }
+    
+    /// Draw from a token budget shared with other agents
+    ///
+    /// The budget is checked before every completion and charged after it.
+    pub fn with_budget(mut self, budget: Arc<Budget>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+    
+    /// Check final answers, requesting revisions for violations
+    ///
+    /// Not applied to structured output, which is validated by its schema.
+    pub fn with_guardrails(mut self, guardrails: Arc<Guardrails>) -> Self {
+        self.guardrails = Some(guardrails);
+        self
+    }
+    
+    /// Record reasoning iterations per run
+    pub fn with_metrics(mut self, metrics: AgentMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+    
     /// Create with default configuration
     pub fn with_defaults(
         provider: Arc<dyn LlmProvider>,
@@ -77,70 +192,348 @@ impl Agent {
         Self::new(provider, tools, AgentConfig::default())
     }
     
-    /// Build the full system prompt including tool descriptions
-    fn build_system_prompt(&self) -> String {
-        let mut prompt = self.config.system_prompt.clone();
+    /// Build the full system prompt including tool descriptions and recalled memories
+    fn build_system_prompt(&self, memories: &[ScoredEntry]) -> Result<String> {
+        let mut prompt = self.base_system_prompt()?;
         
         if self.config.inject_tool_descriptions && !self.tools.is_empty() {
             prompt.push_str("\n\n");
             prompt.push_str(&self.tools.generate_prompt_section());
         }
         
-        prompt
+        if !memories.is_empty() {
+            prompt.push_str("\n\n");
+            prompt.push_str(&crate::memory::prompt_section(memories));
+        }
+        
+        Ok(prompt)
+    }
+    
+    /// The configured system prompt, or the built-in default rendered for today
+    fn base_system_prompt(&self) -> Result<String> {
+        if !self.config.system_prompt.is_empty() {
+            return Ok(self.config.system_prompt.clone());
+        }
+        Ok(PromptRegistry::new().render("default", None, None, &PromptVars::new())?.text)
+    }
+    
+    /// Recall memories relevant to the latest user message
+    ///
+    /// Memory is best-effort: an unavailable embedding model must not fail the run.
+    async fn recall_memories(&self, conversation: &Conversation) -> Vec<ScoredEntry> {
+        let (Some((memory, user_id)), Some(query)) = (&self.memory, last_user_message(conversation)) else {
+            return Vec::new();
+        };
+        
+        memory.recall(user_id, query).await.unwrap_or_else(|e| {
+            tracing::warn!(error = %e, "Memory recall failed");
+            Vec::new()
+        })
+    }
+    
+    /// Store facts from the latest user message
+    async fn observe_memories(&self, conversation: &Conversation) {
+        let (Some((memory, user_id)), Some(message)) = (&self.memory, last_user_message(conversation)) else {
+            return;
+        };
+        
+        if let Err(e) = memory.observe(user_id, message).await {
+            tracing::warn!(error = %e, "Failed to update memory");
+        }
     }
     
     /// Run the agent on a user message
     pub async fn run(&self, conversation: &mut Conversation) -> Result<String> {
+        self.run_detailed(conversation).await.map(|run| run.content)
+    }
+    
+    /// Run the agent, also reporting token usage across all iterations
+    pub async fn run_detailed(&self, conversation: &mut Conversation) -> Result<AgentRun> {
+        self.run_with(conversation, None).await
+    }
+    
+    /// Run the agent, sending the answer's text and its tool calls to `events` as they happen
+    ///
+    /// Text ahead of a tool call in the same completion is sent too. With
+    /// guardrails the answer is sent once checked, as one delta.
+    pub async fn run_streaming(&self, conversation: &mut Conversation, events: &mpsc::Sender<RunEvent>) -> Result<AgentRun> {
+        self.run_with(conversation, Some(events)).await
+    }
+    
+    async fn run_with(&self, conversation: &mut Conversation, events: Option<&mpsc::Sender<RunEvent>>) -> Result<AgentRun> {
+        let span = tracing::info_span!("agent.run", model = %self.config.generation.model);
+        let mut iterations = 0;
+        let result = self.reason(conversation, events, &mut iterations).instrument(span).await;
+        
+        if let Some(metrics) = &self.metrics {
+            // A run stopped by `max_iterations` counts the last one it didn't start
+            let outcome = if result.is_ok() { "ok" } else { "error" };
+            metrics.iterations.observe(&[outcome], iterations.min(self.config.max_iterations) as f64);
+        }
+        result
+    }
+    
+    /// The reasoning loop, counting completions in `iterations`
+    async fn reason(
+        &self,
+        conversation: &mut Conversation,
+        events: Option<&mpsc::Sender<RunEvent>>,
+        iterations: &mut usize,
+    ) -> Result<AgentRun> {
         // Ensure system prompt is set
         if conversation.messages().first().map(|m| &m.role) != Some(&Role::System) {
-            let messages = conversation.messages_mut();
-            messages.insert(0, Message::system(self.build_system_prompt()));
+            let memories = self.recall_memories(conversation).await;
+            let system_prompt = self.build_system_prompt(&memories)?;
+            conversation.messages_mut().insert(0, Message::system(system_prompt));
         }
         
-        let mut iterations = 0;
+        let question = last_user_message(conversation).unwrap_or_default().to_string();
+        let mut usage = TokenUsage::default();
+        let mut trace = Vec::new();
+        let mut tool_results = Vec::new();
+        let mut revisions = 0;
         
         loop {
-            iterations += 1;
+            *iterations += 1;
             
-            if iterations > self.config.max_iterations {
+            if *iterations > self.config.max_iterations {
                 return Err(AgentError::MaxIterations(self.config.max_iterations));
             }
             
-            // Get completion from provider
-            let completion = self.provider
-                .complete(conversation.messages(), &self.config.generation)
-                .await?;
+            // Get completion from provider (streamed unless guardrails may still revise it)
+            self.check_budget()?;
+            let mut sent = 0;
+            let completion = match events.filter(|_| self.guardrails.is_none()) {
+                Some(events) => self.complete_streamed(conversation.messages(), events, &mut sent).await?,
+                None => self.provider.complete(conversation.messages(), &self.config.generation).await?,
+            };
             
             let content = completion.content.clone();
+            if let Some(step) = completion.usage {
+                usage += step;
+                self.charge_budget(step);
+            }
+            if !completion.escalations.is_empty() {
+                tracing::debug!(model = %completion.model, escalations = completion.escalations.len(), "Model cascade escalated");
+            }
+            let mut step = TraceStep::from_completion(&completion);
             
             // Add assistant response to conversation
-            conversation.push(Message::assistant(&content));
+            conversation.push(Message::assistant(&content).with_usage(&completion.model, completion.usage));
             
             // Check for tool calls
             if let Some(tool_call) = self.parse_tool_call(&content) {
                 tracing::debug!(tool = %tool_call.name, "Executing tool");
+                step.tool = Some(tool_call.name.clone());
+                trace.push(step);
                 
                 // Execute the tool
+                send(events, RunEvent::ToolCall(tool_call.clone())).await?;
                 let result = self.execute_tool(&tool_call).await;
+                send(events, RunEvent::ToolResult(result.clone())).await?;
                 
                 // Add tool result to conversation
                 let tool_message = self.format_tool_result(&result);
                 conversation.push(Message::tool(tool_message, tool_call.id.clone()));
+                tool_results.push(result);
                 
                 // Continue reasoning loop
                 continue;
             }
             
-            // No tool call - this is the final response
-            return Ok(content);
+            // No tool call - check the answer against the guardrails
+            let mut violations = Vec::new();
+            if let Some(guardrails) = &self.guardrails {
+                let input = GuardrailInput { question: &question, answer: &content, tool_results: &tool_results };
+                let check = guardrails.check(&input).await;
+                if let Some(step) = check.usage {
+                    usage += step;
+                    self.charge_budget(step);
+                }
+                
+                if !check.violations.is_empty() {
+                    if revisions < guardrails.revisions() {
+                        revisions += 1;
+                        tracing::debug!(violations = check.violations.len(), revision = revisions, "Guardrails requested a revision");
+                        trace.push(step);
+                        conversation.push(Message::user(revision_prompt(&check.violations)));
+                        continue;
+                    }
+                    tracing::warn!(violations = check.violations.len(), "Answer flagged by guardrails");
+                    violations = check.violations;
+                }
+            }
+            
+            // This is the final response; send what was held back
+            let rest = &content[sent..];
+            if !rest.is_empty() {
+                send(events, RunEvent::Delta(rest.to_string())).await?;
+            }
+            self.observe_memories(conversation).await;
+            trace.push(step);
+            return Ok(AgentRun {
+                content,
+                usage,
+                model: completion.model,
+                iterations: *iterations,
+                trace,
+                violations,
+                revisions,
+            });
         }
     }
     
     /// Run with a simple string input (creates temporary conversation)
     pub async fn ask(&self, question: &str) -> Result<String> {
-        let mut conversation = Conversation::with_system_prompt(self.build_system_prompt());
+        self.ask_detailed(question).await.map(|run| run.content)
+    }
+    
+    /// Like `ask`, also reporting token usage
+    pub async fn ask_detailed(&self, question: &str) -> Result<AgentRun> {
+        // `run` inserts the system prompt, with any recalled memories
+        let mut conversation = Conversation::new();
+        conversation.push(Message::user(question));
+        self.run_detailed(&mut conversation).await
+    }
+    
+    /// Ask for a response conforming to a JSON schema and deserialize it
+    ///
+    /// The schema is passed to the provider via `GenerationOptions::response_format`.
+    /// The output is validated either way; on failure the validation errors are
+    /// fed back to the model and it is asked to try again, up to
+    /// `structured_output_retries` times. Tools are not available in this mode,
+    /// since a schema-constrained response cannot contain a tool call.
+    pub async fn ask_structured<T: DeserializeOwned>(
+        &self,
+        question: &str,
+        schema: serde_json::Value,
+    ) -> Result<T> {
+        self.ask_structured_detailed(question, schema).await.map(|(value, _)| value)
+    }
+    
+    /// Like `ask_structured`, also reporting token usage (`content` is the raw JSON)
+    pub async fn ask_structured_detailed<T: DeserializeOwned>(
+        &self,
+        question: &str,
+        schema: serde_json::Value,
+    ) -> Result<(T, AgentRun)> {
+        let mut system_prompt = self.base_system_prompt()?;
+        system_prompt.push_str("\n\nRespond ONLY with a JSON document matching this JSON schema, with no surrounding text:\n");
+        system_prompt.push_str(&serde_json::to_string_pretty(&schema)?);
+        
+        let mut conversation = Conversation::with_system_prompt(system_prompt);
         conversation.push(Message::user(question));
-        self.run(&mut conversation).await
+        
+        let options = GenerationOptions {
+            response_format: Some(schema.clone()),
+            ..self.config.generation.clone()
+        };
+        
+        let mut attempts = 0;
+        let mut usage = TokenUsage::default();
+        let mut trace = Vec::new();
+        
+        loop {
+            self.check_budget()?;
+            let completion = self.provider
+                .complete(conversation.messages(), &options)
+                .await?;
+            if let Some(step) = completion.usage {
+                usage += step;
+                self.charge_budget(step);
+            }
+            trace.push(TraceStep::from_completion(&completion));
+            
+            let errors = match crate::schema::extract_json(&completion.content) {
+                Some(value) => {
+                    let errors = crate::schema::validate(&schema, &value);
+                    if errors.is_empty() {
+                        let run = AgentRun {
+                            content: value.to_string(),
+                            usage,
+                            model: completion.model,
+                            iterations: attempts + 1,
+                            trace,
+                            violations: Vec::new(),
+                            revisions: 0,
+                        };
+                        return serde_json::from_value(value)
+                            .map(|parsed| (parsed, run))
+                            .map_err(|e| {
+                                AgentError::Parse(format!("Structured output did not match target type: {}", e))
+                            });
+                    }
+                    errors
+                }
+                None => vec!["response is not valid JSON".to_string()],
+            };
+            
+            attempts += 1;
+            if attempts > self.config.structured_output_retries {
+                return Err(AgentError::Parse(format!(
+                    "Structured output failed validation: {}",
+                    errors.join("; ")
+                )));
+            }
+            
+            tracing::debug!(attempt = attempts, errors = ?errors, "Structured output invalid, re-prompting");
+            
+            conversation.push(Message::assistant(&completion.content));
+            conversation.push(Message::user(format!(
+                "Your response did not match the required JSON schema:\n- {}\n\nRespond again with ONLY the corrected JSON.",
+                errors.join("\n- ")
+            )));
+        }
+    }
+    
+    /// Stream a completion, sending its text up to the first possible tool call
+    ///
+    /// `sent` is set to how many bytes of the content were sent.
+    async fn complete_streamed(&self, messages: &[Message], events: &mpsc::Sender<RunEvent>, sent: &mut usize) -> Result<Completion> {
+        let mut stream = self.provider.complete_stream(messages, &self.config.generation).await?;
+        let mut content = String::new();
+        let mut usage = None;
+        let mut last = StreamChunk::default();
+        let mut held = false;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            content.push_str(&chunk.delta);
+            usage = chunk.usage.or(usage);
+            
+            if !held {
+                let pending = &content[*sent..];
+                let safe = pending.find(['{', '`']).unwrap_or(pending.len());
+                held = safe < pending.len();
+                if safe > 0 {
+                    send(Some(events), RunEvent::Delta(pending[..safe].to_string())).await?;
+                    *sent += safe;
+                }
+            }
+            if chunk.done {
+                last = chunk;
+                break;
+            }
+        }
+        
+        // The final chunk describes the completion (the model a cascade picked, its escalations)
+        Ok(Completion {
+            content,
+            model: last.model.unwrap_or_else(|| self.config.generation.model.clone()),
+            usage,
+            truncated: last.truncated,
+            finish_reason: last.finish_reason,
+            escalations: last.escalations,
+        })
+    }
+    
+    fn check_budget(&self) -> Result<()> {
+        self.budget.as_ref().map_or(Ok(()), |budget| budget.check())
+    }
+    
+    fn charge_budget(&self, usage: TokenUsage) {
+        if let Some(budget) = &self.budget {
+            budget.charge(usage);
+        }
     }
     
     /// Parse a tool call from LLM response
@@ -227,11 +620,30 @@ impl Agent {
     }
 }
 
+/// Content of the most recent user message
+fn last_user_message(conversation: &Conversation) -> Option<&str> {
+    conversation
+        .messages()
+        .iter()
+        .rev()
+        .find(|m| m.role == Role::User)
+        .map(|m| m.content.as_str())
+}
+
+/// Send an event to a streaming run's listener, if there is one
+async fn send(events: Option<&mpsc::Sender<RunEvent>>, event: RunEvent) -> Result<()> {
+    match events {
+        Some(events) => events.send(event).await.map_err(|_| AgentError::Other("Answer stream closed".into())),
+        None => Ok(()),
+    }
+}
+
 /// Builder for Agent configuration
 pub struct AgentBuilder {
     provider: Option<Arc<dyn LlmProvider>>,
     tools: ToolRegistry,
     config: AgentConfig,
+    memory: Option<(Arc<SemanticMemory>, String)>,
 }
 
 impl Default for AgentBuilder {
@@ -246,6 +658,7 @@ impl AgentBuilder {
             provider: None,
             tools: ToolRegistry::new(),
             config: AgentConfig::default(),
+            memory: None,
         }
     }
     
@@ -284,17 +697,156 @@ impl AgentBuilder {
         self
     }
     
+    pub fn memory(mut self, memory: Arc<SemanticMemory>, user_id: impl Into<String>) -> Self {
+        self.memory = Some((memory, user_id.into()));
+        self
+    }
+    
     pub fn build(self) -> Result<Agent> {
         let provider = self.provider
             .ok_or_else(|| AgentError::Config("Provider is required".into()))?;
         
-        Ok(Agent::new(provider, Arc::new(self.tools), self.config))
+        let mut agent = Agent::new(provider, Arc::new(self.tools), self.config);
+        agent.memory = self.memory;
+        Ok(agent)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::provider::{CompletionStream, FinishReason, ModelInfo, ProviderInfo};
+    use std::sync::Mutex;
+
+    /// Provider that replays canned responses in order
+    struct ScriptedProvider {
+        responses: Mutex<Vec<String>>,
+    }
+
+    impl ScriptedProvider {
+        fn new(responses: &[&str]) -> Self {
+            Self {
+                responses: Mutex::new(responses.iter().rev().map(|s| s.to_string()).collect()),
+            }
+        }
+    }
+
+    #[async_trait::async_trait]
+    impl LlmProvider for ScriptedProvider {
+        async fn info(&self) -> Result<ProviderInfo> {
+            Err(AgentError::Other("unused".into()))
+        }
+        
+        async fn health_check(&self) -> Result<bool> {
+            Ok(true)
+        }
+        
+        async fn complete(&self, _: &[Message], options: &GenerationOptions) -> Result<Completion> {
+            let content = self.responses.lock().unwrap().pop()
+                .ok_or_else(|| AgentError::Provider("script exhausted".into()))?;
+            Ok(Completion {
+                content,
+                model: options.model.clone(),
+                usage: Some(TokenUsage { prompt_tokens: 10, completion_tokens: 5, total_tokens: 15 }),
+                truncated: false,
+                finish_reason: Some(FinishReason::Stop),
+                escalations: Vec::new(),
+            })
+        }
+        
+        async fn complete_stream(&self, messages: &[Message], options: &GenerationOptions) -> Result<CompletionStream> {
+            let completion = self.complete(messages, options).await?;
+            let chars: Vec<char> = completion.content.chars().collect();
+            let mut chunks: Vec<_> = chars
+                .chunks(3)
+                .map(|delta| Ok(StreamChunk { delta: delta.iter().collect(), ..StreamChunk::default() }))
+                .collect();
+            chunks.push(Ok(StreamChunk { delta: String::new(), ..StreamChunk::from(completion) }));
+            Ok(Box::pin(futures::stream::iter(chunks)))
+        }
+        
+        async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn agent_with(responses: &[&str]) -> Agent {
+        Agent::with_defaults(Arc::new(ScriptedProvider::new(responses)), Arc::new(ToolRegistry::new()))
+    }
+
+    #[tokio::test]
+    async fn test_ask_structured_reprompts_on_invalid_output() {
+        #[derive(serde::Deserialize)]
+        struct Plan {
+            symbol: String,
+            percent: f64,
+        }
+        
+        let schema = serde_json::json!({
+            "type": "object",
+            "required": ["symbol", "percent"],
+            "properties": {"symbol": {"type": "string"}, "percent": {"type": "number"}}
+        });
+        let agent = agent_with(&["Sure! {\"symbol\": \"BTC\"}", "{\"symbol\": \"BTC\", \"percent\": 20}"]);
+        
+        let plan: Plan = agent.ask_structured("Plan?", schema).await.unwrap();
+        assert_eq!(plan.symbol, "BTC");
+        assert_eq!(plan.percent, 20.0);
+    }
+
+    #[tokio::test]
+    async fn test_usage_summed_across_iterations() {
+        let mut tools = ToolRegistry::new();
+        tools.register(crate::tool::CalculatorTool);
+        let provider = ScriptedProvider::new(&[
+            "```tool\n{\"name\": \"calculate\", \"arguments\": {\"expression\": \"2 + 2\"}}\n```",
+            "It's 4.",
+        ]);
+        let agent = Agent::with_defaults(Arc::new(provider), Arc::new(tools));
+        
+        let mut conversation = Conversation::new();
+        conversation.push(Message::user("What is 2 + 2?"));
+        let run = agent.run_detailed(&mut conversation).await.unwrap();
+        
+        assert_eq!(run.content, "It's 4.");
+        assert_eq!(run.iterations, 2);
+        assert_eq!(run.usage.total_tokens, 30);
+        let last = conversation.messages().last().unwrap();
+        assert_eq!(last.metadata.as_ref().unwrap().tokens, Some(5));
+    }
+
+    #[tokio::test]
+    async fn test_run_streaming_sends_answer_deltas_and_tool_events() {
+        let mut tools = ToolRegistry::new();
+        tools.register(crate::tool::CalculatorTool);
+        let provider = ScriptedProvider::new(&[
+            "```tool\n{\"name\": \"calculate\", \"arguments\": {\"expression\": \"2 + 2\"}}\n```",
+            "It's 4, as in {2 + 2}.",
+        ]);
+        let agent = Agent::with_defaults(Arc::new(provider), Arc::new(tools));
+        
+        let mut conversation = Conversation::new();
+        conversation.push(Message::user("What is 2 + 2?"));
+        let (tx, mut rx) = mpsc::channel(64);
+        let run = agent.run_streaming(&mut conversation, &tx).await.unwrap();
+        drop(tx);
+        
+        let mut deltas = Vec::new();
+        let mut tools = Vec::new();
+        while let Some(event) = rx.recv().await {
+            match event {
+                RunEvent::Delta(delta) => deltas.push(delta),
+                RunEvent::ToolCall(call) => tools.push(format!("call {}", call.name)),
+                RunEvent::ToolResult(result) => tools.push(format!("result {} {}", result.name, result.output)),
+            }
+        }
+        assert_eq!(tools, ["call calculate", "result calculate 2 + 2 = 4"]);
+        assert!(deltas.len() > 2, "{:?}", deltas);
+        assert_eq!(deltas.concat(), "It's 4, as in {2 + 2}.");
+        assert_eq!(run.content, "It's 4, as in {2 + 2}.");
+        assert_eq!(run.iterations, 2);
+        assert_eq!(run.usage.total_tokens, 30);
+    }
 
     #[test]
     fn test_parse_tool_call() {
@@ -307,4 +859,34 @@ mod tests {
         // Just verify the structure compiles
         assert!(content.contains("```tool"));
     }
+
+    /// Flags answers that hedge with "maybe"
+    struct NoHedging;
+
+    #[async_trait::async_trait]
+    impl crate::guardrail::Guardrail for NoHedging {
+        fn name(&self) -> &str {
+            "no_hedging"
+        }
+        
+        async fn check(&self, input: &GuardrailInput<'_>) -> Result<crate::guardrail::GuardrailCheck> {
+            let hedges = input.answer.contains("maybe");
+            Ok(if hedges { vec![Violation::new("hedging", "Answer definitively")] } else { Vec::new() }.into())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_guardrails_revise_then_flag() {
+        let guardrails = Arc::new(Guardrails::new().rule(NoHedging));
+        let agent = agent_with(&["It's maybe 4.", "It's 4."]).with_guardrails(guardrails);
+        let run = agent.ask_detailed("2 + 2?").await.unwrap();
+        assert_eq!(run.content, "It's 4.");
+        assert_eq!(run.revisions, 1);
+        assert!(run.violations.is_empty());
+        
+        let guardrails = Arc::new(Guardrails::new().rule(NoHedging).max_revisions(0));
+        let agent = agent_with(&["It's maybe 4."]).with_guardrails(guardrails);
+        let run = agent.ask_detailed("2 + 2?").await.unwrap();
+        assert_eq!(run.violations, vec![Violation::new("hedging", "Answer definitively")]);
+    }
 }
@@ -0,0 +1,540 @@
+//! Provider Resilience Middleware
+//!
+//! Decorator for any `LlmProvider` that adds per-call timeouts, jittered
+//! exponential backoff on retryable errors, a client-side concurrency limit,
+//! a token-bucket rate limit, and stall detection for streaming completions.
+//!
+//! ## Usage
+//!
+//! ```rust,ignore
+//! use agent_core::resilience::{ResilienceConfig, ResilientProvider};
+//!
+//! let provider = ResilientProvider::new(
+//!     Arc::new(OllamaProvider::from_env()),
+//!     ResilienceConfig::default().max_retries(5),
+//! );
+//! let agent = AgentBuilder::new().provider(Arc::new(provider)).build()?;
+//! ```
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use futures::StreamExt;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+use crate::error::{AgentError, Result};
+use crate::message::Message;
+use crate::provider::{
+    Completion, CompletionStream, GenerationOptions, LlmProvider, ModelInfo, ProviderInfo,
+};
+
+/// Resilience settings for a wrapped provider
+#[derive(Clone, Debug)]
+pub struct ResilienceConfig {
+    /// Timeout for a single non-streaming completion attempt
+    pub request_timeout: Duration,
+    
+    /// Retries after the first attempt (0 = no retries)
+    pub max_retries: u32,
+    
+    /// Backoff before the first retry (doubled on each subsequent retry)
+    pub initial_backoff: Duration,
+    
+    /// Upper bound for a single backoff delay
+    pub max_backoff: Duration,
+    
+    /// Maximum concurrent in-flight calls (None = unlimited)
+    pub max_concurrent: Option<usize>,
+    
+    /// Sustained requests per second (None = unlimited)
+    pub requests_per_second: Option<f64>,
+    
+    /// Token bucket capacity (burst size)
+    pub burst: u32,
+    
+    /// Maximum wait for the first chunk of a stream, opening it included (per attempt)
+    pub first_chunk_timeout: Duration,
+    
+    /// Maximum wait between consecutive stream chunks
+    pub idle_chunk_timeout: Duration,
+}
+
+impl Default for ResilienceConfig {
+    fn default() -> Self {
+        Self {
+            request_timeout: Duration::from_secs(120),
+            max_retries: 3,
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(10),
+            max_concurrent: Some(8),
+            requests_per_second: None,
+            burst: 1,
+            first_chunk_timeout: Duration::from_secs(60),
+            idle_chunk_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+impl ResilienceConfig {
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.request_timeout = timeout;
+        self
+    }
+    
+    pub fn max_retries(mut self, retries: u32) -> Self {
+        self.max_retries = retries;
+        self
+    }
+    
+    pub fn backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+    
+    pub fn max_concurrent(mut self, limit: Option<usize>) -> Self {
+        self.max_concurrent = limit;
+        self
+    }
+    
+    pub fn rate_limit(mut self, requests_per_second: f64, burst: u32) -> Self {
+        self.requests_per_second = Some(requests_per_second);
+        self.burst = burst.max(1);
+        self
+    }
+    
+    pub fn stream_timeouts(mut self, first_chunk: Duration, idle: Duration) -> Self {
+        self.first_chunk_timeout = first_chunk;
+        self.idle_chunk_timeout = idle;
+        self
+    }
+    
+    /// Backoff delay before retry number `attempt` (0-based), with equal jitter
+    fn backoff_for(&self, attempt: u32) -> Duration {
+        let exp = self.initial_backoff.saturating_mul(2u32.saturating_pow(attempt));
+        let capped = exp.min(self.max_backoff);
+        // Half fixed, half random: avoids synchronized retries without
+        // collapsing the delay to zero
+        let half = capped / 2;
+        half + half.mul_f64(jitter())
+    }
+}
+
+/// Uniform random value in [0, 1)
+///
+/// Takes the low 53 bits of a v4 UUID: all random, unlike the version and
+/// variant bits further up.
+fn jitter() -> f64 {
+    let bits = uuid::Uuid::new_v4().as_u128() & ((1u128 << 53) - 1);
+    bits as f64 / (1u64 << 53) as f64
+}
+
+/// Token bucket rate limiter
+struct TokenBucket {
+    capacity: f64,
+    refill_per_sec: f64,
+    state: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    fn new(refill_per_sec: f64, capacity: u32) -> Self {
+        let capacity = f64::from(capacity.max(1));
+        Self {
+            capacity,
+            refill_per_sec,
+            state: Mutex::new((capacity, Instant::now())),
+        }
+    }
+    
+    /// Wait until a token is available and take it
+    async fn acquire(&self) {
+        loop {
+            let wait = {
+                let mut state = self.state.lock().await;
+                let now = Instant::now();
+                let elapsed = now.duration_since(state.1).as_secs_f64();
+                state.0 = (state.0 + elapsed * self.refill_per_sec).min(self.capacity);
+                state.1 = now;
+                
+                if state.0 >= 1.0 {
+                    state.0 -= 1.0;
+                    return;
+                }
+                
+                Duration::from_secs_f64((1.0 - state.0) / self.refill_per_sec)
+            };
+            tokio::time::sleep(wait).await;
+        }
+    }
+}
+
+/// `LlmProvider` decorator adding timeouts, retries and client-side limits
+pub struct ResilientProvider {
+    inner: Arc<dyn LlmProvider>,
+    config: ResilienceConfig,
+    concurrency: Option<Arc<Semaphore>>,
+    bucket: Option<TokenBucket>,
+}
+
+impl ResilientProvider {
+    /// Wrap a provider
+    pub fn new(inner: Arc<dyn LlmProvider>, config: ResilienceConfig) -> Self {
+        let concurrency = config.max_concurrent.map(|n| Arc::new(Semaphore::new(n.max(1))));
+        let bucket = config
+            .requests_per_second
+            .filter(|rps| *rps > 0.0)
+            .map(|rps| TokenBucket::new(rps, config.burst));
+        
+        Self {
+            inner,
+            config,
+            concurrency,
+            bucket,
+        }
+    }
+    
+    /// Wrap a provider with default settings
+    pub fn with_defaults(inner: Arc<dyn LlmProvider>) -> Self {
+        Self::new(inner, ResilienceConfig::default())
+    }
+    
+    /// Get configuration
+    pub fn config(&self) -> &ResilienceConfig {
+        &self.config
+    }
+    
+    /// Take a concurrency permit and a rate-limit token
+    async fn admit(&self) -> Result<Option<OwnedSemaphorePermit>> {
+        let permit = match &self.concurrency {
+            Some(semaphore) => Some(
+                semaphore
+                    .clone()
+                    .acquire_owned()
+                    .await
+                    .map_err(|_| AgentError::ProviderUnavailable("Provider closed".into()))?,
+            ),
+            None => None,
+        };
+        
+        if let Some(bucket) = &self.bucket {
+            bucket.acquire().await;
+        }
+        
+        Ok(permit)
+    }
+    
+    /// Decide whether to retry after `err` on attempt `attempt` (0-based)
+    async fn should_retry(&self, err: &AgentError, attempt: u32) -> bool {
+        if !err.is_retryable() || attempt >= self.config.max_retries {
+            return false;
+        }
+        
+        let delay = self.config.backoff_for(attempt);
+        tracing::warn!(
+            error = %err,
+            attempt = attempt + 1,
+            delay_ms = delay.as_millis() as u64,
+            "Retrying provider call"
+        );
+        tokio::time::sleep(delay).await;
+        true
+    }
+}
+
+#[async_trait]
+impl LlmProvider for ResilientProvider {
+    async fn info(&self) -> Result<ProviderInfo> {
+        self.inner.info().await
+    }
+    
+    async fn health_check(&self) -> Result<bool> {
+        self.inner.health_check().await
+    }
+    
+    async fn complete(
+        &self,
+        messages: &[Message],
+        options: &GenerationOptions,
+    ) -> Result<Completion> {
+        let mut attempt = 0;
+        
+        loop {
+            let permit = self.admit().await?;
+            let result = tokio::time::timeout(
+                self.config.request_timeout,
+                self.inner.complete(messages, options),
+            )
+            .await
+            .unwrap_or_else(|_| {
+                Err(AgentError::ProviderUnavailable(format!(
+                    "Completion timed out after {:?}",
+                    self.config.request_timeout
+                )))
+            });
+            drop(permit);
+            
+            match result {
+                Ok(completion) => return Ok(completion),
+                Err(e) if self.should_retry(&e, attempt).await => attempt += 1,
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    
+    async fn complete_stream(
+        &self,
+        messages: &[Message],
+        options: &GenerationOptions,
+    ) -> Result<CompletionStream> {
+        let mut attempt = 0;
+        
+        // Retries only cover establishing the stream; once chunks have been
+        // handed to the caller a retry would duplicate output. Opening the
+        // stream and its first chunk share one deadline.
+        let (stream, permit, deadline) = loop {
+            let permit = self.admit().await?;
+            let deadline = Instant::now() + self.config.first_chunk_timeout;
+            let result = tokio::time::timeout_at(deadline, self.inner.complete_stream(messages, options))
+                .await
+                .unwrap_or_else(|_| {
+                    Err(AgentError::ProviderUnavailable(format!(
+                        "Stream not established after {:?}",
+                        self.config.first_chunk_timeout
+                    )))
+                });
+            
+            match result {
+                Ok(stream) => break (stream, permit, deadline),
+                Err(e) => {
+                    drop(permit);
+                    if self.should_retry(&e, attempt).await {
+                        attempt += 1;
+                    } else {
+                        return Err(e);
+                    }
+                }
+            }
+        };
+        
+        Ok(guard_stream(
+            stream,
+            permit,
+            deadline,
+            self.config.first_chunk_timeout,
+            self.config.idle_chunk_timeout,
+        ))
+    }
+    
+    async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+        self.inner.list_models().await
+    }
+    
+    fn supports_json_schema(&self) -> bool {
+        self.inner.supports_json_schema()
+    }
+    
+    fn estimate_tokens(&self, text: &str) -> u32 {
+        self.inner.estimate_tokens(text)
+    }
+}
+
+/// State carried through a guarded stream
+struct StreamGuard {
+    stream: CompletionStream,
+    _permit: Option<OwnedSemaphorePermit>,
+    received_first: bool,
+    finished: bool,
+}
+
+/// Apply the first chunk's deadline and inter-chunk timeouts, holding the
+/// concurrency permit until the stream is dropped
+fn guard_stream(
+    stream: CompletionStream,
+    permit: Option<OwnedSemaphorePermit>,
+    first_chunk_deadline: Instant,
+    first_chunk_timeout: Duration,
+    idle_chunk_timeout: Duration,
+) -> CompletionStream {
+    let state = StreamGuard {
+        stream,
+        _permit: permit,
+        received_first: false,
+        finished: false,
+    };
+    
+    let guarded = futures::stream::unfold(state, move |mut state| async move {
+        if state.finished {
+            return None;
+        }
+        
+        let (deadline, limit) = if state.received_first {
+            (Instant::now() + idle_chunk_timeout, idle_chunk_timeout)
+        } else {
+            (first_chunk_deadline, first_chunk_timeout)
+        };
+        
+        match tokio::time::timeout_at(deadline, state.stream.next()).await {
+            Ok(Some(item)) => {
+                state.received_first = true;
+                state.finished = matches!(&item, Ok(chunk) if chunk.done) || item.is_err();
+                Some((item, state))
+            }
+            Ok(None) => None,
+            Err(_) => {
+                state.finished = true;
+                let stage = if state.received_first { "between chunks" } else { "for first chunk" };
+                Some((
+                    Err(AgentError::ProviderUnavailable(format!(
+                        "Stream stalled: no data {} after {:?}",
+                        stage, limit
+                    ))),
+                    state,
+                ))
+            }
+        }
+    });
+    
+    Box::pin(guarded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{FinishReason, StreamChunk};
+    use std::sync::atomic::{AtomicU32, Ordering};
+
+    /// Fails with `ProviderUnavailable` until `failures` calls have been made
+    struct FlakyProvider {
+        failures: u32,
+        calls: AtomicU32,
+        delay: Duration,
+        
+        /// Time taken to open a stream
+        open_delay: Duration,
+    }
+
+    impl FlakyProvider {
+        fn new(failures: u32, delay: Duration) -> Self {
+            Self { failures, calls: AtomicU32::new(0), delay, open_delay: Duration::ZERO }
+        }
+    }
+
+    #[async_trait]
+    impl LlmProvider for FlakyProvider {
+        async fn info(&self) -> Result<ProviderInfo> {
+            Err(AgentError::Other("unused".into()))
+        }
+        
+        async fn health_check(&self) -> Result<bool> {
+            Ok(true)
+        }
+        
+        async fn complete(&self, _: &[Message], options: &GenerationOptions) -> Result<Completion> {
+            tokio::time::sleep(self.delay).await;
+            let call = self.calls.fetch_add(1, Ordering::SeqCst);
+            if call < self.failures {
+                return Err(AgentError::ProviderUnavailable("flaky".into()));
+            }
+            Ok(Completion {
+                content: "ok".into(),
+                model: options.model.clone(),
+                usage: None,
+                truncated: false,
+                finish_reason: Some(FinishReason::Stop),
+                escalations: Vec::new(),
+            })
+        }
+        
+        async fn complete_stream(&self, _: &[Message], _: &GenerationOptions) -> Result<CompletionStream> {
+            tokio::time::sleep(self.open_delay).await;
+            let delay = self.delay;
+            let chunks = futures::stream::iter(0..2).then(move |i| async move {
+                tokio::time::sleep(delay).await;
+                Ok(StreamChunk { delta: "x".into(), done: i == 1, ..StreamChunk::default() })
+            });
+            Ok(Box::pin(chunks))
+        }
+        
+        async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+            Ok(Vec::new())
+        }
+    }
+
+    fn fast_config() -> ResilienceConfig {
+        ResilienceConfig::default()
+            .backoff(Duration::from_millis(1), Duration::from_millis(5))
+            .request_timeout(Duration::from_millis(200))
+    }
+
+    #[tokio::test]
+    async fn test_retries_until_success() {
+        let inner = Arc::new(FlakyProvider::new(2, Duration::ZERO));
+        let provider = ResilientProvider::new(inner.clone(), fast_config());
+        
+        let completion = provider.complete(&[], &GenerationOptions::default()).await.unwrap();
+        assert_eq!(completion.content, "ok");
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 3);
+    }
+
+    #[tokio::test]
+    async fn test_gives_up_after_max_retries() {
+        let inner = Arc::new(FlakyProvider::new(10, Duration::ZERO));
+        let provider = ResilientProvider::new(inner.clone(), fast_config().max_retries(1));
+        
+        let result = provider.complete(&[], &GenerationOptions::default()).await;
+        assert!(matches!(result, Err(AgentError::ProviderUnavailable(_))));
+        assert_eq!(inner.calls.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_request_timeout() {
+        let inner = Arc::new(FlakyProvider::new(0, Duration::from_millis(500)));
+        let config = fast_config().max_retries(0).request_timeout(Duration::from_millis(20));
+        let provider = ResilientProvider::new(inner, config);
+        
+        let result = provider.complete(&[], &GenerationOptions::default()).await;
+        assert!(matches!(result, Err(AgentError::ProviderUnavailable(_))));
+    }
+
+    #[tokio::test]
+    async fn test_stream_idle_timeout() {
+        let inner = Arc::new(FlakyProvider::new(0, Duration::from_millis(200)));
+        let config = fast_config().stream_timeouts(Duration::from_millis(20), Duration::from_millis(20));
+        let provider = ResilientProvider::new(inner, config);
+        
+        let mut stream = provider.complete_stream(&[], &GenerationOptions::default()).await.unwrap();
+        assert!(matches!(stream.next().await, Some(Err(AgentError::ProviderUnavailable(_)))));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_first_chunk_deadline_includes_opening() {
+        // Opening and the first chunk each fit the timeout, but not together
+        let inner = Arc::new(FlakyProvider { open_delay: Duration::from_millis(40), ..FlakyProvider::new(0, Duration::from_millis(40)) });
+        let config = fast_config().max_retries(0).stream_timeouts(Duration::from_millis(60), Duration::from_secs(1));
+        let provider = ResilientProvider::new(inner, config);
+        
+        let mut stream = provider.complete_stream(&[], &GenerationOptions::default()).await.unwrap();
+        assert!(matches!(stream.next().await, Some(Err(AgentError::ProviderUnavailable(_)))));
+    }
+
+    #[test]
+    fn test_backoff_is_capped() {
+        let config = ResilienceConfig::default()
+            .backoff(Duration::from_millis(100), Duration::from_millis(400));
+        for attempt in 0..10 {
+            assert!(config.backoff_for(attempt) <= Duration::from_millis(400));
+        }
+        assert!(config.backoff_for(0) >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn test_jitter_spans_unit_interval() {
+        let samples: Vec<f64> = (0..2000).map(|_| jitter()).collect();
+        assert!(samples.iter().all(|x| (0.0..1.0).contains(x)));
+        assert!(samples.iter().any(|&x| x < 0.25));
+        assert!(samples.iter().any(|&x| x > 0.75));
+    }
+}
@@ -0,0 +1,309 @@
+//! Sensitive Data Detectors
+//!
+//! Hand-written scanners (no regex engine) returning byte ranges of secrets
+//! and personal data. They favour recall on secrets that can drain a wallet
+//! (seed phrases, private keys) and precision on everything else.
+
+use std::collections::HashSet;
+use std::ops::Range;
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+/// BIP-39 English wordlist (2048 words, one per line)
+const BIP39_ENGLISH: &str = include_str!("bip39_english.txt");
+
+/// Shortest run of wordlist words treated as a seed phrase
+const MIN_SEED_WORDS: usize = 12;
+
+/// Words before a long token that mark it as a credential
+const CREDENTIAL_WORDS: &[&str] = &["api", "key", "secret", "token", "passphrase", "password", "credential"];
+
+/// Words before a 64-hex token that mark it as a hash rather than a key
+const HASH_WORDS: &[&str] = &["tx", "hash", "txid", "transaction", "block", "checksum", "sha256", "commit"];
+
+/// Prefixes of well-known secret key formats
+const SECRET_PREFIXES: &[&str] = &["sk_live_", "sk_test_", "rk_live_", "whsec_", "ghp_", "github_pat_", "xoxb-", "xoxp-"];
+
+/// Kind of sensitive data
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PiiKind {
+    /// BIP-39 mnemonic (12+ wordlist words in a row)
+    SeedPhrase,
+    /// Raw hex, WIF, extended (xprv) or PEM private key
+    PrivateKey,
+    /// Exchange or service API key / secret
+    ApiKey,
+    /// Email address
+    Email,
+    /// Payment card number (Luhn-valid)
+    CardNumber,
+}
+
+impl PiiKind {
+    pub const ALL: [PiiKind; 5] = [
+        PiiKind::SeedPhrase,
+        PiiKind::PrivateKey,
+        PiiKind::ApiKey,
+        PiiKind::Email,
+        PiiKind::CardNumber,
+    ];
+    
+    pub fn name(&self) -> &'static str {
+        match self {
+            PiiKind::SeedPhrase => "seed_phrase",
+            PiiKind::PrivateKey => "private_key",
+            PiiKind::ApiKey => "api_key",
+            PiiKind::Email => "email",
+            PiiKind::CardNumber => "card_number",
+        }
+    }
+    
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name.trim())
+    }
+}
+
+impl std::fmt::Display for PiiKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A detected span of sensitive data
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub kind: PiiKind,
+    
+    /// Byte range in the scanned text
+    pub span: Range<usize>,
+}
+
+/// Find all sensitive spans, without overlaps, in text order
+///
+/// Where detectors overlap the earlier, then longer, span wins (a PEM key
+/// block also contains base64 lines that look like API keys).
+pub fn detect(text: &str) -> Vec<Finding> {
+    let mut findings = Vec::new();
+    pem_keys(text, &mut findings);
+    seed_phrases(text, &mut findings);
+    key_tokens(text, &mut findings);
+    emails(text, &mut findings);
+    card_numbers(text, &mut findings);
+    
+    findings.sort_by(|a, b| a.span.start.cmp(&b.span.start).then(b.span.end.cmp(&a.span.end)));
+    let mut kept: Vec<Finding> = Vec::with_capacity(findings.len());
+    for finding in findings {
+        if kept.last().is_none_or(|last| finding.span.start >= last.span.end) {
+            kept.push(finding);
+        }
+    }
+    kept
+}
+
+fn wordlist() -> &'static HashSet<&'static str> {
+    static WORDS: OnceLock<HashSet<&'static str>> = OnceLock::new();
+    WORDS.get_or_init(|| BIP39_ENGLISH.lines().map(str::trim).filter(|w| !w.is_empty()).collect())
+}
+
+/// Byte ranges of maximal runs of characters matching `is_part`
+fn tokens(text: &str, is_part: impl Fn(char) -> bool) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices() {
+        match (is_part(c), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                ranges.push(s..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        ranges.push(s..text.len());
+    }
+    ranges
+}
+
+/// Lowercased text of the line before `at`, up to `window` bytes
+fn context_before(text: &str, at: usize, window: usize) -> String {
+    let line_start = text[..at].rfind('\n').map_or(0, |i| i + 1);
+    let mut from = at.saturating_sub(window).max(line_start);
+    while !text.is_char_boundary(from) {
+        from += 1;
+    }
+    text[from..at].to_lowercase()
+}
+
+/// Runs of BIP-39 words, allowing list numbering ("1. abandon 2. ability")
+/// and punctuation between them
+fn seed_phrases(text: &str, findings: &mut Vec<Finding>) {
+    let words = wordlist();
+    let mut run: Vec<Range<usize>> = Vec::new();
+    
+    let mut flush = |run: &mut Vec<Range<usize>>| {
+        if run.len() >= MIN_SEED_WORDS {
+            findings.push(Finding { kind: PiiKind::SeedPhrase, span: run[0].start..run[run.len() - 1].end });
+        }
+        run.clear();
+    };
+    
+    for token in tokens(text, |c| c.is_alphanumeric()) {
+        let word = &text[token.clone()];
+        if word.chars().all(|c| c.is_ascii_digit()) && word.len() <= 2 {
+            continue;
+        }
+        if word.len() <= 8 && words.contains(word.to_ascii_lowercase().as_str()) {
+            run.push(token);
+        } else {
+            flush(&mut run);
+        }
+    }
+    flush(&mut run);
+}
+
+/// PEM private key blocks (`-----BEGIN ... PRIVATE KEY-----`)
+fn pem_keys(text: &str, findings: &mut Vec<Finding>) {
+    let mut from = 0;
+    while let Some(offset) = text[from..].find("-----BEGIN ") {
+        let start = from + offset;
+        let header_end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+        if !text[start..header_end].contains("PRIVATE KEY-----") {
+            from = header_end;
+            continue;
+        }
+        
+        // An unterminated block is redacted to the end of the text
+        let end = text[header_end..]
+            .find("-----END ")
+            .and_then(|i| text[header_end + i..].find("KEY-----").map(|j| header_end + i + j + "KEY-----".len()))
+            .unwrap_or(text.len());
+        findings.push(Finding { kind: PiiKind::PrivateKey, span: start..end });
+        from = end;
+    }
+}
+
+fn is_base58(c: char) -> bool {
+    c.is_ascii_alphanumeric() && !matches!(c, '0' | 'O' | 'I' | 'l')
+}
+
+/// Private keys and API keys, both single long tokens
+fn key_tokens(text: &str, findings: &mut Vec<Finding>) {
+    let is_part = |c: char| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '+' | '/' | '=');
+    
+    for token in tokens(text, is_part) {
+        let word = &text[token.clone()];
+        if let Some(kind) = classify_key(text, token.start, word) {
+            findings.push(Finding { kind, span: token });
+        }
+    }
+}
+
+fn classify_key(text: &str, start: usize, word: &str) -> Option<PiiKind> {
+    // Raw 32-byte hex key, unless the context says it's a hash
+    let hex = word.strip_prefix("0x").unwrap_or(word);
+    if hex.len() == 64 && hex.chars().all(|c| c.is_ascii_hexdigit()) {
+        let context = context_before(text, start, 40);
+        return (!HASH_WORDS.iter().any(|w| context.contains(w))).then_some(PiiKind::PrivateKey);
+    }
+    
+    // WIF (uncompressed 5..., compressed K.../L...) and BIP-32 extended keys
+    if word.chars().all(is_base58) {
+        let wif = (word.len() == 51 && word.starts_with('5')) || (word.len() == 52 && (word.starts_with('K') || word.starts_with('L')));
+        let extended = word.len() == 111 && ["xprv", "yprv", "zprv", "tprv"].iter().any(|p| word.starts_with(p));
+        if wif || extended {
+            return Some(PiiKind::PrivateKey);
+        }
+    }
+    
+    if SECRET_PREFIXES.iter().any(|p| word.starts_with(p)) && word.len() >= 20 {
+        return Some(PiiKind::ApiKey);
+    }
+    
+    // Long mixed letter/digit token introduced as a key or secret
+    let has_letter = word.chars().any(|c| c.is_ascii_alphabetic());
+    let has_digit = word.chars().any(|c| c.is_ascii_digit());
+    if word.len() >= 24 && has_letter && has_digit && !word.contains("//") {
+        let context = context_before(text, start, 40);
+        if CREDENTIAL_WORDS.iter().any(|w| context.contains(w)) {
+            return Some(PiiKind::ApiKey);
+        }
+    }
+    
+    None
+}
+
+fn emails(text: &str, findings: &mut Vec<Finding>) {
+    let is_part = |c: char| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '%' | '+' | '-' | '@');
+    
+    for token in tokens(text, is_part) {
+        let word = text[token.clone()].trim_end_matches('.');
+        let Some((local, domain)) = word.split_once('@') else {
+            continue;
+        };
+        let tld = domain.rsplit('.').next().unwrap_or("");
+        if !local.is_empty()
+            && !domain.contains('@')
+            && domain.contains('.')
+            && !domain.starts_with('.')
+            && !domain.contains("..")
+            && tld.len() >= 2
+            && tld.chars().all(|c| c.is_ascii_alphabetic())
+        {
+            findings.push(Finding { kind: PiiKind::Email, span: token.start..token.start + word.len() });
+        }
+    }
+}
+
+/// Digit groups separated by single spaces or dashes, 13-19 digits, Luhn-valid
+fn card_numbers(text: &str, findings: &mut Vec<Finding>) {
+    let bytes = text.as_bytes();
+    let mut i = 0;
+    
+    while i < bytes.len() {
+        let preceded = i > 0 && (bytes[i - 1].is_ascii_alphanumeric() || bytes[i - 1] == b'.');
+        if !bytes[i].is_ascii_digit() || preceded {
+            i += 1;
+            continue;
+        }
+        
+        let start = i;
+        let mut digits = Vec::new();
+        let mut end = i;
+        while i < bytes.len() {
+            if bytes[i].is_ascii_digit() {
+                digits.push(bytes[i] - b'0');
+                i += 1;
+                end = i;
+            } else if matches!(bytes[i], b' ' | b'-') && bytes.get(i + 1).is_some_and(u8::is_ascii_digit) {
+                i += 1;
+            } else {
+                break;
+            }
+        }
+        
+        let followed = bytes.get(end).is_some_and(|b| b.is_ascii_alphanumeric());
+        if (13..=19).contains(&digits.len()) && !followed && luhn(&digits) {
+            findings.push(Finding { kind: PiiKind::CardNumber, span: start..end });
+        }
+    }
+}
+
+fn luhn(digits: &[u8]) -> bool {
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, &d)| {
+            let d = u32::from(d);
+            if i % 2 == 1 {
+                if d * 2 > 9 { d * 2 - 9 } else { d * 2 }
+            } else {
+                d
+            }
+        })
+        .sum();
+    sum % 10 == 0
+}
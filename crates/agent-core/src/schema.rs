@@ -0,0 +1,211 @@
+//! JSON Schema Validation
+//!
+//! Minimal validator for the subset of JSON Schema used for structured output:
+//! `type`, `properties`, `required`, `additionalProperties`, `items`, `enum`,
+//! `minimum`/`maximum`, `minLength`/`maxLength` and `minItems`/`maxItems`.
+//! Unknown keywords are ignored, so schemas generated by other tools still work.
+
+use serde_json::Value;
+
+/// Validate `value` against `schema`, returning a list of violations
+///
+/// An empty list means the value conforms. Each entry is prefixed with the
+/// JSON pointer of the offending location (e.g. `/allocations/0/percent`).
+pub fn validate(schema: &Value, value: &Value) -> Vec<String> {
+    let mut errors = Vec::new();
+    validate_at(schema, value, "", &mut errors);
+    errors
+}
+
+fn validate_at(schema: &Value, value: &Value, path: &str, errors: &mut Vec<String>) {
+    let Some(schema) = schema.as_object() else {
+        return; // `true` / `{}` accept anything
+    };
+    let location = if path.is_empty() { "/" } else { path };
+    
+    if let Some(expected) = schema.get("type") {
+        let matches = match expected {
+            Value::String(t) => type_matches(t, value),
+            Value::Array(types) => types
+                .iter()
+                .filter_map(Value::as_str)
+                .any(|t| type_matches(t, value)),
+            _ => true,
+        };
+        if !matches {
+            errors.push(format!(
+                "{}: expected type {}, got {}",
+                location,
+                expected,
+                type_name(value)
+            ));
+            return;
+        }
+    }
+    
+    if let Some(Value::Array(allowed)) = schema.get("enum") {
+        if !allowed.contains(value) {
+            errors.push(format!("{}: value {} is not one of {}", location, value, Value::Array(allowed.clone())));
+        }
+    }
+    
+    match value {
+        Value::Object(map) => {
+            if let Some(Value::Array(required)) = schema.get("required") {
+                for name in required.iter().filter_map(Value::as_str) {
+                    if !map.contains_key(name) {
+                        errors.push(format!("{}: missing required property '{}'", location, name));
+                    }
+                }
+            }
+            
+            let properties = schema.get("properties").and_then(Value::as_object);
+            for (key, child) in map {
+                let child_path = format!("{}/{}", path, key);
+                match properties.and_then(|p| p.get(key)) {
+                    Some(child_schema) => validate_at(child_schema, child, &child_path, errors),
+                    None => match schema.get("additionalProperties") {
+                        Some(Value::Bool(false)) => {
+                            errors.push(format!("{}: unexpected property '{}'", location, key));
+                        }
+                        Some(extra @ Value::Object(_)) => validate_at(extra, child, &child_path, errors),
+                        _ => {}
+                    },
+                }
+            }
+        }
+        Value::Array(items) => {
+            if let Some(min) = schema.get("minItems").and_then(Value::as_u64) {
+                if (items.len() as u64) < min {
+                    errors.push(format!("{}: expected at least {} items, got {}", location, min, items.len()));
+                }
+            }
+            if let Some(max) = schema.get("maxItems").and_then(Value::as_u64) {
+                if items.len() as u64 > max {
+                    errors.push(format!("{}: expected at most {} items, got {}", location, max, items.len()));
+                }
+            }
+            if let Some(item_schema) = schema.get("items") {
+                for (i, item) in items.iter().enumerate() {
+                    validate_at(item_schema, item, &format!("{}/{}", path, i), errors);
+                }
+            }
+        }
+        Value::Number(n) => {
+            let n = n.as_f64().unwrap_or(0.0);
+            if let Some(min) = schema.get("minimum").and_then(Value::as_f64) {
+                if n < min {
+                    errors.push(format!("{}: {} is less than minimum {}", location, n, min));
+                }
+            }
+            if let Some(max) = schema.get("maximum").and_then(Value::as_f64) {
+                if n > max {
+                    errors.push(format!("{}: {} is greater than maximum {}", location, n, max));
+                }
+            }
+        }
+        Value::String(s) => {
+            let len = s.chars().count() as u64;
+            if let Some(min) = schema.get("minLength").and_then(Value::as_u64) {
+                if len < min {
+                    errors.push(format!("{}: string shorter than {} characters", location, min));
+                }
+            }
+            if let Some(max) = schema.get("maxLength").and_then(Value::as_u64) {
+                if len > max {
+                    errors.push(format!("{}: string longer than {} characters", location, max));
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+fn type_matches(expected: &str, value: &Value) -> bool {
+    match expected {
+        "object" => value.is_object(),
+        "array" => value.is_array(),
+        "string" => value.is_string(),
+        "boolean" => value.is_boolean(),
+        "null" => value.is_null(),
+        "number" => value.is_number(),
+        "integer" => value.as_i64().is_some() || value.as_u64().is_some()
+            || value.as_f64().is_some_and(|f| f.fract() == 0.0),
+        _ => true,
+    }
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+/// Extract a JSON document from an LLM response
+///
+/// Accepts bare JSON, or JSON wrapped in a ```json / ``` fenced block with
+/// surrounding prose.
+pub fn extract_json(content: &str) -> Option<Value> {
+    let trimmed = content.trim();
+    if let Ok(value) = serde_json::from_str(trimmed) {
+        return Some(value);
+    }
+    
+    if let Some(start) = trimmed.find("```") {
+        let after = &trimmed[start + 3..];
+        let after = after.strip_prefix("json").unwrap_or(after);
+        if let Some(end) = after.find("```") {
+            if let Ok(value) = serde_json::from_str(after[..end].trim()) {
+                return Some(value);
+            }
+        }
+    }
+    
+    // Fall back to the outermost object/array in the text
+    let start = trimmed.find(['{', '['])?;
+    let close = if trimmed[start..].starts_with('{') { '}' } else { ']' };
+    let end = trimmed.rfind(close)?;
+    if end <= start {
+        return None;
+    }
+    serde_json::from_str(&trimmed[start..=end]).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_validate_object() {
+        let schema = json!({
+            "type": "object",
+            "required": ["symbol", "percent"],
+            "properties": {
+                "symbol": {"type": "string"},
+                "percent": {"type": "number", "minimum": 0, "maximum": 100}
+            },
+            "additionalProperties": false
+        });
+        
+        assert!(validate(&schema, &json!({"symbol": "BTC", "percent": 20})).is_empty());
+        
+        let errors = validate(&schema, &json!({"percent": 120, "extra": 1}));
+        assert_eq!(errors.len(), 3);
+        assert!(errors.iter().any(|e| e.contains("missing required property 'symbol'")));
+        assert!(errors.iter().any(|e| e.starts_with("/percent")));
+    }
+
+    #[test]
+    fn test_extract_json() {
+        let content = "Here is the plan:\n```json\n{\"a\": 1}\n```\nThanks";
+        assert_eq!(extract_json(content), Some(json!({"a": 1})));
+        assert_eq!(extract_json("[1, 2]"), Some(json!([1, 2])));
+        assert_eq!(extract_json("no json here"), None);
+    }
+}
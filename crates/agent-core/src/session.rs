@@ -7,6 +7,7 @@ use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 use crate::message::Conversation;
+use crate::prompt::RenderedPrompt;
 
 /// Unique session identifier
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -53,6 +54,14 @@ pub struct SessionMetadata {
     /// Custom tags
     pub tags: Vec<String>,
     
+    /// Prompt template that produced this session (`name@version`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_version: Option<String>,
+    
+    /// Automatic titling and tagging has run (it may have found no labels)
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub auto_labeled: bool,
+    
     /// Extra key-value metadata
     #[serde(flatten)]
     pub extra: std::collections::HashMap<String, serde_json::Value>,
@@ -65,6 +74,8 @@ impl Default for SessionMetadata {
             user_id: None,
             model: "llama3.2".into(),
             tags: Vec::new(),
+            prompt_version: None,
+            auto_labeled: false,
             extra: std::collections::HashMap::new(),
         }
     }
@@ -120,6 +131,13 @@ impl Session {
         session
     }
     
+    /// Create from a rendered prompt template, recording its version
+    pub fn with_prompt(prompt: &RenderedPrompt) -> Self {
+        let mut session = Self::with_system_prompt(prompt.text.clone());
+        session.metadata.prompt_version = Some(prompt.id());
+        session
+    }
+    
     /// Update the activity timestamp
     pub fn touch(&mut self) {
         self.updated_at = Utc::now();
@@ -141,7 +159,7 @@ impl Session {
                 .find(|m| m.role == crate::message::Role::User)
                 .map(|m| {
                     let preview: String = m.content.chars().take(50).collect();
-                    if m.content.len() > 50 {
+                    if m.content.chars().count() > 50 {
                         format!("{}...", preview)
                     } else {
                         preview
@@ -187,6 +205,27 @@ pub trait SessionStore: Send + Sync {
     
     /// List sessions for a user
     fn list(&self, user_id: Option<&str>, limit: usize) -> crate::Result<Vec<Session>>;
+    
+    /// Change a stored session, returning it as saved (`None` if it doesn't exist)
+    ///
+    /// The default loads and saves; stores that can should apply the change
+    /// atomically, so concurrent updates don't overwrite each other.
+    fn update(&self, id: &SessionId, change: &mut dyn FnMut(&mut Session)) -> crate::Result<Option<Session>> {
+        let Some(mut session) = self.load(id)? else {
+            return Ok(None);
+        };
+        change(&mut session);
+        self.save(&session)?;
+        Ok(Some(session))
+    }
+    
+    /// List sessions carrying a tag (most recent first)
+    fn list_by_tag(&self, tag: &str, user_id: Option<&str>, limit: usize) -> crate::Result<Vec<Session>> {
+        let mut sessions = self.list(user_id, usize::MAX)?;
+        sessions.retain(|s| s.metadata.tags.iter().any(|t| t == tag));
+        sessions.truncate(limit);
+        Ok(sessions)
+    }
 }
 
 /// In-memory session store (for development/testing)
@@ -226,6 +265,14 @@ impl SessionStore for MemorySessionStore {
         Ok(())
     }
     
+    fn update(&self, id: &SessionId, change: &mut dyn FnMut(&mut Session)) -> crate::Result<Option<Session>> {
+        let mut sessions = self.sessions.write().unwrap();
+        Ok(sessions.get_mut(id).map(|session| {
+            change(session);
+            session.clone()
+        }))
+    }
+    
     fn list(&self, user_id: Option<&str>, limit: usize) -> crate::Result<Vec<Session>> {
         let sessions = self.sessions.read().unwrap();
         let mut result: Vec<_> = sessions
@@ -247,6 +294,7 @@ impl SessionStore for MemorySessionStore {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::message::Message;
 
     #[test]
     fn test_session_creation() {
@@ -255,6 +303,16 @@ mod tests {
         assert_eq!(session.message_count(), 0);
     }
 
+    #[test]
+    fn test_title_truncates_by_chars() {
+        let mut session = Session::new();
+        session.conversation.push(Message::user("€".repeat(50)));
+        assert_eq!(session.title(), "€".repeat(50));
+        
+        session.conversation.messages_mut()[0].content = "€".repeat(51);
+        assert_eq!(session.title(), format!("{}...", "€".repeat(50)));
+    }
+
     #[test]
     fn test_memory_store() {
         let store = MemorySessionStore::new();
@@ -266,5 +324,10 @@ mod tests {
         let loaded = store.load(&id).unwrap();
         assert!(loaded.is_some());
         assert_eq!(loaded.unwrap().id, id);
+        
+        let updated = store.update(&id, &mut |s| s.metadata.tags.push("btc".into())).unwrap().unwrap();
+        assert_eq!(updated.metadata.tags, ["btc"]);
+        assert_eq!(store.load(&id).unwrap().unwrap().metadata.tags, ["btc"]);
+        assert!(store.update(&SessionId::new(), &mut |_| {}).unwrap().is_none());
     }
 }
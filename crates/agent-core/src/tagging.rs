@@ -0,0 +1,259 @@
+//! Session Titling and Tagging
+//!
+//! After the first exchange of a session, `SessionTagger` asks a model for a
+//! short title and a few tags. Tags are normalized to lowercase kebab-case
+//! (`dca-plan`, `btc`) so they can be used as filters (see
+//! `SessionStore::list_by_tag`).
+//!
+//! The default instructions are generic; domains supply their own tag
+//! vocabulary (see `crypto_advisor::TAGGING_INSTRUCTIONS`).
+
+use std::sync::Arc;
+
+use serde::Deserialize;
+
+use crate::error::{AgentError, Result};
+use crate::message::{Message, Role};
+use crate::provider::{GenerationOptions, LlmProvider, TokenUsage};
+use crate::session::{Session, SessionMetadata};
+
+/// Longest generated title, in characters
+const MAX_TITLE_CHARS: usize = 60;
+
+const DEFAULT_INSTRUCTIONS: &str = "Tags: the main topics of the conversation and the user's intent, e.g. \"how-to\", \"comparison\", \"troubleshooting\".";
+
+const TAGGER_PROMPT: &str = r#"You label conversations. Given the first exchange of a conversation, write a short title (at most 6 words, no quotes) and a few lowercase kebab-case tags.
+
+{{instructions}}
+
+Respond ONLY with JSON: {"title": "<title>", "tags": ["<tag>", ...]}"#;
+
+#[derive(Deserialize)]
+struct TaggerResponse {
+    #[serde(default)]
+    title: String,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+/// A generated title and tags
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SessionLabels {
+    pub title: Option<String>,
+    pub tags: Vec<String>,
+    
+    /// Tokens spent generating them
+    pub usage: Option<TokenUsage>,
+}
+
+impl SessionLabels {
+    /// The title and tags a session already carries
+    pub fn of(metadata: &SessionMetadata) -> Self {
+        Self {
+            title: metadata.title.clone(),
+            tags: metadata.tags.clone(),
+            usage: None,
+        }
+    }
+    
+    /// Set the title unless one exists, and add new tags
+    pub fn apply(&self, metadata: &mut SessionMetadata) {
+        if metadata.title.is_none() {
+            metadata.title = self.title.clone();
+        }
+        for tag in &self.tags {
+            if !metadata.tags.contains(tag) {
+                metadata.tags.push(tag.clone());
+            }
+        }
+    }
+}
+
+/// Generates session titles and tags with an LLM
+pub struct SessionTagger {
+    provider: Arc<dyn LlmProvider>,
+    model: String,
+    instructions: String,
+    max_tags: usize,
+}
+
+impl SessionTagger {
+    pub fn new(provider: Arc<dyn LlmProvider>, model: impl Into<String>) -> Self {
+        Self {
+            provider,
+            model: model.into(),
+            instructions: DEFAULT_INSTRUCTIONS.into(),
+            max_tags: 6,
+        }
+    }
+    
+    /// What the tags should describe (replaces the generic default)
+    pub fn instructions(mut self, instructions: impl Into<String>) -> Self {
+        self.instructions = instructions.into();
+        self
+    }
+    
+    pub fn max_tags(mut self, max: usize) -> Self {
+        self.max_tags = max;
+        self
+    }
+    
+    /// Whether a session has had its first exchange but was never labeled
+    ///
+    /// Set `auto_labeled` before labeling, so a tagger finding nothing isn't asked again.
+    pub fn needs_labels(session: &Session) -> bool {
+        let metadata = &session.metadata;
+        !metadata.auto_labeled
+            && metadata.title.is_none()
+            && metadata.tags.is_empty()
+            && session.conversation.messages().iter().any(|m| m.role == Role::Assistant)
+    }
+    
+    /// Generate a title and tags from a session's first exchange
+    pub async fn label(&self, session: &Session) -> Result<SessionLabels> {
+        let messages = session.conversation.messages();
+        let question = messages.iter().find(|m| m.role == Role::User);
+        let answer = messages.iter().rev().find(|m| m.role == Role::Assistant);
+        let (Some(question), Some(answer)) = (question, answer) else {
+            return Err(AgentError::Session("Session has no exchange to label".into()));
+        };
+        
+        let schema = serde_json::json!({
+            "type": "object",
+            "required": ["title", "tags"],
+            "properties": {
+                "title": {"type": "string"},
+                "tags": {"type": "array", "items": {"type": "string"}}
+            }
+        });
+        let options = GenerationOptions {
+            model: self.model.clone(),
+            temperature: 0.0,
+            response_format: Some(schema),
+            ..Default::default()
+        };
+        let prompt = [
+            Message::system(TAGGER_PROMPT.replace("{{instructions}}", &self.instructions)),
+            Message::user(format!("USER:\n{}\n\nASSISTANT:\n{}", question.content, answer.content)),
+        ];
+        
+        let completion = self.provider.complete(&prompt, &options).await?;
+        let response = crate::schema::extract_json(&completion.content)
+            .and_then(|value| serde_json::from_value::<TaggerResponse>(value).ok())
+            .ok_or_else(|| AgentError::Parse("Tagger response is not valid JSON".into()))?;
+        
+        let mut tags: Vec<String> = Vec::new();
+        for tag in response.tags.iter().filter_map(|t| normalize_tag(t)) {
+            if !tags.contains(&tag) && tags.len() < self.max_tags {
+                tags.push(tag);
+            }
+        }
+        
+        Ok(SessionLabels {
+            title: clean_title(&response.title),
+            tags,
+            usage: completion.usage,
+        })
+    }
+}
+
+/// Lowercase kebab-case (`DCA Plan` -> `dca-plan`), or None if nothing is left
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let mut out = String::new();
+    for c in tag.trim().chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() {
+            out.push(c);
+        } else if !out.is_empty() && !out.ends_with('-') {
+            out.push('-');
+        }
+    }
+    let out = out.trim_end_matches('-');
+    (!out.is_empty()).then(|| out.to_string())
+}
+
+/// One line, no wrapping quotes, at most `MAX_TITLE_CHARS` characters
+fn clean_title(title: &str) -> Option<String> {
+    let line = title.lines().next().unwrap_or("").trim();
+    let line = line.trim_matches(|c| matches!(c, '"' | '\'' | '*' | '#' | '`')).trim();
+    if line.is_empty() {
+        return None;
+    }
+    if line.chars().count() > MAX_TITLE_CHARS {
+        let cut: String = line.chars().take(MAX_TITLE_CHARS - 3).collect();
+        return Some(format!("{}...", cut.trim_end()));
+    }
+    Some(line.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::provider::{Completion, CompletionStream, FinishReason, ModelInfo, ProviderInfo};
+    use crate::session::{MemorySessionStore, SessionStore};
+    use async_trait::async_trait;
+
+    /// Provider answering every request with the same text
+    struct FixedProvider(&'static str);
+
+    #[async_trait]
+    impl LlmProvider for FixedProvider {
+        async fn info(&self) -> Result<ProviderInfo> {
+            Err(AgentError::Other("unused".into()))
+        }
+        
+        async fn health_check(&self) -> Result<bool> {
+            Ok(true)
+        }
+        
+        async fn complete(&self, _: &[Message], options: &GenerationOptions) -> Result<Completion> {
+            Ok(Completion {
+                content: self.0.to_string(),
+                model: options.model.clone(),
+                usage: None,
+                truncated: false,
+                finish_reason: Some(FinishReason::Stop),
+                escalations: Vec::new(),
+            })
+        }
+        
+        async fn complete_stream(&self, _: &[Message], _: &GenerationOptions) -> Result<CompletionStream> {
+            Err(AgentError::Other("unused".into()))
+        }
+        
+        async fn list_models(&self) -> Result<Vec<ModelInfo>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_label_and_list_by_tag() {
+        let reply = r#"{"title": "\"Monthly BTC and ETH DCA plan\"", "tags": ["BTC", "eth", "DCA Plan", "dca-plan", "Risk: Conservative"]}"#;
+        let tagger = SessionTagger::new(Arc::new(FixedProvider(reply)), "small");
+        
+        let mut session = Session::new();
+        session.metadata.user_id = Some("u1".into());
+        assert!(!SessionTagger::needs_labels(&session));
+        session.conversation.push(Message::user("How should I DCA $500 a month into BTC and ETH?"));
+        session.conversation.push(Message::assistant("Split it 60/40 ..."));
+        assert!(SessionTagger::needs_labels(&session));
+        
+        let labels = tagger.label(&session).await.unwrap();
+        assert_eq!(labels.title.as_deref(), Some("Monthly BTC and ETH DCA plan"));
+        assert_eq!(labels.tags, vec!["btc", "eth", "dca-plan", "risk-conservative"]);
+        
+        labels.apply(&mut session.metadata);
+        assert!(!SessionTagger::needs_labels(&session));
+        
+        let mut unlabeled = session.clone();
+        unlabeled.metadata.title = None;
+        unlabeled.metadata.tags.clear();
+        unlabeled.metadata.auto_labeled = true;
+        assert!(!SessionTagger::needs_labels(&unlabeled));
+        
+        let store = MemorySessionStore::new();
+        store.save(&session).unwrap();
+        store.save(&Session::new()).unwrap();
+        assert_eq!(store.list_by_tag("dca-plan", Some("u1"), 10).unwrap().len(), 1);
+        assert!(store.list_by_tag("sol", None, 10).unwrap().is_empty());
+    }
+}
@@ -7,13 +7,15 @@ use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
+use tracing::Instrument;
 
 use crate::error::{AgentError, Result};
 
 /// Tool call request from the LLM
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ToolCall {
-    /// Tool identifier
+    /// Tool identifier (the system prompt asks for `"tool"`)
+    #[serde(alias = "tool")]
     pub name: String,
     
     /// Arguments as key-value pairs
@@ -150,6 +152,7 @@ pub trait Tool: Send + Sync {
 }
 
 /// Registry for available tools
+#[derive(Clone)]
 pub struct ToolRegistry {
     tools: HashMap<String, Arc<dyn Tool>>,
 }
@@ -179,6 +182,18 @@ impl ToolRegistry {
         self.tools.insert(schema.name.clone(), tool);
     }
     
+    /// Keep only the tools whose names match a predicate
+    pub fn retain(&mut self, mut keep: impl FnMut(&str) -> bool) {
+        self.tools.retain(|name, _| keep(name));
+    }
+    
+    /// Replace every tool with a decorated version (such as `MeteredTool`)
+    pub fn wrap(&mut self, mut decorate: impl FnMut(Arc<dyn Tool>) -> Arc<dyn Tool>) {
+        for tool in self.tools.values_mut() {
+            *tool = decorate(tool.clone());
+        }
+    }
+    
     /// Get a tool by name
     pub fn get(&self, name: &str) -> Option<Arc<dyn Tool>> {
         self.tools.get(name).cloned()
@@ -194,7 +209,7 @@ impl ToolRegistry {
         tool.validate(call)?;
         
         // Execute
-        tool.execute(call).await
+        tool.execute(call).instrument(tracing::info_span!("tool.call", tool = %call.name)).await
     }
     
     /// Get all tool schemas (for system prompt generation)
@@ -417,4 +432,16 @@ mod tests {
         assert!(registry.get("calculate").is_some());
         assert!(registry.get("unknown").is_none());
     }
+
+    #[test]
+    fn test_tool_call_key() {
+        // The system prompt's format, and the serialized form
+        let call: ToolCall = serde_json::from_str(r#"{"tool": "calculate", "arguments": {"expression": "2 + 2"}}"#).unwrap();
+        assert_eq!(call.name, "calculate");
+        assert_eq!(call.arguments["expression"], "2 + 2");
+        
+        let call: ToolCall = serde_json::from_str(r#"{"name": "datetime", "arguments": {}}"#).unwrap();
+        assert_eq!(call.name, "datetime");
+        assert_eq!(serde_json::to_value(&call).unwrap()["name"], "datetime");
+    }
 }
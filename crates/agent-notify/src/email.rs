@@ -0,0 +1,178 @@
+//! Email over SMTP
+
+use std::time::Duration;
+
+use async_trait::async_trait;
+use lettre::message::{header::ContentType, Mailbox};
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+use serde::{Deserialize, Serialize};
+
+use crate::error::{NotifyError, Result};
+use crate::{Channel, Notifier, Preferences, Rendered};
+
+/// How the SMTP connection is secured
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpTls {
+    /// Plain text (local relays and test sinks only)
+    None,
+    /// Upgrade with STARTTLS (usually port 587)
+    #[default]
+    StartTls,
+    /// TLS from the start (usually port 465)
+    Tls,
+}
+
+/// SMTP relay settings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    
+    /// Sender, e.g. `Advisor <alerts@example.com>`
+    pub from: String,
+    
+    pub tls: SmtpTls,
+    pub timeout: Duration,
+}
+
+impl SmtpConfig {
+    pub fn new(host: impl Into<String>, port: u16, from: impl Into<String>) -> Self {
+        Self {
+            host: host.into(),
+            port,
+            username: None,
+            password: None,
+            from: from.into(),
+            tls: SmtpTls::default(),
+            timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+/// Sends notifications as plain-text email to the user's address
+pub struct EmailNotifier {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+}
+
+impl EmailNotifier {
+    pub fn new(config: &SmtpConfig) -> Result<Self> {
+        let from = config
+            .from
+            .parse::<Mailbox>()
+            .map_err(|e| NotifyError::InvalidAddress(format!("{}: {}", config.from, e)))?;
+        
+        let builder = match config.tls {
+            SmtpTls::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(&config.host),
+            SmtpTls::StartTls => AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&config.host)
+                .map_err(|e| NotifyError::Email(e.to_string()))?,
+            SmtpTls::Tls => AsyncSmtpTransport::<Tokio1Executor>::relay(&config.host)
+                .map_err(|e| NotifyError::Email(e.to_string()))?,
+        };
+        let mut builder = builder.port(config.port).timeout(Some(config.timeout));
+        if let (Some(username), Some(password)) = (&config.username, &config.password) {
+            builder = builder.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+        
+        Ok(Self { transport: builder.build(), from })
+    }
+}
+
+#[async_trait]
+impl Notifier for EmailNotifier {
+    fn channel(&self) -> Channel {
+        Channel::Email
+    }
+    
+    async fn deliver(&self, message: &Rendered, preferences: &Preferences) -> Result<()> {
+        let address = preferences.email.as_deref().ok_or(NotifyError::NoAddress(Channel::Email))?;
+        let to = address
+            .parse::<Mailbox>()
+            .map_err(|e| NotifyError::InvalidAddress(format!("{}: {}", address, e)))?;
+        
+        let email = Message::builder()
+            .from(self.from.clone())
+            .to(to)
+            .subject(message.subject.clone())
+            .header(ContentType::TEXT_PLAIN)
+            .body(message.body.clone())
+            .map_err(|e| NotifyError::Email(e.to_string()))?;
+        
+        self.transport.send(email).await.map_err(|e| NotifyError::Email(e.to_string()))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Notification, NotificationKind};
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// Minimal SMTP server accepting one message; returns the envelope and data
+    async fn smtp_sink(listener: TcpListener) -> Vec<String> {
+        let (stream, _) = listener.accept().await.unwrap();
+        let (read, mut write) = stream.into_split();
+        let mut lines = BufReader::new(read).lines();
+        let mut received = Vec::new();
+        
+        write.write_all(b"220 sink ESMTP\r\n").await.unwrap();
+        while let Some(line) = lines.next_line().await.unwrap() {
+            let command = line.to_ascii_uppercase();
+            let reply: &[u8] = if command.starts_with("EHLO") || command.starts_with("HELO") {
+                b"250 sink\r\n"
+            } else if command.starts_with("DATA") {
+                write.write_all(b"354 go ahead\r\n").await.unwrap();
+                while let Some(line) = lines.next_line().await.unwrap() {
+                    if line == "." {
+                        break;
+                    }
+                    received.push(line);
+                }
+                b"250 queued\r\n"
+            } else if command.starts_with("QUIT") {
+                write.write_all(b"221 bye\r\n").await.unwrap();
+                break;
+            } else {
+                received.push(line);
+                b"250 ok\r\n"
+            };
+            write.write_all(reply).await.unwrap();
+        }
+        received
+    }
+
+    #[tokio::test]
+    async fn test_email_delivered_over_smtp() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let sink = tokio::spawn(smtp_sink(listener));
+        
+        let mut config = SmtpConfig::new("127.0.0.1", port, "Advisor <alerts@example.com>");
+        config.tls = SmtpTls::None;
+        let notifier = EmailNotifier::new(&config).unwrap();
+        
+        let preferences = Preferences { email: Some("ana@example.com".into()), ..Preferences::default() };
+        let message = Rendered {
+            notification: Notification::new("ana", NotificationKind::PriceAlert),
+            subject: "Price alert: BTC".into(),
+            body: "BTC crossed $100000".into(),
+        };
+        notifier.deliver(&message, &preferences).await.unwrap();
+        
+        let received = sink.await.unwrap();
+        assert!(received.iter().any(|line| line == "MAIL FROM:<alerts@example.com>"));
+        assert!(received.iter().any(|line| line == "RCPT TO:<ana@example.com>"));
+        assert!(received.iter().any(|line| line == "Subject: Price alert: BTC"));
+        assert!(received.iter().any(|line| line == "BTC crossed $100000"));
+        
+        // No address configured
+        let result = notifier.deliver(&message, &Preferences::default()).await;
+        assert!(matches!(result, Err(NotifyError::NoAddress(Channel::Email))));
+    }
+}
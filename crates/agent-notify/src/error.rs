@@ -0,0 +1,36 @@
+//! Notification Error Types
+
+use thiserror::Error;
+
+use crate::Channel;
+
+/// Result type alias
+pub type Result<T> = std::result::Result<T, NotifyError>;
+
+/// Notification errors
+#[derive(Error, Debug)]
+pub enum NotifyError {
+    /// Template failed to load or render
+    #[error("Template error: {0}")]
+    Template(String),
+    
+    /// The user has no address for a channel they enabled
+    #[error("No {0} address configured")]
+    NoAddress(Channel),
+    
+    /// Email address or webhook URL rejected
+    #[error("Invalid address: {0}")]
+    InvalidAddress(String),
+    
+    /// SMTP delivery failed
+    #[error("Email delivery failed: {0}")]
+    Email(String),
+    
+    /// Webhook delivery failed (after retries)
+    #[error("Webhook delivery failed: {0}")]
+    Webhook(String),
+    
+    /// Storage error
+    #[error("Storage error: {0}")]
+    Storage(String),
+}
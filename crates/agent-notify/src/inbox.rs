@@ -0,0 +1,251 @@
+//! In-app Inbox
+//!
+//! Every user has an inbox of rendered notifications, newest first, which the
+//! web UI reads and marks as read. Only the most recent items are kept.
+
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, RwLock};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{NotifyError, Result};
+use crate::{Channel, NotificationKind, Notifier, Preferences, Rendered};
+
+/// Items kept per user; older ones are dropped
+pub const DEFAULT_INBOX_CAPACITY: usize = 200;
+
+/// A notification in a user's inbox
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InboxItem {
+    pub id: Uuid,
+    pub kind: NotificationKind,
+    pub subject: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub read: bool,
+}
+
+/// Storage for user inboxes
+pub trait Inbox: Send + Sync {
+    fn push(&self, user_id: &str, item: InboxItem) -> Result<()>;
+    
+    /// Newest first
+    fn list(&self, user_id: &str, unread_only: bool, limit: usize) -> Result<Vec<InboxItem>>;
+    
+    /// Returns false if the user has no such item
+    fn mark_read(&self, user_id: &str, id: Uuid) -> Result<bool>;
+    
+    /// Returns how many items were unread
+    fn mark_all_read(&self, user_id: &str) -> Result<usize>;
+    
+    fn unread_count(&self, user_id: &str) -> Result<usize>;
+}
+
+/// In-memory inboxes, optionally saved to a JSON file after every change
+pub struct MemoryInbox {
+    capacity: usize,
+    path: Option<PathBuf>,
+    items: RwLock<HashMap<String, VecDeque<InboxItem>>>,
+}
+
+impl Default for MemoryInbox {
+    fn default() -> Self {
+        Self::new(DEFAULT_INBOX_CAPACITY)
+    }
+}
+
+impl MemoryInbox {
+    pub fn new(capacity: usize) -> Self {
+        Self { capacity: capacity.max(1), path: None, items: RwLock::new(HashMap::new()) }
+    }
+    
+    /// Load the inboxes saved at `path` (none if the file doesn't exist yet)
+    pub fn open(path: impl Into<PathBuf>, capacity: usize) -> Result<Self> {
+        let path = path.into();
+        let items: HashMap<String, VecDeque<InboxItem>> = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| NotifyError::Storage(format!("{}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(NotifyError::Storage(format!("{}: {}", path.display(), e))),
+        };
+        
+        Ok(Self { capacity: capacity.max(1), path: Some(path), items: RwLock::new(items) })
+    }
+    
+    /// Apply `change` to a user's inbox and save, undoing it if the save fails
+    fn change<T>(&self, user_id: &str, change: impl FnOnce(&mut VecDeque<InboxItem>) -> T) -> Result<T> {
+        let mut items = self.items.write().map_err(|e| NotifyError::Storage(e.to_string()))?;
+        let previous = items.get(user_id).cloned();
+        let result = change(items.entry(user_id.to_string()).or_default());
+        if let Err(e) = save(self.path.as_ref(), &items) {
+            match previous {
+                Some(previous) => items.insert(user_id.to_string(), previous),
+                None => items.remove(user_id),
+            };
+            return Err(e);
+        }
+        Ok(result)
+    }
+}
+
+/// Write all inboxes to a temporary file, then move it over the old one
+fn save(path: Option<&PathBuf>, items: &HashMap<String, VecDeque<InboxItem>>) -> Result<()> {
+    let Some(path) = path else {
+        return Ok(());
+    };
+    let write = || -> std::io::Result<()> {
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            std::fs::create_dir_all(parent)?;
+        }
+        let items: BTreeMap<_, _> = items.iter().filter(|(_, inbox)| !inbox.is_empty()).collect();
+        
+        let tmp = path.with_extension("tmp");
+        std::fs::write(&tmp, serde_json::to_vec_pretty(&items)?)?;
+        std::fs::rename(&tmp, path)
+    };
+    write().map_err(|e| NotifyError::Storage(format!("{}: {}", path.display(), e)))
+}
+
+impl Inbox for MemoryInbox {
+    fn push(&self, user_id: &str, item: InboxItem) -> Result<()> {
+        let capacity = self.capacity;
+        self.change(user_id, |inbox| {
+            inbox.push_front(item);
+            inbox.truncate(capacity);
+        })
+    }
+    
+    fn list(&self, user_id: &str, unread_only: bool, limit: usize) -> Result<Vec<InboxItem>> {
+        let items = self.items.read().map_err(|e| NotifyError::Storage(e.to_string()))?;
+        Ok(items
+            .get(user_id)
+            .into_iter()
+            .flatten()
+            .filter(|item| !unread_only || !item.read)
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+    
+    fn mark_read(&self, user_id: &str, id: Uuid) -> Result<bool> {
+        self.change(user_id, |inbox| {
+            let item = inbox.iter_mut().find(|item| item.id == id);
+            item.map(|item| item.read = true).is_some()
+        })
+    }
+    
+    fn mark_all_read(&self, user_id: &str) -> Result<usize> {
+        self.change(user_id, |inbox| {
+            let mut marked = 0;
+            for item in inbox.iter_mut().filter(|item| !item.read) {
+                item.read = true;
+                marked += 1;
+            }
+            marked
+        })
+    }
+    
+    fn unread_count(&self, user_id: &str) -> Result<usize> {
+        let items = self.items.read().map_err(|e| NotifyError::Storage(e.to_string()))?;
+        Ok(items.get(user_id).map_or(0, |inbox| inbox.iter().filter(|item| !item.read).count()))
+    }
+}
+
+/// Delivers to the in-app inbox
+pub struct InboxNotifier {
+    inbox: Arc<dyn Inbox>,
+}
+
+impl InboxNotifier {
+    pub fn new(inbox: Arc<dyn Inbox>) -> Self {
+        Self { inbox }
+    }
+}
+
+#[async_trait]
+impl Notifier for InboxNotifier {
+    fn channel(&self) -> Channel {
+        Channel::InApp
+    }
+    
+    async fn deliver(&self, message: &Rendered, _preferences: &Preferences) -> Result<()> {
+        let notification = &message.notification;
+        self.inbox.push(
+            &notification.user_id,
+            InboxItem {
+                id: notification.id,
+                kind: notification.kind,
+                subject: message.subject.clone(),
+                body: message.body.clone(),
+                created_at: notification.created_at,
+                read: false,
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn item(subject: &str) -> InboxItem {
+        InboxItem {
+            id: Uuid::new_v4(),
+            kind: NotificationKind::PriceAlert,
+            subject: subject.into(),
+            body: String::new(),
+            created_at: Utc::now(),
+            read: false,
+        }
+    }
+
+    #[test]
+    fn test_memory_inbox() {
+        let inbox = MemoryInbox::new(2);
+        let first = item("first");
+        inbox.push("ana", first.clone()).unwrap();
+        inbox.push("ana", item("second")).unwrap();
+        inbox.push("ana", item("third")).unwrap();
+        
+        // Capped, newest first
+        let items = inbox.list("ana", false, 10).unwrap();
+        let subjects: Vec<_> = items.iter().map(|item| item.subject.as_str()).collect();
+        assert_eq!(subjects, ["third", "second"]);
+        assert!(!inbox.mark_read("ana", first.id).unwrap());
+        
+        assert!(inbox.mark_read("ana", items[0].id).unwrap());
+        assert!(!inbox.mark_read("bob", items[1].id).unwrap());
+        assert_eq!(inbox.unread_count("ana").unwrap(), 1);
+        assert_eq!(inbox.list("ana", true, 10).unwrap()[0].subject, "second");
+        
+        assert_eq!(inbox.mark_all_read("ana").unwrap(), 1);
+        assert_eq!(inbox.unread_count("ana").unwrap(), 0);
+        assert!(inbox.list("bob", false, 10).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_inbox_saved() {
+        let dir = std::env::temp_dir().join(format!("inbox-{}", Uuid::new_v4()));
+        let path = dir.join("inbox.json");
+        let inbox = MemoryInbox::open(&path, 10).unwrap();
+        let first = item("first");
+        inbox.push("ana", first.clone()).unwrap();
+        inbox.push("ana", item("second")).unwrap();
+        inbox.mark_read("ana", first.id).unwrap();
+        
+        let reopened = MemoryInbox::open(&path, 10).unwrap();
+        assert_eq!(reopened.list("ana", false, 10).unwrap(), inbox.list("ana", false, 10).unwrap());
+        assert_eq!(reopened.unread_count("ana").unwrap(), 1);
+        
+        // A failed save leaves the inbox as it was
+        std::fs::remove_dir_all(&dir).unwrap();
+        std::fs::write(&dir, "").unwrap();
+        assert!(inbox.push("ana", item("third")).is_err());
+        assert_eq!(inbox.list("ana", false, 10).unwrap().len(), 2);
+        let _ = std::fs::remove_file(&dir);
+    }
+}
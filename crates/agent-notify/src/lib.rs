@@ -0,0 +1,409 @@
+//! # agent-notify
+//!
+//! User notifications for rust-agent: DCA purchases, price alerts, failed
+//! payments and expiring licenses.
+//!
+//! A [`Notification`] names a user, a kind and the values its template needs.
+//! [`NotificationService`] renders it with the kind's [`Template`], looks up
+//! the user's [`Preferences`] and hands it to a [`Notifier`] for every channel
+//! the user enabled for that kind:
+//!
+//! - **In-app** ([`InboxNotifier`]) - a per-user inbox read by the web UI
+//! - **Email** ([`EmailNotifier`]) - plain text over SMTP
+//! - **Webhook** ([`WebhookNotifier`]) - signed JSON POSTs, retried with backoff
+//!
+//! ## Usage
+//!
+//! ```rust,ignore
+//! use agent_notify::{Notification, NotificationKind, NotificationService};
+//!
+//! let service = NotificationService::new(templates, preferences)
+//!     .with_notifier(Arc::new(InboxNotifier::new(inbox)))
+//!     .with_notifier(Arc::new(EmailNotifier::new(&smtp)?));
+//!
+//! service.notify(
+//!     Notification::new("user-1", NotificationKind::PriceAlert)
+//!         .with("title", "Price alert: BTC")
+//!         .with("message", "BTC rose to $100250")
+//!         .with("condition", "BTC at or above $100000"),
+//! ).await?;
+//! ```
+
+mod email;
+mod error;
+mod inbox;
+mod preferences;
+mod template;
+mod webhook;
+
+pub use email::{EmailNotifier, SmtpConfig, SmtpTls};
+pub use error::{NotifyError, Result};
+pub use inbox::{Inbox, InboxItem, InboxNotifier, MemoryInbox, DEFAULT_INBOX_CAPACITY};
+pub use preferences::{MemoryPreferenceStore, PreferenceStore, Preferences, WebhookEndpoint};
+pub use template::{Template, Templates};
+pub use webhook::{check_webhook_url, sign, WebhookNotifier, DEFAULT_ATTEMPTS, ID_HEADER, SIGNATURE_HEADER};
+
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// Where a notification is delivered
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Channel {
+    InApp,
+    Email,
+    Webhook,
+}
+
+impl Channel {
+    pub const ALL: &'static [Self] = &[Self::InApp, Self::Email, Self::Webhook];
+    
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::InApp => "in_app",
+            Self::Email => "email",
+            Self::Webhook => "webhook",
+        }
+    }
+}
+
+impl std::fmt::Display for Channel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// What a notification is about
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NotificationKind {
+    /// A scheduled DCA purchase was made
+    DcaPurchase,
+    /// A DCA plan stopped making purchases
+    DcaPaused,
+    /// A price alert fired
+    PriceAlert,
+    /// A subscription payment failed
+    PaymentFailed,
+    /// A license expires soon
+    LicenseExpiring,
+}
+
+impl NotificationKind {
+    pub const ALL: &'static [Self] = &[Self::DcaPurchase, Self::DcaPaused, Self::PriceAlert, Self::PaymentFailed, Self::LicenseExpiring];
+    
+    /// Name used in templates file names and the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::DcaPurchase => "dca_purchase",
+            Self::DcaPaused => "dca_paused",
+            Self::PriceAlert => "price_alert",
+            Self::PaymentFailed => "payment_failed",
+            Self::LicenseExpiring => "license_expiring",
+        }
+    }
+    
+    /// Billing notices, which can't be opted out of entirely
+    pub fn is_transactional(&self) -> bool {
+        matches!(self, Self::PaymentFailed | Self::LicenseExpiring)
+    }
+}
+
+impl std::fmt::Display for NotificationKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A notification for one user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Notification {
+    pub id: Uuid,
+    pub user_id: String,
+    pub kind: NotificationKind,
+    
+    /// Template values
+    pub data: BTreeMap<String, String>,
+    
+    /// Notifications with the same key are only sent once
+    pub dedup_key: Option<String>,
+    
+    pub created_at: DateTime<Utc>,
+}
+
+impl Notification {
+    pub fn new(user_id: impl Into<String>, kind: NotificationKind) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            user_id: user_id.into(),
+            kind,
+            data: BTreeMap::new(),
+            dedup_key: None,
+            created_at: Utc::now(),
+        }
+    }
+    
+    /// Add a template value
+    pub fn with(mut self, key: impl Into<String>, value: impl ToString) -> Self {
+        self.data.insert(key.into(), value.to_string());
+        self
+    }
+    
+    /// Only send once per key (within the service's dedup window)
+    pub fn dedup(mut self, key: impl Into<String>) -> Self {
+        self.dedup_key = Some(key.into());
+        self
+    }
+}
+
+/// A notification with its template filled in
+#[derive(Debug, Clone)]
+pub struct Rendered {
+    pub notification: Notification,
+    pub subject: String,
+    pub body: String,
+}
+
+/// Delivers rendered notifications on one channel
+#[async_trait]
+pub trait Notifier: Send + Sync {
+    fn channel(&self) -> Channel;
+    
+    /// Deliver to the user, using the addresses in their preferences
+    async fn deliver(&self, message: &Rendered, preferences: &Preferences) -> Result<()>;
+}
+
+/// Outcome of delivering on one channel
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Delivery {
+    pub channel: Channel,
+    
+    /// Why delivery failed (None if delivered)
+    pub error: Option<String>,
+}
+
+/// How long a dedup key suppresses repeats by default
+pub const DEFAULT_DEDUP_WINDOW_DAYS: i64 = 30;
+
+/// Renders notifications and delivers them on each user's channels
+pub struct NotificationService {
+    templates: Templates,
+    preferences: Arc<dyn PreferenceStore>,
+    notifiers: HashMap<Channel, Arc<dyn Notifier>>,
+    dedup_window: Duration,
+    sent: Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl NotificationService {
+    pub fn new(templates: Templates, preferences: Arc<dyn PreferenceStore>) -> Self {
+        Self {
+            templates,
+            preferences,
+            notifiers: HashMap::new(),
+            dedup_window: Duration::days(DEFAULT_DEDUP_WINDOW_DAYS),
+            sent: Mutex::new(HashMap::new()),
+        }
+    }
+    
+    /// Deliver on the notifier's channel (replacing any notifier for it)
+    pub fn with_notifier(mut self, notifier: Arc<dyn Notifier>) -> Self {
+        self.notifiers.insert(notifier.channel(), notifier);
+        self
+    }
+    
+    pub fn with_dedup_window(mut self, window: Duration) -> Self {
+        self.dedup_window = window;
+        self
+    }
+    
+    pub fn preferences(&self) -> &Arc<dyn PreferenceStore> {
+        &self.preferences
+    }
+    
+    /// Whether a notifier is registered for `channel`
+    pub fn supports(&self, channel: Channel) -> bool {
+        self.notifiers.contains_key(&channel)
+    }
+    
+    /// Render and deliver on the user's channels for its kind
+    ///
+    /// Returns one [`Delivery`] per channel tried: none if the user opted out
+    /// or the dedup key was already sent. Delivery failures are reported in
+    /// the result, not as errors.
+    pub async fn notify(&self, notification: Notification) -> Result<Vec<Delivery>> {
+        let dedup_key = notification.dedup_key.clone();
+        if let Some(key) = &dedup_key {
+            if !self.claim(key)? {
+                tracing::debug!("Notification {} already sent", key);
+                return Ok(Vec::new());
+            }
+        }
+        
+        let result = self.deliver(notification).await;
+        let failed = match &result {
+            Ok(deliveries) => !deliveries.is_empty() && deliveries.iter().all(|delivery| delivery.error.is_some()),
+            Err(_) => true,
+        };
+        if failed {
+            // Nothing reached the user, so a retry with the same key may go out
+            if let Some(key) = &dedup_key {
+                self.sent.lock().map_err(|e| NotifyError::Storage(e.to_string()))?.remove(key);
+            }
+        }
+        result
+    }
+    
+    /// Notify in the background, logging failures
+    pub fn dispatch(self: &Arc<Self>, notification: Notification) {
+        let service = self.clone();
+        tokio::spawn(async move {
+            let kind = notification.kind;
+            if let Err(e) = service.notify(notification).await {
+                tracing::warn!("⚠ {} notification not sent: {}", kind, e);
+            }
+        });
+    }
+    
+    /// Record `key` as sent; false if it already was within the window
+    fn claim(&self, key: &str) -> Result<bool> {
+        let now = Utc::now();
+        let mut sent = self.sent.lock().map_err(|e| NotifyError::Storage(e.to_string()))?;
+        sent.retain(|_, at| now - *at < self.dedup_window);
+        if sent.contains_key(key) {
+            return Ok(false);
+        }
+        sent.insert(key.to_string(), now);
+        Ok(true)
+    }
+    
+    async fn deliver(&self, notification: Notification) -> Result<Vec<Delivery>> {
+        let preferences = self.preferences.get(&notification.user_id)?;
+        let channels = preferences.channels_for(notification.kind);
+        if channels.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let (subject, body) = self.templates.get(notification.kind).render(&notification.data)?;
+        let message = Rendered { notification, subject, body };
+        
+        let deliveries = channels.into_iter().map(|channel| {
+            let (message, preferences) = (&message, &preferences);
+            async move {
+                let result = match self.notifiers.get(&channel) {
+                    Some(notifier) => notifier.deliver(message, preferences).await,
+                    None => Err(NotifyError::Storage(format!("{} notifications are not configured", channel))),
+                };
+                let error = result.err().map(|e| {
+                    tracing::warn!(
+                        "⚠ {} notification for {} not delivered by {}: {}",
+                        message.notification.kind,
+                        message.notification.user_id,
+                        channel,
+                        e
+                    );
+                    e.to_string()
+                });
+                Delivery { channel, error }
+            }
+        });
+        Ok(futures::future::join_all(deliveries).await)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeSet;
+
+    /// Fails every delivery
+    struct Failing;
+
+    #[async_trait]
+    impl Notifier for Failing {
+        fn channel(&self) -> Channel {
+            Channel::Webhook
+        }
+        
+        async fn deliver(&self, _message: &Rendered, _preferences: &Preferences) -> Result<()> {
+            Err(NotifyError::Webhook("down".into()))
+        }
+    }
+
+    fn service() -> (NotificationService, Arc<MemoryInbox>, Arc<MemoryPreferenceStore>) {
+        let inbox = Arc::new(MemoryInbox::default());
+        let preferences = Arc::new(MemoryPreferenceStore::new());
+        let service = NotificationService::new(Templates::builtin(), preferences.clone())
+            .with_notifier(Arc::new(InboxNotifier::new(inbox.clone())))
+            .with_notifier(Arc::new(Failing));
+        (service, inbox, preferences)
+    }
+
+    fn alert(user_id: &str) -> Notification {
+        Notification::new(user_id, NotificationKind::PriceAlert)
+            .with("title", "Price alert: BTC")
+            .with("message", "BTC rose to $100250")
+            .with("condition", "BTC at or above $100000")
+    }
+
+    #[tokio::test]
+    async fn test_notify_renders_into_inbox() {
+        let (service, inbox, _) = service();
+        let deliveries = service.notify(alert("ana")).await.unwrap();
+        assert_eq!(deliveries, vec![Delivery { channel: Channel::InApp, error: None }]);
+        
+        let items = inbox.list("ana", false, 10).unwrap();
+        assert_eq!(items[0].subject, "Price alert: BTC");
+        assert!(items[0].body.contains("Your alert: BTC at or above $100000."));
+        
+        // Missing template values are an error
+        let result = service.notify(Notification::new("ana", NotificationKind::PriceAlert)).await;
+        assert!(matches!(result, Err(NotifyError::Template(_))));
+    }
+
+    #[tokio::test]
+    async fn test_notify_preferences_and_opt_out() {
+        let (service, inbox, preferences) = service();
+        let mut prefs = Preferences {
+            webhook: Some(WebhookEndpoint { url: "https://example.com/hook".into(), secret: "s".into() }),
+            channels: BTreeSet::from([Channel::InApp, Channel::Webhook]),
+            ..Preferences::default()
+        };
+        prefs.kinds.insert(NotificationKind::DcaPurchase, BTreeSet::new());
+        preferences.set("ana", prefs).unwrap();
+        
+        // Failures on one channel don't stop the others
+        let deliveries = service.notify(alert("ana")).await.unwrap();
+        assert_eq!(deliveries.len(), 2);
+        assert!(deliveries.iter().any(|d| d.channel == Channel::Webhook && d.error.is_some()));
+        assert_eq!(inbox.unread_count("ana").unwrap(), 1);
+        
+        // Opted out of DCA purchases
+        let purchase = Notification::new("ana", NotificationKind::DcaPurchase);
+        assert!(service.notify(purchase).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_notify_dedup() {
+        let (service, inbox, _) = service();
+        assert_eq!(service.notify(alert("ana").dedup("alert-1")).await.unwrap().len(), 1);
+        assert!(service.notify(alert("ana").dedup("alert-1")).await.unwrap().is_empty());
+        assert_eq!(service.notify(alert("ana").dedup("alert-2")).await.unwrap().len(), 1);
+        assert_eq!(inbox.unread_count("ana").unwrap(), 2);
+        
+        // A key whose every delivery failed can be retried
+        let (service, _, preferences) = self::service();
+        let prefs = Preferences {
+            webhook: Some(WebhookEndpoint { url: "https://example.com/hook".into(), secret: "s".into() }),
+            channels: BTreeSet::from([Channel::Webhook]),
+            ..Preferences::default()
+        };
+        preferences.set("bob", prefs).unwrap();
+        assert_eq!(service.notify(alert("bob").dedup("alert-3")).await.unwrap().len(), 1);
+        assert_eq!(service.notify(alert("bob").dedup("alert-3")).await.unwrap().len(), 1);
+    }
+}
@@ -0,0 +1,226 @@
+//! Per-user Channel Preferences
+//!
+//! Users pick the channels they're notified on, optionally per kind of
+//! notification; an empty channel set for a kind opts out of it. Billing
+//! notices can't be muted entirely: opting out still leaves them in the inbox.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use serde::{Deserialize, Serialize};
+
+use crate::error::{NotifyError, Result};
+use crate::{Channel, NotificationKind};
+
+/// Where a user's webhooks are delivered
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookEndpoint {
+    pub url: String,
+    
+    /// HMAC-SHA256 key for the `X-Notification-Signature` header
+    pub secret: String,
+}
+
+/// A user's addresses and channel choices
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Preferences {
+    #[serde(default)]
+    pub email: Option<String>,
+    
+    #[serde(default)]
+    pub webhook: Option<WebhookEndpoint>,
+    
+    /// Channels used for every kind without an override
+    #[serde(default = "default_channels")]
+    pub channels: BTreeSet<Channel>,
+    
+    /// Per-kind overrides; an empty set opts out of that kind
+    #[serde(default)]
+    pub kinds: BTreeMap<NotificationKind, BTreeSet<Channel>>,
+}
+
+fn default_channels() -> BTreeSet<Channel> {
+    BTreeSet::from([Channel::InApp])
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        Self {
+            email: None,
+            webhook: None,
+            channels: default_channels(),
+            kinds: BTreeMap::new(),
+        }
+    }
+}
+
+impl Preferences {
+    /// Channels a notification of `kind` goes out on
+    pub fn channels_for(&self, kind: NotificationKind) -> BTreeSet<Channel> {
+        let channels = self.kinds.get(&kind).unwrap_or(&self.channels);
+        if channels.is_empty() && kind.is_transactional() {
+            return default_channels();
+        }
+        channels.clone()
+    }
+    
+    /// Whether the user has opted out of `kind` entirely
+    pub fn is_muted(&self, kind: NotificationKind) -> bool {
+        self.channels_for(kind).is_empty()
+    }
+    
+    /// Reject enabled channels without an address
+    pub fn validate(&self) -> Result<()> {
+        let enabled = self.channels.iter().chain(self.kinds.values().flatten());
+        for &channel in enabled {
+            match channel {
+                Channel::Email if self.email.is_none() => return Err(NotifyError::NoAddress(channel)),
+                Channel::Webhook if self.webhook.is_none() => return Err(NotifyError::NoAddress(channel)),
+                _ => {}
+            }
+        }
+        if let Some(email) = &self.email {
+            let valid = email
+                .split_once('@')
+                .is_some_and(|(user, domain)| !user.is_empty() && domain.contains('.') && !email.contains(char::is_whitespace));
+            if !valid {
+                return Err(NotifyError::InvalidAddress(email.clone()));
+            }
+        }
+        if let Some(webhook) = &self.webhook {
+            if !(webhook.url.starts_with("https://") || webhook.url.starts_with("http://")) {
+                return Err(NotifyError::InvalidAddress(webhook.url.clone()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Storage for user preferences
+pub trait PreferenceStore: Send + Sync {
+    /// The user's preferences (defaults if never saved)
+    fn get(&self, user_id: &str) -> Result<Preferences>;
+    
+    fn set(&self, user_id: &str, preferences: Preferences) -> Result<()>;
+}
+
+/// In-memory preferences, optionally saved to a JSON file after every change
+#[derive(Default)]
+pub struct MemoryPreferenceStore {
+    path: Option<PathBuf>,
+    preferences: RwLock<HashMap<String, Preferences>>,
+}
+
+impl MemoryPreferenceStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Load the preferences saved at `path` (none if the file doesn't exist yet)
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let preferences = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| NotifyError::Storage(format!("{}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => HashMap::new(),
+            Err(e) => return Err(NotifyError::Storage(format!("{}: {}", path.display(), e))),
+        };
+        
+        Ok(Self { path: Some(path), preferences: RwLock::new(preferences) })
+    }
+    
+    /// Write all preferences to a temporary file, then move it over the old one
+    fn save(&self, preferences: &HashMap<String, Preferences>) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let write = || -> std::io::Result<()> {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)?;
+            }
+            let preferences: BTreeMap<_, _> = preferences.iter().collect();
+            
+            let tmp = path.with_extension("tmp");
+            std::fs::write(&tmp, serde_json::to_vec_pretty(&preferences)?)?;
+            std::fs::rename(&tmp, path)
+        };
+        write().map_err(|e| NotifyError::Storage(format!("{}: {}", path.display(), e)))
+    }
+}
+
+impl PreferenceStore for MemoryPreferenceStore {
+    fn get(&self, user_id: &str) -> Result<Preferences> {
+        let preferences = self.preferences.read().map_err(|e| NotifyError::Storage(e.to_string()))?;
+        Ok(preferences.get(user_id).cloned().unwrap_or_default())
+    }
+    
+    fn set(&self, user_id: &str, preferences: Preferences) -> Result<()> {
+        let mut stored = self.preferences.write().map_err(|e| NotifyError::Storage(e.to_string()))?;
+        let previous = stored.insert(user_id.to_string(), preferences);
+        if let Err(e) = self.save(&stored) {
+            match previous {
+                Some(previous) => stored.insert(user_id.to_string(), previous),
+                None => stored.remove(user_id),
+            };
+            return Err(e);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_channels_for() {
+        let mut preferences = Preferences {
+            email: Some("ana@example.com".into()),
+            channels: BTreeSet::from([Channel::InApp, Channel::Email]),
+            ..Preferences::default()
+        };
+        preferences.kinds.insert(NotificationKind::PriceAlert, BTreeSet::new());
+        preferences.kinds.insert(NotificationKind::PaymentFailed, BTreeSet::new());
+        
+        assert_eq!(preferences.channels_for(NotificationKind::DcaPurchase).len(), 2);
+        assert!(preferences.is_muted(NotificationKind::PriceAlert));
+        // Billing notices fall back to the inbox
+        assert_eq!(
+            preferences.channels_for(NotificationKind::PaymentFailed),
+            BTreeSet::from([Channel::InApp])
+        );
+        assert!(preferences.validate().is_ok());
+    }
+
+    #[test]
+    fn test_validate() {
+        let mut preferences = Preferences::default();
+        preferences.channels.insert(Channel::Webhook);
+        assert!(matches!(preferences.validate(), Err(NotifyError::NoAddress(Channel::Webhook))));
+        
+        let preferences = Preferences { email: Some("not-an-address".into()), ..Preferences::default() };
+        assert!(matches!(preferences.validate(), Err(NotifyError::InvalidAddress(_))));
+    }
+
+    #[test]
+    fn test_preferences_saved() {
+        let dir = std::env::temp_dir().join(format!("preferences-{}", uuid::Uuid::new_v4()));
+        let path = dir.join("preferences.json");
+        let store = MemoryPreferenceStore::open(&path).unwrap();
+        let mut muted = Preferences::default();
+        muted.kinds.insert(NotificationKind::PriceAlert, BTreeSet::new());
+        store.set("ana", muted.clone()).unwrap();
+        
+        let reopened = MemoryPreferenceStore::open(&path).unwrap();
+        assert_eq!(reopened.get("ana").unwrap(), muted);
+        assert_eq!(reopened.get("bob").unwrap(), Preferences::default());
+        
+        // A failed save leaves the preferences as they were
+        std::fs::remove_dir_all(&dir).unwrap();
+        std::fs::write(&dir, "").unwrap();
+        assert!(store.set("ana", Preferences::default()).is_err());
+        assert_eq!(store.get("ana").unwrap(), muted);
+        let _ = std::fs::remove_file(&dir);
+    }
+}
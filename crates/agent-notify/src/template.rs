@@ -0,0 +1,177 @@
+//! Notification Templates
+//!
+//! Each kind of notification has a subject and a body with `{{name}}`
+//! placeholders filled from the notification's data. The built-in templates
+//! can be replaced per kind from a directory holding `<kind>.txt` files: the
+//! first line is the subject, the rest (after one blank line) the body.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::error::{NotifyError, Result};
+use crate::NotificationKind;
+
+/// Subject and body with `{{name}}` placeholders
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    pub subject: String,
+    pub body: String,
+}
+
+impl Template {
+    pub fn new(subject: impl Into<String>, body: impl Into<String>) -> Self {
+        Self { subject: subject.into(), body: body.into() }
+    }
+    
+    /// Parse the file format: subject line, blank line, body
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = text.replace("\r\n", "\n");
+        let (subject, body) = text.split_once('\n').unwrap_or((text.as_str(), ""));
+        let subject = subject.trim();
+        if subject.is_empty() {
+            return Err(NotifyError::Template("first line must be the subject".into()));
+        }
+        Ok(Self::new(subject, body.trim_start_matches('\n').trim_end()))
+    }
+    
+    /// Fill the placeholders; a placeholder without a value is an error
+    pub fn render(&self, data: &BTreeMap<String, String>) -> Result<(String, String)> {
+        Ok((fill(&self.subject, data)?, fill(&self.body, data)?))
+    }
+}
+
+fn fill(text: &str, data: &BTreeMap<String, String>) -> Result<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find("}}")
+            .ok_or_else(|| NotifyError::Template(format!("unclosed placeholder in '{}'", text)))?;
+        let name = after[..end].trim();
+        let value = data
+            .get(name)
+            .ok_or_else(|| NotifyError::Template(format!("no value for '{{{{{}}}}}'", name)))?;
+        out.push_str(value);
+        rest = &after[end + 2..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
+
+/// Templates for every notification kind
+#[derive(Debug, Clone)]
+pub struct Templates {
+    templates: HashMap<NotificationKind, Template>,
+}
+
+impl Default for Templates {
+    fn default() -> Self {
+        Self::builtin()
+    }
+}
+
+impl Templates {
+    /// The templates shipped with the crate
+    pub fn builtin() -> Self {
+        let templates = NotificationKind::ALL
+            .iter()
+            .map(|&kind| (kind, builtin(kind)))
+            .collect();
+        Self { templates }
+    }
+    
+    /// Built-ins, overridden by `<kind>.txt` files found in `dir`
+    pub fn load_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let mut templates = Self::builtin();
+        for &kind in NotificationKind::ALL {
+            let path = dir.join(format!("{}.txt", kind.as_str()));
+            match std::fs::read_to_string(&path) {
+                Ok(text) => {
+                    let template = Template::parse(&text)
+                        .map_err(|e| NotifyError::Template(format!("{}: {}", path.display(), e)))?;
+                    templates.set(kind, template);
+                }
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => return Err(NotifyError::Template(format!("{}: {}", path.display(), e))),
+            }
+        }
+        Ok(templates)
+    }
+    
+    pub fn set(&mut self, kind: NotificationKind, template: Template) {
+        self.templates.insert(kind, template);
+    }
+    
+    pub fn get(&self, kind: NotificationKind) -> &Template {
+        &self.templates[&kind]
+    }
+}
+
+fn builtin(kind: NotificationKind) -> Template {
+    match kind {
+        NotificationKind::DcaPurchase => Template::new(
+            "DCA purchase: {{quantity}} {{symbol}}",
+            "Your DCA plan bought {{quantity}} {{symbol}} for ${{amount}} at ${{price}}.\n\n\
+             Purchase {{entry}} of {{periods}} ({{mode}} trading).",
+        ),
+        NotificationKind::DcaPaused => Template::new(
+            "Your {{symbol}} DCA plan is paused",
+            "Your DCA plan for {{symbol}} has stopped making purchases: {{reason}}.\n\n\
+             Cancel it and create a new plan to continue.",
+        ),
+        NotificationKind::PriceAlert => Template::new(
+            "{{title}}",
+            "{{message}}.\n\nYour alert: {{condition}}.",
+        ),
+        NotificationKind::PaymentFailed => Template::new(
+            "Your payment failed",
+            "We couldn't charge your card for your {{plan}} subscription.\n\n\
+             Please update your payment method to keep your license active.",
+        ),
+        NotificationKind::LicenseExpiring => Template::new(
+            "Your {{plan}} license expires in {{days}} days",
+            "Your {{plan}} license expires on {{expires_at}}.\n\n\
+             Renew before then to keep access to your plan's features.",
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn data(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn test_render() {
+        let template = Template::new("Hi {{name}}", "{{ name }} owes ${{amount}}.");
+        let (subject, body) = template.render(&data(&[("name", "Ana"), ("amount", "5")])).unwrap();
+        assert_eq!(subject, "Hi Ana");
+        assert_eq!(body, "Ana owes $5.");
+        
+        assert!(template.render(&data(&[("name", "Ana")])).is_err());
+        assert!(Template::new("{{open", "").render(&data(&[])).is_err());
+    }
+
+    #[test]
+    fn test_parse_and_load_dir() {
+        let template = Template::parse("Subject {{x}}\r\n\r\nLine one\nLine two\n").unwrap();
+        assert_eq!(template, Template::new("Subject {{x}}", "Line one\nLine two"));
+        assert!(Template::parse("\nbody").is_err());
+        
+        let dir = std::env::temp_dir().join(format!("agent-notify-templates-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("price_alert.txt"), "Alert {{symbol}}\n\nMoved").unwrap();
+        
+        let templates = Templates::load_dir(&dir).unwrap();
+        assert_eq!(templates.get(NotificationKind::PriceAlert).subject, "Alert {{symbol}}");
+        assert_eq!(templates.get(NotificationKind::DcaPurchase), &builtin(NotificationKind::DcaPurchase));
+        
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,351 @@
+//! Signed Outbound Webhooks
+//!
+//! Notifications are POSTed as JSON to the user's endpoint. Each request
+//! carries `X-Notification-Signature: t=<unix time>,v1=<hex>`, where the hex
+//! is HMAC-SHA256 of `"<unix time>.<body>"` keyed with the endpoint secret, so
+//! receivers can check the sender and reject replays. Network errors, 429 and
+//! 5xx responses are retried with exponential backoff.
+//!
+//! Endpoints on loopback, private, link-local or unspecified addresses are
+//! refused (when the URL is saved, before each delivery and again when the
+//! client connects), and redirects are not followed.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+
+use crate::error::{NotifyError, Result};
+use crate::{Channel, NotificationKind, Notifier, Preferences, Rendered};
+
+/// Signature header
+pub const SIGNATURE_HEADER: &str = "X-Notification-Signature";
+
+/// Notification ID header; the same on every retry, for deduplication
+pub const ID_HEADER: &str = "X-Notification-Id";
+
+/// Attempts per notification, including the first
+pub const DEFAULT_ATTEMPTS: u32 = 4;
+
+/// `t=<timestamp>,v1=<hex HMAC-SHA256 of "<timestamp>.<body>">`
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("t={},v1={}", timestamp, hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Addresses a webhook must not reach: this host and the networks behind it
+fn is_internal(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => ip.is_loopback() || ip.is_private() || ip.is_link_local() || ip.is_unspecified() || ip.is_broadcast(),
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_internal(IpAddr::V4(ip)),
+            // fc00::/7 (unique local) and fe80::/10 (link-local)
+            None => ip.is_loopback() || ip.is_unspecified() || (ip.segments()[0] & 0xfe00) == 0xfc00 || (ip.segments()[0] & 0xffc0) == 0xfe80,
+        },
+    }
+}
+
+/// Resolve `host`, refusing it if any of its addresses is internal
+async fn public_addrs(host: &str, port: u16) -> Result<Vec<SocketAddr>> {
+    let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port))
+        .await
+        .map_err(|e| NotifyError::InvalidAddress(format!("{}: {}", host, e)))?
+        .collect();
+    if addrs.is_empty() {
+        return Err(NotifyError::InvalidAddress(format!("{} has no addresses", host)));
+    }
+    if addrs.iter().any(|addr| is_internal(addr.ip())) {
+        return Err(NotifyError::InvalidAddress(format!("{} resolves to an internal address", host)));
+    }
+    Ok(addrs)
+}
+
+/// Check that a webhook URL points at a public host
+pub async fn check_webhook_url(url: &str) -> Result<()> {
+    let parsed = reqwest::Url::parse(url).map_err(|e| NotifyError::InvalidAddress(format!("{}: {}", url, e)))?;
+    let host = parsed.host_str().ok_or_else(|| NotifyError::InvalidAddress(format!("{} has no host", url)))?;
+    
+    // IPv6 literals keep their brackets in `host_str`
+    match host.trim_start_matches('[').trim_end_matches(']').parse::<IpAddr>() {
+        Ok(ip) if is_internal(ip) => Err(NotifyError::InvalidAddress(format!("{} is an internal address", host))),
+        Ok(_) => Ok(()),
+        Err(_) => public_addrs(host, parsed.port_or_known_default().unwrap_or(443)).await.map(drop),
+    }
+}
+
+/// Resolver used by the webhook client, so a name can't switch to an internal
+/// address between the check and the connection
+struct PublicResolver;
+
+impl reqwest::dns::Resolve for PublicResolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let host = name.as_str().to_string();
+        Box::pin(async move {
+            let addrs = public_addrs(&host, 0).await?;
+            Ok(Box::new(addrs.into_iter()) as reqwest::dns::Addrs)
+        })
+    }
+}
+
+/// JSON body of a webhook
+#[derive(Debug, Serialize)]
+struct Payload<'a> {
+    id: String,
+    kind: NotificationKind,
+    subject: &'a str,
+    body: &'a str,
+    data: &'a std::collections::BTreeMap<String, String>,
+    created_at: DateTime<Utc>,
+}
+
+/// Delivers to the user's webhook endpoint
+pub struct WebhookNotifier {
+    client: reqwest::Client,
+    attempts: u32,
+    backoff: Duration,
+    allow_internal: bool,
+}
+
+impl Default for WebhookNotifier {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WebhookNotifier {
+    pub fn new() -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(10))
+            .redirect(reqwest::redirect::Policy::none())
+            .dns_resolver(Arc::new(PublicResolver))
+            .build()
+            .unwrap_or_default();
+        Self { client, attempts: DEFAULT_ATTEMPTS, backoff: Duration::from_millis(500), allow_internal: false }
+    }
+    
+    /// Deliver to local test servers
+    #[cfg(test)]
+    fn allow_internal(mut self) -> Self {
+        self.allow_internal = true;
+        self
+    }
+    
+    /// Attempts per notification (at least one)
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts.max(1);
+        self
+    }
+    
+    /// Wait before the first retry, doubled for each one after
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+}
+
+#[async_trait]
+impl Notifier for WebhookNotifier {
+    fn channel(&self) -> Channel {
+        Channel::Webhook
+    }
+    
+    async fn deliver(&self, message: &Rendered, preferences: &Preferences) -> Result<()> {
+        let endpoint = preferences.webhook.as_ref().ok_or(NotifyError::NoAddress(Channel::Webhook))?;
+        if !self.allow_internal {
+            check_webhook_url(&endpoint.url).await?;
+        }
+        let notification = &message.notification;
+        let body = serde_json::to_vec(&Payload {
+            id: notification.id.to_string(),
+            kind: notification.kind,
+            subject: &message.subject,
+            body: &message.body,
+            data: &notification.data,
+            created_at: notification.created_at,
+        })
+        .map_err(|e| NotifyError::Webhook(e.to_string()))?;
+        
+        let mut backoff = self.backoff;
+        let mut attempt = 1;
+        loop {
+            // Signed per attempt, so the timestamp stays fresh
+            let signature = sign(&endpoint.secret, Utc::now().timestamp(), &body);
+            let result = self
+                .client
+                .post(&endpoint.url)
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .header(SIGNATURE_HEADER, signature)
+                .header(ID_HEADER, notification.id.to_string())
+                .body(body.clone())
+                .send()
+                .await;
+            
+            let (error, retryable) = match result {
+                Ok(response) if response.status().is_success() => return Ok(()),
+                Ok(response) => {
+                    let status = response.status();
+                    (format!("{} returned {}", endpoint.url, status), status.is_server_error() || status.as_u16() == 429)
+                }
+                Err(e) => (format!("{}: {}", endpoint.url, e), true),
+            };
+            
+            if !retryable || attempt >= self.attempts {
+                return Err(NotifyError::Webhook(format!("{} (attempt {})", error, attempt)));
+            }
+            tracing::debug!("Webhook attempt {} failed, retrying in {:?}: {}", attempt, backoff, error);
+            tokio::time::sleep(backoff).await;
+            backoff *= 2;
+            attempt += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Notification, WebhookEndpoint};
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::TcpListener;
+
+    /// Minimal HTTP server answering with `statuses` in turn; returns each request's headers and body
+    async fn http_sink(listener: TcpListener, statuses: Vec<u16>) -> Vec<(Vec<String>, Vec<u8>)> {
+        let mut requests = Vec::new();
+        for status in statuses {
+            let (stream, _) = listener.accept().await.unwrap();
+            let mut reader = BufReader::new(stream);
+            let mut headers = Vec::new();
+            let mut length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).await.unwrap();
+                let line = line.trim_end().to_string();
+                if line.is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("content-length") {
+                        length = value.trim().parse().unwrap();
+                    }
+                }
+                headers.push(line);
+            }
+            let mut body = vec![0; length];
+            reader.read_exact(&mut body).await.unwrap();
+            
+            let response = format!("HTTP/1.1 {} X\r\ncontent-length: 0\r\nconnection: close\r\n\r\n", status);
+            reader.get_mut().write_all(response.as_bytes()).await.unwrap();
+            requests.push((headers, body));
+        }
+        requests
+    }
+
+    fn header<'a>(headers: &'a [String], name: &str) -> Option<&'a str> {
+        headers.iter().find_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            key.eq_ignore_ascii_case(name).then(|| value.trim())
+        })
+    }
+
+    #[test]
+    fn test_sign() {
+        let signature = sign("secret", 1_700_000_000, b"{}");
+        assert!(signature.starts_with("t=1700000000,v1="));
+        assert_eq!(signature.len(), "t=1700000000,v1=".len() + 64);
+        assert_ne!(signature, sign("other", 1_700_000_000, b"{}"));
+    }
+
+    #[tokio::test]
+    async fn test_webhook_signed_and_retried() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let sink = tokio::spawn(http_sink(listener, vec![500, 200]));
+        
+        let notifier = WebhookNotifier::new().with_backoff(Duration::from_millis(10)).allow_internal();
+        let preferences = Preferences {
+            webhook: Some(WebhookEndpoint { url, secret: "whsec".into() }),
+            ..Preferences::default()
+        };
+        let message = Rendered {
+            notification: Notification::new("ana", NotificationKind::DcaPurchase).with("symbol", "BTC"),
+            subject: "DCA purchase".into(),
+            body: "Bought".into(),
+        };
+        notifier.deliver(&message, &preferences).await.unwrap();
+        
+        let requests = sink.await.unwrap();
+        assert_eq!(requests.len(), 2);
+        let (headers, body) = &requests[1];
+        
+        let id = message.notification.id.to_string();
+        assert_eq!(header(headers, ID_HEADER), Some(id.as_str()));
+        let signature = header(headers, SIGNATURE_HEADER).unwrap();
+        let timestamp: i64 = signature[2..signature.find(',').unwrap()].parse().unwrap();
+        assert_eq!(signature, sign("whsec", timestamp, body));
+        
+        let payload: serde_json::Value = serde_json::from_slice(body).unwrap();
+        assert_eq!(payload["kind"], "dca_purchase");
+        assert_eq!(payload["data"]["symbol"], "BTC");
+    }
+
+    #[tokio::test]
+    async fn test_webhook_client_error_not_retried() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let sink = tokio::spawn(http_sink(listener, vec![400]));
+        
+        let notifier = WebhookNotifier::new().with_backoff(Duration::from_millis(10)).allow_internal();
+        let preferences = Preferences {
+            webhook: Some(WebhookEndpoint { url, secret: "whsec".into() }),
+            ..Preferences::default()
+        };
+        let message = Rendered {
+            notification: Notification::new("ana", NotificationKind::PriceAlert),
+            subject: String::new(),
+            body: String::new(),
+        };
+        let result = notifier.deliver(&message, &preferences).await;
+        assert!(matches!(result, Err(NotifyError::Webhook(_))));
+        assert_eq!(sink.await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_internal_urls_rejected() {
+        for url in [
+            "https://127.0.0.1/hook",
+            "https://10.1.2.3/hook",
+            "https://192.168.0.10:8443/hook",
+            "http://169.254.169.254/latest/meta-data",
+            "https://0.0.0.0/hook",
+            "https://[::1]/hook",
+            "https://[::ffff:127.0.0.1]/hook",
+            "https://[fd00::1]/hook",
+            "https://localhost/hook",
+        ] {
+            assert!(matches!(check_webhook_url(url).await, Err(NotifyError::InvalidAddress(_))), "{}", url);
+        }
+        assert!(check_webhook_url("https://93.184.215.14/hook").await.is_ok());
+        
+        // Checked again before delivery, without sending anything
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        let preferences = Preferences {
+            webhook: Some(WebhookEndpoint { url, secret: "whsec".into() }),
+            ..Preferences::default()
+        };
+        let message = Rendered {
+            notification: Notification::new("ana", NotificationKind::PriceAlert),
+            subject: String::new(),
+            body: String::new(),
+        };
+        let result = WebhookNotifier::new().deliver(&message, &preferences).await;
+        assert!(matches!(result, Err(NotifyError::InvalidAddress(_))));
+    }
+}
@@ -32,6 +32,30 @@ pub enum PaymentError {
     #[error("Rate limit exceeded")]
     RateLimited,
     
+    /// Organization not found
+    #[error("Organization not found: {0}")]
+    OrgNotFound(String),
+    
+    /// Account is not a member of the organization
+    #[error("Member not found: {0}")]
+    MemberNotFound(String),
+    
+    /// Member's role does not allow the change
+    #[error("Not permitted: {0}")]
+    NotPermitted(String),
+    
+    /// Every seat of the plan is taken (members and pending invites)
+    #[error("All {0} seats are taken")]
+    SeatLimit(u32),
+    
+    /// Invite token unknown, revoked or expired
+    #[error("Invite invalid: {0}")]
+    InvalidInvite(String),
+    
+    /// Change conflicts with existing memberships
+    #[error("{0}")]
+    OrgConflict(String),
+    
     /// Configuration error
     #[error("Configuration error: {0}")]
     Config(String),
@@ -54,6 +78,9 @@ impl PaymentError {
             PaymentError::LicenseNotFound(_) => "License key not found.",
             PaymentError::LicenseInvalid(_) => "Your license is no longer valid.",
             PaymentError::RateLimited => "You've exceeded your usage limit.",
+            PaymentError::OrgNotFound(_) => "Organization not found.",
+            PaymentError::SeatLimit(_) => "Every seat of your plan is taken.",
+            PaymentError::InvalidInvite(_) => "This invite is no longer valid.",
             PaymentError::Config(_) => "Service configuration error.",
             _ => "An error occurred processing your request.",
         }
@@ -68,13 +68,25 @@
 //!
 //! // Redirect user to: session.checkout_url
 //! ```
+//!
+//! ## Teams
+//!
+//! A Team license is shared through an [`Organization`]: members join with
+//! invites, each taking one of the plan's seats, and the usage they record
+//! is charged to the organization's license.
 
 mod checkout;
 mod license;
 mod webhook;
 mod error;
+mod usage;
+mod quota;
+mod org;
 
 pub use checkout::{CheckoutRequest, CheckoutSession, StripeClient};
-pub use license::{License, LicenseStore, MemoryLicenseStore, Plan};
+pub use license::{License, LicenseKey, LicenseStore, LicenseVerification, MemoryLicenseStore, Plan};
+pub use org::{Invite, Member, MemoryOrgStore, OrgStore, Organization, Role, INVITE_TTL_DAYS, MAX_ORG_NAME_CHARS};
+pub use quota::{seconds_until_reset, ConcurrencyLimiter, ConcurrencyPermit, DailyQuota, PlanLimits, PlanTable};
+pub use usage::{CostTable, MemberUsage, ModelRate, ModelUsage, UsageRecord, UsageStatement};
 pub use webhook::{WebhookEvent, WebhookHandler};
 pub use error::{PaymentError, Result};
@@ -8,6 +8,7 @@ use std::collections::HashMap;
 use std::sync::RwLock;
 
 use crate::error::Result;
+use crate::usage::UsageRecord;
 
 /// License key (formatted: XXXX-XXXX-XXXX-XXXX)
 #[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -107,6 +108,10 @@ pub struct License {
     /// Customer email
     pub email: String,
     
+    /// Account the license belongs to (None = usable by anyone holding the key)
+    #[serde(default)]
+    pub user_id: Option<String>,
+    
     /// Plan tier
     pub plan: Plan,
     
@@ -127,6 +132,14 @@ pub struct License {
     
     /// Last usage reset date
     pub usage_reset_date: Option<chrono::NaiveDate>,
+    
+    /// Tokens charged over the license lifetime
+    #[serde(default)]
+    pub tokens_used: u64,
+    
+    /// Cost charged over the license lifetime (micro-dollars)
+    #[serde(default)]
+    pub cost_micros: i64,
 }
 
 impl License {
@@ -136,6 +149,7 @@ impl License {
             key: LicenseKey::generate(),
             subscription_id,
             email,
+            user_id: None,
             plan,
             active: true,
             created_at: Utc::now(),
@@ -143,9 +157,16 @@ impl License {
             last_verified: None,
             usage_today: 0,
             usage_reset_date: None,
+            tokens_used: 0,
+            cost_micros: 0,
         }
     }
     
+    /// Whether an account may use this license
+    pub fn usable_by(&self, user_id: &str) -> bool {
+        self.user_id.as_deref().is_none_or(|owner| owner == user_id)
+    }
+    
     /// Check if license is valid (active and not expired)
     pub fn is_valid(&self) -> bool {
         if !self.active {
@@ -180,6 +201,12 @@ impl License {
         true
     }
     
+    /// Add a priced request to the lifetime totals
+    pub fn charge(&mut self, record: &UsageRecord) {
+        self.tokens_used += u64::from(record.prompt_tokens) + u64::from(record.completion_tokens);
+        self.cost_micros += record.cost_micros;
+    }
+    
     /// Deactivate the license
     pub fn deactivate(&mut self) {
         self.active = false;
@@ -218,6 +245,20 @@ impl LicenseVerification {
             message: Some(message.into()),
         }
     }
+    
+    /// The license is fine but today's requests are used up
+    pub fn rate_limited(plan: Plan) -> Self {
+        Self {
+            valid: false,
+            plan: Some(plan),
+            remaining_requests: Some(0),
+            message: Some("Rate limit exceeded".into()),
+        }
+    }
+    
+    pub fn is_rate_limited(&self) -> bool {
+        !self.valid && self.remaining_requests == Some(0)
+    }
 }
 
 /// License storage trait
@@ -231,17 +272,38 @@ pub trait LicenseStore: Send + Sync {
     /// Get license by subscription ID
     fn get_by_subscription(&self, subscription_id: &str) -> Result<Option<License>>;
     
+    /// Newest valid license owned by an account (stores without owners find none)
+    fn get_by_user(&self, _user_id: &str) -> Result<Option<License>> {
+        Ok(None)
+    }
+    
     /// Delete a license
     fn delete(&self, key: &LicenseKey) -> Result<()>;
     
     /// Verify and use a license (atomic check + increment)
     fn verify_and_use(&self, key: &LicenseKey) -> Result<LicenseVerification>;
+    
+    /// Charge a priced request to its license (stores without usage keep nothing)
+    fn record_usage(&self, _record: &UsageRecord) -> Result<()> {
+        Ok(())
+    }
+    
+    /// Usage records of a license within `[from, to)`
+    fn usage_records(&self, _key: &LicenseKey, _from: DateTime<Utc>, _to: DateTime<Utc>) -> Result<Vec<UsageRecord>> {
+        Ok(Vec::new())
+    }
+    
+    /// Active licenses expiring within `[from, to)` (none unless the store can tell)
+    fn expiring(&self, _from: DateTime<Utc>, _to: DateTime<Utc>) -> Result<Vec<License>> {
+        Ok(Vec::new())
+    }
 }
 
 /// In-memory license store (for development)
 pub struct MemoryLicenseStore {
     licenses: RwLock<HashMap<LicenseKey, License>>,
     by_subscription: RwLock<HashMap<String, LicenseKey>>,
+    usage: RwLock<Vec<UsageRecord>>,
 }
 
 impl Default for MemoryLicenseStore {
@@ -255,6 +317,7 @@ impl MemoryLicenseStore {
         Self {
             licenses: RwLock::new(HashMap::new()),
             by_subscription: RwLock::new(HashMap::new()),
+            usage: RwLock::new(Vec::new()),
         }
     }
 }
@@ -286,6 +349,15 @@ impl LicenseStore for MemoryLicenseStore {
         }
     }
     
+    fn get_by_user(&self, user_id: &str) -> Result<Option<License>> {
+        let licenses = self.licenses.read().unwrap();
+        Ok(licenses
+            .values()
+            .filter(|l| l.user_id.as_deref() == Some(user_id) && l.is_valid())
+            .max_by_key(|l| l.created_at)
+            .cloned())
+    }
+    
     fn delete(&self, key: &LicenseKey) -> Result<()> {
         let mut licenses = self.licenses.write().unwrap();
         let mut by_sub = self.by_subscription.write().unwrap();
@@ -306,7 +378,7 @@ impl LicenseStore for MemoryLicenseStore {
             }
             
             if !license.check_and_increment_usage() {
-                return Ok(LicenseVerification::invalid("Rate limit exceeded"));
+                return Ok(LicenseVerification::rate_limited(license.plan.clone()));
             }
             
             let remaining = license.plan.rate_limit().saturating_sub(license.usage_today);
@@ -315,6 +387,35 @@ impl LicenseStore for MemoryLicenseStore {
             Ok(LicenseVerification::invalid("License not found"))
         }
     }
+    
+    fn record_usage(&self, record: &UsageRecord) -> Result<()> {
+        let mut licenses = self.licenses.write().unwrap();
+        
+        if let Some(license) = licenses.get_mut(&record.license_key) {
+            license.charge(record);
+        }
+        self.usage.write().unwrap().push(record.clone());
+        
+        Ok(())
+    }
+    
+    fn usage_records(&self, key: &LicenseKey, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<UsageRecord>> {
+        let usage = self.usage.read().unwrap();
+        Ok(usage
+            .iter()
+            .filter(|r| &r.license_key == key && r.recorded_at >= from && r.recorded_at < to)
+            .cloned()
+            .collect())
+    }
+    
+    fn expiring(&self, from: DateTime<Utc>, to: DateTime<Utc>) -> Result<Vec<License>> {
+        let licenses = self.licenses.read().unwrap();
+        Ok(licenses
+            .values()
+            .filter(|l| l.active && l.expires_at.is_some_and(|at| at >= from && at < to))
+            .cloned()
+            .collect())
+    }
 }
 
 #[cfg(test)]
@@ -354,4 +455,39 @@ mod tests {
         // 51st should fail
         assert!(!license.check_and_increment_usage());
     }
+
+    #[test]
+    fn test_license_owner() {
+        let store = MemoryLicenseStore::new();
+        let mut license = License::new("sub_123".into(), "test@example.com".into(), Plan::Pro);
+        license.user_id = Some("u1".into());
+        store.save(&license).unwrap();
+        
+        assert!(license.usable_by("u1"));
+        assert!(!license.usable_by("u2"));
+        assert_eq!(store.get_by_user("u1").unwrap().map(|l| l.key), Some(license.key));
+        assert!(store.get_by_user("u2").unwrap().is_none());
+    }
+
+    #[test]
+    fn test_expiring() {
+        let store = MemoryLicenseStore::new();
+        let now = Utc::now();
+        let mut soon = License::new("sub_1".into(), "a@example.com".into(), Plan::Pro);
+        soon.expires_at = Some(now + chrono::Duration::days(3));
+        let mut later = License::new("sub_2".into(), "b@example.com".into(), Plan::Pro);
+        later.expires_at = Some(now + chrono::Duration::days(30));
+        let mut cancelled = soon.clone();
+        cancelled.key = LicenseKey::generate();
+        cancelled.subscription_id = "sub_3".into();
+        cancelled.deactivate();
+        let renewing = License::new("sub_4".into(), "c@example.com".into(), Plan::Pro);
+        for license in [&soon, &later, &cancelled, &renewing] {
+            store.save(license).unwrap();
+        }
+        
+        let expiring = store.expiring(now, now + chrono::Duration::days(7)).unwrap();
+        assert_eq!(expiring.len(), 1);
+        assert_eq!(expiring[0].key, soon.key);
+    }
 }
@@ -0,0 +1,539 @@
+//! Organizations
+//!
+//! A Team license is shared through an organization: its holder invites
+//! colleagues, and each member takes one of the plan's seats
+//! (`Plan::seats`, the owner included). A pending invite holds a seat until
+//! it is accepted, revoked or expires, so an organization never hands out
+//! more invites than it has free seats.
+//!
+//! Roles, from most to least privileged:
+//!
+//! - **owner** - holds the license; grants and revokes admin, transfers
+//!   ownership and deletes the organization
+//! - **admin** - invites and removes members, edits shared resources
+//! - **member** - uses the license and reads shared resources
+//!
+//! An account belongs to at most one organization.
+
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use crate::error::{PaymentError, Result};
+use crate::license::LicenseKey;
+
+/// How long an invite can be accepted
+pub const INVITE_TTL_DAYS: i64 = 7;
+
+/// Longest organization name
+pub const MAX_ORG_NAME_CHARS: usize = 64;
+
+/// A member's role (ordered by privilege)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Member,
+    Admin,
+    Owner,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Role::Member => "member",
+            Role::Admin => "admin",
+            Role::Owner => "owner",
+        }
+    }
+    
+    /// Whether the role may invite and remove members and edit shared resources
+    pub fn can_manage(self) -> bool {
+        self >= Role::Admin
+    }
+}
+
+/// An account's membership
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Member {
+    pub user_id: String,
+    pub role: Role,
+    pub joined_at: DateTime<Utc>,
+}
+
+/// An invitation to join, redeemed with its token
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Invite {
+    pub token: String,
+    
+    /// Role the new member gets
+    pub role: Role,
+    
+    /// Member who sent it
+    pub invited_by: String,
+    
+    /// Who it is meant for, as a reminder for the admins (e.g. an email address)
+    #[serde(default)]
+    pub label: Option<String>,
+    
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Invite {
+    pub fn is_pending(&self, now: DateTime<Utc>) -> bool {
+        now < self.expires_at
+    }
+}
+
+/// An organization sharing one license
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Organization {
+    pub id: String,
+    pub name: String,
+    
+    /// License every member is charged to
+    pub license_key: LicenseKey,
+    
+    /// In the order they joined, the owner first
+    pub members: Vec<Member>,
+    
+    /// Invites not yet accepted (expired ones are dropped on the next change)
+    #[serde(default)]
+    pub invites: Vec<Invite>,
+    
+    pub created_at: DateTime<Utc>,
+}
+
+impl Organization {
+    /// A new organization with `owner_id` as its only member
+    pub fn new(name: impl Into<String>, owner_id: impl Into<String>, license_key: LicenseKey) -> Self {
+        let now = Utc::now();
+        Self {
+            id: uuid::Uuid::new_v4().to_string(),
+            name: name.into(),
+            license_key,
+            members: vec![Member { user_id: owner_id.into(), role: Role::Owner, joined_at: now }],
+            invites: Vec::new(),
+            created_at: now,
+        }
+    }
+    
+    /// Owner id the organization's shared resources (portfolios, conversations) are kept under
+    pub fn scope(&self) -> String {
+        format!("org:{}", self.id)
+    }
+    
+    /// The organization id of an owner id made by `scope`
+    pub fn id_of_scope(owner: &str) -> Option<&str> {
+        owner.strip_prefix("org:")
+    }
+    
+    pub fn member(&self, user_id: &str) -> Option<&Member> {
+        self.members.iter().find(|m| m.user_id == user_id)
+    }
+    
+    pub fn role_of(&self, user_id: &str) -> Option<Role> {
+        self.member(user_id).map(|m| m.role)
+    }
+    
+    pub fn owner_id(&self) -> Option<&str> {
+        self.members.iter().find(|m| m.role == Role::Owner).map(|m| m.user_id.as_str())
+    }
+    
+    /// Seats taken: members and pending invites
+    pub fn seats_used(&self, now: DateTime<Utc>) -> u32 {
+        let pending = self.invites.iter().filter(|i| i.is_pending(now)).count();
+        u32::try_from(self.members.len() + pending).unwrap_or(u32::MAX)
+    }
+    
+    /// Whether a member holds one of `seats` seats
+    ///
+    /// The owner always does, then members in the order they joined; after a
+    /// downgrade the members past the plan's seats lose access to the license.
+    pub fn seated(&self, user_id: &str, seats: u32) -> bool {
+        match self.role_of(user_id) {
+            Some(Role::Owner) => true,
+            Some(_) => self
+                .members
+                .iter()
+                .filter(|m| m.role != Role::Owner)
+                .position(|m| m.user_id == user_id)
+                .is_some_and(|position| position + 1 < seats as usize),
+            None => false,
+        }
+    }
+    
+    /// Invite someone as `role`, taking a seat until the invite is accepted or expires
+    pub fn invite(&mut self, by: &str, role: Role, label: Option<String>, seats: u32, now: DateTime<Utc>) -> Result<Invite> {
+        let inviter = self.manager(by)?;
+        if role == Role::Owner {
+            return Err(PaymentError::NotPermitted("ownership is transferred, not invited".into()));
+        }
+        if role == Role::Admin && inviter != Role::Owner {
+            return Err(PaymentError::NotPermitted("only the owner can invite admins".into()));
+        }
+        
+        self.invites.retain(|i| i.is_pending(now));
+        if self.seats_used(now) >= seats {
+            return Err(PaymentError::SeatLimit(seats));
+        }
+        
+        let invite = Invite {
+            token: format!("inv_{}", uuid::Uuid::new_v4().simple()),
+            role,
+            invited_by: by.to_string(),
+            label,
+            created_at: now,
+            expires_at: now + Duration::days(INVITE_TTL_DAYS),
+        };
+        self.invites.push(invite.clone());
+        Ok(invite)
+    }
+    
+    /// Withdraw a pending invite
+    pub fn revoke_invite(&mut self, by: &str, token: &str) -> Result<()> {
+        self.manager(by)?;
+        let before = self.invites.len();
+        self.invites.retain(|i| i.token != token);
+        if self.invites.len() == before {
+            return Err(PaymentError::InvalidInvite("no pending invite with this token".into()));
+        }
+        Ok(())
+    }
+    
+    /// Redeem an invite, joining as the role it grants
+    pub fn accept(&mut self, token: &str, user_id: &str, seats: u32, now: DateTime<Utc>) -> Result<Member> {
+        self.invites.retain(|i| i.is_pending(now));
+        let Some(index) = self.invites.iter().position(|i| i.token == token) else {
+            return Err(PaymentError::InvalidInvite("unknown or expired invite".into()));
+        };
+        if self.member(user_id).is_some() {
+            return Err(PaymentError::OrgConflict("already a member of this organization".into()));
+        }
+        // The invite held a seat, unless the plan has lost seats since
+        if self.members.len() >= seats as usize {
+            return Err(PaymentError::SeatLimit(seats));
+        }
+        
+        let invite = self.invites.remove(index);
+        let member = Member { user_id: user_id.to_string(), role: invite.role, joined_at: now };
+        self.members.push(member.clone());
+        Ok(member)
+    }
+    
+    /// Change a member's role (owner only); making someone owner hands over ownership
+    pub fn set_role(&mut self, by: &str, user_id: &str, role: Role) -> Result<Member> {
+        if self.role_of(by) != Some(Role::Owner) {
+            return Err(PaymentError::NotPermitted("only the owner can change roles".into()));
+        }
+        if by == user_id {
+            return Err(PaymentError::OrgConflict("transfer ownership to another member instead".into()));
+        }
+        let index = self
+            .members
+            .iter()
+            .position(|m| m.user_id == user_id)
+            .ok_or_else(|| PaymentError::MemberNotFound(user_id.to_string()))?;
+        
+        if role == Role::Owner {
+            for member in self.members.iter_mut().filter(|m| m.role == Role::Owner) {
+                member.role = Role::Admin;
+            }
+        }
+        self.members[index].role = role;
+        Ok(self.members[index].clone())
+    }
+    
+    /// Remove a member: anyone but the owner may leave, and managers remove less privileged members
+    pub fn remove(&mut self, by: &str, user_id: &str) -> Result<Member> {
+        let target = self.role_of(user_id).ok_or_else(|| PaymentError::MemberNotFound(user_id.to_string()))?;
+        if target == Role::Owner {
+            return Err(PaymentError::OrgConflict(
+                "the owner cannot leave; transfer ownership or delete the organization".into(),
+            ));
+        }
+        if by != user_id {
+            let remover = self.manager(by)?;
+            if remover <= target {
+                return Err(PaymentError::NotPermitted(format!("only the owner can remove an {}", target.as_str())));
+            }
+        }
+        
+        let index = self.members.iter().position(|m| m.user_id == user_id).unwrap_or_default();
+        Ok(self.members.remove(index))
+    }
+    
+    /// The role of a member allowed to manage the organization
+    fn manager(&self, user_id: &str) -> Result<Role> {
+        match self.role_of(user_id) {
+            Some(role) if role.can_manage() => Ok(role),
+            Some(_) => Err(PaymentError::NotPermitted("admins and the owner manage the organization".into())),
+            None => Err(PaymentError::OrgNotFound(self.id.clone())),
+        }
+    }
+}
+
+/// Organization storage trait
+pub trait OrgStore: Send + Sync {
+    /// Add an organization (fails if its license or one of its members already has one)
+    fn create(&self, org: &Organization) -> Result<()>;
+    
+    fn get(&self, id: &str) -> Result<Option<Organization>>;
+    
+    /// The organization an account is a member of
+    fn for_user(&self, user_id: &str) -> Result<Option<Organization>>;
+    
+    /// The organization holding an invite token
+    fn by_invite(&self, token: &str) -> Result<Option<Organization>>;
+    
+    /// Apply `change` to an organization and save it, returning the updated organization
+    ///
+    /// Fails with `OrgConflict`, keeping the organization as it was, if the
+    /// change adds a member of another organization.
+    fn update(&self, id: &str, change: &mut dyn FnMut(&mut Organization) -> Result<()>) -> Result<Organization>;
+    
+    /// Remove an organization; false if there was none
+    fn delete(&self, id: &str) -> Result<bool>;
+}
+
+/// In-memory organization store, optionally saved to a JSON file after every change
+pub struct MemoryOrgStore {
+    path: Option<PathBuf>,
+    orgs: RwLock<HashMap<String, Organization>>,
+}
+
+impl Default for MemoryOrgStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryOrgStore {
+    pub fn new() -> Self {
+        Self {
+            path: None,
+            orgs: RwLock::new(HashMap::new()),
+        }
+    }
+    
+    /// Load the organizations saved at `path` (none if the file doesn't exist yet)
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let orgs: Vec<Organization> = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| PaymentError::Storage(format!("{}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(PaymentError::Storage(format!("{}: {}", path.display(), e))),
+        };
+        
+        Ok(Self {
+            path: Some(path),
+            orgs: RwLock::new(orgs.into_iter().map(|org| (org.id.clone(), org)).collect()),
+        })
+    }
+    
+    /// Number of organizations
+    pub fn len(&self) -> usize {
+        self.orgs.read().unwrap().len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// Write all organizations to a temporary file, then move it over the old one
+    fn save(&self, orgs: &HashMap<String, Organization>) -> Result<()> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let write = || -> std::io::Result<()> {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut orgs: Vec<&Organization> = orgs.values().collect();
+            orgs.sort_by_key(|org| org.created_at);
+            
+            let tmp = path.with_extension("tmp");
+            std::fs::write(&tmp, serde_json::to_vec_pretty(&orgs)?)?;
+            std::fs::rename(&tmp, path)
+        };
+        write().map_err(|e| PaymentError::Storage(format!("{}: {}", path.display(), e)))
+    }
+    
+    /// Fail if a member of `org` belongs to another organization
+    fn check_members(orgs: &HashMap<String, Organization>, org: &Organization) -> Result<()> {
+        let taken = orgs
+            .values()
+            .filter(|other| other.id != org.id)
+            .any(|other| other.members.iter().any(|m| org.member(&m.user_id).is_some()));
+        if taken {
+            return Err(PaymentError::OrgConflict("already a member of another organization".into()));
+        }
+        Ok(())
+    }
+}
+
+impl OrgStore for MemoryOrgStore {
+    fn create(&self, org: &Organization) -> Result<()> {
+        let mut orgs = self.orgs.write().unwrap();
+        if orgs.values().any(|other| other.license_key == org.license_key) {
+            return Err(PaymentError::OrgConflict("this license already has an organization".into()));
+        }
+        Self::check_members(&orgs, org)?;
+        
+        orgs.insert(org.id.clone(), org.clone());
+        if let Err(e) = self.save(&orgs) {
+            orgs.remove(&org.id);
+            return Err(e);
+        }
+        Ok(())
+    }
+    
+    fn get(&self, id: &str) -> Result<Option<Organization>> {
+        Ok(self.orgs.read().unwrap().get(id).cloned())
+    }
+    
+    fn for_user(&self, user_id: &str) -> Result<Option<Organization>> {
+        let orgs = self.orgs.read().unwrap();
+        Ok(orgs.values().find(|org| org.member(user_id).is_some()).cloned())
+    }
+    
+    fn by_invite(&self, token: &str) -> Result<Option<Organization>> {
+        let orgs = self.orgs.read().unwrap();
+        Ok(orgs.values().find(|org| org.invites.iter().any(|i| i.token == token)).cloned())
+    }
+    
+    fn update(&self, id: &str, change: &mut dyn FnMut(&mut Organization) -> Result<()>) -> Result<Organization> {
+        let mut orgs = self.orgs.write().unwrap();
+        let stored = orgs.get(id).ok_or_else(|| PaymentError::OrgNotFound(id.to_string()))?;
+        
+        let mut org = stored.clone();
+        change(&mut org)?;
+        Self::check_members(&orgs, &org)?;
+        let previous = orgs.insert(org.id.clone(), org.clone());
+        if let Err(e) = self.save(&orgs) {
+            orgs.extend(previous.map(|previous| (previous.id.clone(), previous)));
+            return Err(e);
+        }
+        Ok(org)
+    }
+    
+    fn delete(&self, id: &str) -> Result<bool> {
+        let mut orgs = self.orgs.write().unwrap();
+        let Some(removed) = orgs.remove(id) else {
+            return Ok(false);
+        };
+        if let Err(e) = self.save(&orgs) {
+            orgs.insert(removed.id.clone(), removed);
+            return Err(e);
+        }
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn team() -> Organization {
+        Organization::new("Acme", "owner", LicenseKey::generate())
+    }
+
+    #[test]
+    fn test_invites_take_seats() {
+        let mut org = team();
+        let now = Utc::now();
+        
+        let first = org.invite("owner", Role::Admin, None, 3, now).unwrap();
+        let second = org.invite("owner", Role::Member, Some("bob@example.com".into()), 3, now).unwrap();
+        assert!(matches!(org.invite("owner", Role::Member, None, 3, now), Err(PaymentError::SeatLimit(3))));
+        
+        org.accept(&second.token, "bob", 3, now).unwrap();
+        assert_eq!(org.role_of("bob"), Some(Role::Member));
+        assert_eq!(org.seats_used(now), 3);
+        assert!(matches!(org.accept(&second.token, "carol", 3, now), Err(PaymentError::InvalidInvite(_))));
+        
+        // An expired invite frees its seat
+        let later = now + Duration::days(INVITE_TTL_DAYS + 1);
+        assert_eq!(org.seats_used(later), 2);
+        assert!(matches!(org.accept(&first.token, "alice", 3, later), Err(PaymentError::InvalidInvite(_))));
+        assert!(org.invite("owner", Role::Member, None, 3, later).is_ok());
+    }
+
+    #[test]
+    fn test_roles() {
+        let mut org = team();
+        let now = Utc::now();
+        for (user, role) in [("admin", Role::Admin), ("member", Role::Member), ("other", Role::Member)] {
+            let invite = org.invite("owner", role, None, 5, now).unwrap();
+            org.accept(&invite.token, user, 5, now).unwrap();
+        }
+        
+        assert!(matches!(org.invite("member", Role::Member, None, 5, now), Err(PaymentError::NotPermitted(_))));
+        assert!(matches!(org.invite("admin", Role::Admin, None, 5, now), Err(PaymentError::NotPermitted(_))));
+        assert!(matches!(org.invite("stranger", Role::Member, None, 5, now), Err(PaymentError::OrgNotFound(_))));
+        assert!(matches!(org.set_role("admin", "member", Role::Admin), Err(PaymentError::NotPermitted(_))));
+        assert!(matches!(org.remove("member", "other"), Err(PaymentError::NotPermitted(_))));
+        assert!(matches!(org.remove("admin", "owner"), Err(PaymentError::OrgConflict(_))));
+        
+        // Admins remove members, members leave, only the owner removes admins
+        org.remove("admin", "other").unwrap();
+        org.remove("member", "member").unwrap();
+        let invite = org.invite("owner", Role::Admin, None, 5, now).unwrap();
+        org.accept(&invite.token, "second", 5, now).unwrap();
+        assert!(matches!(org.remove("admin", "second"), Err(PaymentError::NotPermitted(_))));
+        
+        // Handing over ownership demotes the old owner to admin
+        org.set_role("owner", "admin", Role::Owner).unwrap();
+        assert_eq!(org.owner_id(), Some("admin"));
+        assert_eq!(org.role_of("owner"), Some(Role::Admin));
+        assert_eq!(org.members.iter().filter(|m| m.role == Role::Owner).count(), 1);
+    }
+
+    #[test]
+    fn test_seated_after_downgrade() {
+        let mut org = team();
+        let now = Utc::now();
+        for user in ["a", "b", "c"] {
+            let invite = org.invite("owner", Role::Member, None, 5, now).unwrap();
+            org.accept(&invite.token, user, 5, now).unwrap();
+        }
+        
+        assert_eq!(Organization::id_of_scope(&org.scope()), Some(org.id.as_str()));
+        assert!(["owner", "a", "b", "c"].iter().all(|user| org.seated(user, 5)));
+        assert!(org.seated("owner", 1));
+        assert!(!org.seated("a", 1));
+        assert!(org.seated("a", 2) && !org.seated("b", 2));
+        assert!(!org.seated("stranger", 5));
+    }
+
+    #[test]
+    fn test_store_one_org_per_account() {
+        let store = MemoryOrgStore::new();
+        let org = team();
+        store.create(&org).unwrap();
+        
+        let same_license = Organization::new("Copy", "someone", org.license_key.clone());
+        assert!(matches!(store.create(&same_license), Err(PaymentError::OrgConflict(_))));
+        
+        let other = Organization::new("Other", "other-owner", LicenseKey::generate());
+        store.create(&other).unwrap();
+        let updated = store
+            .update(&other.id, &mut |org| org.invite("other-owner", Role::Member, None, 5, Utc::now()).map(|_| ()))
+            .unwrap();
+        let token = updated.invites[0].token.clone();
+        assert_eq!(store.by_invite(&token).unwrap().map(|o| o.id), Some(other.id.clone()));
+        
+        // The owner of the first organization can't join the second
+        let joined = store.update(&other.id, &mut |org| org.accept(&token, "owner", 5, Utc::now()).map(|_| ()));
+        assert!(matches!(joined, Err(PaymentError::OrgConflict(_))));
+        assert_eq!(store.get(&other.id).unwrap().unwrap().invites.len(), 1);
+        
+        store.update(&other.id, &mut |org| org.accept(&token, "newcomer", 5, Utc::now()).map(|_| ())).unwrap();
+        assert_eq!(store.for_user("newcomer").unwrap().map(|o| o.id), Some(other.id.clone()));
+        assert!(store.delete(&other.id).unwrap());
+        assert!(store.for_user("newcomer").unwrap().is_none());
+    }
+}
@@ -0,0 +1,215 @@
+//! Plan Limits and Quotas
+//!
+//! What each plan may use per request (models, tools, output tokens) and at
+//! once (concurrent requests), plus the daily request quota of callers
+//! without a license. Licensed daily quotas are counted on the `License`
+//! itself (`LicenseStore::verify_and_use`).
+
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use crate::error::{PaymentError, Result};
+use crate::license::Plan;
+
+/// Per-request and concurrency limits of a plan
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlanLimits {
+    /// Models the plan may use (empty = any). An entry also allows its
+    /// tagged variants: `llama3.2` allows `llama3.2:1b`.
+    #[serde(default)]
+    pub models: Vec<String>,
+    
+    /// Tools the plan may use (empty = all)
+    #[serde(default)]
+    pub tools: Vec<String>,
+    
+    /// Most tokens one generation may produce
+    pub max_tokens: u32,
+    
+    /// Requests one caller may run at once
+    pub max_concurrent: u32,
+}
+
+impl PlanLimits {
+    pub fn allows_model(&self, model: &str) -> bool {
+        let base = model.split_once(':').map_or(model, |(base, _)| base);
+        self.models.is_empty() || self.models.iter().any(|m| m == model || m == base)
+    }
+    
+    pub fn allows_tool(&self, tool: &str) -> bool {
+        self.tools.is_empty() || self.tools.iter().any(|t| t == tool)
+    }
+}
+
+/// Limits of every plan
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlanTable {
+    pub free: PlanLimits,
+    pub pro: PlanLimits,
+    pub team: PlanLimits,
+}
+
+impl Default for PlanTable {
+    /// Free: small default model and basic tools; paid plans: everything
+    fn default() -> Self {
+        let strings = |items: &[&str]| items.iter().map(|s| s.to_string()).collect();
+        Self {
+            free: PlanLimits {
+                models: strings(&["llama3.2", "auto"]),
+                tools: strings(&["datetime", "calculator", "price_lookup", "dca_calculator", "knowledge_search"]),
+                max_tokens: 1024,
+                max_concurrent: 1,
+            },
+            pro: PlanLimits {
+                models: Vec::new(),
+                tools: Vec::new(),
+                max_tokens: 2048,
+                max_concurrent: 4,
+            },
+            team: PlanLimits {
+                models: Vec::new(),
+                tools: Vec::new(),
+                max_tokens: 4096,
+                max_concurrent: 16,
+            },
+        }
+    }
+}
+
+impl PlanTable {
+    /// Load from JSON (`{"free": {...}, "pro": {...}, "team": {...}}`)
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| PaymentError::Config(format!("Invalid plan limits: {}", e)))
+    }
+    
+    pub fn limits(&self, plan: &Plan) -> &PlanLimits {
+        match plan {
+            Plan::Free => &self.free,
+            Plan::Pro => &self.pro,
+            Plan::Team => &self.team,
+        }
+    }
+}
+
+/// Seconds until daily quotas reset (midnight UTC)
+pub fn seconds_until_reset() -> u64 {
+    let now = Utc::now();
+    let midnight = (now.date_naive() + chrono::Days::new(1)).and_hms_opt(0, 0, 0).map(|t| t.and_utc());
+    midnight.map_or(0, |m| u64::try_from((m - now).num_seconds()).unwrap_or(0))
+}
+
+/// Daily request counts of callers without a license
+#[derive(Default)]
+pub struct DailyQuota {
+    counts: Mutex<HashMap<String, (NaiveDate, u32)>>,
+}
+
+impl DailyQuota {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Count one request; the requests left today, or None if the limit is reached
+    pub fn try_use(&self, subject: &str, limit: u32) -> Option<u32> {
+        let today = Utc::now().date_naive();
+        let mut counts = self.counts.lock().unwrap();
+        
+        // Entries from earlier days are stale; drop them as we go
+        counts.retain(|_, (day, _)| *day == today);
+        let (_, used) = counts.entry(subject.to_string()).or_insert((today, 0));
+        if *used >= limit {
+            return None;
+        }
+        *used += 1;
+        Some(limit - *used)
+    }
+}
+
+/// Requests in flight per caller
+#[derive(Clone, Default)]
+pub struct ConcurrencyLimiter {
+    in_flight: Arc<Mutex<HashMap<String, u32>>>,
+}
+
+impl ConcurrencyLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Take a slot, held until the permit is dropped; None if all are taken
+    pub fn try_acquire(&self, subject: &str, limit: u32) -> Option<ConcurrencyPermit> {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        let count = in_flight.entry(subject.to_string()).or_insert(0);
+        if *count >= limit {
+            return None;
+        }
+        *count += 1;
+        Some(ConcurrencyPermit {
+            in_flight: self.in_flight.clone(),
+            subject: subject.to_string(),
+        })
+    }
+    
+    /// Requests a caller has in flight
+    pub fn in_flight(&self, subject: &str) -> u32 {
+        self.in_flight.lock().unwrap().get(subject).copied().unwrap_or(0)
+    }
+}
+
+/// A concurrency slot, released on drop
+pub struct ConcurrencyPermit {
+    in_flight: Arc<Mutex<HashMap<String, u32>>>,
+    subject: String,
+}
+
+impl Drop for ConcurrencyPermit {
+    fn drop(&mut self) {
+        let mut in_flight = self.in_flight.lock().unwrap();
+        if let Some(count) = in_flight.get_mut(&self.subject) {
+            *count -= 1;
+            if *count == 0 {
+                in_flight.remove(&self.subject);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plan_limits() {
+        let table = PlanTable::default();
+        let free = table.limits(&Plan::Free);
+        assert!(free.allows_model("llama3.2:1b"));
+        assert!(!free.allows_model("llama3.1:70b"));
+        assert!(!free.allows_tool("portfolio_tracker"));
+        assert!(table.limits(&Plan::Pro).allows_model("llama3.1:70b"));
+        assert!(table.limits(&Plan::Pro).allows_tool("portfolio_tracker"));
+        
+        let json = r#"{"free": {"max_tokens": 256, "max_concurrent": 1}, "pro": {"models": ["mistral"], "max_tokens": 1024, "max_concurrent": 2}, "team": {"max_tokens": 4096, "max_concurrent": 8}}"#;
+        let table = PlanTable::from_json(json).unwrap();
+        assert!(table.limits(&Plan::Free).allows_model("anything"));
+        assert!(!table.limits(&Plan::Pro).allows_model("llama3.2"));
+    }
+
+    #[test]
+    fn test_daily_quota_and_concurrency() {
+        let quota = DailyQuota::new();
+        assert_eq!(quota.try_use("u1", 2), Some(1));
+        assert_eq!(quota.try_use("u1", 2), Some(0));
+        assert_eq!(quota.try_use("u1", 2), None);
+        assert_eq!(quota.try_use("u2", 2), Some(1));
+        
+        let limiter = ConcurrencyLimiter::new();
+        let first = limiter.try_acquire("u1", 1).unwrap();
+        assert!(limiter.try_acquire("u1", 1).is_none());
+        assert!(limiter.try_acquire("u2", 1).is_some());
+        drop(first);
+        assert_eq!(limiter.in_flight("u1"), 0);
+        assert!(limiter.try_acquire("u1", 1).is_some());
+    }
+}
@@ -0,0 +1,244 @@
+//! Usage Accounting
+//!
+//! Token usage charged to licenses, priced with per-model cost tables.
+//! Records name the account that made the request, so the statement of a
+//! license shared by an organization breaks usage down by member.
+//!
+//! Amounts are integer micro-dollars (1 USD = 1,000,000) so fractions of a
+//! cent add up exactly across many small requests.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+use crate::error::{PaymentError, Result};
+use crate::license::{License, LicenseKey, Plan};
+
+/// Price of one model, in micro-dollars per 1,000 tokens
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelRate {
+    pub input_per_1k: i64,
+    pub output_per_1k: i64,
+}
+
+impl ModelRate {
+    pub fn new(input_per_1k: i64, output_per_1k: i64) -> Self {
+        Self { input_per_1k, output_per_1k }
+    }
+    
+    /// Cost of a request in micro-dollars (rounded to nearest)
+    pub fn cost(&self, prompt_tokens: u32, completion_tokens: u32) -> i64 {
+        let scaled = i64::from(prompt_tokens) * self.input_per_1k
+            + i64::from(completion_tokens) * self.output_per_1k;
+        (scaled + 500) / 1000
+    }
+}
+
+/// Per-model cost table
+///
+/// Lookups try the exact model name, then the name without its tag
+/// (`llama3.2:3b` → `llama3.2`), then fall back to the default rate.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CostTable {
+    #[serde(default)]
+    pub default: ModelRate,
+    #[serde(default)]
+    pub models: HashMap<String, ModelRate>,
+}
+
+impl Default for CostTable {
+    /// Internal compute rates for the bundled local models
+    fn default() -> Self {
+        Self {
+            default: ModelRate::new(50, 100),
+            models: HashMap::from([
+                ("llama3.2".to_string(), ModelRate::new(20, 40)),
+                ("llama3.1".to_string(), ModelRate::new(50, 100)),
+                ("mistral".to_string(), ModelRate::new(40, 80)),
+                ("nomic-embed-text".to_string(), ModelRate::new(5, 0)),
+            ]),
+        }
+    }
+}
+
+impl CostTable {
+    /// Load a table from JSON: `{"default": {...}, "models": {"llama3.2": {"input_per_1k": 20, "output_per_1k": 40}}}`
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| PaymentError::Config(format!("Invalid cost table: {}", e)))
+    }
+    
+    /// Add or replace a model's rate
+    pub fn with_rate(mut self, model: impl Into<String>, rate: ModelRate) -> Self {
+        self.models.insert(model.into(), rate);
+        self
+    }
+    
+    /// Rate for a model
+    pub fn rate(&self, model: &str) -> ModelRate {
+        self.models
+            .get(model)
+            .or_else(|| model.split_once(':').and_then(|(base, _)| self.models.get(base)))
+            .copied()
+            .unwrap_or(self.default)
+    }
+    
+    /// Price a request
+    pub fn price(&self, license_key: LicenseKey, model: &str, prompt_tokens: u32, completion_tokens: u32) -> UsageRecord {
+        UsageRecord {
+            license_key,
+            model: model.to_string(),
+            prompt_tokens,
+            completion_tokens,
+            cost_micros: self.rate(model).cost(prompt_tokens, completion_tokens),
+            recorded_at: Utc::now(),
+            user_id: None,
+        }
+    }
+}
+
+/// One charged request
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UsageRecord {
+    pub license_key: LicenseKey,
+    pub model: String,
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub cost_micros: i64,
+    pub recorded_at: DateTime<Utc>,
+    
+    /// Account that made the request
+    #[serde(default)]
+    pub user_id: Option<String>,
+}
+
+impl UsageRecord {
+    /// Attribute the request to an account
+    pub fn by(mut self, user_id: impl Into<String>) -> Self {
+        self.user_id = Some(user_id.into());
+        self
+    }
+}
+
+/// Usage of one model within a statement
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct ModelUsage {
+    pub model: String,
+    pub requests: u64,
+    pub prompt_tokens: u64,
+    pub completion_tokens: u64,
+    pub cost_micros: i64,
+}
+
+/// Usage of one account within a statement
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MemberUsage {
+    /// None for requests recorded without an account
+    pub user_id: Option<String>,
+    pub requests: u64,
+    pub total_tokens: u64,
+    pub cost_micros: i64,
+}
+
+/// Usage statement for a license over a period
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct UsageStatement {
+    pub license_key: LicenseKey,
+    pub plan: Plan,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub models: Vec<ModelUsage>,
+    
+    /// Usage by account, most expensive first
+    #[serde(default)]
+    pub members: Vec<MemberUsage>,
+    
+    pub total_tokens: u64,
+    pub total_cost_micros: i64,
+}
+
+impl UsageStatement {
+    /// Summarize the records of `license` that fall within `[from, to)`
+    pub fn build(license: &License, records: &[UsageRecord], from: DateTime<Utc>, to: DateTime<Utc>) -> Self {
+        let mut by_model: BTreeMap<&str, ModelUsage> = BTreeMap::new();
+        let mut by_member: BTreeMap<Option<&str>, MemberUsage> = BTreeMap::new();
+        
+        for record in records
+            .iter()
+            .filter(|r| r.license_key == license.key && r.recorded_at >= from && r.recorded_at < to)
+        {
+            let entry = by_model.entry(&record.model).or_insert_with(|| ModelUsage {
+                model: record.model.clone(),
+                ..Default::default()
+            });
+            entry.requests += 1;
+            entry.prompt_tokens += u64::from(record.prompt_tokens);
+            entry.completion_tokens += u64::from(record.completion_tokens);
+            entry.cost_micros += record.cost_micros;
+            
+            let member = by_member.entry(record.user_id.as_deref()).or_insert_with(|| MemberUsage {
+                user_id: record.user_id.clone(),
+                ..Default::default()
+            });
+            member.requests += 1;
+            member.total_tokens += u64::from(record.prompt_tokens) + u64::from(record.completion_tokens);
+            member.cost_micros += record.cost_micros;
+        }
+        
+        let models: Vec<ModelUsage> = by_model.into_values().collect();
+        let mut members: Vec<MemberUsage> = by_member.into_values().collect();
+        members.sort_by_key(|m| std::cmp::Reverse(m.cost_micros));
+        Self {
+            license_key: license.key.clone(),
+            plan: license.plan.clone(),
+            from,
+            to,
+            total_tokens: models.iter().map(|m| m.prompt_tokens + m.completion_tokens).sum(),
+            total_cost_micros: models.iter().map(|m| m.cost_micros).sum(),
+            models,
+            members,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_lookup_and_cost() {
+        let table = CostTable::default().with_rate("custom", ModelRate::new(1000, 2000));
+        assert_eq!(table.rate("llama3.2:3b"), table.rate("llama3.2"));
+        assert_eq!(table.rate("unknown"), table.default);
+        
+        // 1500 prompt + 500 completion tokens at $0.001 / $0.002 per 1K
+        assert_eq!(table.rate("custom").cost(1500, 500), 2500);
+    }
+
+    #[test]
+    fn test_statement_groups_by_model() {
+        let license = License::new("sub_1".into(), "team@example.com".into(), Plan::Team);
+        let table = CostTable::default();
+        let records = vec![
+            table.price(license.key.clone(), "llama3.2", 1000, 200).by("alice"),
+            table.price(license.key.clone(), "llama3.2", 500, 100).by("bob"),
+            table.price(license.key.clone(), "mistral", 100, 100).by("alice"),
+            table.price(LicenseKey::generate(), "llama3.2", 9999, 9999),
+        ];
+        
+        let now = Utc::now();
+        let statement = UsageStatement::build(&license, &records, now - chrono::Duration::hours(1), now + chrono::Duration::hours(1));
+        
+        assert_eq!(statement.models.len(), 2);
+        assert_eq!(statement.models[0].model, "llama3.2");
+        assert_eq!(statement.models[0].requests, 2);
+        assert_eq!(statement.total_tokens, 2000);
+        assert_eq!(statement.total_cost_micros, records[..3].iter().map(|r| r.cost_micros).sum::<i64>());
+        
+        // Rolled up by member, most expensive first
+        assert_eq!(statement.members.len(), 2);
+        assert_eq!(statement.members[0].user_id.as_deref(), Some("alice"));
+        assert_eq!(statement.members[0].requests, 2);
+        assert_eq!(statement.members[0].total_tokens, 1400);
+        assert_eq!(statement.members.iter().map(|m| m.cost_micros).sum::<i64>(), statement.total_cost_micros);
+    }
+}
@@ -18,6 +18,8 @@ pub enum WebhookEvent {
         subscription_id: String,
         customer_email: String,
         plan: Plan,
+        /// Account that started the checkout
+        user_id: Option<String>,
     },
     
     /// Subscription updated - might need to change plan
@@ -72,13 +74,15 @@ impl<S: LicenseStore> WebhookHandler<S> {
                 subscription_id,
                 customer_email,
                 plan,
+                user_id,
                 ..
             } => {
-                let license = License::new(
+                let mut license = License::new(
                     subscription_id.clone(),
                     customer_email.clone(),
                     plan.clone(),
                 );
+                license.user_id = user_id.clone();
                 
                 self.license_store.save(&license)?;
                 
@@ -140,9 +144,9 @@ impl<S: LicenseStore> WebhookHandler<S> {
                 tracing::warn!(
                     subscription_id = ?subscription_id,
                     email = ?customer_email,
-                    "Payment failed - may need to notify customer"
+                    "Payment failed"
                 );
-                // Could send notification, implement grace period, etc.
+                // The caller notifies the customer (it knows how to reach them)
             }
             
             WebhookEvent::Other { event_type } => {
@@ -172,6 +176,7 @@ impl<S: LicenseStore> WebhookHandler<S> {
                             .unwrap_or_default(),
                         customer_email: session.customer_email.clone().unwrap_or_default(),
                         plan,
+                        user_id: session.metadata.as_ref().and_then(|m| m.get("user_id")).cloned(),
                     })
                 } else {
                     Err(PaymentError::WebhookParse("Invalid checkout session data".into()))
@@ -23,7 +23,7 @@
 pub mod ollama;
 
 #[cfg(feature = "ollama")]
-pub use ollama::OllamaProvider;
+pub use ollama::{OllamaEmbeddings, OllamaProvider};
 
 // Re-export core types for convenience
 pub use agent_core::{
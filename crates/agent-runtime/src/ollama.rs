@@ -5,6 +5,7 @@
 use std::pin::Pin;
 
 use agent_core::{
+    embedding::EmbeddingProvider,
     error::{AgentError, Result},
     message::{Message, Role},
     provider::{
@@ -17,7 +18,9 @@ use futures::{Stream, StreamExt};
 use ollama_rs::{
     generation::{
         chat::{ChatMessage, ChatMessageResponse, MessageRole, request::ChatMessageRequest},
+        embeddings::request::{EmbeddingsInput, GenerateEmbeddingsRequest},
         options::GenerationOptions as OllamaOptions,
+        parameters::{FormatType, JsonStructure},
     },
     Ollama,
 };
@@ -53,13 +56,22 @@ impl OllamaConfig {
             .ok()
             .and_then(|p| p.parse().ok())
             .unwrap_or(11434);
+        let timeout_secs = std::env::var("OLLAMA_TIMEOUT_SECS")
+            .ok()
+            .and_then(|t| t.parse().ok())
+            .unwrap_or(120);
         
         Self {
             host,
             port,
-            ..Default::default()
+            timeout_secs,
         }
     }
+    
+    /// Connection timeout as a `Duration`
+    pub fn timeout(&self) -> std::time::Duration {
+        std::time::Duration::from_secs(self.timeout_secs)
+    }
 }
 
 /// Ollama LLM provider
@@ -71,26 +83,33 @@ pub struct OllamaProvider {
 impl OllamaProvider {
     /// Create a new Ollama provider with custom host/port
     pub fn new(host: impl Into<String>, port: u16) -> Self {
-        let config = OllamaConfig {
+        Self::from_config(OllamaConfig {
             host: host.into(),
             port,
             ..Default::default()
-        };
-        
-        Self {
-            client: Ollama::new(&config.host, config.port),
-            config,
-        }
+        })
     }
     
     /// Create from configuration
     pub fn from_config(config: OllamaConfig) -> Self {
+        // Only the connect phase is bounded here; per-call and streaming
+        // timeouts belong to `agent_core::ResilientProvider`
+        let http = reqwest::Client::builder()
+            .connect_timeout(config.timeout())
+            .build()
+            .unwrap_or_default();
+        
         Self {
-            client: Ollama::new(&config.host, config.port),
+            client: Ollama::new_with_client(&config.host, config.port, http),
             config,
         }
     }
     
+    /// Get configuration
+    pub fn config(&self) -> &OllamaConfig {
+        &self.config
+    }
+    
     /// Create from environment variables
     pub fn from_env() -> Self {
         Self::from_config(OllamaConfig::from_env())
@@ -129,6 +148,7 @@ impl OllamaProvider {
             }),
             truncated: false,
             finish_reason: Some(FinishReason::Stop),
+            escalations: Vec::new(),
         }
     }
     
@@ -139,6 +159,24 @@ impl OllamaProvider {
             .top_p(opts.top_p)
             .num_predict(opts.max_tokens as i32)
     }
+    
+    /// Build a chat request, passing any response schema as Ollama's `format`
+    fn build_request(messages: &[Message], options: &GenerationOptions) -> Result<ChatMessageRequest> {
+        let mut request = ChatMessageRequest::new(
+            options.model.clone(),
+            Self::convert_messages(messages),
+        ).options(Self::build_options(options));
+        
+        if let Some(schema) = &options.response_format {
+            let schema = serde_json::from_value(schema.clone())
+                .map_err(|e| AgentError::Config(format!("Invalid response schema: {}", e)))?;
+            request = request.format(FormatType::StructuredJson(Box::new(
+                JsonStructure::new_for_schema(schema),
+            )));
+        }
+        
+        Ok(request)
+    }
 }
 
 #[async_trait]
@@ -170,13 +208,7 @@ impl LlmProvider for OllamaProvider {
         messages: &[Message],
         options: &GenerationOptions,
     ) -> Result<Completion> {
-        let ollama_messages = Self::convert_messages(messages);
-        let ollama_options = Self::build_options(options);
-        
-        let request = ChatMessageRequest::new(
-            options.model.clone(),
-            ollama_messages,
-        ).options(ollama_options);
+        let request = Self::build_request(messages, options)?;
         
         let response = self.client
             .send_chat_messages(request)
@@ -191,30 +223,31 @@ impl LlmProvider for OllamaProvider {
         messages: &[Message],
         options: &GenerationOptions,
     ) -> Result<CompletionStream> {
-        let ollama_messages = Self::convert_messages(messages);
-        let ollama_options = Self::build_options(options);
-        
-        let request = ChatMessageRequest::new(
-            options.model.clone(),
-            ollama_messages,
-        ).options(ollama_options);
+        let request = Self::build_request(messages, options)?;
         
         let stream = self.client
             .send_chat_messages_stream(request)
             .await
             .map_err(|e| AgentError::Provider(e.to_string()))?;
         
-        // Transform the stream
-        let mapped = stream.map(|result| {
+        // Transform the stream (the final chunk describes the completion, as `complete` does)
+        let model = options.model.clone();
+        let mapped = stream.map(move |result| {
             result
-                .map(|chunk| StreamChunk {
-                    delta: chunk.message.content,
-                    done: chunk.done.unwrap_or(false),
-                    usage: chunk.final_data.as_ref().map(|d| TokenUsage {
-                        prompt_tokens: d.prompt_eval_count.unwrap_or(0) as u32,
-                        completion_tokens: d.eval_count.unwrap_or(0) as u32,
-                        total_tokens: (d.prompt_eval_count.unwrap_or(0) + d.eval_count.unwrap_or(0)) as u32,
-                    }),
+                .map(|chunk| {
+                    let done = chunk.done.unwrap_or(false);
+                    StreamChunk {
+                        delta: chunk.message.content,
+                        done,
+                        usage: chunk.final_data.as_ref().map(|d| TokenUsage {
+                            prompt_tokens: d.prompt_eval_count.unwrap_or(0) as u32,
+                            completion_tokens: d.eval_count.unwrap_or(0) as u32,
+                            total_tokens: (d.prompt_eval_count.unwrap_or(0) + d.eval_count.unwrap_or(0)) as u32,
+                        }),
+                        model: done.then(|| model.clone()),
+                        finish_reason: done.then_some(FinishReason::Stop),
+                        ..StreamChunk::default()
+                    }
                 })
                 .map_err(|e| AgentError::Provider(e.to_string()))
         });
@@ -239,12 +272,73 @@ impl LlmProvider for OllamaProvider {
             .collect())
     }
     
+    fn supports_json_schema(&self) -> bool {
+        // Requires Ollama 0.5.0 or later
+        true
+    }
+    
     fn estimate_tokens(&self, text: &str) -> u32 {
         // Llama tokenizer is roughly 4 chars per token
         (text.len() / 4) as u32
     }
 }
 
+/// Ollama embedding provider (`/api/embed`)
+pub struct OllamaEmbeddings {
+    client: Ollama,
+    model: String,
+}
+
+impl OllamaEmbeddings {
+    /// Create an embedding provider sharing an existing provider's connection settings
+    pub fn new(provider: &OllamaProvider, model: impl Into<String>) -> Self {
+        Self {
+            client: provider.client.clone(),
+            model: model.into(),
+        }
+    }
+    
+    /// Create from environment variables (`EMBEDDING_MODEL`, default `nomic-embed-text`)
+    pub fn from_env() -> Self {
+        let model = std::env::var("EMBEDDING_MODEL")
+            .unwrap_or_else(|_| "nomic-embed-text".into());
+        Self::new(&OllamaProvider::from_env(), model)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for OllamaEmbeddings {
+    async fn embed(&self, inputs: &[String]) -> Result<Vec<Vec<f32>>> {
+        if inputs.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let request = GenerateEmbeddingsRequest::new(
+            self.model.clone(),
+            EmbeddingsInput::Multiple(inputs.to_vec()),
+        );
+        
+        let response = self.client
+            .generate_embeddings(request)
+            .await
+            .map_err(|e| AgentError::Provider(format!("Ollama embedding error: {}", e)))?;
+        
+        if response.embeddings.len() != inputs.len() {
+            return Err(AgentError::Provider(format!(
+                "Ollama returned {} embeddings for {} inputs",
+                response.embeddings.len(),
+                inputs.len()
+            )));
+        }
+        
+        Ok(response.embeddings)
+    }
+    
+    fn model(&self) -> &str {
+        &self.model
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
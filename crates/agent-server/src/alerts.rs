@@ -0,0 +1,224 @@
+//! Price Alert API
+//!
+//! Users set alerts on prices and portfolios ("tell me when ETH drops below
+//! 3000") here or through the `price_alert` tool in chat. Every
+//! `alerts.tick_secs` the `AlertEngine` evaluates the active alerts against
+//! the exchange and each one that fires is sent to its owner as a
+//! `price_alert` notification. Alerts are kept in a JSON file
+//! (`storage.alerts_path`); the price history behind `percent_move` alerts is
+//! not, so it starts over after a restart.
+//!
+//! Every route is scoped to the signed-in user; other users' alerts are
+//! reported as not found.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use agent_notify::{Notification, NotificationKind};
+use crypto_advisor::{AdvisorError, Alert, AlertCondition, AlertEngine, AlertEvent, AlertStatus};
+
+use crate::auth::UserId;
+use crate::error::{ApiError, ApiJson, ApiPath, ErrorCode};
+use crate::openapi::{AlertStatusName, Condition};
+use crate::state::AppState;
+
+/// Longest cooldown between firings of a repeating alert (7 days)
+const MAX_COOLDOWN_MINUTES: u32 = 7 * 24 * 60;
+
+// ============================================================================
+// Requests and Responses
+// ============================================================================
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CreateAlertRequest {
+    #[schemars(with = "Condition")]
+    pub condition: AlertCondition,
+    
+    /// Fire every time the condition recurs, instead of once
+    #[serde(default)]
+    pub repeat: bool,
+    
+    /// How far the condition must clear before a repeating alert re-arms:
+    /// percent of the price for price alerts, percentage points otherwise (default 1)
+    #[serde(default)]
+    pub hysteresis_percent: Option<Decimal>,
+    
+    /// Least minutes between firings of a repeating alert (default 60)
+    #[serde(default)]
+    pub cooldown_minutes: Option<u32>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct AlertView {
+    pub id: String,
+    #[schemars(with = "Condition")]
+    pub condition: AlertCondition,
+    
+    /// e.g. "ETH at or below $3000"
+    pub description: String,
+    
+    pub repeat: bool,
+    pub hysteresis_percent: Decimal,
+    pub cooldown_minutes: u32,
+    #[schemars(with = "AlertStatusName")]
+    pub status: AlertStatus,
+    pub fire_count: u32,
+    pub last_fired_at: Option<DateTime<Utc>>,
+    
+    /// Highest portfolio value seen (drawdown alerts)
+    pub peak_value: Option<Decimal>,
+    
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<Alert> for AlertView {
+    fn from(alert: Alert) -> Self {
+        Self {
+            id: alert.id.to_string(),
+            description: alert.condition.describe(),
+            condition: alert.condition,
+            repeat: alert.repeat,
+            hysteresis_percent: alert.hysteresis_percent,
+            cooldown_minutes: alert.cooldown_minutes,
+            status: alert.status,
+            fire_count: alert.fire_count,
+            last_fired_at: alert.last_fired_at,
+            peak_value: alert.peak_value.map(|value| value.round_dp(2)),
+            created_at: alert.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct AlertListResponse {
+    /// Oldest first
+    pub alerts: Vec<AlertView>,
+}
+
+/// Alert storage failures get their own code; the rest map as usual
+fn alert_error(e: AdvisorError) -> ApiError {
+    match e {
+        AdvisorError::Storage(cause) => {
+            tracing::error!(code = %ErrorCode::AlertsUnavailable, "{}", cause);
+            ApiError::new(StatusCode::SERVICE_UNAVAILABLE, ErrorCode::AlertsUnavailable, "Alert storage unavailable")
+        }
+        e => e.into(),
+    }
+}
+
+fn not_found(id: &str) -> ApiError {
+    AdvisorError::AlertNotFound(id.to_string()).into()
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+/// List the caller's alerts
+pub async fn list_alerts(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+) -> Result<Json<AlertListResponse>, ApiError> {
+    let alerts = state.alerts.list(Some(user_id.as_str())).map_err(alert_error)?;
+    Ok(Json(AlertListResponse { alerts: alerts.into_iter().map(AlertView::from).collect() }))
+}
+
+/// Set an alert
+pub async fn create_alert(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiJson(payload): ApiJson<CreateAlertRequest>,
+) -> Result<(StatusCode, Json<AlertView>), ApiError> {
+    let mut alert = Alert::new(Some(user_id.as_str()), payload.condition);
+    alert.repeat = payload.repeat;
+    if let Some(hysteresis) = payload.hysteresis_percent {
+        alert.hysteresis_percent = hysteresis;
+    }
+    if let Some(cooldown) = payload.cooldown_minutes {
+        if cooldown > MAX_COOLDOWN_MINUTES {
+            return Err(AdvisorError::InvalidAlert(format!("cooldown_minutes must be at most {}", MAX_COOLDOWN_MINUTES)).into());
+        }
+        alert.cooldown_minutes = cooldown;
+    }
+    let alert = alert.validated()?;
+    
+    state.alerts.create(&alert).await.map_err(alert_error)?;
+    tracing::info!(user_id = %user_id, alert_id = %alert.id, "Alert set: {}", alert.condition.describe());
+    Ok((StatusCode::CREATED, Json(AlertView::from(alert))))
+}
+
+/// One of the caller's alerts
+pub async fn get_alert(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiPath(id): ApiPath<String>,
+) -> Result<Json<AlertView>, ApiError> {
+    let uuid = Uuid::parse_str(&id).map_err(|_| not_found(&id))?;
+    let alert = state.alerts.get(Some(user_id.as_str()), uuid).map_err(alert_error)?.ok_or_else(|| not_found(&id))?;
+    Ok(Json(AlertView::from(alert)))
+}
+
+/// Delete one of the caller's alerts
+pub async fn delete_alert(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiPath(id): ApiPath<String>,
+) -> Result<StatusCode, ApiError> {
+    let uuid = Uuid::parse_str(&id).map_err(|_| not_found(&id))?;
+    if state.alerts.delete(Some(user_id.as_str()), uuid).await.map_err(alert_error)? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(not_found(&id))
+    }
+}
+
+// ============================================================================
+// Engine
+// ============================================================================
+
+/// Evaluate alerts every `tick`, until the server starts draining
+pub fn spawn(state: AppState, engine: Arc<AlertEngine>, tick: Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tick);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                () = state.shutdown.draining() => break,
+            }
+            // Shutdown waits for a tick in progress
+            let _in_flight = state.shutdown.track();
+            match engine.evaluate(Utc::now()).await {
+                Ok(events) => events.iter().for_each(|event| notify(&state, event)),
+                Err(e) => tracing::warn!("⚠ Alerts not evaluated: {}", e),
+            }
+        }
+    });
+}
+
+/// Tell the owner an alert fired
+fn notify(state: &AppState, event: &AlertEvent) {
+    tracing::info!(alert_id = %event.alert_id, "Alert fired: {}", event.message);
+    let Some(owner_id) = &event.owner_id else {
+        return;
+    };
+    let notification = Notification::new(owner_id, NotificationKind::PriceAlert)
+        .with("title", &event.title)
+        .with("message", &event.message)
+        .with("condition", &event.condition)
+        .with("value", event.value.round_dp(2))
+        .with("alert_id", event.alert_id)
+        .dedup(event.dedup_key.clone());
+    state.notifications.dispatch(notification);
+}
@@ -0,0 +1,761 @@
+//! Authentication
+//!
+//! Requests authenticate with one of:
+//!
+//! - an **API key** - `X-API-Key: ak_...` or `Authorization: Bearer ak_...`;
+//!   keys are stored as SHA-256 hashes and shown only when created
+//! - a **session token** issued at login (username/password, argon2 hashes) -
+//!   the `agent_session` cookie for the web UI, or a bearer token for other
+//!   clients. Tokens are HMAC-signed (`<user_id>.<expiry>.<signature>`), so
+//!   the server keeps no session table; logging out clears the cookie.
+//!
+//! `require_user` rejects unauthenticated requests and attaches the caller's
+//! `UserId` to the rest.
+//!
+//! Accounts and API key hashes are kept in a JSON file (`storage.users_path`),
+//! so user ids - which portfolios, DCA jobs, alerts and organizations are
+//! stored under - survive restarts.
+//!
+//! Logins take as long for unknown usernames as for wrong passwords, and
+//! after repeated failures a username has to wait between attempts. The
+//! backoff is per username, not per client address: behind a proxy every
+//! client shares one address, and a distributed guesser has many.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex, RwLock};
+
+use argon2::password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use axum::{
+    extract::{Extension, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use chrono::{DateTime, Duration, Utc};
+use hmac::{Hmac, Mac};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use agent_payments::{LicenseStore, Plan, Role};
+
+use crate::error::{ApiError, ApiJson, ErrorCode};
+use crate::openapi::{PlanName, RoleName};
+use crate::state::AppState;
+
+/// Cookie carrying the web UI's session token
+pub const SESSION_COOKIE: &str = "agent_session";
+
+/// Prefix telling API keys apart from session tokens
+const API_KEY_PREFIX: &str = "ak_";
+
+const MIN_PASSWORD_CHARS: usize = 8;
+
+/// Failed logins a username gets before it has to wait between attempts
+const FREE_LOGIN_FAILURES: u32 = 5;
+
+/// Longest wait between login attempts; failures older than this are forgotten
+const MAX_LOGIN_BACKOFF_SECS: i64 = 900;
+
+/// How often an API key's `last_used` is updated
+const API_KEY_USE_RESOLUTION_SECS: i64 = 60;
+
+/// Verified against on unknown usernames, so they take as long as wrong passwords
+static DUMMY_PASSWORD_HASH: LazyLock<String> = LazyLock::new(|| {
+    let salt = SaltString::encode_b64(b"dummy-password-salt").expect("a valid salt");
+    Argon2::default()
+        .hash_password(b"dummy password", &salt)
+        .expect("argon2 hashes a fixed password")
+        .to_string()
+});
+
+/// An authenticated account
+#[derive(Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize, JsonSchema)]
+pub struct UserId(String);
+
+impl UserId {
+    pub fn generate() -> Self {
+        Self(uuid::Uuid::new_v4().to_string())
+    }
+    
+    pub fn from_string(id: impl Into<String>) -> Self {
+        Self(id.into())
+    }
+    
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl std::fmt::Display for UserId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum AuthError {
+    #[error("Username must be 3-32 letters, digits, '.', '_' or '-'")]
+    InvalidUsername,
+    
+    #[error("Password must be at least {MIN_PASSWORD_CHARS} characters")]
+    WeakPassword,
+    
+    #[error("Username is already taken")]
+    UsernameTaken,
+    
+    #[error("Invalid username or password")]
+    InvalidCredentials,
+    
+    #[error("Too many failed logins; try again in {0} seconds")]
+    TooManyAttempts(u64),
+    
+    #[error("Password hashing failed: {0}")]
+    Hash(String),
+    
+    #[error("Account storage failed: {0}")]
+    Storage(String),
+}
+
+impl From<AuthError> for ApiError {
+    fn from(e: AuthError) -> Self {
+        let (status, code) = match &e {
+            AuthError::InvalidUsername | AuthError::WeakPassword => (StatusCode::BAD_REQUEST, ErrorCode::InvalidCredentialsFormat),
+            AuthError::UsernameTaken => (StatusCode::CONFLICT, ErrorCode::UsernameTaken),
+            AuthError::InvalidCredentials => (StatusCode::UNAUTHORIZED, ErrorCode::InvalidCredentials),
+            AuthError::TooManyAttempts(secs) => {
+                let retry_after = HeaderValue::from(*secs);
+                return ApiError::new(StatusCode::TOO_MANY_REQUESTS, ErrorCode::RateLimited, e.to_string())
+                    .with_header(header::RETRY_AFTER, retry_after);
+            }
+            AuthError::Hash(cause) => {
+                tracing::error!("Password hashing error: {}", cause);
+                (StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::AuthError)
+            }
+            AuthError::Storage(cause) => {
+                tracing::error!("Account storage error: {}", cause);
+                (StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::AuthError)
+            }
+        };
+        ApiError::new(status, code, e.to_string())
+    }
+}
+
+/// A user account
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct User {
+    pub id: UserId,
+    pub username: String,
+    pub created_at: DateTime<Utc>,
+    
+    /// Argon2 PHC string
+    password_hash: String,
+}
+
+/// An API key as shown to its owner (the key itself is never stored)
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct ApiKeyInfo {
+    pub id: String,
+    
+    /// First characters of the key, to recognize it by
+    pub prefix: String,
+    
+    pub label: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+#[derive(Default)]
+struct Accounts {
+    users: HashMap<UserId, User>,
+    by_username: HashMap<String, UserId>,
+    
+    /// SHA-256 of the key (hex) -> owner and details
+    api_keys: HashMap<String, (UserId, ApiKeyInfo)>,
+}
+
+/// Saved form of `Accounts`
+#[derive(Serialize, Deserialize)]
+struct AccountsFile {
+    users: Vec<User>,
+    api_keys: Vec<StoredApiKey>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct StoredApiKey {
+    /// SHA-256 of the key (hex)
+    hash: String,
+    user_id: UserId,
+    #[serde(flatten)]
+    info: ApiKeyInfo,
+}
+
+/// User accounts and API keys, in memory or saved to a JSON file
+#[derive(Default)]
+pub struct MemoryUserStore {
+    path: Option<PathBuf>,
+    accounts: RwLock<Accounts>,
+    
+    /// Lowercase username -> consecutive failed logins and the last one
+    failed_logins: Mutex<HashMap<String, (u32, DateTime<Utc>)>>,
+}
+
+impl MemoryUserStore {
+    /// Accounts kept in memory only
+    #[cfg(test)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Load the accounts saved at `path` (none if the file doesn't exist yet)
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, AuthError> {
+        let path = path.into();
+        let file: AccountsFile = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|e| AuthError::Storage(format!("{}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => AccountsFile { users: Vec::new(), api_keys: Vec::new() },
+            Err(e) => return Err(AuthError::Storage(format!("{}: {}", path.display(), e))),
+        };
+        
+        let accounts = Accounts {
+            by_username: file.users.iter().map(|u| (u.username.to_ascii_lowercase(), u.id.clone())).collect(),
+            users: file.users.into_iter().map(|u| (u.id.clone(), u)).collect(),
+            api_keys: file.api_keys.into_iter().map(|k| (k.hash, (k.user_id, k.info))).collect(),
+        };
+        Ok(Self {
+            path: Some(path),
+            accounts: RwLock::new(accounts),
+            failed_logins: Mutex::default(),
+        })
+    }
+    
+    /// Number of accounts
+    pub fn len(&self) -> usize {
+        self.accounts.read().unwrap().users.len()
+    }
+    
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// Write the accounts to disk, including API key use recorded since the last change
+    pub fn flush(&self) -> Result<(), AuthError> {
+        self.save(&self.accounts.read().unwrap())
+    }
+    
+    /// Write all accounts to a temporary file, then move it over the old one
+    ///
+    /// Callers hold the accounts lock, so saves land in order; changes come
+    /// from blocking threads (see the handlers), not the async runtime.
+    fn save(&self, accounts: &Accounts) -> Result<(), AuthError> {
+        let Some(path) = &self.path else {
+            return Ok(());
+        };
+        let write = || -> std::io::Result<()> {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut users: Vec<User> = accounts.users.values().cloned().collect();
+            users.sort_by_key(|u| u.created_at);
+            let mut api_keys: Vec<StoredApiKey> = accounts
+                .api_keys
+                .iter()
+                .map(|(hash, (user_id, info))| StoredApiKey { hash: hash.clone(), user_id: user_id.clone(), info: info.clone() })
+                .collect();
+            api_keys.sort_by_key(|k| k.info.created_at);
+            
+            let file = AccountsFile { users, api_keys };
+            let tmp = path.with_extension("tmp");
+            std::fs::write(&tmp, serde_json::to_vec_pretty(&file)?)?;
+            std::fs::rename(&tmp, path)
+        };
+        write().map_err(|e| AuthError::Storage(format!("{}: {}", path.display(), e)))
+    }
+    
+    /// Create an account (argon2 hashing - call off the async runtime)
+    pub fn register(&self, username: &str, password: &str) -> Result<User, AuthError> {
+        let username = username.trim();
+        let valid_name = (3..=32).contains(&username.chars().count())
+            && username.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '-'));
+        if !valid_name {
+            return Err(AuthError::InvalidUsername);
+        }
+        if password.chars().count() < MIN_PASSWORD_CHARS {
+            return Err(AuthError::WeakPassword);
+        }
+        
+        let salt = SaltString::encode_b64(uuid::Uuid::new_v4().as_bytes()).map_err(|e| AuthError::Hash(e.to_string()))?;
+        let password_hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)
+            .map_err(|e| AuthError::Hash(e.to_string()))?
+            .to_string();
+        
+        let mut accounts = self.accounts.write().unwrap();
+        let key = username.to_ascii_lowercase();
+        if accounts.by_username.contains_key(&key) {
+            return Err(AuthError::UsernameTaken);
+        }
+        
+        let user = User {
+            id: UserId::generate(),
+            username: username.to_string(),
+            created_at: Utc::now(),
+            password_hash,
+        };
+        accounts.by_username.insert(key.clone(), user.id.clone());
+        accounts.users.insert(user.id.clone(), user.clone());
+        if let Err(e) = self.save(&accounts) {
+            accounts.by_username.remove(&key);
+            accounts.users.remove(&user.id);
+            return Err(e);
+        }
+        Ok(user)
+    }
+    
+    /// Check a username and password (argon2 - call off the async runtime)
+    ///
+    /// Fails with `TooManyAttempts`, without checking the password, while the
+    /// username is backing off after failed logins.
+    pub fn authenticate(&self, username: &str, password: &str) -> Result<User, AuthError> {
+        let key = username.trim().to_ascii_lowercase();
+        let now = Utc::now();
+        if let Some(wait) = self.login_wait(&key, now) {
+            return Err(AuthError::TooManyAttempts(wait));
+        }
+        
+        let user = {
+            let accounts = self.accounts.read().unwrap();
+            accounts.by_username.get(&key).and_then(|id| accounts.users.get(id)).cloned()
+        };
+        
+        // Unknown usernames are checked against a dummy hash, taking as long as a wrong password
+        let stored = user.as_ref().map_or(DUMMY_PASSWORD_HASH.as_str(), |user| user.password_hash.as_str());
+        let hash = PasswordHash::new(stored).map_err(|e| AuthError::Hash(e.to_string()))?;
+        let verified = Argon2::default().verify_password(password.as_bytes(), &hash).is_ok();
+        
+        let mut failed_logins = self.failed_logins.lock().unwrap();
+        match user.filter(|_| verified) {
+            Some(user) => {
+                failed_logins.remove(&key);
+                Ok(user)
+            }
+            None => {
+                let (failures, last) = failed_logins.entry(key).or_insert((0, now));
+                *failures += 1;
+                *last = now;
+                Err(AuthError::InvalidCredentials)
+            }
+        }
+    }
+    
+    /// Seconds a username must still wait before its next login attempt
+    fn login_wait(&self, key: &str, now: DateTime<Utc>) -> Option<u64> {
+        let mut failed_logins = self.failed_logins.lock().unwrap();
+        failed_logins.retain(|_, (_, last)| now - *last < Duration::seconds(MAX_LOGIN_BACKOFF_SECS));
+        
+        let (failures, last) = failed_logins.get(key)?;
+        let excess = failures.checked_sub(FREE_LOGIN_FAILURES)?;
+        let backoff = (1_i64 << excess.min(16)).min(MAX_LOGIN_BACKOFF_SECS);
+        let wait = (*last + Duration::seconds(backoff) - now).num_milliseconds();
+        u64::try_from(wait).ok().filter(|&wait| wait > 0).map(|wait| wait.div_ceil(1000))
+    }
+    
+    pub fn get(&self, id: &UserId) -> Option<User> {
+        self.accounts.read().unwrap().users.get(id).cloned()
+    }
+    
+    /// Create an API key; the returned key is not retrievable later
+    pub fn create_api_key(&self, user_id: &UserId, label: Option<String>) -> Result<(String, ApiKeyInfo), AuthError> {
+        let key = format!("{}{}{}", API_KEY_PREFIX, uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+        let info = ApiKeyInfo {
+            id: uuid::Uuid::new_v4().to_string(),
+            prefix: key[..API_KEY_PREFIX.len() + 8].to_string(),
+            label,
+            created_at: Utc::now(),
+            last_used: None,
+        };
+        
+        let mut accounts = self.accounts.write().unwrap();
+        let hash = hash_api_key(&key);
+        accounts.api_keys.insert(hash.clone(), (user_id.clone(), info.clone()));
+        if let Err(e) = self.save(&accounts) {
+            accounts.api_keys.remove(&hash);
+            return Err(e);
+        }
+        Ok((key, info))
+    }
+    
+    /// Delete one of a user's API keys; false if they have no key with that id
+    pub fn revoke_api_key(&self, user_id: &UserId, key_id: &str) -> Result<bool, AuthError> {
+        let mut accounts = self.accounts.write().unwrap();
+        let Some(hash) = accounts
+            .api_keys
+            .iter()
+            .find(|(_, (owner, info))| owner == user_id && info.id == key_id)
+            .map(|(hash, _)| hash.clone())
+        else {
+            return Ok(false);
+        };
+        
+        let revoked = accounts.api_keys.remove(&hash);
+        if let Err(e) = self.save(&accounts) {
+            accounts.api_keys.extend(revoked.map(|key| (hash, key)));
+            return Err(e);
+        }
+        Ok(true)
+    }
+    
+    pub fn api_keys(&self, user_id: &UserId) -> Vec<ApiKeyInfo> {
+        let accounts = self.accounts.read().unwrap();
+        let mut keys: Vec<ApiKeyInfo> = accounts
+            .api_keys
+            .values()
+            .filter(|(owner, _)| owner == user_id)
+            .map(|(_, info)| info.clone())
+            .collect();
+        keys.sort_by_key(|k| k.created_at);
+        keys
+    }
+    
+    /// Owner of an API key (recording its use to the minute, saved with the next change or `flush`)
+    pub fn user_for_api_key(&self, key: &str) -> Option<UserId> {
+        let hash = hash_api_key(key);
+        let now = Utc::now();
+        let (owner, stale) = {
+            let accounts = self.accounts.read().unwrap();
+            let (owner, info) = accounts.api_keys.get(&hash)?;
+            let stale = info
+                .last_used
+                .is_none_or(|used| now - used >= Duration::seconds(API_KEY_USE_RESOLUTION_SECS));
+            (owner.clone(), stale)
+        };
+        
+        // Most requests only read; the write lock is taken about once a minute per key
+        if stale {
+            let mut accounts = self.accounts.write().unwrap();
+            if let Some((_, info)) = accounts.api_keys.get_mut(&hash) {
+                info.last_used = Some(now);
+            }
+        }
+        Some(owner)
+    }
+}
+
+fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Issues and checks signed session tokens
+pub struct SessionTokens {
+    secret: Vec<u8>,
+    ttl: Duration,
+    secure_cookie: bool,
+}
+
+impl SessionTokens {
+    pub fn new(secret: impl Into<Vec<u8>>, ttl: Duration) -> Self {
+        Self {
+            secret: secret.into(),
+            ttl,
+            secure_cookie: false,
+        }
+    }
+    
+    /// A random secret: tokens stop working when the server restarts
+    pub fn ephemeral(ttl: Duration) -> Self {
+        let secret = [uuid::Uuid::new_v4().into_bytes(), uuid::Uuid::new_v4().into_bytes()].concat();
+        Self::new(secret, ttl)
+    }
+    
+    /// Mark the session cookie `Secure` (HTTPS only)
+    pub fn secure_cookie(mut self, secure: bool) -> Self {
+        self.secure_cookie = secure;
+        self
+    }
+    
+    /// A token for a user and when it expires
+    pub fn issue(&self, user_id: &UserId) -> (String, DateTime<Utc>) {
+        let expires_at = Utc::now() + self.ttl;
+        let payload = format!("{}.{}", user_id, expires_at.timestamp());
+        let signature = hex::encode(self.mac(&payload).finalize().into_bytes());
+        (format!("{}.{}", payload, signature), expires_at)
+    }
+    
+    /// The user of a valid, unexpired token
+    pub fn verify(&self, token: &str) -> Option<UserId> {
+        let (payload, signature) = token.rsplit_once('.')?;
+        self.mac(payload).verify_slice(&hex::decode(signature).ok()?).ok()?;
+        
+        let (user_id, expires) = payload.rsplit_once('.')?;
+        let expires = DateTime::from_timestamp(expires.parse().ok()?, 0)?;
+        (expires > Utc::now()).then(|| UserId(user_id.to_string()))
+    }
+    
+    /// `Set-Cookie` value carrying a token
+    pub fn cookie(&self, token: &str) -> String {
+        self.set_cookie(token, self.ttl.num_seconds())
+    }
+    
+    /// `Set-Cookie` value removing the session cookie
+    pub fn clear_cookie(&self) -> String {
+        self.set_cookie("", 0)
+    }
+    
+    fn set_cookie(&self, value: &str, max_age: i64) -> String {
+        let secure = if self.secure_cookie { "; Secure" } else { "" };
+        format!("{}={}; Path=/; HttpOnly; SameSite=Lax; Max-Age={}{}", SESSION_COOKIE, value, max_age, secure)
+    }
+    
+    fn mac(&self, payload: &str) -> Hmac<Sha256> {
+        let mut mac = Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts any key length");
+        mac.update(payload.as_bytes());
+        mac
+    }
+}
+
+/// The user behind a request's API key, bearer token or session cookie
+pub fn authenticate(state: &AppState, headers: &HeaderMap) -> Option<UserId> {
+    if let Some(key) = headers.get("x-api-key").and_then(|v| v.to_str().ok()) {
+        return state.users.user_for_api_key(key.trim());
+    }
+    
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim)
+        .or_else(|| session_cookie(headers))?;
+    if token.starts_with(API_KEY_PREFIX) {
+        return state.users.user_for_api_key(token);
+    }
+    
+    // Tokens outlive deleted accounts, so check the account still exists
+    state.tokens.verify(token).filter(|id| state.users.get(id).is_some())
+}
+
+fn session_cookie(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|v| v.to_str().ok())
+        .flat_map(|v| v.split(';'))
+        .filter_map(|pair| pair.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value)
+}
+
+/// Middleware rejecting unauthenticated requests and attaching the `UserId`
+pub async fn require_user(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    match authenticate(&state, request.headers()) {
+        Some(user_id) => {
+            request.extensions_mut().insert(user_id);
+            next.run(request).await
+        }
+        None => ApiError::new(StatusCode::UNAUTHORIZED, ErrorCode::Unauthorized, "Authentication required")
+            .with_header(header::WWW_AUTHENTICATE, HeaderValue::from_static("Bearer"))
+            .into_response(),
+    }
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+#[derive(Deserialize, JsonSchema)]
+pub struct CredentialsRequest {
+    pub username: String,
+    pub password: String,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct LoginResponse {
+    pub user_id: UserId,
+    pub username: String,
+    /// Session token (also set as the `agent_session` cookie)
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct AccountResponse {
+    pub user_id: UserId,
+    pub username: String,
+    pub created_at: DateTime<Utc>,
+    pub api_keys: Vec<ApiKeyInfo>,
+    /// Active license linked to the account
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub license_key: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<PlanName>")]
+    pub plan: Option<Plan>,
+    /// Organization the account is a member of
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub org_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(with = "Option<RoleName>")]
+    pub org_role: Option<Role>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct ApiKeyRequest {
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ApiKeyResponse {
+    /// The key itself - shown only once
+    pub api_key: String,
+    #[serde(flatten)]
+    pub info: ApiKeyInfo,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RevokeApiKeyRequest {
+    pub id: String,
+}
+
+/// Create an account and sign in
+pub async fn register(
+    State(state): State<AppState>,
+    ApiJson(payload): ApiJson<CredentialsRequest>,
+) -> Result<Response, ApiError> {
+    let users = state.users.clone();
+    let user = tokio::task::spawn_blocking(move || users.register(&payload.username, &payload.password))
+        .await
+        .map_err(|e| AuthError::Hash(e.to_string()))??;
+    
+    tracing::info!(user_id = %user.id, username = %user.username, "Account created");
+    Ok(signed_in(&state, user, StatusCode::CREATED))
+}
+
+/// Sign in with a username and password
+pub async fn login(
+    State(state): State<AppState>,
+    ApiJson(payload): ApiJson<CredentialsRequest>,
+) -> Result<Response, ApiError> {
+    let users = state.users.clone();
+    let user = tokio::task::spawn_blocking(move || users.authenticate(&payload.username, &payload.password))
+        .await
+        .map_err(|e| AuthError::Hash(e.to_string()))??;
+    
+    Ok(signed_in(&state, user, StatusCode::OK))
+}
+
+fn signed_in(state: &AppState, user: User, status: StatusCode) -> Response {
+    let (token, expires_at) = state.tokens.issue(&user.id);
+    let cookie = state.tokens.cookie(&token);
+    let body = LoginResponse {
+        user_id: user.id,
+        username: user.username,
+        token,
+        expires_at,
+    };
+    (status, [(header::SET_COOKIE, cookie)], Json(body)).into_response()
+}
+
+/// Clear the session cookie
+pub async fn logout(State(state): State<AppState>) -> Response {
+    (StatusCode::NO_CONTENT, [(header::SET_COOKIE, state.tokens.clear_cookie())]).into_response()
+}
+
+/// The signed-in account, its API keys, license and organization
+pub async fn account(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+) -> Result<Json<AccountResponse>, ApiError> {
+    let user = state.users.get(&user_id).ok_or(AuthError::InvalidCredentials)?;
+    let license = state.license_store.get_by_user(user_id.as_str()).ok().flatten();
+    let org = state.orgs.for_user(user_id.as_str()).ok().flatten();
+    
+    Ok(Json(AccountResponse {
+        api_keys: state.users.api_keys(&user_id),
+        license_key: license.as_ref().map(|l| l.key.to_string()),
+        plan: license.map(|l| l.plan),
+        org_role: org.as_ref().and_then(|o| o.role_of(user_id.as_str())),
+        org_id: org.map(|o| o.id),
+        user_id,
+        username: user.username,
+        created_at: user.created_at,
+    }))
+}
+
+/// Create an API key for the signed-in account
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiJson(payload): ApiJson<ApiKeyRequest>,
+) -> Result<(StatusCode, Json<ApiKeyResponse>), ApiError> {
+    let (users, owner) = (state.users.clone(), user_id.clone());
+    let (api_key, info) = tokio::task::spawn_blocking(move || users.create_api_key(&owner, payload.label))
+        .await
+        .map_err(|e| AuthError::Storage(e.to_string()))??;
+    tracing::info!(user_id = %user_id, key = %info.prefix, "API key created");
+    Ok((StatusCode::CREATED, Json(ApiKeyResponse { api_key, info })))
+}
+
+/// Revoke one of the signed-in account's API keys
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiJson(payload): ApiJson<RevokeApiKeyRequest>,
+) -> Result<StatusCode, ApiError> {
+    let (users, owner, key_id) = (state.users.clone(), user_id.clone(), payload.id.clone());
+    let revoked = tokio::task::spawn_blocking(move || users.revoke_api_key(&owner, &key_id))
+        .await
+        .map_err(|e| AuthError::Storage(e.to_string()))??;
+    if revoked {
+        tracing::info!(user_id = %user_id, key_id = %payload.id, "API key revoked");
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(ApiError::not_found(ErrorCode::KeyNotFound, "API key not found"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_accounts_survive_reopen() {
+        let path = std::env::temp_dir().join(format!("users-{}.json", uuid::Uuid::new_v4()));
+        let users = MemoryUserStore::open(&path).unwrap();
+        let alice = users.register("alice", "correct horse").unwrap();
+        let (key, info) = users.create_api_key(&alice.id, Some("ci".into())).unwrap();
+        let (_, revoked) = users.create_api_key(&alice.id, None).unwrap();
+        assert!(users.revoke_api_key(&alice.id, &revoked.id).unwrap());
+        
+        let reopened = MemoryUserStore::open(&path).unwrap();
+        assert_eq!(reopened.authenticate("Alice", "correct horse").unwrap().id, alice.id);
+        assert_eq!(reopened.user_for_api_key(&key), Some(alice.id.clone()));
+        assert_eq!(reopened.api_keys(&alice.id).iter().map(|k| &k.id).collect::<Vec<_>>(), [&info.id]);
+        assert!(matches!(reopened.register("ALICE", "another password"), Err(AuthError::UsernameTaken)));
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn test_failed_logins_back_off() {
+        let users = MemoryUserStore::new();
+        users.register("alice", "correct horse").unwrap();
+        for _ in 0..FREE_LOGIN_FAILURES {
+            assert!(matches!(users.authenticate("alice", "wrong"), Err(AuthError::InvalidCredentials)));
+        }
+        
+        // Even the right password waits, and so does an unknown username after its own failures
+        assert!(matches!(users.authenticate("Alice", "correct horse"), Err(AuthError::TooManyAttempts(1))));
+        for _ in 0..FREE_LOGIN_FAILURES {
+            assert!(matches!(users.authenticate("mallory", "guess"), Err(AuthError::InvalidCredentials)));
+        }
+        assert!(matches!(users.authenticate("mallory", "guess"), Err(AuthError::TooManyAttempts(_))));
+        
+        // The backoff doubles with each failure, and a success forgets them
+        let earlier = Utc::now() - Duration::seconds(2);
+        users.failed_logins.lock().unwrap().insert("alice".into(), (FREE_LOGIN_FAILURES + 2, earlier));
+        assert!(matches!(users.authenticate("alice", "correct horse"), Err(AuthError::TooManyAttempts(_))));
+        users.failed_logins.lock().unwrap().insert("alice".into(), (FREE_LOGIN_FAILURES, earlier));
+        assert!(users.authenticate("alice", "correct horse").is_ok());
+        assert!(users.failed_logins.lock().unwrap().get("alice").is_none());
+    }
+}
@@ -0,0 +1,57 @@
+//! Command-Line Subcommands
+//!
+//! `agent-server` serves HTTP by default. Maintenance tasks run as
+//! subcommands and exit:
+//!
+//! ```text
+//! agent-server index-knowledge [--dir knowledge] [--out data/knowledge.json] [--no-embed]
+//! ```
+
+use agent_core::EmbeddingProvider;
+use agent_runtime::{OllamaEmbeddings, OllamaProvider};
+use crypto_advisor::KnowledgeBase;
+
+use crate::config::Config;
+
+/// Rebuild the knowledge base index from a directory of documents
+///
+/// Paths and the embedding model default to `config`'s `tools` and `provider`
+pub async fn index_knowledge(args: &[String], config: &Config) -> anyhow::Result<()> {
+    let mut dir = config.tools.knowledge_dir.display().to_string();
+    let mut out = config.tools.knowledge_index_path.display().to_string();
+    let mut embed = true;
+    
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--dir" => dir = args.next().cloned().ok_or_else(|| anyhow::anyhow!("--dir requires a path"))?,
+            "--out" => out = args.next().cloned().ok_or_else(|| anyhow::anyhow!("--out requires a path"))?,
+            "--no-embed" => embed = false,
+            other => anyhow::bail!("Unknown argument: {}", other),
+        }
+    }
+    
+    let mut knowledge = KnowledgeBase::from_dir(&dir)?;
+    tracing::info!(
+        "Ingested {} chunks from {} documents in {}",
+        knowledge.len(),
+        knowledge.source_count(),
+        dir
+    );
+    
+    if embed {
+        let ollama = OllamaProvider::from_config(config.provider.ollama());
+        let embedder = OllamaEmbeddings::new(&ollama, config.provider.embedding_model.clone());
+        match knowledge.embed(&embedder).await {
+            Ok(()) => tracing::info!("✓ Embedded chunks with {}", embedder.model()),
+            Err(e) => {
+                tracing::warn!("⚠ Embedding failed ({}) - index will use BM25 only", e);
+                knowledge = KnowledgeBase::from_dir(&dir)?;
+            }
+        }
+    }
+    
+    knowledge.save(&out)?;
+    tracing::info!("✓ Wrote knowledge index to {}", out);
+    Ok(())
+}
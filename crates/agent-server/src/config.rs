@@ -0,0 +1,1073 @@
+//! Server Configuration
+//!
+//! One typed `Config`, built in layers (later layers win):
+//!
+//! 1. defaults
+//! 2. a TOML file: `--config <path>`, else `AGENT_CONFIG`, else
+//!    `agent-server.toml` when it exists
+//! 3. environment variables (the names used before the file existed, such as
+//!    `BIND_ADDR`, `OLLAMA_HOST` or `STRIPE_SECRET_KEY`)
+//! 4. command-line flags (`--bind`, `--model`, `--cors-origin`, ...)
+//!
+//! `validate` reports every problem at once, each prefixed with the key it
+//! is about. On SIGHUP the configuration is loaded again and its `Settings`
+//! (models, agent budgets, plan limits, prices, CORS origins) replace the
+//! running ones; other changes are logged and need a restart.
+
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, RwLock};
+
+use axum::http::HeaderValue;
+use serde::Deserialize;
+
+use agent_core::safety::SafetyPolicy;
+use agent_notify::{SmtpConfig, SmtpTls};
+use agent_payments::{CostTable, PlanTable};
+use agent_runtime::ollama::OllamaConfig;
+
+/// Config file used when none is named and it exists
+pub const DEFAULT_CONFIG_FILE: &str = "agent-server.toml";
+
+/// Model used when neither the configuration nor a cascade names one
+const FALLBACK_MODEL: &str = "llama3.2";
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConfigError {
+    #[error("Cannot read config file {path}: {source}")]
+    Read { path: PathBuf, source: std::io::Error },
+    
+    #[error("Invalid config file {path}: {message}")]
+    Parse { path: PathBuf, message: String },
+    
+    #[error("{0}")]
+    Flag(String),
+    
+    #[error("Invalid configuration:\n  - {}", .0.join("\n  - "))]
+    Invalid(Vec<String>),
+}
+
+// ============================================================================
+// Config
+// ============================================================================
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub provider: ProviderConfig,
+    pub agent: AgentConfig,
+    pub tools: ToolsConfig,
+    pub plans: PlansConfig,
+    pub storage: StorageConfig,
+    pub safety: SafetyConfig,
+    pub auth: AuthConfig,
+    pub stripe: StripeConfig,
+    pub telemetry: TelemetryConfig,
+    pub scheduler: SchedulerConfig,
+    pub notifications: NotificationsConfig,
+    pub alerts: AlertsConfig,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ServerConfig {
+    pub bind_addr: String,
+    
+    /// Directory of the web frontend
+    pub static_dir: PathBuf,
+    
+    /// Origins allowed to call the API from a browser (`*` = any; empty = same origin only)
+    pub cors_origins: Vec<String>,
+    
+    /// Serve HTTPS instead of HTTP
+    pub tls: Option<TlsConfig>,
+    
+    /// On SIGTERM/Ctrl-C, seconds `/ready` reports draining before the listener closes
+    pub drain_delay_secs: u64,
+    
+    /// Seconds running requests get to finish once the listener has closed
+    pub shutdown_grace_secs: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            bind_addr: "0.0.0.0:3000".into(),
+            static_dir: "static".into(),
+            cors_origins: Vec::new(),
+            tls: None,
+            drain_delay_secs: 0,
+            shutdown_grace_secs: 30,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct TlsConfig {
+    /// PEM certificate chain
+    pub cert_path: PathBuf,
+    
+    /// PEM private key
+    pub key_path: PathBuf,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ProviderConfig {
+    pub ollama_host: String,
+    pub ollama_port: u16,
+    pub timeout_secs: u64,
+    
+    /// Model for requests that don't name one (default: the cascade, else `llama3.2`)
+    pub default_model: Option<String>,
+    
+    /// Cheap-first model cascade, used for model `auto`
+    pub cascade_models: Vec<String>,
+    
+    pub embedding_model: String,
+    
+    /// Titles and tags new conversations (disabled if unset)
+    pub tagging_model: Option<String>,
+}
+
+impl ProviderConfig {
+    /// Connection settings for the Ollama provider
+    pub fn ollama(&self) -> OllamaConfig {
+        OllamaConfig { host: self.ollama_host.clone(), port: self.ollama_port, timeout_secs: self.timeout_secs }
+    }
+}
+
+impl Default for ProviderConfig {
+    fn default() -> Self {
+        Self {
+            ollama_host: "http://localhost".into(),
+            ollama_port: 11434,
+            timeout_secs: 120,
+            default_model: None,
+            cascade_models: Vec::new(),
+            embedding_model: "nomic-embed-text".into(),
+            tagging_model: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AgentConfig {
+    /// Tokens one multi-agent request may use across all its agents
+    pub team_max_tokens: u32,
+    
+    /// Sub-agent runs one multi-agent request may start
+    pub team_max_delegations: usize,
+    
+    /// Revisions the advisor guardrails may request before flagging an answer
+    pub guardrail_max_revisions: usize,
+    
+    /// Model for the LLM critic guardrail (rule-based checks only if unset)
+    pub guardrail_critic_model: Option<String>,
+}
+
+impl Default for AgentConfig {
+    fn default() -> Self {
+        Self {
+            team_max_tokens: 50_000,
+            team_max_delegations: 8,
+            guardrail_max_revisions: 1,
+            guardrail_critic_model: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ToolsConfig {
+    /// Tools not to register
+    pub disabled: Vec<String>,
+    
+    pub knowledge_dir: PathBuf,
+    pub knowledge_index_path: PathBuf,
+}
+
+impl Default for ToolsConfig {
+    fn default() -> Self {
+        Self {
+            disabled: Vec::new(),
+            knowledge_dir: "knowledge".into(),
+            knowledge_index_path: "data/knowledge.json".into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct PlansConfig {
+    /// Per-plan limits (`[plans.limits.free]`, ...); built-in if unset
+    pub limits: Option<PlanLimitsTable>,
+    
+    /// The same limits as a JSON file
+    pub limits_path: Option<PathBuf>,
+    
+    /// Per-model token prices (JSON); built-in if unset
+    pub model_costs_path: Option<PathBuf>,
+}
+
+/// `PlanTable`, compared by value so reloads can tell what changed
+#[derive(Clone, Debug, Deserialize)]
+#[serde(transparent)]
+pub struct PlanLimitsTable(pub PlanTable);
+
+impl PartialEq for PlanLimitsTable {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.free == other.0.free && self.0.pro == other.0.pro && self.0.team == other.0.team
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct StorageConfig {
+    /// Semantic memory index
+    pub memory_path: PathBuf,
+    
+    /// SQLite database of portfolios (in memory if unset)
+    pub portfolio_db_path: Option<PathBuf>,
+    
+    /// Versioned prompt templates
+    pub prompts_dir: PathBuf,
+    
+    /// How often the prompt directory is checked for changes (0 = never)
+    pub prompt_reload_secs: u64,
+    
+    /// Scheduled DCA jobs (JSON)
+    pub dca_jobs_path: PathBuf,
+    
+    /// Price and portfolio alerts (JSON)
+    pub alerts_path: PathBuf,
+    
+    /// Organizations, their members and invites (JSON)
+    pub orgs_path: PathBuf,
+    
+    /// User accounts and API key hashes (JSON)
+    pub users_path: PathBuf,
+    
+    /// In-app notification inboxes (JSON)
+    pub inbox_path: PathBuf,
+    
+    /// Notification channels, addresses and opt-outs (JSON)
+    pub notification_preferences_path: PathBuf,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            memory_path: "data/memory.json".into(),
+            portfolio_db_path: None,
+            prompts_dir: "prompts".into(),
+            prompt_reload_secs: 5,
+            dca_jobs_path: "data/dca_jobs.json".into(),
+            alerts_path: "data/alerts.json".into(),
+            orgs_path: "data/orgs.json".into(),
+            users_path: "data/users.json".into(),
+            inbox_path: "data/inbox.json".into(),
+            notification_preferences_path: "data/notification_preferences.json".into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SafetyConfig {
+    /// `kind=action` pairs (see `SafetyPolicy::parse`)
+    pub policy: String,
+    
+    /// JSON-lines audit log of screened content
+    pub audit_log: Option<PathBuf>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthConfig {
+    /// Session token signing key (random per start if unset)
+    pub secret: Option<String>,
+    pub session_hours: i64,
+    
+    /// Mark the session cookie `Secure` (behind HTTPS)
+    pub secure_cookie: bool,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            secret: None,
+            session_hours: 168,
+            secure_cookie: false,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct StripeConfig {
+    pub secret_key: Option<String>,
+    pub webhook_secret: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct TelemetryConfig {
+    /// Serve Prometheus metrics at `/metrics`
+    pub metrics: bool,
+    
+    /// Bearer token scrapers must send (open if unset)
+    pub metrics_token: Option<String>,
+    
+    /// OTLP/gRPC collector for trace export (e.g. `http://localhost:4317`; disabled if unset)
+    pub otlp_endpoint: Option<String>,
+    
+    /// `service.name` of exported spans
+    pub service_name: String,
+}
+
+impl Default for TelemetryConfig {
+    fn default() -> Self {
+        Self {
+            metrics: true,
+            metrics_token: None,
+            otlp_endpoint: None,
+            service_name: "agent-server".into(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SchedulerConfig {
+    /// How often due DCA purchases are looked for (0 = never)
+    pub tick_secs: u64,
+    
+    /// Purchases overdue by more than this are missed, and handled by the job's catch-up policy
+    pub missed_after_secs: u64,
+}
+
+impl Default for SchedulerConfig {
+    fn default() -> Self {
+        Self {
+            tick_secs: 60,
+            missed_after_secs: 3600,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct AlertsConfig {
+    /// How often alerts are evaluated against current prices (0 = never)
+    pub tick_secs: u64,
+}
+
+impl Default for AlertsConfig {
+    fn default() -> Self {
+        Self { tick_secs: 60 }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct NotificationsConfig {
+    /// SMTP relay for email notifications (email disabled if unset)
+    pub smtp: Option<SmtpSettings>,
+    
+    /// `<kind>.txt` templates replacing the built-in ones
+    pub templates_dir: Option<PathBuf>,
+    
+    /// Deliveries tried per webhook notification, including the first
+    pub webhook_attempts: u32,
+    
+    /// Accept `http://` webhook URLs (development only)
+    pub allow_http_webhooks: bool,
+    
+    /// Remind license holders this many days before expiry (0 = never)
+    pub license_notice_days: u32,
+    
+    /// How often expiring licenses are looked for
+    pub license_check_secs: u64,
+}
+
+impl Default for NotificationsConfig {
+    fn default() -> Self {
+        Self {
+            smtp: None,
+            templates_dir: None,
+            webhook_attempts: agent_notify::DEFAULT_ATTEMPTS,
+            allow_http_webhooks: false,
+            license_notice_days: 7,
+            license_check_secs: 3600,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct SmtpSettings {
+    pub host: String,
+    pub port: u16,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    
+    /// Sender, e.g. `Advisor <alerts@example.com>`
+    pub from: String,
+    
+    /// `starttls`, `tls` or `none`
+    pub tls: SmtpTls,
+}
+
+impl Default for SmtpSettings {
+    fn default() -> Self {
+        Self {
+            host: String::new(),
+            port: 587,
+            username: None,
+            password: None,
+            from: String::new(),
+            tls: SmtpTls::StartTls,
+        }
+    }
+}
+
+impl SmtpSettings {
+    pub fn to_config(&self) -> SmtpConfig {
+        let mut config = SmtpConfig::new(&self.host, self.port, &self.from);
+        config.username.clone_from(&self.username);
+        config.password.clone_from(&self.password);
+        config.tls = self.tls;
+        config
+    }
+}
+
+// ============================================================================
+// Loading
+// ============================================================================
+
+impl Config {
+    /// Load defaults, the config file, the environment and `args`, then validate
+    pub fn load(args: &[String]) -> Result<Self, ConfigError> {
+        Self::load_with(args, env)
+    }
+    
+    /// `load`, reading environment variables with `env`
+    fn load_with(args: &[String], env: impl Fn(&str) -> Option<String>) -> Result<Self, ConfigError> {
+        let flags = Flags::parse(args)?;
+        
+        let path = flags
+            .config
+            .clone()
+            .or_else(|| env("AGENT_CONFIG").map(PathBuf::from))
+            .or_else(|| Path::new(DEFAULT_CONFIG_FILE).exists().then(|| DEFAULT_CONFIG_FILE.into()));
+        let mut config = match &path {
+            Some(path) => Self::from_file(path)?,
+            None => Self::default(),
+        };
+        
+        config.apply_env(env)?;
+        flags.apply(&mut config);
+        config.validate()?;
+        Ok(config)
+    }
+    
+    pub fn from_file(path: &Path) -> Result<Self, ConfigError> {
+        let text = std::fs::read_to_string(path).map_err(|source| ConfigError::Read { path: path.into(), source })?;
+        toml::from_str(&text).map_err(|e| ConfigError::Parse { path: path.into(), message: e.to_string() })
+    }
+    
+    /// Models the configuration names (metrics count calls to any other model as `other`)
+    pub fn models(&self) -> Vec<String> {
+        let default = self.provider.default_model.clone().unwrap_or_else(|| FALLBACK_MODEL.into());
+        std::iter::once(default)
+            .chain(self.provider.cascade_models.iter().cloned())
+            .chain(self.provider.tagging_model.clone())
+            .chain(self.agent.guardrail_critic_model.clone())
+            .collect()
+    }
+    
+    /// Override with the environment variables that are set
+    fn apply_env(&mut self, env: impl Fn(&str) -> Option<String>) -> Result<(), ConfigError> {
+        let mut errors = Vec::new();
+        
+        set(&mut self.server.bind_addr, env("BIND_ADDR"));
+        set(&mut self.server.static_dir, env("STATIC_DIR").map(PathBuf::from));
+        set(&mut self.server.cors_origins, env("CORS_ORIGINS").map(|v| list(&v)));
+        match (env("TLS_CERT_PATH"), env("TLS_KEY_PATH")) {
+            (Some(cert_path), Some(key_path)) => {
+                self.server.tls = Some(TlsConfig { cert_path: cert_path.into(), key_path: key_path.into() });
+            }
+            (None, None) => {}
+            _ => errors.push("TLS_CERT_PATH and TLS_KEY_PATH must be set together".into()),
+        }
+        set(&mut self.server.drain_delay_secs, parsed(&env, "DRAIN_DELAY_SECS", &mut errors));
+        set(&mut self.server.shutdown_grace_secs, parsed(&env, "SHUTDOWN_GRACE_SECS", &mut errors));
+        
+        set(&mut self.provider.ollama_host, env("OLLAMA_HOST"));
+        set(&mut self.provider.ollama_port, parsed(&env, "OLLAMA_PORT", &mut errors));
+        set(&mut self.provider.timeout_secs, parsed(&env, "OLLAMA_TIMEOUT_SECS", &mut errors));
+        set_some(&mut self.provider.default_model, env("DEFAULT_MODEL"));
+        set(&mut self.provider.cascade_models, env("CASCADE_MODELS").map(|v| list(&v)));
+        set(&mut self.provider.embedding_model, env("EMBEDDING_MODEL"));
+        set_some(&mut self.provider.tagging_model, env("TAGGING_MODEL"));
+        
+        set(&mut self.agent.team_max_tokens, parsed(&env, "TEAM_MAX_TOKENS", &mut errors));
+        set(&mut self.agent.team_max_delegations, parsed(&env, "TEAM_MAX_DELEGATIONS", &mut errors));
+        set(&mut self.agent.guardrail_max_revisions, parsed(&env, "GUARDRAIL_MAX_REVISIONS", &mut errors));
+        set_some(&mut self.agent.guardrail_critic_model, env("GUARDRAIL_CRITIC_MODEL"));
+        
+        set(&mut self.tools.disabled, env("DISABLED_TOOLS").map(|v| list(&v)));
+        set(&mut self.tools.knowledge_dir, env("KNOWLEDGE_DIR").map(PathBuf::from));
+        set(&mut self.tools.knowledge_index_path, env("KNOWLEDGE_INDEX_PATH").map(PathBuf::from));
+        
+        set_some(&mut self.plans.limits_path, env("PLAN_LIMITS_PATH").map(PathBuf::from));
+        set_some(&mut self.plans.model_costs_path, env("MODEL_COSTS_PATH").map(PathBuf::from));
+        
+        set(&mut self.storage.memory_path, env("MEMORY_PATH").map(PathBuf::from));
+        set_some(&mut self.storage.portfolio_db_path, env("PORTFOLIO_DB_PATH").map(PathBuf::from));
+        set(&mut self.storage.prompts_dir, env("PROMPTS_DIR").map(PathBuf::from));
+        set(&mut self.storage.prompt_reload_secs, parsed(&env, "PROMPT_RELOAD_SECS", &mut errors));
+        set(&mut self.storage.dca_jobs_path, env("DCA_JOBS_PATH").map(PathBuf::from));
+        set(&mut self.storage.alerts_path, env("ALERTS_PATH").map(PathBuf::from));
+        set(&mut self.storage.orgs_path, env("ORGS_PATH").map(PathBuf::from));
+        set(&mut self.storage.users_path, env("USERS_PATH").map(PathBuf::from));
+        set(&mut self.storage.inbox_path, env("INBOX_PATH").map(PathBuf::from));
+        set(&mut self.storage.notification_preferences_path, env("NOTIFICATION_PREFERENCES_PATH").map(PathBuf::from));
+        
+        set(&mut self.safety.policy, env("SAFETY_POLICY"));
+        set_some(&mut self.safety.audit_log, env("SAFETY_AUDIT_LOG").map(PathBuf::from));
+        
+        set_some(&mut self.auth.secret, env("AUTH_SECRET"));
+        set(&mut self.auth.session_hours, parsed(&env, "AUTH_SESSION_HOURS", &mut errors));
+        set(&mut self.auth.secure_cookie, env("AUTH_SECURE_COOKIE").map(|v| v == "true" || v == "1"));
+        
+        set_some(&mut self.stripe.secret_key, env("STRIPE_SECRET_KEY"));
+        set_some(&mut self.stripe.webhook_secret, env("STRIPE_WEBHOOK_SECRET"));
+        
+        set(&mut self.telemetry.metrics, env("METRICS_ENABLED").map(|v| v == "true" || v == "1"));
+        set_some(&mut self.telemetry.metrics_token, env("METRICS_TOKEN"));
+        set_some(&mut self.telemetry.otlp_endpoint, env("OTEL_EXPORTER_OTLP_ENDPOINT"));
+        set(&mut self.telemetry.service_name, env("OTEL_SERVICE_NAME"));
+        
+        set(&mut self.scheduler.tick_secs, parsed(&env, "SCHEDULER_TICK_SECS", &mut errors));
+        set(&mut self.scheduler.missed_after_secs, parsed(&env, "SCHEDULER_MISSED_AFTER_SECS", &mut errors));
+        set(&mut self.alerts.tick_secs, parsed(&env, "ALERTS_TICK_SECS", &mut errors));
+        
+        if let Some(host) = env("SMTP_HOST") {
+            self.notifications.smtp.get_or_insert_with(SmtpSettings::default).host = host;
+        }
+        if let Some(smtp) = &mut self.notifications.smtp {
+            set(&mut smtp.port, parsed(&env, "SMTP_PORT", &mut errors));
+            set_some(&mut smtp.username, env("SMTP_USERNAME"));
+            set_some(&mut smtp.password, env("SMTP_PASSWORD"));
+            set(&mut smtp.from, env("SMTP_FROM"));
+            match env("SMTP_TLS").as_deref() {
+                Some("starttls") => smtp.tls = SmtpTls::StartTls,
+                Some("tls") => smtp.tls = SmtpTls::Tls,
+                Some("none") => smtp.tls = SmtpTls::None,
+                Some(other) => errors.push(format!("SMTP_TLS: '{}' is not starttls, tls or none", other)),
+                None => {}
+            }
+        }
+        set_some(&mut self.notifications.templates_dir, env("NOTIFICATION_TEMPLATES_DIR").map(PathBuf::from));
+        set(&mut self.notifications.webhook_attempts, parsed(&env, "WEBHOOK_ATTEMPTS", &mut errors));
+        set(&mut self.notifications.license_notice_days, parsed(&env, "LICENSE_NOTICE_DAYS", &mut errors));
+        
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
+    }
+    
+    /// Every problem with the configuration, or Ok
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut errors = Vec::new();
+        
+        if self.server.bind_addr.parse::<SocketAddr>().is_err() {
+            errors.push(format!("server.bind_addr: '{}' is not an address (host:port)", self.server.bind_addr));
+        }
+        for origin in &self.server.cors_origins {
+            let valid = origin == "*"
+                || ((origin.starts_with("http://") || origin.starts_with("https://"))
+                    && !origin.ends_with('/')
+                    && HeaderValue::from_str(origin).is_ok());
+            if !valid {
+                errors.push(format!("server.cors_origins: '{}' is not an origin (scheme://host[:port], or *)", origin));
+            }
+        }
+        if let Some(tls) = &self.server.tls {
+            require_file(&mut errors, "server.tls.cert_path", &tls.cert_path);
+            require_file(&mut errors, "server.tls.key_path", &tls.key_path);
+        }
+        
+        if !self.provider.ollama_host.starts_with("http://") && !self.provider.ollama_host.starts_with("https://") {
+            errors.push(format!("provider.ollama_host: '{}' must start with http:// or https://", self.provider.ollama_host));
+        }
+        if self.provider.ollama_port == 0 {
+            errors.push("provider.ollama_port: must not be 0".into());
+        }
+        if self.provider.timeout_secs == 0 {
+            errors.push("provider.timeout_secs: must be at least 1".into());
+        }
+        if self.provider.default_model.as_deref().is_some_and(|m| m.trim().is_empty()) {
+            errors.push("provider.default_model: must not be empty".into());
+        }
+        
+        if self.agent.team_max_tokens == 0 {
+            errors.push("agent.team_max_tokens: must be at least 1".into());
+        }
+        
+        if self.plans.limits.is_some() && self.plans.limits_path.is_some() {
+            errors.push("plans: set either limits or limits_path, not both".into());
+        }
+        if let Some(path) = &self.plans.limits_path {
+            require_file(&mut errors, "plans.limits_path", path);
+        }
+        if let Some(path) = &self.plans.model_costs_path {
+            require_file(&mut errors, "plans.model_costs_path", path);
+        }
+        if let Some(PlanLimitsTable(table)) = &self.plans.limits {
+            for (plan, limits) in [("free", &table.free), ("pro", &table.pro), ("team", &table.team)] {
+                if limits.max_tokens == 0 || limits.max_concurrent == 0 {
+                    errors.push(format!("plans.limits.{}: max_tokens and max_concurrent must be at least 1", plan));
+                }
+            }
+        }
+        
+        if cfg!(not(feature = "sqlite")) && self.storage.portfolio_db_path.is_some() {
+            errors.push("storage.portfolio_db_path: needs a server built with the `sqlite` feature".into());
+        }
+        
+        if let Err(e) = SafetyPolicy::parse(&self.safety.policy) {
+            errors.push(format!("safety.policy: {}", e));
+        }
+        
+        if self.auth.session_hours <= 0 {
+            errors.push("auth.session_hours: must be at least 1".into());
+        }
+        
+        if self.stripe.secret_key.is_some() != self.stripe.webhook_secret.is_some() {
+            errors.push("stripe: secret_key and webhook_secret must be set together".into());
+        }
+        
+        if let Some(endpoint) = &self.telemetry.otlp_endpoint {
+            if !endpoint.starts_with("http://") && !endpoint.starts_with("https://") {
+                errors.push(format!("telemetry.otlp_endpoint: '{}' must start with http:// or https://", endpoint));
+            } else if cfg!(not(feature = "otlp")) {
+                errors.push("telemetry.otlp_endpoint: needs a server built with the `otlp` feature".into());
+            }
+        }
+        if self.telemetry.service_name.trim().is_empty() {
+            errors.push("telemetry.service_name: must not be empty".into());
+        }
+        
+        if self.scheduler.missed_after_secs < self.scheduler.tick_secs {
+            // Otherwise purchases found a tick late would count as missed
+            errors.push("scheduler.missed_after_secs: must be at least scheduler.tick_secs".into());
+        }
+        
+        if let Some(smtp) = &self.notifications.smtp {
+            if smtp.host.trim().is_empty() {
+                errors.push("notifications.smtp.host: must not be empty".into());
+            }
+            if smtp.port == 0 {
+                errors.push("notifications.smtp.port: must not be 0".into());
+            }
+            if !smtp.from.contains('@') {
+                errors.push(format!("notifications.smtp.from: '{}' is not an email address", smtp.from));
+            }
+            if smtp.username.is_some() != smtp.password.is_some() {
+                errors.push("notifications.smtp: username and password must be set together".into());
+            }
+        }
+        if let Some(dir) = &self.notifications.templates_dir {
+            if !dir.is_dir() {
+                errors.push(format!("notifications.templates_dir: no directory at {}", dir.display()));
+            }
+        }
+        if self.notifications.webhook_attempts == 0 {
+            errors.push("notifications.webhook_attempts: must be at least 1".into());
+        }
+        if self.notifications.license_check_secs == 0 {
+            errors.push("notifications.license_check_secs: must be at least 1".into());
+        }
+        
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
+    }
+    
+    /// Check the configured tools are registered (tools are known only once registered)
+    pub fn validate_tools(&self, registered: &[&str]) -> Result<(), ConfigError> {
+        let registered: HashSet<&str> = registered.iter().copied().collect();
+        let unknown: Vec<String> = self
+            .tools
+            .disabled
+            .iter()
+            .filter(|name| !registered.contains(name.as_str()))
+            .map(|name| format!("tools.disabled: unknown tool '{}'", name))
+            .collect();
+        
+        if unknown.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(unknown))
+        }
+    }
+    
+    /// Keys whose change only takes effect after a restart
+    pub fn restart_required(&self, other: &Config) -> Vec<&'static str> {
+        let mut changed = Vec::new();
+        let mut check = |key, same: bool| {
+            if !same {
+                changed.push(key);
+            }
+        };
+        
+        check("server.bind_addr", self.server.bind_addr == other.server.bind_addr);
+        check("server.static_dir", self.server.static_dir == other.server.static_dir);
+        check("server.tls", self.server.tls == other.server.tls);
+        check("server.drain_delay_secs", self.server.drain_delay_secs == other.server.drain_delay_secs);
+        check("server.shutdown_grace_secs", self.server.shutdown_grace_secs == other.server.shutdown_grace_secs);
+        check("provider.ollama_*", {
+            let (a, b) = (&self.provider, &other.provider);
+            a.ollama_host == b.ollama_host && a.ollama_port == b.ollama_port && a.timeout_secs == b.timeout_secs
+        });
+        check("provider.cascade_models", self.provider.cascade_models == other.provider.cascade_models);
+        check("provider.embedding_model", self.provider.embedding_model == other.provider.embedding_model);
+        check("provider.tagging_model", self.provider.tagging_model == other.provider.tagging_model);
+        check("tools", self.tools == other.tools);
+        check("storage", self.storage == other.storage);
+        check("safety", self.safety == other.safety);
+        check("auth", self.auth == other.auth);
+        check("stripe", self.stripe == other.stripe);
+        check("telemetry", self.telemetry == other.telemetry);
+        check("scheduler", self.scheduler == other.scheduler);
+        check("alerts", self.alerts == other.alerts);
+        check("notifications", {
+            // Only the webhook scheme check is live
+            let mut other = other.notifications.clone();
+            other.allow_http_webhooks = self.notifications.allow_http_webhooks;
+            self.notifications == other
+        });
+        changed
+    }
+}
+
+fn env(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.is_empty())
+}
+
+fn set<T>(field: &mut T, value: Option<T>) {
+    if let Some(value) = value {
+        *field = value;
+    }
+}
+
+fn set_some<T>(field: &mut Option<T>, value: Option<T>) {
+    if value.is_some() {
+        *field = value;
+    }
+}
+
+/// A numeric environment variable, if set (errors are collected)
+fn parsed<T: std::str::FromStr>(env: impl Fn(&str) -> Option<String>, name: &str, errors: &mut Vec<String>) -> Option<T> {
+    let value = env(name)?;
+    match value.trim().parse() {
+        Ok(value) => Some(value),
+        Err(_) => {
+            errors.push(format!("{}: '{}' is not a valid number", name, value));
+            None
+        }
+    }
+}
+
+/// Comma-separated list, blanks dropped
+fn list(value: &str) -> Vec<String> {
+    value.split(',').map(|v| v.trim().to_string()).filter(|v| !v.is_empty()).collect()
+}
+
+fn require_file(errors: &mut Vec<String>, key: &str, path: &Path) {
+    if !path.is_file() {
+        errors.push(format!("{}: no file at {}", key, path.display()));
+    }
+}
+
+/// Command-line flags (the top layer)
+#[derive(Default)]
+struct Flags {
+    config: Option<PathBuf>,
+    bind_addr: Option<String>,
+    static_dir: Option<PathBuf>,
+    default_model: Option<String>,
+    cors_origins: Option<Vec<String>>,
+    tls_cert: Option<PathBuf>,
+    tls_key: Option<PathBuf>,
+    portfolio_db: Option<PathBuf>,
+}
+
+impl Flags {
+    fn parse(args: &[String]) -> Result<Self, ConfigError> {
+        let mut flags = Self::default();
+        
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            let mut value = || args.next().cloned().ok_or_else(|| ConfigError::Flag(format!("{} requires a value", arg)));
+            match arg.as_str() {
+                "--config" => flags.config = Some(value()?.into()),
+                "--bind" => flags.bind_addr = Some(value()?),
+                "--static-dir" => flags.static_dir = Some(value()?.into()),
+                "--model" => flags.default_model = Some(value()?),
+                "--cors-origin" => flags.cors_origins.get_or_insert_with(Vec::new).push(value()?),
+                "--tls-cert" => flags.tls_cert = Some(value()?.into()),
+                "--tls-key" => flags.tls_key = Some(value()?.into()),
+                "--portfolio-db" => flags.portfolio_db = Some(value()?.into()),
+                other => return Err(ConfigError::Flag(format!("Unknown argument: {}", other))),
+            }
+        }
+        
+        if flags.tls_cert.is_some() != flags.tls_key.is_some() {
+            return Err(ConfigError::Flag("--tls-cert and --tls-key must be given together".into()));
+        }
+        Ok(flags)
+    }
+    
+    fn apply(&self, config: &mut Config) {
+        set(&mut config.server.bind_addr, self.bind_addr.clone());
+        set(&mut config.server.static_dir, self.static_dir.clone());
+        set_some(&mut config.provider.default_model, self.default_model.clone());
+        set(&mut config.server.cors_origins, self.cors_origins.clone());
+        if let (Some(cert_path), Some(key_path)) = (&self.tls_cert, &self.tls_key) {
+            config.server.tls = Some(TlsConfig { cert_path: cert_path.clone(), key_path: key_path.clone() });
+        }
+        set_some(&mut config.storage.portfolio_db_path, self.portfolio_db.clone());
+    }
+}
+
+// ============================================================================
+// Live Settings
+// ============================================================================
+
+/// The part of the configuration that is read per request, and so can be reloaded
+#[derive(Debug)]
+pub struct Settings {
+    /// Model used when a request doesn't name one (`auto` when a cascade is configured)
+    pub default_model: String,
+    
+    pub team_max_tokens: u32,
+    pub team_max_delegations: usize,
+    pub guardrail_max_revisions: usize,
+    pub guardrail_critic_model: Option<String>,
+    
+    /// Models, tools, output tokens and concurrency each plan allows
+    pub plan_limits: PlanTable,
+    
+    /// Per-model token prices used to charge usage to licenses
+    pub cost_table: CostTable,
+    
+    /// Browser origins allowed by CORS (`*` = any)
+    pub cors_origins: Vec<String>,
+    
+    /// Accept `http://` webhook URLs in notification preferences
+    pub allow_http_webhooks: bool,
+}
+
+impl Settings {
+    /// Build from a validated config (reads the plan and price files)
+    ///
+    /// `cascade_alias` is the model name of the cascade, if one is configured.
+    pub fn from_config(config: &Config, cascade_alias: Option<&str>) -> anyhow::Result<Self> {
+        let plan_limits = match (&config.plans.limits, &config.plans.limits_path) {
+            (Some(PlanLimitsTable(table)), _) => table.clone(),
+            (None, Some(path)) => PlanTable::from_json(&std::fs::read_to_string(path)?)?,
+            (None, None) => PlanTable::default(),
+        };
+        let cost_table = match &config.plans.model_costs_path {
+            Some(path) => CostTable::from_json(&std::fs::read_to_string(path)?)?,
+            None => CostTable::default(),
+        };
+        
+        Ok(Self {
+            default_model: config
+                .provider
+                .default_model
+                .clone()
+                .or_else(|| cascade_alias.map(String::from))
+                .unwrap_or_else(|| FALLBACK_MODEL.into()),
+            team_max_tokens: config.agent.team_max_tokens,
+            team_max_delegations: config.agent.team_max_delegations,
+            guardrail_max_revisions: config.agent.guardrail_max_revisions,
+            guardrail_critic_model: config.agent.guardrail_critic_model.clone(),
+            plan_limits,
+            cost_table,
+            cors_origins: config.server.cors_origins.clone(),
+            allow_http_webhooks: config.notifications.allow_http_webhooks,
+        })
+    }
+    
+    pub fn allows_origin(&self, origin: &HeaderValue) -> bool {
+        self.cors_origins.iter().any(|o| o == "*" || o.as_bytes() == origin.as_bytes())
+    }
+}
+
+/// The current `Settings`, replaced as a whole on reload
+///
+/// Readers take a snapshot (`current`), so one request sees one version.
+pub struct LiveSettings {
+    current: RwLock<Arc<Settings>>,
+}
+
+impl LiveSettings {
+    pub fn new(settings: Settings) -> Self {
+        Self { current: RwLock::new(Arc::new(settings)) }
+    }
+    
+    pub fn current(&self) -> Arc<Settings> {
+        self.current.read().unwrap().clone()
+    }
+    
+    pub fn replace(&self, settings: Settings) {
+        *self.current.write().unwrap() = Arc::new(settings);
+    }
+}
+
+/// Reload the configuration on SIGHUP, applying the settings that can change live
+#[cfg(unix)]
+pub fn reload_on_sighup(args: Vec<String>, loaded: Config, cascade_alias: Option<String>, settings: Arc<LiveSettings>) {
+    use tokio::signal::unix::{signal, SignalKind};
+    
+    let mut hangups = match signal(SignalKind::hangup()) {
+        Ok(hangups) => hangups,
+        Err(e) => {
+            tracing::warn!("⚠ Config reload on SIGHUP disabled: {}", e);
+            return;
+        }
+    };
+    
+    tokio::spawn(async move {
+        let mut running = loaded;
+        while hangups.recv().await.is_some() {
+            let config = match Config::load(&args) {
+                Ok(config) => config,
+                Err(e) => {
+                    tracing::warn!("⚠ Config reload failed, keeping the running configuration: {}", e);
+                    continue;
+                }
+            };
+            match Settings::from_config(&config, cascade_alias.as_deref()) {
+                Ok(new) => settings.replace(new),
+                Err(e) => {
+                    tracing::warn!("⚠ Config reload failed, keeping the running configuration: {}", e);
+                    continue;
+                }
+            }
+            
+            tracing::info!("✓ Reloaded configuration");
+            let pending = running.restart_required(&config);
+            if !pending.is_empty() {
+                tracing::warn!("⚠ Changes to {} take effect after a restart", pending.join(", "));
+            }
+            running.server.cors_origins = config.server.cors_origins;
+            running.provider.default_model = config.provider.default_model;
+            running.agent = config.agent;
+            running.plans = config.plans;
+            running.notifications.allow_http_webhooks = config.notifications.allow_http_webhooks;
+        }
+    });
+}
+
+/// Config reload needs SIGHUP, which only Unix has
+#[cfg(not(unix))]
+pub fn reload_on_sighup(_args: Vec<String>, _loaded: Config, _cascade_alias: Option<String>, _settings: Arc<LiveSettings>) {}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    /// A config file with `contents`, in a fresh temporary directory
+    fn config_file(contents: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("config-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("agent-server.toml");
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    fn load(args: &[&str], vars: &[(&str, &str)]) -> Result<Config, ConfigError> {
+        let args: Vec<String> = args.iter().map(|a| a.to_string()).collect();
+        let vars: HashMap<String, String> = vars.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        Config::load_with(&args, |name| vars.get(name).cloned())
+    }
+
+    fn errors(result: Result<Config, ConfigError>) -> Vec<String> {
+        match result {
+            Err(ConfigError::Invalid(errors)) => errors,
+            other => panic!("expected Invalid, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_layers_file_then_env_then_flags() {
+        let path = config_file(
+            "[server]\nbind_addr = \"127.0.0.1:4000\"\n\n[provider]\ndefault_model = \"file-model\"\nollama_port = 1111\n",
+        );
+        let path = path.to_str().unwrap();
+        
+        // The file overrides defaults
+        let config = load(&["--config", path], &[]).unwrap();
+        assert_eq!(config.server.bind_addr, "127.0.0.1:4000");
+        assert_eq!(config.provider.default_model.as_deref(), Some("file-model"));
+        assert_eq!(config.provider.ollama_port, 1111);
+        assert_eq!(config.provider.timeout_secs, ProviderConfig::default().timeout_secs);
+        
+        // The environment overrides the file (which it can also name)
+        let vars = [("AGENT_CONFIG", path), ("BIND_ADDR", "127.0.0.1:5000"), ("DEFAULT_MODEL", "env-model")];
+        let config = load(&[], &vars).unwrap();
+        assert_eq!(config.server.bind_addr, "127.0.0.1:5000");
+        assert_eq!(config.provider.default_model.as_deref(), Some("env-model"));
+        assert_eq!(config.provider.ollama_port, 1111);
+        
+        // Flags override both
+        let config = load(&["--model", "cli-model"], &vars).unwrap();
+        assert_eq!(config.server.bind_addr, "127.0.0.1:5000");
+        assert_eq!(config.provider.default_model.as_deref(), Some("cli-model"));
+        let _ = std::fs::remove_dir_all(Path::new(path).parent().unwrap());
+    }
+
+    #[test]
+    fn test_validation_errors() {
+        let path = config_file(
+            "[server]\nbind_addr = \"nowhere\"\ncors_origins = [\"example.com\"]\n\n[provider]\ntimeout_secs = 0\n\n[stripe]\nsecret_key = \"sk_test\"\n",
+        );
+        let path = path.to_str().unwrap();
+        
+        // Every problem is reported, each with its key
+        let problems = errors(load(&["--config", path], &[]));
+        assert_eq!(problems.len(), 4, "{:?}", problems);
+        for key in ["server.bind_addr:", "server.cors_origins:", "provider.timeout_secs:", "stripe:"] {
+            assert!(problems.iter().any(|e| e.starts_with(key)), "no {} in {:?}", key, problems);
+        }
+        
+        // A later layer can fix an earlier one's mistake
+        let problems = errors(load(&["--config", path, "--bind", "127.0.0.1:3000"], &[("OLLAMA_TIMEOUT_SECS", "30")]));
+        assert_eq!(problems.len(), 2, "{:?}", problems);
+        
+        // Bad environment values, unknown keys and incomplete flags are rejected before validation
+        assert_eq!(errors(load(&[], &[("OLLAMA_PORT", "eleven")])), vec!["OLLAMA_PORT: 'eleven' is not a valid number"]);
+        let typo = config_file("[server]\nbind_adr = \"127.0.0.1:3000\"\n");
+        assert!(matches!(load(&["--config", typo.to_str().unwrap()], &[]), Err(ConfigError::Parse { .. })));
+        assert!(matches!(load(&["--tls-cert", "cert.pem"], &[]), Err(ConfigError::Flag(_))));
+        let _ = std::fs::remove_dir_all(Path::new(path).parent().unwrap());
+        let _ = std::fs::remove_dir_all(typo.parent().unwrap());
+    }
+}
@@ -0,0 +1,251 @@
+//! DCA Job API
+//!
+//! Registers a user's dollar-cost averaging plan with the scheduler
+//! (`scheduler.rs`), which makes the purchases as they come due. Every route
+//! is scoped to the signed-in user; other users' jobs are reported as not
+//! found.
+//!
+//! `POST /dca/jobs` honours an `Idempotency-Key` header: sending the same key
+//! again returns the job created the first time (200) instead of a second one.
+
+use axum::{
+    extract::{Extension, State},
+    http::{HeaderMap, StatusCode},
+    Json,
+};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crypto_advisor::{AdvisorError, DCAStrategy};
+
+use crate::auth::UserId;
+use crate::error::{ApiError, ApiJson, ApiPath};
+use crate::scheduler::{CatchUp, DcaJob, ExecutionMode, JobError, JobRun, JobStatus};
+use crate::state::AppState;
+
+/// Header making job creation safe to retry
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+
+/// Longest idempotency key
+const MAX_IDEMPOTENCY_KEY_CHARS: usize = 128;
+
+/// Most purchases in one plan
+const MAX_PERIODS: u32 = 520;
+
+/// Longest time between purchases
+const MAX_INTERVAL_DAYS: u32 = 366;
+
+// ============================================================================
+// Requests and Responses
+// ============================================================================
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CreateDcaJobRequest {
+    pub symbol: String,
+    
+    /// Total to invest in USD, split evenly over the periods
+    pub total_amount: Decimal,
+    
+    /// Number of purchases
+    pub periods: u32,
+    
+    /// Days between purchases
+    pub interval_days: u32,
+    
+    /// First purchase (default: now); past purchases are handled by `catch_up`
+    #[serde(default)]
+    pub start_date: Option<DateTime<Utc>>,
+    
+    /// Portfolio to record the purchases in (default: not recorded)
+    #[serde(default)]
+    pub portfolio: Option<String>,
+    
+    /// Default: paper
+    #[serde(default)]
+    pub mode: ExecutionMode,
+    
+    /// Purchases missed while the server was down (default: latest)
+    #[serde(default)]
+    pub catch_up: CatchUp,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ScheduleEntryView {
+    pub date: DateTime<Utc>,
+    pub amount: Decimal,
+    pub executed: bool,
+    pub execution_price: Option<Decimal>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct DcaJobView {
+    pub id: String,
+    pub symbol: String,
+    pub portfolio: Option<String>,
+    pub mode: ExecutionMode,
+    pub catch_up: CatchUp,
+    pub status: JobStatus,
+    pub total_amount: Decimal,
+    pub amount_per_period: Decimal,
+    pub periods: u32,
+    pub interval_days: u32,
+    
+    /// When the next purchase is due (None once completed or cancelled)
+    pub next_purchase: Option<DateTime<Utc>>,
+    
+    pub completion_percent: Decimal,
+    
+    /// Average price paid, weighted by amount
+    pub average_price: Option<Decimal>,
+    
+    pub schedule: Vec<ScheduleEntryView>,
+    
+    /// Purchases made and entries skipped, in the order they happened
+    pub runs: Vec<JobRun>,
+    
+    /// Why the last purchase attempt failed (it is retried)
+    pub last_error: Option<String>,
+    
+    pub created_at: DateTime<Utc>,
+}
+
+impl From<&DcaJob> for DcaJobView {
+    fn from(job: &DcaJob) -> Self {
+        let strategy = &job.strategy;
+        Self {
+            id: job.id.to_string(),
+            symbol: job.symbol.clone(),
+            portfolio: job.portfolio.clone(),
+            mode: job.mode,
+            catch_up: job.catch_up,
+            status: job.status,
+            total_amount: strategy.total_amount,
+            amount_per_period: strategy.amount_per_period,
+            periods: strategy.periods,
+            interval_days: strategy.interval_days,
+            next_purchase: job.next_due(),
+            completion_percent: strategy.completion_percent().round_dp(2),
+            average_price: strategy.average_price().map(|price| price.round_dp(2)),
+            schedule: strategy
+                .schedule
+                .iter()
+                .map(|entry| ScheduleEntryView {
+                    date: entry.date,
+                    amount: entry.amount,
+                    executed: entry.executed,
+                    execution_price: entry.execution_price,
+                })
+                .collect(),
+            runs: job.runs.clone(),
+            last_error: job.last_error.clone(),
+            created_at: job.created_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct DcaJobListResponse {
+    /// Oldest first
+    pub jobs: Vec<DcaJobView>,
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+/// List the caller's DCA jobs
+pub async fn list_jobs(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+) -> Json<DcaJobListResponse> {
+    let jobs = state.jobs.list(user_id.as_str());
+    Json(DcaJobListResponse { jobs: jobs.iter().map(DcaJobView::from).collect() })
+}
+
+/// Register a DCA plan with the scheduler
+pub async fn create_job(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    headers: HeaderMap,
+    ApiJson(payload): ApiJson<CreateDcaJobRequest>,
+) -> Result<(StatusCode, Json<DcaJobView>), ApiError> {
+    let idempotency_key = match headers.get(IDEMPOTENCY_KEY) {
+        Some(value) => {
+            let key = value.to_str().unwrap_or_default().trim();
+            if key.is_empty() || key.chars().count() > MAX_IDEMPOTENCY_KEY_CHARS {
+                return Err(JobError::Invalid(format!("Idempotency-Key must be 1-{} characters", MAX_IDEMPOTENCY_KEY_CHARS)).into());
+            }
+            Some(key.to_string())
+        }
+        None => None,
+    };
+    
+    if payload.mode == ExecutionMode::Live {
+        return Err(JobError::LiveUnavailable.into());
+    }
+    if payload.total_amount <= Decimal::ZERO {
+        return Err(JobError::Invalid("total_amount must be positive".into()).into());
+    }
+    if !(1..=MAX_PERIODS).contains(&payload.periods) || !(1..=MAX_INTERVAL_DAYS).contains(&payload.interval_days) {
+        return Err(JobError::Invalid(format!(
+            "periods must be 1-{} and interval_days 1-{}",
+            MAX_PERIODS, MAX_INTERVAL_DAYS
+        ))
+        .into());
+    }
+    
+    // The asset must be priced, and the portfolio the caller's
+    state.exchange.get_price(&payload.symbol).await?;
+    if let Some(name) = &payload.portfolio {
+        state
+            .portfolios
+            .get(Some(user_id.as_str()), name)?
+            .ok_or_else(|| AdvisorError::PortfolioNotFound(name.clone()))?;
+    }
+    
+    let strategy = DCAStrategy::starting(
+        payload.total_amount,
+        payload.periods,
+        payload.interval_days,
+        payload.start_date.unwrap_or_else(Utc::now),
+    );
+    let mut job = DcaJob::new(user_id.as_str(), &payload.symbol, strategy);
+    job.portfolio = payload.portfolio;
+    job.mode = payload.mode;
+    job.catch_up = payload.catch_up;
+    job.idempotency_key = idempotency_key;
+    
+    let (job, created) = state.jobs.create(job).await?;
+    let status = if created { StatusCode::CREATED } else { StatusCode::OK };
+    Ok((status, Json(DcaJobView::from(&job))))
+}
+
+/// One of the caller's DCA jobs, with its schedule and purchases
+pub async fn get_job(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiPath(id): ApiPath<String>,
+) -> Result<Json<DcaJobView>, ApiError> {
+    let job = state.jobs.get(user_id.as_str(), &id)?;
+    Ok(Json(DcaJobView::from(&job)))
+}
+
+/// Cancel a job (purchases already made are kept)
+pub async fn cancel_job(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiPath(id): ApiPath<String>,
+) -> Result<Json<DcaJobView>, ApiError> {
+    let job = state.jobs.get(user_id.as_str(), &id)?;
+    let job = state
+        .jobs
+        .update(job.id, |job| {
+            if matches!(job.status, JobStatus::Active | JobStatus::Paused) {
+                job.status = JobStatus::Cancelled;
+            }
+        })
+        .await?;
+    Ok(Json(DcaJobView::from(&job)))
+}
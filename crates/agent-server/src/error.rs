@@ -0,0 +1,305 @@
+//! API Errors
+//!
+//! Every REST error is an `ApiError`, sent as `{"error": "...", "code": "..."}`
+//! with a stable `ErrorCode`. Errors of the libraries (`AgentError`,
+//! `PaymentError`, `AdvisorError`) convert with `?`, so the same failure gets
+//! the same status and code on every endpoint. Clients should branch on the
+//! code, not the message; codes are only ever added, never renamed.
+
+use axum::{
+    extract::{rejection::{JsonRejection, PathRejection, QueryRejection}, FromRequest, FromRequestParts, Path, Query},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use schemars::JsonSchema;
+use serde::Serialize;
+
+use agent_core::AgentError;
+use agent_notify::NotifyError;
+use agent_payments::PaymentError;
+use crypto_advisor::AdvisorError;
+
+/// Stable, machine-readable error codes
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, JsonSchema)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum ErrorCode {
+    // Requests
+    InvalidRequest,
+    PayloadTooLarge,
+    InvalidFormat,
+    InvalidExport,
+    PromptError,
+    
+    // Accounts
+    Unauthorized,
+    InvalidCredentials,
+    InvalidCredentialsFormat,
+    UsernameTaken,
+    KeyNotFound,
+    AuthError,
+    
+    // Plans and licenses
+    InvalidLicense,
+    ModelNotAllowed,
+    RateLimited,
+    TooManyConcurrent,
+    LicenseUnavailable,
+    
+    // Organizations
+    OrgNotFound,
+    MemberNotFound,
+    RoleNotPermitted,
+    SeatLimitReached,
+    InvalidInvite,
+    OrgConflict,
+    OrgsUnavailable,
+    
+    // Chat
+    ContentBlocked,
+    ContextOverflow,
+    BudgetExceeded,
+    ProviderUnavailable,
+    OllamaError,
+    AgentError,
+    
+    // Conversations
+    ConversationNotFound,
+    ConversationExists,
+    SessionError,
+    StreamNotFound,
+    ResumeUnavailable,
+    
+    // Portfolios and market data
+    UnsupportedAsset,
+    InvalidPortfolio,
+    PortfolioNotFound,
+    PortfolioExists,
+    PositionNotFound,
+    PortfolioUnavailable,
+    PriceUnavailable,
+    ExchangeError,
+    
+    // Scheduled DCA jobs
+    JobNotFound,
+    LiveTradingUnavailable,
+    JobsUnavailable,
+    
+    // Price alerts
+    AlertNotFound,
+    AlertsUnavailable,
+    
+    // Notifications
+    NotificationNotFound,
+    InvalidPreferences,
+    NotificationsUnavailable,
+    
+    // Payments
+    PaymentsDisabled,
+    CheckoutError,
+    UsageError,
+    MissingSignature,
+    InvalidSignature,
+    WebhookError,
+    
+    InternalError,
+}
+
+impl std::fmt::Display for ErrorCode {
+    /// The code as sent on the wire (`RATE_LIMITED`)
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.serialize(f)
+    }
+}
+
+/// Error response body
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct ErrorResponse {
+    /// What went wrong, for people
+    pub error: String,
+    /// What went wrong, for programs
+    pub code: ErrorCode,
+}
+
+/// An error response: status, code, message and any extra headers
+#[derive(Debug)]
+pub struct ApiError {
+    status: StatusCode,
+    code: ErrorCode,
+    message: String,
+    headers: HeaderMap,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+            headers: HeaderMap::new(),
+        }
+    }
+    
+    pub fn bad_request(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, code, message)
+    }
+    
+    pub fn not_found(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, code, message)
+    }
+    
+    pub fn internal(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, code, message)
+    }
+    
+    pub fn with_header(mut self, name: header::HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+    
+    pub fn status(&self) -> StatusCode {
+        self.status
+    }
+    
+    pub fn code(&self) -> ErrorCode {
+        self.code
+    }
+    
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+    
+    pub fn headers_mut(&mut self) -> &mut HeaderMap {
+        &mut self.headers
+    }
+    
+    /// Seconds until a retry can succeed (the `Retry-After` header)
+    pub fn retry_after(&self) -> Option<u64> {
+        self.headers.get(header::RETRY_AFTER)?.to_str().ok()?.parse().ok()
+    }
+    
+    /// Log server-side failures, whose cause is not shown to the client
+    fn logged(self, cause: &dyn std::fmt::Display) -> Self {
+        if self.status.is_server_error() {
+            tracing::error!(code = %self.code, "{}", cause);
+        }
+        self
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = ErrorResponse { error: self.message, code: self.code };
+        (self.status, self.headers, Json(body)).into_response()
+    }
+}
+
+impl From<AgentError> for ApiError {
+    fn from(e: AgentError) -> Self {
+        let (status, code) = match &e {
+            AgentError::ContentBlocked(_) => (StatusCode::UNPROCESSABLE_ENTITY, ErrorCode::ContentBlocked),
+            AgentError::ContextOverflow { .. } => (StatusCode::BAD_REQUEST, ErrorCode::ContextOverflow),
+            AgentError::BudgetExceeded(_) => (StatusCode::UNPROCESSABLE_ENTITY, ErrorCode::BudgetExceeded),
+            AgentError::RateLimited(_) => (StatusCode::TOO_MANY_REQUESTS, ErrorCode::RateLimited),
+            AgentError::ProviderUnavailable(_) => (StatusCode::SERVICE_UNAVAILABLE, ErrorCode::ProviderUnavailable),
+            AgentError::Session(_) => (StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::SessionError),
+            _ => (StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::AgentError),
+        };
+        Self::new(status, code, e.user_message()).logged(&e)
+    }
+}
+
+impl From<PaymentError> for ApiError {
+    fn from(e: PaymentError) -> Self {
+        let (status, code, message) = match &e {
+            PaymentError::Stripe(_) => (StatusCode::BAD_GATEWAY, ErrorCode::CheckoutError, e.user_message()),
+            PaymentError::WebhookSignature(_) => (StatusCode::BAD_REQUEST, ErrorCode::InvalidSignature, "Invalid signature"),
+            PaymentError::WebhookParse(_) => (StatusCode::BAD_REQUEST, ErrorCode::WebhookError, "Invalid webhook event"),
+            PaymentError::LicenseNotFound(_) => (StatusCode::NOT_FOUND, ErrorCode::InvalidLicense, e.user_message()),
+            PaymentError::LicenseInvalid(_) => (StatusCode::FORBIDDEN, ErrorCode::InvalidLicense, e.user_message()),
+            PaymentError::RateLimited => (StatusCode::TOO_MANY_REQUESTS, ErrorCode::RateLimited, e.user_message()),
+            PaymentError::OrgNotFound(_) => (StatusCode::NOT_FOUND, ErrorCode::OrgNotFound, e.user_message()),
+            PaymentError::MemberNotFound(_) => return Self::not_found(ErrorCode::MemberNotFound, e.to_string()),
+            PaymentError::NotPermitted(_) => return Self::new(StatusCode::FORBIDDEN, ErrorCode::RoleNotPermitted, e.to_string()),
+            PaymentError::SeatLimit(_) => return Self::new(StatusCode::CONFLICT, ErrorCode::SeatLimitReached, e.to_string()),
+            PaymentError::InvalidInvite(_) => return Self::not_found(ErrorCode::InvalidInvite, e.to_string()),
+            PaymentError::OrgConflict(_) => return Self::new(StatusCode::CONFLICT, ErrorCode::OrgConflict, e.to_string()),
+            PaymentError::Storage(_) => (StatusCode::SERVICE_UNAVAILABLE, ErrorCode::LicenseUnavailable, "License service unavailable"),
+            PaymentError::Config(_) => (StatusCode::INTERNAL_SERVER_ERROR, ErrorCode::InternalError, e.user_message()),
+        };
+        Self::new(status, code, message).logged(&e)
+    }
+}
+
+impl From<AdvisorError> for ApiError {
+    fn from(e: AdvisorError) -> Self {
+        let (status, code) = match &e {
+            AdvisorError::UnsupportedAsset(_) => (StatusCode::BAD_REQUEST, ErrorCode::UnsupportedAsset),
+            AdvisorError::InvalidAllocation(_)
+            | AdvisorError::InsufficientFunds { .. }
+            | AdvisorError::PositionLimitExceeded { .. }
+            | AdvisorError::RiskThresholdExceeded(_) => (StatusCode::UNPROCESSABLE_ENTITY, ErrorCode::InvalidPortfolio),
+            AdvisorError::InvalidTransaction(_) | AdvisorError::InvalidAlert(_) => (StatusCode::BAD_REQUEST, ErrorCode::InvalidRequest),
+            AdvisorError::PortfolioNotFound(_) => (StatusCode::NOT_FOUND, ErrorCode::PortfolioNotFound),
+            AdvisorError::PositionNotFound(_) => (StatusCode::NOT_FOUND, ErrorCode::PositionNotFound),
+            AdvisorError::AlertNotFound(_) => (StatusCode::NOT_FOUND, ErrorCode::AlertNotFound),
+            AdvisorError::PortfolioExists(_) => (StatusCode::CONFLICT, ErrorCode::PortfolioExists),
+            AdvisorError::PriceUnavailable(_) => (StatusCode::SERVICE_UNAVAILABLE, ErrorCode::PriceUnavailable),
+            AdvisorError::Storage(_) => {
+                return Self::new(StatusCode::SERVICE_UNAVAILABLE, ErrorCode::PortfolioUnavailable, "Portfolio storage unavailable").logged(&e);
+            }
+            AdvisorError::Exchange(_) | AdvisorError::Network(_) => {
+                return Self::new(StatusCode::BAD_GATEWAY, ErrorCode::ExchangeError, "Market data is unavailable").logged(&e);
+            }
+            _ => {
+                return Self::internal(ErrorCode::InternalError, "An unexpected error occurred.").logged(&e);
+            }
+        };
+        Self::new(status, code, e.to_string())
+    }
+}
+
+impl From<NotifyError> for ApiError {
+    fn from(e: NotifyError) -> Self {
+        match &e {
+            NotifyError::NoAddress(_) | NotifyError::InvalidAddress(_) => {
+                Self::bad_request(ErrorCode::InvalidPreferences, e.to_string())
+            }
+            _ => Self::new(StatusCode::SERVICE_UNAVAILABLE, ErrorCode::NotificationsUnavailable, "Notifications unavailable")
+                .logged(&e),
+        }
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::new(rejection.status(), ErrorCode::InvalidRequest, rejection.body_text())
+    }
+}
+
+impl From<PathRejection> for ApiError {
+    fn from(rejection: PathRejection) -> Self {
+        Self::new(rejection.status(), ErrorCode::InvalidRequest, rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::new(rejection.status(), ErrorCode::InvalidRequest, rejection.body_text())
+    }
+}
+
+/// `Json` whose rejections (malformed body, wrong content type) are `ApiError`s
+#[derive(FromRequest)]
+#[from_request(via(Json), rejection(ApiError))]
+pub struct ApiJson<T>(pub T);
+
+/// `Path` whose rejections are `ApiError`s
+#[derive(FromRequestParts)]
+#[from_request(via(Path), rejection(ApiError))]
+pub struct ApiPath<T>(pub T);
+
+/// `Query` whose rejections are `ApiError`s
+#[derive(FromRequestParts)]
+#[from_request(via(Query), rejection(ApiError))]
+pub struct ApiQuery<T>(pub T);
@@ -1,35 +1,59 @@
 //! HTTP/WebSocket Handlers
 
 use axum::{
-    extract::{State, WebSocketUpgrade, ws::{Message, WebSocket}},
-    http::{HeaderMap, StatusCode},
+    extract::{Extension, State, WebSocketUpgrade, ws::{close_code, CloseFrame, Message, WebSocket}},
+    http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
+use chrono::{DateTime, Datelike, Utc};
 use futures::{SinkExt, StreamExt};
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::Instrument;
 
 use agent_core::{
-    message::Conversation,
-    provider::GenerationOptions,
-    reasoning::{Agent, AgentConfig},
+    export,
+    guardrail::{CriticGuardrail, Guardrails, Violation},
+    orchestration::AgentTrace,
+    prompt::{PromptVars, RenderedPrompt},
+    provider::{GenerationOptions, TokenUsage},
+    reasoning::{Agent, AgentConfig, RunEvent, TraceStep},
+    safety::Stage,
+    session::{Session, SessionId, SessionStore},
+    tagging::{SessionLabels, SessionTagger},
+    ToolRegistry,
 };
 use agent_payments::{
     CheckoutRequest as PaymentCheckoutRequest, LicenseKey, LicenseStore,
-    LicenseVerification, Plan, WebhookHandler,
+    LicenseVerification, Organization, Plan, UsageStatement, WebhookHandler,
 };
 
 // Use crypto-advisor's specialized system prompt
-use crypto_advisor::CRYPTO_ADVISOR_PROMPT;
+use crypto_advisor::{policy, team, tools::{PortfolioTrackerTool, PriceAlertTool}, RiskProfile, CRYPTO_ADVISOR_TEMPLATE_NAME};
 
+use crate::auth::UserId;
+use crate::error::{ApiError, ApiJson, ErrorCode};
+use crate::notifications;
+use crate::orgs;
+use crate::openapi::Usage;
+use crate::quota::{request_license, resolve, Admission, Entitlement};
 use crate::state::AppState;
 
+/// Prompt template for the generic tool-using assistant
+pub const ASSISTANT_PROMPT: &str = "assistant";
+
+/// Events a streamed answer may get ahead of its client
+const STREAM_EVENT_CAPACITY: usize = 64;
+
 // ============================================================================
 // Response Types
 // ============================================================================
 
-#[derive(Serialize)]
+#[derive(Serialize, JsonSchema)]
 pub struct HealthResponse {
     pub status: &'static str,
     pub version: &'static str,
@@ -38,7 +62,7 @@ pub struct HealthResponse {
     pub tools_available: Vec<String>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct ChatRequest {
     pub message: String,
     #[serde(default)]
@@ -50,22 +74,121 @@ pub struct ChatRequest {
     /// Use crypto advisor mode (specialized prompt)
     #[serde(default)]
     pub crypto_mode: bool,
+    /// JSON schema for a machine-readable answer (structured output mode)
+    #[serde(default)]
+    pub response_schema: Option<serde_json::Value>,
+    /// Authenticated user (set by the server, never read from the body); owns
+    /// the conversation and its long-term memories
+    #[serde(skip)]
+    pub user_id: Option<String>,
+    /// Name to address the user by (prompt variable)
+    #[serde(default)]
+    pub user_name: Option<String>,
+    /// Stated risk profile, e.g. "conservative" (prompt variable)
+    #[serde(default)]
+    pub risk_profile: Option<String>,
+    /// Pin a prompt template version instead of the active/A-B choice
+    #[serde(default)]
+    pub prompt_version: Option<String>,
+    /// Answer with the advisor team (planner delegating to specialist agents)
+    #[serde(default)]
+    pub multi_agent: bool,
+    /// Start the conversation in an organization's shared space (new conversations only)
+    #[serde(default)]
+    pub org_id: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct ChatResponse {
     pub message: String,
     pub conversation_id: String,
     pub model: String,
+    /// Parsed answer when `response_schema` was given
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<serde_json::Value>,
+    /// Tokens used across all reasoning iterations
+    #[schemars(with = "Usage")]
+    pub usage: TokenUsage,
+    /// Model and cascade escalations for each reasoning step
+    #[schemars(with = "Vec<serde_json::Value>")]
+    pub trace: Vec<TraceStep>,
+    /// Prompt template that produced the answer (`name@version`)
+    pub prompt_version: String,
+    /// Sub-agent runs in multi-agent mode
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[schemars(with = "Vec<serde_json::Value>")]
+    pub agents: Vec<AgentTrace>,
+    /// Whether the answer still breaks advisor policy after revisions
+    pub flagged: bool,
+    /// Policy violations behind the flag
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    #[schemars(with = "Vec<serde_json::Value>")]
+    pub violations: Vec<Violation>,
 }
 
-#[derive(Debug, Serialize)]
-pub struct ErrorResponse {
-    pub error: String,
-    pub code: String,
+/// A streamed chat event: a WebSocket message, or an SSE event named after its `type`
+#[derive(Clone, Debug, Serialize, JsonSchema)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum StreamEvent {
+    /// Part of the answer
+    Chunk {
+        content: String,
+        prompt_version: String,
+        conversation_id: String,
+    },
+    /// The model called a tool, which runs on the server
+    ToolCall {
+        name: String,
+        arguments: HashMap<String, serde_json::Value>,
+    },
+    /// The called tool's result
+    ToolResult {
+        name: String,
+        success: bool,
+        output: String,
+    },
+    /// The answer is complete; no events follow for this message
+    Done {
+        model: String,
+        #[schemars(with = "Usage")]
+        usage: TokenUsage,
+        prompt_version: String,
+        conversation_id: String,
+        /// Whether the answer still breaks advisor policy after revisions
+        flagged: bool,
+    },
+    /// The answer failed; no events follow for this message
+    Error {
+        error: String,
+        code: ErrorCode,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        retry_after: Option<u64>,
+    },
 }
 
-#[derive(Debug, Deserialize)]
+impl StreamEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            StreamEvent::Chunk { .. } => "chunk",
+            StreamEvent::ToolCall { .. } => "tool_call",
+            StreamEvent::ToolResult { .. } => "tool_result",
+            StreamEvent::Done { .. } => "done",
+            StreamEvent::Error { .. } => "error",
+        }
+    }
+}
+
+impl From<ApiError> for StreamEvent {
+    fn from(e: ApiError) -> Self {
+        StreamEvent::Error {
+            error: e.message().to_string(),
+            code: e.code(),
+            retry_after: e.retry_after(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct CheckoutRequest {
     pub plan: String,
     pub email: String,
@@ -73,18 +196,90 @@ pub struct CheckoutRequest {
     pub cancel_url: String,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct CheckoutResponse {
     pub checkout_url: String,
     pub session_id: String,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, JsonSchema)]
 pub struct VerifyLicenseRequest {
-    pub license_key: String,
+    /// License to check (default: the account's own)
+    #[serde(default)]
+    pub license_key: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UsageStatementRequest {
+    /// License to report on (default: the account's own)
+    #[serde(default)]
+    pub license_key: Option<String>,
+    /// Period start (default: start of the current month)
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    /// Period end (default: now)
+    #[serde(default)]
+    pub to: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SessionExportRequest {
+    pub conversation_id: String,
+    /// `json` (default, importable), `markdown` or `jsonl` (OpenAI fine-tuning format)
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct UserExportRequest {
+    /// `json` (default) or `jsonl`
+    #[serde(default)]
+    pub format: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SessionImportResponse {
+    pub conversation_id: String,
+    pub messages: usize,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SessionListRequest {
+    /// Only conversations with this tag (normalized, e.g. "DCA Plan" -> "dca-plan")
+    #[serde(default)]
+    pub tag: Option<String>,
+    /// Most conversations to return (default 50, at most 500)
+    #[serde(default)]
+    pub limit: Option<usize>,
+    /// List an organization's shared conversations instead of your own
+    #[serde(default)]
+    pub org_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct SessionShareRequest {
+    pub conversation_id: String,
+    /// Organization to share one of your conversations with; null takes a
+    /// shared conversation back into your own (admins and the owner)
+    #[serde(default)]
+    pub org_id: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SessionSummary {
+    pub conversation_id: String,
+    pub title: String,
+    pub tags: Vec<String>,
+    pub updated_at: DateTime<Utc>,
+    pub messages: usize,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct SessionListResponse {
+    pub sessions: Vec<SessionSummary>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, JsonSchema)]
 pub struct ModelInfo {
     pub id: String,
     pub name: String,
@@ -111,17 +306,11 @@ pub async fn health_check(State(state): State<AppState>) -> Json<HealthResponse>
 /// List available models
 pub async fn list_models(
     State(state): State<AppState>,
-) -> Result<Json<Vec<ModelInfo>>, (StatusCode, Json<ErrorResponse>)> {
+) -> Result<Json<Vec<ModelInfo>>, ApiError> {
     let models = state.provider.list_models().await.map_err(|e| {
-        (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(ErrorResponse {
-                error: format!("Ollama unavailable: {}", e),
-                code: "OLLAMA_ERROR".into(),
-            }),
-        )
+        ApiError::new(StatusCode::SERVICE_UNAVAILABLE, ErrorCode::OllamaError, format!("Ollama unavailable: {}", e))
     })?;
-
+    
     let model_info: Vec<ModelInfo> = models
         .into_iter()
         .map(|m| ModelInfo {
@@ -129,96 +318,410 @@ pub async fn list_models(
             name: m.name,
         })
         .collect();
-
+    
     Ok(Json(model_info))
 }
 
 /// Main chat endpoint (non-streaming)
 pub async fn chat_handler(
     State(state): State<AppState>,
-    Json(payload): Json<ChatRequest>,
-) -> Result<Json<ChatResponse>, (StatusCode, Json<ErrorResponse>)> {
-    // Verify license if provided
-    if let Some(ref key) = payload.license_key {
-        let license_key = LicenseKey::from_string(key);
-        match state.license_store.verify_and_use(&license_key) {
-            Ok(verification) if !verification.valid => {
-                return Err((
-                    StatusCode::FORBIDDEN,
-                    Json(ErrorResponse {
-                        error: verification.message.unwrap_or_else(|| "Invalid license".into()),
-                        code: "INVALID_LICENSE".into(),
-                    }),
-                ));
-            }
-            Err(e) => {
-                tracing::warn!("License verification error: {}", e);
-            }
-            _ => {}
-        }
+    Extension(entitlement): Extension<Entitlement>,
+    admission: Option<Extension<Admission>>,
+    ApiJson(mut payload): ApiJson<ChatRequest>,
+) -> Result<Json<ChatResponse>, ApiError> {
+    // `enforce` leaves resumable requests to the stream handler; this one can't resume
+    if admission.is_none() {
+        return Err(ApiError::bad_request(ErrorCode::InvalidRequest, "Last-Event-ID resumes streams; send it to /chat/stream"));
     }
-
-    // Get model
-    let model = payload.model.clone().unwrap_or_else(|| "llama3.2".into());
+    payload.user_id = Some(entitlement.user_id.to_string());
+    
+    // Redact secrets before anything (memory, the model) sees the message
+    payload.message = screen_message(&state, &payload)?;
+    
+    // Get model (the plan's allow-list was checked by the quota middleware)
+    let model = payload.model.clone().unwrap_or_else(|| state.settings.current().default_model.clone());
+    
+    // Continue the conversation if it exists
+    let mut session = open_session(&state, &payload)?;
+    let tools = user_tools(&state, &entitlement);
+    
+    // Multi-agent mode: the planner delegates to specialist agents
+    if payload.multi_agent {
+        return team_chat(state, payload, session, tools, model, entitlement).await;
+    }
+    
+    let prompt = chat_prompt(&state, &payload, &tools)?;
+    let agent = chat_agent(&state, &payload, &entitlement, tools, &model, &prompt);
     
-    // Select system prompt based on mode
-    let system_prompt = if payload.crypto_mode {
-        CRYPTO_ADVISOR_PROMPT.to_string()
-    } else {
-        // Default generic prompt
-        "You are a helpful AI assistant with access to tools. Use them when needed.".to_string()
+    // Run agent (structured mode returns the validated JSON as `data`). Plain
+    // runs continue the session's conversation, tool calls included; structured
+    // answers are recorded as a single exchange.
+    let prompt_version = session.metadata.prompt_version.clone().unwrap_or_else(|| prompt.id());
+    session.metadata.prompt_version = Some(prompt_version.clone());
+    let result = match payload.response_schema {
+        Some(schema) => agent
+            .ask_structured_detailed::<serde_json::Value>(&payload.message, schema)
+            .await
+            .map(|(data, run)| {
+                record_exchange(&mut session, &payload.message, &run.content, &run.model, run.usage);
+                (run, Some(data))
+            }),
+        None => {
+            session.conversation.push(agent_core::Message::user(&payload.message));
+            agent.run_detailed(&mut session.conversation).await.map(|run| (run, None))
+        }
     };
     
-    // Create agent config
+    let (run, data) = result?;
+    
+    if let Some(key) = entitlement.license {
+        charge_usage(&state, &entitlement.user_id, key, &run.model, run.usage);
+    }
+    
+    session.metadata.model = run.model.clone();
+    save_session(&state, &mut session);
+    
+    Ok(Json(ChatResponse {
+        message: run.content,
+        conversation_id: session.id.to_string(),
+        model: run.model,
+        data,
+        usage: run.usage,
+        trace: run.trace,
+        prompt_version,
+        agents: Vec::new(),
+        flagged: !run.violations.is_empty(),
+        violations: run.violations,
+    }))
+}
+
+/// The system prompt for a chat request, by mode
+fn chat_prompt(state: &AppState, request: &ChatRequest, tools: &ToolRegistry) -> Result<RenderedPrompt, ApiError> {
+    let template = if request.crypto_mode { CRYPTO_ADVISOR_TEMPLATE_NAME } else { ASSISTANT_PROMPT };
+    render_prompt(state, template, request, tools).map_err(prompt_error)
+}
+
+/// The agent answering a chat request: the user's tools and memory, and advisor guardrails in crypto mode
+fn chat_agent(
+    state: &AppState,
+    request: &ChatRequest,
+    entitlement: &Entitlement,
+    tools: Arc<ToolRegistry>,
+    model: &str,
+    prompt: &RenderedPrompt,
+) -> Agent {
     let config = AgentConfig {
-        system_prompt,
+        system_prompt: prompt.text.clone(),
         generation: GenerationOptions {
-            model: model.clone(),
+            model: model.to_string(),
+            max_tokens: entitlement.limits.max_tokens,
             ..Default::default()
         },
         ..Default::default()
     };
     
-    let agent = Agent::new(
+    let mut agent = Agent::new(state.provider.clone(), tools, config).with_metrics(state.metrics.agent.clone());
+    
+    if let (Some(memory), Some(user_id)) = (&state.memory, &request.user_id) {
+        agent = agent.with_memory(memory.clone(), user_id.clone());
+    }
+    
+    if request.crypto_mode {
+        agent = agent.with_guardrails(advisor_guardrails(state, request));
+    }
+    agent
+}
+
+/// Answer with the advisor team, under a shared token budget
+async fn team_chat(
+    state: AppState,
+    payload: ChatRequest,
+    mut session: Session,
+    tools: Arc<ToolRegistry>,
+    model: String,
+    entitlement: Entitlement,
+) -> Result<Json<ChatResponse>, ApiError> {
+    if payload.response_schema.is_some() {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            "response_schema is not supported in multi-agent mode",
+        ));
+    }
+    
+    let vars = prompt_vars(&payload, &tools);
+    let (team, prompt) = team::advisor_team(
         state.provider.clone(),
-        state.tools.clone(),
-        config,
+        tools,
+        &state.prompts,
+        prompt_bucket(&payload),
+        &vars,
+    )
+    .map_err(prompt_error)?;
+    
+    let settings = state.settings.current();
+    let team = Arc::new(
+        team.generation(GenerationOptions { model, max_tokens: entitlement.limits.max_tokens, ..Default::default() })
+            .max_tokens(settings.team_max_tokens)
+            .max_delegations(settings.team_max_delegations)
+            .guardrails(advisor_guardrails(&state, &payload))
+            .metrics(state.metrics.agent.clone()),
     );
     
-    // Run agent
-    let response = agent.ask(&payload.message).await.map_err(|e| {
-        tracing::error!("Agent error: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.user_message(),
-                code: "AGENT_ERROR".into(),
-            }),
-        )
-    })?;
-
-    let conversation_id = payload.conversation_id
-        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
-
+    let result = team.run(team::PLANNER, &payload.message).await?;
+    
+    // Sub-agents share the planner's model, so the whole run is priced at its rate
+    if let Some(key) = entitlement.license {
+        charge_usage(&state, &entitlement.user_id, key, &result.run.model, result.usage);
+    }
+    
+    // Only the planner's final answer is kept; sub-agent runs are in `agents`
+    record_exchange(&mut session, &payload.message, &result.run.content, &result.run.model, result.usage);
+    session.metadata.prompt_version.get_or_insert_with(|| prompt.id());
+    session.metadata.model = result.run.model.clone();
+    save_session(&state, &mut session);
+    
     Ok(Json(ChatResponse {
-        message: response,
-        conversation_id,
-        model,
+        message: result.run.content,
+        conversation_id: session.id.to_string(),
+        model: result.run.model,
+        data: None,
+        usage: result.usage,
+        trace: result.run.trace,
+        prompt_version: prompt.id(),
+        agents: result.agents,
+        flagged: !result.run.violations.is_empty(),
+        violations: result.run.violations,
     }))
 }
 
+/// The request's conversation, or a new one owned by the requesting user (or their team, with `org_id`)
+///
+/// A conversation of another user, not shared with the requesting one, is reported as not found.
+fn open_session(state: &AppState, request: &ChatRequest) -> Result<Session, ApiError> {
+    let Some(id) = request.conversation_id.as_deref() else {
+        let mut session = Session::new();
+        session.metadata.user_id = new_owner(state, request)?;
+        return Ok(session);
+    };
+    
+    let id = SessionId::from_string(id);
+    match state.sessions.load(&id) {
+        Ok(Some(session)) if can_open(state, &session, request.user_id.as_deref()) => Ok(session),
+        Ok(Some(_)) => Err(conversation_not_found()),
+        Ok(None) => {
+            let mut session = Session::with_id(id);
+            session.metadata.user_id = new_owner(state, request)?;
+            Ok(session)
+        }
+        Err(e) => Err(session_error(e)),
+    }
+}
+
+/// Owner of a conversation a request starts: the team named by `org_id`, else the user
+fn new_owner(state: &AppState, request: &ChatRequest) -> Result<Option<String>, ApiError> {
+    match (request.org_id.as_deref(), request.user_id.as_deref()) {
+        (Some(org_id), Some(user_id)) => Ok(orgs::scope(state, user_id, Some(org_id))?.owner().map(String::from)),
+        _ => Ok(request.user_id.clone()),
+    }
+}
+
+/// Whether `user_id` may access a session: their own, their team's, or one without an owner
+fn can_open(state: &AppState, session: &Session, user_id: Option<&str>) -> bool {
+    match (session.metadata.user_id.as_deref(), user_id) {
+        (None, _) => true,
+        (Some(owner), Some(user_id)) => owner == user_id || orgs::shared_with(state, owner, user_id),
+        (Some(_), None) => false,
+    }
+}
+
+fn conversation_not_found() -> ApiError {
+    ApiError::not_found(ErrorCode::ConversationNotFound, "Conversation not found")
+}
+
+/// A failure of the session store (not the agent)
+pub(crate) fn session_error(e: agent_core::AgentError) -> ApiError {
+    tracing::error!("Session store error: {}", e);
+    ApiError::internal(ErrorCode::SessionError, e.user_message())
+}
+
+/// A prompt template that can't be rendered for the request (unknown version, missing variable)
+fn prompt_error(e: agent_core::AgentError) -> ApiError {
+    ApiError::bad_request(ErrorCode::PromptError, e.to_string())
+}
+
+/// Append a question and its final answer to a session
+fn record_exchange(session: &mut Session, question: &str, answer: &str, model: &str, usage: TokenUsage) {
+    session.conversation.push(agent_core::Message::user(question));
+    session.conversation.push(agent_core::Message::assistant(answer).with_usage(model, Some(usage)));
+}
+
+/// Persist a session; a failed save is logged, not returned to the user
+///
+/// After the first exchange the session is titled and tagged in the background,
+/// once: later exchanges don't try again, even if the tagger found nothing.
+fn save_session(state: &AppState, session: &mut Session) {
+    session.touch();
+    let label = state.tagger.is_some() && SessionTagger::needs_labels(session);
+    if label {
+        session.metadata.auto_labeled = true;
+    }
+    
+    // Keep a title and tags the tagger stored after this copy was loaded
+    let saved = state.sessions.update(&session.id, &mut |stored| {
+        let labels = SessionLabels::of(&stored.metadata);
+        *stored = session.clone();
+        labels.apply(&mut stored.metadata);
+    });
+    let saved = match saved {
+        Ok(Some(stored)) => {
+            *session = stored;
+            Ok(())
+        }
+        Ok(None) => state.sessions.save(session),
+        Err(e) => Err(e),
+    };
+    if let Err(e) = saved {
+        tracing::warn!("Failed to save session {}: {}", session.id, e);
+        return;
+    }
+    
+    if let (true, Some(tagger)) = (label, &state.tagger) {
+        tokio::spawn(label_session(state.sessions.clone(), tagger.clone(), session.clone()));
+    }
+}
+
+/// Title and tag a session, merging the labels into its latest stored version
+async fn label_session(sessions: Arc<dyn SessionStore>, tagger: Arc<SessionTagger>, snapshot: Session) {
+    let labels = match tagger.label(&snapshot).await {
+        Ok(labels) => labels,
+        Err(e) => {
+            tracing::warn!("Failed to tag session {}: {}", snapshot.id, e);
+            return;
+        }
+    };
+    
+    // One update, so messages saved while the tagger ran are kept
+    let session = match sessions.update(&snapshot.id, &mut |session| labels.apply(&mut session.metadata)) {
+        Ok(Some(session)) => session,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::warn!("Failed to save tags for session {}: {}", snapshot.id, e);
+            return;
+        }
+    };
+    
+    tracing::debug!(
+        session = %session.id,
+        tags = ?session.metadata.tags,
+        tokens = labels.usage.map_or(0, |u| u.total_tokens),
+        "Session tagged"
+    );
+}
+
+/// Advisor policy checks for the request's risk profile (conservative by default)
+fn advisor_guardrails(state: &AppState, request: &ChatRequest) -> Arc<Guardrails> {
+    let profile = request
+        .risk_profile
+        .as_deref()
+        .and_then(RiskProfile::from_name)
+        .unwrap_or_default();
+    
+    let settings = state.settings.current();
+    let mut guardrails = policy::advisor_guardrails(profile).max_revisions(settings.guardrail_max_revisions);
+    if let Some(model) = &settings.guardrail_critic_model {
+        guardrails = guardrails.rule(CriticGuardrail::new(state.provider.clone(), model.clone()));
+    }
+    Arc::new(guardrails)
+}
+
+/// Screen a request's message with the safety policy
+///
+/// Returns the message with redactions applied; fails only with `ContentBlocked`.
+fn screen_message(state: &AppState, request: &ChatRequest) -> agent_core::Result<String> {
+    let subject = request.user_id.as_deref().or(request.conversation_id.as_deref());
+    state.safety.screen(&request.message, Stage::Request, subject)
+}
+
+/// Render a request's system prompt from the template registry
+///
+/// A/B splits are keyed by user, falling back to the conversation, so a
+/// user keeps the same prompt variant across requests.
+fn render_prompt(
+    state: &AppState,
+    template: &str,
+    request: &ChatRequest,
+    tools: &ToolRegistry,
+) -> agent_core::Result<RenderedPrompt> {
+    state.prompts.render(
+        template,
+        request.prompt_version.as_deref(),
+        prompt_bucket(request),
+        &prompt_vars(request, tools),
+    )
+}
+
+fn prompt_vars(request: &ChatRequest, tools: &ToolRegistry) -> PromptVars {
+    PromptVars::new()
+        .tools(tools)
+        .set_opt("user_name", request.user_name.clone())
+        .set_opt("risk_profile", request.risk_profile.clone())
+}
+
+fn prompt_bucket(request: &ChatRequest) -> Option<&str> {
+    request.user_id.as_deref().or(request.conversation_id.as_deref())
+}
+
+/// Price a request with the cost table and charge it to a license, on behalf of a user
+pub(crate) fn charge_usage(state: &AppState, user_id: &UserId, key: LicenseKey, model: &str, usage: TokenUsage) {
+    let record = state
+        .settings
+        .current()
+        .cost_table
+        .price(key, model, usage.prompt_tokens, usage.completion_tokens)
+        .by(user_id.as_str());
+    if let Err(e) = state.license_store.record_usage(&record) {
+        tracing::warn!("Failed to record usage: {}", e);
+    }
+}
+
+/// The tools a plan allows, with the portfolio tracker and price alerts scoped to the caller
+pub(crate) fn user_tools(state: &AppState, entitlement: &Entitlement) -> Arc<ToolRegistry> {
+    let mut tools = (*state.tools).clone();
+    tools.register(
+        PortfolioTrackerTool::with_store(state.exchange.clone(), state.portfolios.clone())
+            .owned_by(entitlement.user_id.as_str()),
+    );
+    tools.register(PriceAlertTool::new(state.alerts.clone()).owned_by(entitlement.user_id.as_str()));
+    tools.retain(|name| entitlement.limits.allows_tool(name));
+    Arc::new(tools)
+}
+
 /// WebSocket streaming chat
 pub async fn chat_stream_handler(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
 ) -> Response {
-    ws.on_upgrade(move |socket| handle_stream(socket, state))
+    ws.on_upgrade(move |socket| handle_stream(socket, state, user_id))
 }
 
-async fn handle_stream(socket: WebSocket, state: AppState) {
+async fn handle_stream(socket: WebSocket, state: AppState, user_id: UserId) {
     let (mut sender, mut receiver) = socket.split();
+    let _in_flight = state.shutdown.track();
     
-    while let Some(msg) = receiver.next().await {
+    loop {
+        // While draining, close between answers (never in the middle of one)
+        let msg = tokio::select! {
+            msg = receiver.next() => msg,
+            () = state.shutdown.draining() => {
+                let close = CloseFrame { code: close_code::RESTART, reason: "server shutting down".into() };
+                let _ = sender.send(Message::Close(Some(close))).await;
+                return;
+            }
+        };
+        let Some(msg) = msg else {
+            break;
+        };
         let msg = match msg {
             Ok(Message::Text(text)) => text,
             Ok(Message::Close(_)) => break,
@@ -228,82 +731,318 @@ async fn handle_stream(socket: WebSocket, state: AppState) {
             }
             _ => continue,
         };
-
+        
         // Parse request
         let request: ChatRequest = match serde_json::from_str(&msg) {
             Ok(r) => r,
             Err(e) => {
-                let error = serde_json::json!({"type": "error", "error": e.to_string()});
-                let _ = sender.send(Message::Text(error.to_string().into())).await;
+                let error = ApiError::bad_request(ErrorCode::InvalidRequest, e.to_string());
+                let _ = sender.send(ws_message(&error.into())).await;
                 continue;
             }
         };
-
-        let model = request.model.unwrap_or_else(|| "llama3.2".into());
         
-        // Select system prompt
-        let system_prompt = if request.crypto_mode {
-            CRYPTO_ADVISOR_PROMPT
-        } else {
-            "You are a helpful assistant."
+        // Each message is admitted like a chat request; the slot is held while streaming
+        let model = request.model.clone().unwrap_or_else(|| state.settings.current().default_model.clone());
+        let admitted = resolve(&state, &user_id, request.license_key.as_deref()).and_then(|entitlement| {
+            entitlement.check_model(&model)?;
+            let admission = entitlement.admit(&state)?;
+            Ok((entitlement, admission))
+        });
+        let (entitlement, admission) = match admitted {
+            Ok(admitted) => admitted,
+            Err(e) => {
+                let _ = sender.send(ws_message(&ApiError::from(e).into())).await;
+                continue;
+            }
         };
         
-        let messages = vec![
-            agent_core::Message::system(system_prompt),
-            agent_core::Message::user(request.message),
-        ];
+        // Dropping the events (client gone) cancels the provider stream
+        let mut events = chat_events(state.clone(), request, entitlement, admission);
+        while let Some(event) = events.recv().await {
+            if sender.send(ws_message(&event)).await.is_err() {
+                return;
+            }
+        }
+    }
+}
 
-        let options = GenerationOptions {
-            model: model.clone(),
-            ..Default::default()
-        };
+fn ws_message(event: &StreamEvent) -> Message {
+    Message::Text(serde_json::to_string(event).unwrap_or_default().into())
+}
+
+/// Stream one answer as `chunk`, `tool_call`, `tool_result`, then `done` or `error` events
+/// (shared by the WebSocket and SSE endpoints)
+///
+/// The answer is generated in its own task, which stops (cancelling the
+/// agent run) once the receiver is dropped. Shutdown waits for it,
+/// up to the grace period.
+pub(crate) fn chat_events(
+    state: AppState,
+    mut request: ChatRequest,
+    entitlement: Entitlement,
+    admission: Admission,
+) -> mpsc::Receiver<StreamEvent> {
+    let (tx, rx) = mpsc::channel(STREAM_EVENT_CAPACITY);
+    request.user_id = Some(entitlement.user_id.to_string());
+    
+    // The task outlives the request, but its spans still belong to it
+    let in_flight = state.shutdown.track();
+    tokio::spawn(
+        async move {
+            let (_admission, _in_flight) = (admission, in_flight);
+            if let Err(error) = stream_answer(&state, request, &entitlement, &tx).await {
+                let _ = tx.send(error.into()).await;
+            }
+        }
+        .instrument(tracing::Span::current()),
+    );
+    rx
+}
 
-        // Stream response
-        match state.provider.complete_stream(&messages, &options).await {
-            Ok(mut stream) => {
-                while let Some(result) = stream.next().await {
-                    match result {
-                        Ok(chunk) => {
-                            let response = serde_json::json!({
-                                "type": "chunk",
-                                "content": chunk.delta,
-                                "done": chunk.done,
-                            });
-                            if sender.send(Message::Text(response.to_string().into())).await.is_err() {
-                                break;
-                            }
-                        }
-                        Err(e) => {
-                            let error = serde_json::json!({"type": "error", "error": e.to_string()});
-                            let _ = sender.send(Message::Text(error.to_string().into())).await;
-                            break;
-                        }
+async fn stream_answer(
+    state: &AppState,
+    mut request: ChatRequest,
+    entitlement: &Entitlement,
+    tx: &mpsc::Sender<StreamEvent>,
+) -> Result<(), ApiError> {
+    request.message = screen_message(state, &request)?;
+    let model = request.model.clone().unwrap_or_else(|| state.settings.current().default_model.clone());
+    
+    // Continue the conversation if it exists
+    let mut session = open_session(state, &request)?;
+    let tools = user_tools(state, entitlement);
+    let prompt = chat_prompt(state, &request, &tools)?;
+    let agent = chat_agent(state, &request, entitlement, tools, &model, &prompt);
+    
+    let prompt_version = session.metadata.prompt_version.clone().unwrap_or_else(|| prompt.id());
+    session.metadata.prompt_version = Some(prompt_version.clone());
+    let conversation_id = session.id.to_string();
+    session.conversation.push(agent_core::Message::user(&request.message));
+    
+    // Forward the run's events as they happen; returning (client gone) drops the run
+    let (events, mut received) = mpsc::channel(STREAM_EVENT_CAPACITY);
+    let event = |event| run_event(event, &prompt_version, &conversation_id);
+    let result = {
+        let conversation = &mut session.conversation;
+        let run = async move { agent.run_streaming(conversation, &events).await };
+        tokio::pin!(run);
+        loop {
+            tokio::select! {
+                biased;
+                Some(next) = received.recv() => {
+                    if tx.send(event(next)).await.is_err() {
+                        tracing::debug!("Stream client disconnected, cancelling generation");
+                        return Ok(());
                     }
                 }
+                result = &mut run => break result,
             }
-            Err(e) => {
-                let error = serde_json::json!({"type": "error", "error": e.to_string()});
-                let _ = sender.send(Message::Text(error.to_string().into())).await;
-            }
         }
+    };
+    // The run dropped its sender, so this ends after the events it left behind
+    while let Some(next) = received.recv().await {
+        if tx.send(event(next)).await.is_err() {
+            return Ok(());
+        }
+    }
+    let run = result?;
+    
+    if let Some(key) = &entitlement.license {
+        charge_usage(state, &entitlement.user_id, key.clone(), &run.model, run.usage);
+    }
+    session.metadata.model = run.model.clone();
+    save_session(state, &mut session);
+    
+    let done = StreamEvent::Done {
+        model: run.model,
+        usage: run.usage,
+        prompt_version,
+        conversation_id,
+        flagged: !run.violations.is_empty(),
+    };
+    if tx.send(done).await.is_err() {
+        tracing::debug!("Stream client disconnected before the answer finished");
+    }
+    Ok(())
+}
+
+/// The stream event for an event of an agent run
+fn run_event(event: RunEvent, prompt_version: &str, conversation_id: &str) -> StreamEvent {
+    match event {
+        RunEvent::Delta(content) => StreamEvent::Chunk {
+            content,
+            prompt_version: prompt_version.to_string(),
+            conversation_id: conversation_id.to_string(),
+        },
+        RunEvent::ToolCall(call) => StreamEvent::ToolCall { name: call.name, arguments: call.arguments },
+        RunEvent::ToolResult(result) => StreamEvent::ToolResult {
+            name: result.name,
+            success: result.success,
+            output: result.output,
+        },
+    }
+}
+
+/// List a user's (or their team's) conversations, newest first, optionally only those with a tag
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiJson(payload): ApiJson<SessionListRequest>,
+) -> Result<Json<SessionListResponse>, ApiError> {
+    let limit = payload.limit.unwrap_or(50).min(500);
+    let scope = orgs::scope(&state, user_id.as_str(), payload.org_id.as_deref())?;
+    let sessions = match payload.tag.as_deref().and_then(agent_core::tagging::normalize_tag) {
+        Some(tag) => state.sessions.list_by_tag(&tag, scope.owner(), limit),
+        None => state.sessions.list(scope.owner(), limit),
+    }
+    .map_err(session_error)?;
+    
+    let sessions = sessions
+        .iter()
+        .map(|session| SessionSummary {
+            conversation_id: session.id.to_string(),
+            title: session.title(),
+            tags: session.metadata.tags.clone(),
+            updated_at: session.updated_at,
+            messages: session.message_count(),
+        })
+        .collect();
+    
+    Ok(Json(SessionListResponse { sessions }))
+}
+
+/// Export one conversation (`json`, `markdown` or `jsonl`)
+pub async fn export_session(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiJson(payload): ApiJson<SessionExportRequest>,
+) -> Result<Response, ApiError> {
+    let session = match state.sessions.load(&SessionId::from_string(&payload.conversation_id)) {
+        Ok(Some(session)) if can_open(&state, &session, Some(user_id.as_str())) => session,
+        Ok(_) => return Err(conversation_not_found()),
+        Err(e) => return Err(session_error(e)),
+    };
+    
+    let format = payload.format.as_deref().unwrap_or("json");
+    let body = match format {
+        "json" => export::to_json(&session),
+        "markdown" | "md" => Ok(export::to_markdown(&session)),
+        "jsonl" => export::to_openai_jsonl([&session]),
+        other => return Err(unknown_export_format(other)),
+    }
+    .map_err(session_error)?;
+    
+    Ok(export_response(body, format, &format!("conversation-{}", session.id)))
+}
+
+/// Import a conversation exported as JSON (it becomes the caller's)
+pub async fn import_session(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    body: String,
+) -> Result<Json<SessionImportResponse>, ApiError> {
+    let mut session = export::from_json(&body).map_err(|e| ApiError::bad_request(ErrorCode::InvalidExport, e.to_string()))?;
+    session.metadata.user_id = Some(user_id.to_string());
+    
+    // Never overwrite another user's conversation
+    match state.sessions.load(&session.id) {
+        Ok(Some(existing)) if existing.metadata.user_id != session.metadata.user_id => {
+            return Err(ApiError::new(
+                StatusCode::CONFLICT,
+                ErrorCode::ConversationExists,
+                "A conversation with this id already exists",
+            ));
+        }
+        Ok(_) => {}
+        Err(e) => return Err(session_error(e)),
+    }
+    
+    state.sessions.save(&session).map_err(session_error)?;
+    
+    Ok(Json(SessionImportResponse {
+        conversation_id: session.id.to_string(),
+        messages: session.message_count(),
+    }))
+}
+
+/// Share one of the caller's conversations with their organization, or take a shared one back
+pub async fn share_session(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiJson(payload): ApiJson<SessionShareRequest>,
+) -> Result<StatusCode, ApiError> {
+    let mut session = match state.sessions.load(&SessionId::from_string(&payload.conversation_id)) {
+        Ok(Some(session)) if can_open(&state, &session, Some(user_id.as_str())) => session,
+        Ok(_) => return Err(conversation_not_found()),
+        Err(e) => return Err(session_error(e)),
+    };
+    
+    let owner = session.metadata.user_id.clone().unwrap_or_default();
+    session.metadata.user_id = match payload.org_id.as_deref() {
+        Some(org_id) if owner == user_id.as_str() => {
+            orgs::scope(&state, user_id.as_str(), Some(org_id))?.owner().map(String::from)
+        }
+        Some(_) => return Err(ApiError::bad_request(ErrorCode::InvalidRequest, "Only your own conversations can be shared")),
+        None => {
+            let Some(org_id) = Organization::id_of_scope(&owner) else {
+                return Err(ApiError::bad_request(ErrorCode::InvalidRequest, "The conversation is not shared"));
+            };
+            orgs::scope(&state, user_id.as_str(), Some(org_id))?.editable()?;
+            Some(user_id.to_string())
+        }
+    };
+    
+    state.sessions.save(&session).map_err(session_error)?;
+    tracing::info!(user_id = %user_id, conversation_id = %session.id, owner = ?session.metadata.user_id, "Conversation moved");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Export every conversation of a user (GDPR data access)
+pub async fn export_user_data(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiJson(payload): ApiJson<UserExportRequest>,
+) -> Result<Response, ApiError> {
+    let format = payload.format.as_deref().unwrap_or("json");
+    let body = match format {
+        "json" => export::export_user(state.sessions.as_ref(), user_id.as_str())
+            .and_then(|export| serde_json::to_string_pretty(&export).map_err(Into::into)),
+        "jsonl" => state
+            .sessions
+            .list(Some(user_id.as_str()), usize::MAX)
+            .and_then(|sessions| export::to_openai_jsonl(&sessions)),
+        other => return Err(unknown_export_format(other)),
     }
+    .map_err(session_error)?;
+    
+    tracing::info!(user_id = %user_id, format, "User data exported");
+    Ok(export_response(body, format, &format!("user-{}", user_id)))
+}
+
+/// A download with the content type of an export format
+fn export_response(body: String, format: &str, name: &str) -> Response {
+    let (content_type, extension) = match format {
+        "markdown" | "md" => ("text/markdown; charset=utf-8", "md"),
+        "jsonl" => ("application/jsonl", "jsonl"),
+        _ => ("application/json", "json"),
+    };
+    let disposition = format!("attachment; filename=\"{}.{}\"", name, extension);
+    ([(header::CONTENT_TYPE, content_type.to_string()), (header::CONTENT_DISPOSITION, disposition)], body).into_response()
+}
+
+fn unknown_export_format(format: &str) -> ApiError {
+    ApiError::bad_request(ErrorCode::InvalidFormat, format!("Unknown export format '{}'", format))
 }
 
 /// Create Stripe checkout session
 pub async fn create_checkout(
     State(state): State<AppState>,
-    Json(payload): Json<CheckoutRequest>,
-) -> Result<Json<CheckoutResponse>, (StatusCode, Json<ErrorResponse>)> {
-    let stripe = state.stripe.as_ref().ok_or_else(|| {
-        (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(ErrorResponse {
-                error: "Payments not configured".into(),
-                code: "PAYMENTS_DISABLED".into(),
-            }),
-        )
-    })?;
-
+    Extension(user_id): Extension<UserId>,
+    ApiJson(payload): ApiJson<CheckoutRequest>,
+) -> Result<Json<CheckoutResponse>, ApiError> {
+    let stripe = state.stripe.as_ref().ok_or_else(payments_disabled)?;
+    
     let plan = Plan::from_str(&payload.plan);
     
     let request = PaymentCheckoutRequest {
@@ -311,20 +1050,11 @@ pub async fn create_checkout(
         customer_email: payload.email,
         success_url: payload.success_url,
         cancel_url: payload.cancel_url,
-        user_id: None,
+        user_id: Some(user_id.to_string()),
     };
-
-    let session = stripe.create_checkout_session(request).await.map_err(|e| {
-        tracing::error!("Checkout error: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.user_message().into(),
-                code: "CHECKOUT_ERROR".into(),
-            }),
-        )
-    })?;
-
+    
+    let session = stripe.create_checkout_session(request).await?;
+    
     Ok(Json(CheckoutResponse {
         checkout_url: session.checkout_url,
         session_id: session.id,
@@ -334,72 +1064,234 @@ pub async fn create_checkout(
 /// Verify license key
 pub async fn verify_license(
     State(state): State<AppState>,
-    Json(payload): Json<VerifyLicenseRequest>,
+    Extension(user_id): Extension<UserId>,
+    ApiJson(payload): ApiJson<VerifyLicenseRequest>,
 ) -> Json<LicenseVerification> {
-    let key = LicenseKey::from_string(&payload.license_key);
+    let Ok(Some(key)) = request_license(&state, &user_id, payload.license_key.as_deref()) else {
+        state.metrics.license_verified("invalid");
+        return Json(LicenseVerification::invalid("License not found or invalid"));
+    };
     
     match state.license_store.get(&key) {
         Ok(Some(license)) if license.is_valid() => {
+            state.metrics.license_verified("valid");
             let remaining = license.plan.rate_limit().saturating_sub(license.usage_today);
             Json(LicenseVerification::valid(license.plan, remaining))
         }
-        _ => Json(LicenseVerification::invalid("License not found or invalid")),
+        Err(_) => {
+            state.metrics.license_verified("error");
+            Json(LicenseVerification::invalid("License not found or invalid"))
+        }
+        _ => {
+            state.metrics.license_verified("invalid");
+            Json(LicenseVerification::invalid("License not found or invalid"))
+        }
     }
 }
 
+/// Usage statement for a license (tokens and cost per model and member)
+pub async fn usage_statement(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiJson(payload): ApiJson<UsageStatementRequest>,
+) -> Result<Json<UsageStatement>, ApiError> {
+    let license = request_license(&state, &user_id, payload.license_key.as_deref())
+        .map_err(ApiError::from)?
+        .and_then(|key| state.license_store.get(&key).ok().flatten())
+        .ok_or_else(|| ApiError::not_found(ErrorCode::InvalidLicense, "License not found"))?;
+    let key = license.key.clone();
+    
+    let (from, to) = statement_period(payload.from, payload.to);
+    let mut records = state.license_store.usage_records(&key, from, to).map_err(|e| {
+        tracing::error!("Usage lookup error: {}", e);
+        ApiError::internal(ErrorCode::UsageError, e.user_message())
+    })?;
+    
+    // Members using their organization's license see their own usage (admins see all at /orgs/{id}/usage)
+    if !license.usable_by(user_id.as_str()) {
+        records.retain(|r| r.user_id.as_deref() == Some(user_id.as_str()));
+    }
+    
+    Ok(Json(UsageStatement::build(&license, &records, from, to)))
+}
+
+/// A statement's period: up to `to` (default now), from `from` (default the start of that month)
+pub(crate) fn statement_period(from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> (DateTime<Utc>, DateTime<Utc>) {
+    let to = to.unwrap_or_else(Utc::now);
+    let from = from.unwrap_or_else(|| {
+        to.date_naive()
+            .with_day(1)
+            .and_then(|d| d.and_hms_opt(0, 0, 0))
+            .map_or(to, |d| d.and_utc())
+    });
+    (from, to)
+}
+
 /// Stripe webhook handler
 pub async fn stripe_webhook(
     State(state): State<AppState>,
     headers: HeaderMap,
     body: String,
-) -> Result<StatusCode, (StatusCode, Json<ErrorResponse>)> {
-    let stripe = state.stripe.as_ref().ok_or_else(|| {
-        (
-            StatusCode::SERVICE_UNAVAILABLE,
-            Json(ErrorResponse {
-                error: "Payments not configured".into(),
-                code: "PAYMENTS_DISABLED".into(),
-            }),
-        )
-    })?;
-
+) -> Result<StatusCode, ApiError> {
+    let stripe = state.stripe.as_ref().ok_or_else(payments_disabled)?;
+    
     let signature = headers
         .get("stripe-signature")
         .and_then(|v| v.to_str().ok())
-        .ok_or_else(|| {
-            (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: "Missing Stripe signature".into(),
-                    code: "MISSING_SIGNATURE".into(),
-                }),
-            )
-        })?;
-
+        .ok_or_else(|| ApiError::bad_request(ErrorCode::MissingSignature, "Missing Stripe signature"))?;
+    
     let handler = WebhookHandler::new(state.license_store.clone());
     
     let event = handler.parse_event(&body, signature, stripe.webhook_secret())
         .map_err(|e| {
             tracing::warn!("Webhook signature failed: {}", e);
-            (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: "Invalid signature".into(),
-                    code: "INVALID_SIGNATURE".into(),
-                }),
-            )
+            state.metrics.webhook_event("unknown", "rejected");
+            ApiError::from(e)
         })?;
-
-    handler.handle(event).await.map_err(|e| {
+    
+    // `EventType` displays as its JSON string, quotes included
+    let event_type = event.type_.to_string().trim_matches('"').to_string();
+    let event = handler.handle(event).await.map_err(|e| {
         tracing::error!("Webhook processing error: {}", e);
-        (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: "Webhook processing failed".into(),
-                code: "WEBHOOK_ERROR".into(),
-            }),
-        )
+        state.metrics.webhook_event(&event_type, "failed");
+        ApiError::internal(ErrorCode::WebhookError, "Webhook processing failed")
     })?;
-
+    state.metrics.webhook_event(&event_type, "processed");
+    notifications::payment_failed(&state, &event);
+    
     Ok(StatusCode::OK)
 }
+
+fn payments_disabled() -> ApiError {
+    ApiError::new(StatusCode::SERVICE_UNAVAILABLE, ErrorCode::PaymentsDisabled, "Payments not configured")
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::{to_bytes, Body}, http::{header, Request, StatusCode}, Router};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    /// The REST API and a session token for a new account
+    fn api(dir: &std::path::Path) -> (Router, String) {
+        let state = AppState::for_tests(dir);
+        let user = state.users.register("alice", "correct horse").unwrap();
+        let (token, _) = state.tokens.issue(&user.id);
+        (crate::api_routes(&state).router.with_state(state), token)
+    }
+
+    async fn post_chat(router: &Router, token: &str, body: &str, last_event_id: Option<&str>) -> (StatusCode, serde_json::Value) {
+        let mut request = Request::post("/chat")
+            .header(header::AUTHORIZATION, format!("Bearer {}", token))
+            .header(header::CONTENT_TYPE, "application/json");
+        if let Some(id) = last_event_id {
+            request = request.header("last-event-id", id);
+        }
+        let response = router.clone().oneshot(request.body(Body::from(body.to_string())).unwrap()).await.unwrap();
+        let status = response.status();
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn test_chat_rejects_malformed_json() {
+        let dir = std::env::temp_dir().join(format!("handlers-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (router, token) = api(&dir);
+        
+        let (status, body) = post_chat(&router, &token, "{\"message\": ", None).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "INVALID_REQUEST");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    /// Answers with the next scripted response, streamed in one chunk
+    struct ScriptedProvider(std::sync::Mutex<Vec<&'static str>>);
+
+    #[async_trait::async_trait]
+    impl agent_core::LlmProvider for ScriptedProvider {
+        async fn info(&self) -> agent_core::Result<agent_core::provider::ProviderInfo> {
+            Err(agent_core::AgentError::Other("unused".into()))
+        }
+        
+        async fn health_check(&self) -> agent_core::Result<bool> {
+            Ok(true)
+        }
+        
+        async fn complete(&self, _: &[agent_core::Message], options: &GenerationOptions) -> agent_core::Result<agent_core::provider::Completion> {
+            let content = self.0.lock().unwrap().remove(0).to_string();
+            Ok(agent_core::provider::Completion {
+                content,
+                model: options.model.clone(),
+                usage: Some(TokenUsage { prompt_tokens: 10, completion_tokens: 5, total_tokens: 15 }),
+                truncated: false,
+                finish_reason: None,
+                escalations: Vec::new(),
+            })
+        }
+        
+        async fn complete_stream(&self, messages: &[agent_core::Message], options: &GenerationOptions) -> agent_core::Result<agent_core::provider::CompletionStream> {
+            let chunk = agent_core::provider::StreamChunk::from(self.complete(messages, options).await?);
+            Ok(Box::pin(futures::stream::iter([Ok(chunk)])))
+        }
+        
+        async fn list_models(&self) -> agent_core::Result<Vec<agent_core::provider::ModelInfo>> {
+            Ok(Vec::new())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_stream_sends_tool_events_then_done() {
+        let dir = std::env::temp_dir().join(format!("handlers-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let mut state = AppState::for_tests(&dir);
+        state.provider = Arc::new(ScriptedProvider(std::sync::Mutex::new(vec![
+            "```tool\n{\"name\": \"datetime\", \"arguments\": {}}\n```",
+            "It's late.",
+        ])));
+        let mut tools = ToolRegistry::new();
+        tools.register(agent_core::tool::DateTimeTool);
+        state.tools = Arc::new(tools);
+        state.prompts.register(agent_core::prompt::PromptTemplate::new(ASSISTANT_PROMPT, "v1", "Tools: {{tools}}"));
+        
+        let user = state.users.register("alice", "correct horse").unwrap();
+        let entitlement = resolve(&state, &user.id, None).unwrap();
+        let admission = entitlement.admit(&state).unwrap();
+        let request: ChatRequest = serde_json::from_str(r#"{"message": "What time is it?"}"#).unwrap();
+        let mut events = chat_events(state.clone(), request, entitlement, admission);
+        
+        let mut names = Vec::new();
+        let mut answer = String::new();
+        while let Some(event) = events.recv().await {
+            names.push(event.name());
+            match event {
+                StreamEvent::Chunk { content, .. } => answer.push_str(&content),
+                StreamEvent::ToolCall { name, .. } | StreamEvent::ToolResult { name, .. } => assert_eq!(name, "datetime"),
+                StreamEvent::Done { usage, conversation_id, .. } => {
+                    assert_eq!(usage.total_tokens, 30);
+                    let session = state.sessions.load(&SessionId::from_string(&conversation_id)).unwrap().unwrap();
+                    assert!(session.conversation.messages().iter().any(|m| m.content == "It's late."));
+                }
+                StreamEvent::Error { error, .. } => panic!("{}", error),
+            }
+        }
+        assert_eq!(names.first(), Some(&"tool_call"));
+        assert_eq!(names[1], "tool_result");
+        assert_eq!(names.last(), Some(&"done"));
+        assert_eq!(answer, "It's late.");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_chat_rejects_last_event_id() {
+        let dir = std::env::temp_dir().join(format!("handlers-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let (router, token) = api(&dir);
+        
+        let (status, body) = post_chat(&router, &token, r#"{"message": "Hi"}"#, Some("stream:3")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body["code"], "INVALID_REQUEST");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -5,49 +5,169 @@
 //! This version includes crypto-advisor tools for cryptocurrency
 //! investment guidance with DCA and risk management.
 
+mod alerts;
+mod auth;
+mod cli;
+mod config;
+mod dca;
+mod error;
 mod handlers;
+mod notifications;
+mod openai;
+mod openapi;
+mod orgs;
+mod portfolios;
+mod quota;
+mod scheduler;
+mod shutdown;
 mod state;
+mod stream;
+mod telemetry;
+mod tls;
 
 use std::sync::Arc;
+use std::time::Duration;
 
-use axum::{routing::{get, post}, Router};
+use axum::{middleware, routing::{delete, get, post, put}, Router};
 use tower_http::{
-    cors::{Any, CorsLayer},
+    cors::{AllowOrigin, Any, CorsLayer},
     trace::TraceLayer,
 };
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
-use agent_core::tool::{CalculatorTool, DateTimeTool, ToolRegistry};
-use agent_payments::{MemoryLicenseStore, StripeClient};
-use agent_runtime::OllamaProvider;
+use agent_core::{
+    cascade::{CascadeConfig, CascadeProvider},
+    memory::{MemoryConfig, SemanticMemory},
+    metrics::{MeteredProvider, MeteredTool},
+    prompt::{PromptRegistry, PromptTemplate, BUILTIN_VERSION},
+    resilience::{ResilienceConfig, ResilientProvider},
+    safety::{ContentFilter, FileAudit, SafeSessionStore, SafetyPolicy, SafetyProvider},
+    session::MemorySessionStore,
+    tagging::SessionTagger,
+    tool::{CalculatorTool, DateTimeTool, ToolRegistry},
+    EmbeddingProvider, LlmProvider, VectorIndex,
+};
+use agent_notify::{
+    EmailNotifier, Inbox, InboxNotifier, MemoryInbox, MemoryPreferenceStore, NotificationService, Templates, WebhookNotifier,
+    DEFAULT_INBOX_CAPACITY,
+};
+use agent_payments::{ConcurrencyLimiter, DailyQuota, MemoryLicenseStore, MemoryOrgStore, StripeClient};
+use agent_runtime::{OllamaEmbeddings, OllamaProvider};
 
 // Import crypto-advisor tools
 use crypto_advisor::{
-    tools::{PriceLookupTool, DCACalculatorTool, RiskAnalyzerTool, PortfolioTrackerTool},
+    tools::{PriceLookupTool, DCACalculatorTool, RiskAnalyzerTool, PortfolioTrackerTool, KnowledgeSearchTool, PriceAlertTool},
     exchange::MockExchangeClient,
+    AlertEngine, AlertStore, KnowledgeBase, MemoryAlertStore, MemoryPortfolioStore, PortfolioStore,
 };
 
 use crate::handlers::{
+    ASSISTANT_PROMPT,
     chat_handler, chat_stream_handler, create_checkout, health_check, 
-    stripe_webhook, verify_license, list_models,
+    stripe_webhook, usage_statement, verify_license, list_models,
+    list_sessions, export_session, import_session, share_session, export_user_data,
 };
+use crate::auth::{MemoryUserStore, SessionTokens};
+use crate::config::{Config, LiveSettings, Settings};
+use crate::scheduler::JobStore;
+use crate::shutdown::Shutdown;
+use crate::openapi::Routes;
 use crate::state::AppState;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize tracing
-    tracing_subscriber::registry()
-        .with(tracing_subscriber::EnvFilter::new(
-            std::env::var("RUST_LOG").unwrap_or_else(|_| "info,tower_http=debug".into()),
-        ))
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
     // Load environment
     dotenvy::dotenv().ok();
-
-    // Initialize LLM provider
-    let provider = Arc::new(OllamaProvider::from_env());
+    
+    // Configuration: TOML file, then environment, then flags (maintenance
+    // subcommands take their own arguments)
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let index_knowledge = args.first().map(String::as_str) == Some("index-knowledge");
+    let config = Config::load(if index_knowledge { &[] } else { &args })?;
+    
+    // Initialize tracing (logs, and OTLP export when configured)
+    let telemetry = telemetry::init(&config.telemetry)?;
+    
+    // Maintenance subcommands run and exit
+    if index_knowledge {
+        return cli::index_knowledge(&args[1..], &config).await;
+    }
+    
+    // Metrics for /metrics, recorded by the provider stack, tools, agents and handlers
+    let metrics = Arc::new(telemetry::ServerMetrics::new(config.telemetry.metrics_token.clone()));
+    
+    // Initialize LLM provider, wrapped with timeouts, retries and limits
+    let ollama_config = config.provider.ollama();
+    let resilience = ResilienceConfig::default()
+        .request_timeout(ollama_config.timeout())
+        .stream_timeouts(ollama_config.timeout(), Duration::from_secs(30));
+    let ollama = OllamaProvider::from_config(ollama_config);
+    
+    // Semantic memory shares the Ollama connection, using an embedding model
+    let memory_path = &config.storage.memory_path;
+    let memory = match VectorIndex::open(memory_path) {
+        Ok(index) => {
+            tracing::info!("✓ Semantic memory: {} ({} facts)", memory_path.display(), index.len());
+            Some(Arc::new(SemanticMemory::new(
+                Arc::new(OllamaEmbeddings::new(&ollama, config.provider.embedding_model.clone())),
+                index,
+                MemoryConfig::default(),
+            )))
+        }
+        Err(e) => {
+            tracing::warn!("⚠ Semantic memory disabled - failed to load {}: {}", memory_path.display(), e);
+            None
+        }
+    };
+    
+    // Knowledge base, queried with the same embedding model it was indexed with
+    let knowledge_path = &config.tools.knowledge_index_path;
+    let knowledge = match KnowledgeBase::load(knowledge_path) {
+        Ok(knowledge) => {
+            tracing::info!("✓ Knowledge base: {} chunks from {} documents", knowledge.len(), knowledge.source_count());
+            let embedder = knowledge.embedding_model().map(|model| {
+                Arc::new(OllamaEmbeddings::new(&ollama, model)) as Arc<dyn EmbeddingProvider>
+            });
+            Some((knowledge, embedder))
+        }
+        Err(e) => {
+            tracing::warn!("⚠ Knowledge base not loaded from {}: {}", knowledge_path.display(), e);
+            tracing::warn!("  Build it with: agent-server index-knowledge");
+            None
+        }
+    };
+    
+    // Metered outside the retries, so a retried call counts once
+    let mut provider: Arc<dyn LlmProvider> = Arc::new(
+        MeteredProvider::new(Arc::new(ResilientProvider::new(Arc::new(ollama), resilience)), metrics.agent.clone())
+            .with_models(config.models()),
+    );
+    
+    // Optional model cascade: cheapest model first, escalate when verification fails
+    let cascade_models = &config.provider.cascade_models;
+    let cascade_alias = if cascade_models.is_empty() {
+        None
+    } else {
+        tracing::info!("✓ Model cascade: {}", cascade_models.join(" → "));
+        let cascade = CascadeConfig::new(cascade_models.clone());
+        let alias = cascade.alias.clone();
+        provider = Arc::new(CascadeProvider::new(provider, cascade));
+        Some(alias)
+    };
+    
+    // Content safety: screen prompts and completions for secrets and PII
+    let safety_policy = SafetyPolicy::parse(&config.safety.policy)?;
+    let mut safety = ContentFilter::new(safety_policy);
+    if let Some(path) = &config.safety.audit_log {
+        match FileAudit::open(path) {
+            Ok(audit) => {
+                tracing::info!("✓ Safety audit log: {}", path.display());
+                safety = safety.with_audit(Arc::new(audit));
+            }
+            Err(e) => tracing::warn!("⚠ Safety audit log {} not opened: {} - logging only", path.display(), e),
+        }
+    }
+    let safety = Arc::new(safety);
+    provider = Arc::new(SafetyProvider::new(provider, safety.clone()));
     
     // Verify Ollama connection
     match provider.health_check().await {
@@ -65,11 +185,11 @@ async fn main() -> anyhow::Result<()> {
             tracing::warn!("  Make sure Ollama is running: ollama serve");
         }
     }
-
+    
     // Initialize exchange client for crypto tools
     let exchange: Arc<dyn crypto_advisor::exchange::ExchangeClient> = 
         Arc::new(MockExchangeClient::new());
-
+    
     // Initialize tools
     let mut tools = ToolRegistry::new();
     
@@ -81,16 +201,156 @@ async fn main() -> anyhow::Result<()> {
     tools.register(PriceLookupTool::new(exchange.clone()));
     tools.register(DCACalculatorTool::new(exchange.clone()));
     tools.register(RiskAnalyzerTool::new(exchange.clone()));
-    tools.register(PortfolioTrackerTool::new(exchange.clone()));
+    
+    // Portfolios persist in SQLite when storage.portfolio_db_path is set (in memory otherwise;
+    // validation rejects the path in builds without the `sqlite` feature)
+    let portfolios: Arc<dyn PortfolioStore> = match &config.storage.portfolio_db_path {
+        #[cfg(feature = "sqlite")]
+        Some(path) => {
+            tracing::info!("✓ Portfolios stored in {}", path.display());
+            Arc::new(crypto_advisor::store::SqlitePortfolioStore::open(path)?)
+        }
+        _ => Arc::new(MemoryPortfolioStore::new()),
+    };
+    
+    // Requests get a copy of this tracker scoped to the signed-in user
+    tools.register(PortfolioTrackerTool::with_store(exchange.clone(), portfolios.clone()));
+    
+    // Price alerts, kept across restarts (also scoped per request)
+    let alerts: Arc<dyn AlertStore> = Arc::new(MemoryAlertStore::open(&config.storage.alerts_path)?);
+    let active_alerts = alerts.active()?.len();
+    if active_alerts > 0 {
+        tracing::info!("✓ Alerts: {} active ({})", active_alerts, config.storage.alerts_path.display());
+    }
+    tools.register(PriceAlertTool::new(alerts.clone()));
+    
+    if let Some((knowledge, embedder)) = knowledge {
+        let mut tool = KnowledgeSearchTool::new(Arc::new(knowledge));
+        if let Some(embedder) = embedder {
+            tool = tool.with_embedder(embedder);
+        }
+        tools.register(tool);
+    }
+    
+    // Tools switched off in the configuration
+    config.validate_tools(&tools.names())?;
+    tools.retain(|name| !config.tools.disabled.iter().any(|d| d == name));
+    tools.wrap(|tool| Arc::new(MeteredTool::new(tool, metrics.agent.clone())));
     
     tracing::info!("Registered {} tools:", tools.len());
     for name in tools.names() {
         tracing::info!("  • {}", name);
     }
-
+    
+    // Prompt templates: built-ins, overridden by versioned files in PROMPTS_DIR
+    let mut prompts = PromptRegistry::new();
+    prompts.register(PromptTemplate::new(
+        ASSISTANT_PROMPT,
+        BUILTIN_VERSION,
+        "You are a helpful AI assistant with access to tools ({{tools}}). Use them when needed.\n\n{{> user_context}}",
+    ));
+    crypto_advisor::register_prompts(&prompts);
+    
+    let prompts_dir = config.storage.prompts_dir.clone();
+    match prompts.load_dir(&prompts_dir) {
+        Ok(()) => {
+            for name in prompts.names() {
+                tracing::info!("  Prompt {}: {}", name, prompts.versions(&name).join(", "));
+            }
+        }
+        Err(e) => tracing::warn!("⚠ Prompt templates not loaded from {}: {} - using built-ins", prompts_dir.display(), e),
+    }
+    let prompts = Arc::new(prompts);
+    
+    // Hot reload: poll the prompt directory for changes
+    let reload_secs = config.storage.prompt_reload_secs;
+    if reload_secs > 0 {
+        let prompts = prompts.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_secs(reload_secs));
+            loop {
+                interval.tick().await;
+                match prompts.reload_if_changed() {
+                    Ok(true) => tracing::info!("✓ Reloaded prompt templates from {}", prompts_dir.display()),
+                    Ok(false) => {}
+                    Err(e) => tracing::warn!("⚠ Prompt reload failed, keeping previous templates: {}", e),
+                }
+            }
+        });
+    }
+    
+    // Per-request settings (models, budgets, plan limits, prices, CORS), reloaded on SIGHUP
+    let settings = Arc::new(LiveSettings::new(Settings::from_config(&config, cascade_alias.as_deref())?));
+    if let Some(model) = &settings.current().guardrail_critic_model {
+        tracing::info!("✓ Guardrail critic: {}", model);
+    }
+    
+    // Conversation history, redacted by the safety filter before it is stored
+    let sessions = Arc::new(SafeSessionStore::new(MemorySessionStore::new(), safety.clone()));
+    
+    // Automatic conversation titles and tags (off unless TAGGING_MODEL is set)
+    let tagger = config.provider.tagging_model.clone().map(|model| {
+        tracing::info!("✓ Session tagging: {}", model);
+        Arc::new(SessionTagger::new(provider.clone(), model).instructions(crypto_advisor::TAGGING_INSTRUCTIONS))
+    });
+    
+    // Authentication: accounts, API keys and signed login sessions
+    let session_ttl = chrono::Duration::hours(config.auth.session_hours);
+    let tokens = match config.auth.secret.clone() {
+        Some(secret) => SessionTokens::new(secret, session_ttl),
+        None => {
+            tracing::warn!("⚠ AUTH_SECRET not set - login sessions end when the server restarts");
+            SessionTokens::ephemeral(session_ttl)
+        }
+    };
+    let tokens = tokens.secure_cookie(config.auth.secure_cookie);
+    let users = MemoryUserStore::open(&config.storage.users_path)?;
+    if !users.is_empty() {
+        tracing::info!("✓ Accounts: {} ({})", users.len(), config.storage.users_path.display());
+    }
+    
+    // Scheduled DCA jobs, kept across restarts
+    let jobs = JobStore::open(&config.storage.dca_jobs_path)?;
+    let active_jobs = jobs.active().len();
+    if active_jobs > 0 {
+        tracing::info!("✓ DCA jobs: {} active ({})", active_jobs, config.storage.dca_jobs_path.display());
+    }
+    
+    // Notifications: in-app inbox, plus email and webhooks when configured; inboxes and
+    // preferences (opt-outs included) are kept across restarts
+    let inbox: Arc<dyn Inbox> = Arc::new(MemoryInbox::open(&config.storage.inbox_path, DEFAULT_INBOX_CAPACITY)?);
+    let preferences = Arc::new(MemoryPreferenceStore::open(&config.storage.notification_preferences_path)?);
+    let templates = match &config.notifications.templates_dir {
+        Some(dir) => {
+            let templates = Templates::load_dir(dir)?;
+            tracing::info!("✓ Notification templates: {}", dir.display());
+            templates
+        }
+        None => Templates::builtin(),
+    };
+    let mut notifications = NotificationService::new(templates, preferences)
+        .with_notifier(Arc::new(InboxNotifier::new(inbox.clone())))
+        .with_notifier(Arc::new(WebhookNotifier::new().with_attempts(config.notifications.webhook_attempts)));
+    match &config.notifications.smtp {
+        Some(smtp) => {
+            notifications = notifications.with_notifier(Arc::new(EmailNotifier::new(&smtp.to_config())?));
+            tracing::info!("✓ Email notifications via {}:{}", smtp.host, smtp.port);
+        }
+        None => tracing::info!("  Email notifications disabled (set SMTP_HOST to enable)"),
+    }
+    
     // Initialize payments
     let license_store = Arc::new(MemoryLicenseStore::new());
-    let stripe = StripeClient::from_env().ok();
+    
+    // Organizations sharing Team licenses, kept across restarts
+    let orgs = MemoryOrgStore::open(&config.storage.orgs_path)?;
+    if !orgs.is_empty() {
+        tracing::info!("✓ Organizations: {} ({})", orgs.len(), config.storage.orgs_path.display());
+    }
+    let stripe = match (&config.stripe.secret_key, &config.stripe.webhook_secret) {
+        (Some(secret_key), Some(webhook_secret)) => Some(StripeClient::new(secret_key, webhook_secret)),
+        _ => None,
+    };
     
     if stripe.is_some() {
         tracing::info!("✓ Stripe configured");
@@ -98,61 +358,271 @@ async fn main() -> anyhow::Result<()> {
         tracing::warn!("⚠ Stripe not configured - payments disabled");
         tracing::warn!("  Set STRIPE_SECRET_KEY and STRIPE_WEBHOOK_SECRET in .env");
     }
-
+    
     // Build application state
     let state = AppState {
         provider,
+        settings: settings.clone(),
         tools: Arc::new(tools),
+        exchange,
+        portfolios,
+        jobs: Arc::new(jobs),
+        alerts,
+        prompts,
+        safety,
+        sessions,
+        tagger,
+        streams: Arc::new(stream::StreamBuffer::new()),
+        memory,
+        users: Arc::new(users),
+        tokens: Arc::new(tokens),
         license_store,
+        orgs: Arc::new(orgs),
+        free_quota: Arc::new(DailyQuota::new()),
+        concurrency: ConcurrencyLimiter::new(),
         stripe: stripe.map(Arc::new),
+        notifications: Arc::new(notifications),
+        inbox,
+        metrics,
+        shutdown: Arc::new(Shutdown::new()),
     };
-
-    // CORS configuration
+    let shutdown = state.shutdown.clone();
+    let flush_state = state.clone();
+    
+    // DCA scheduler: makes purchases as they come due (stops when draining starts)
+    if config.scheduler.tick_secs > 0 {
+        scheduler::spawn(
+            state.clone(),
+            Duration::from_secs(config.scheduler.tick_secs),
+            chrono::Duration::from_std(Duration::from_secs(config.scheduler.missed_after_secs))?,
+        );
+    }
+    
+    // Alert engine: evaluates alerts against current prices (stops when draining starts)
+    if config.alerts.tick_secs > 0 {
+        let engine = AlertEngine::new(state.exchange.clone(), state.portfolios.clone(), state.alerts.clone());
+        alerts::spawn(state.clone(), Arc::new(engine), Duration::from_secs(config.alerts.tick_secs));
+    }
+    
+    // License expiry reminders
+    if config.notifications.license_notice_days > 0 {
+        notifications::spawn_license_reminders(
+            state.clone(),
+            Duration::from_secs(config.notifications.license_check_secs),
+            config.notifications.license_notice_days,
+        );
+    }
+    
+    // CORS: only the configured origins (checked per request, so reloads apply)
+    if config.server.cors_origins.is_empty() {
+        tracing::warn!("⚠ No CORS origins configured - browsers on other origins are refused (set CORS_ORIGINS, or * for any)");
+    }
+    let cors_settings = settings.clone();
     let cors = CorsLayer::new()
-        .allow_origin(Any)
+        .allow_origin(AllowOrigin::predicate(move |origin, _| cors_settings.current().allows_origin(origin)))
         .allow_methods(Any)
         .allow_headers(Any);
-
+    
+    // REST API, served under /api/v1 (and /api, for clients from before versioning)
+    let api = api_routes(&state).router;
+    
+    // OpenAI-compatible API (bearer license keys)
+    let openai_api = Router::new()
+        .route("/v1/chat/completions", post(openai::chat_completions))
+        .route("/v1/models", get(openai::list_models))
+        .route_layer(middleware::from_fn_with_state(state.clone(), openai::require_license));
+    
     // Build router
-    let app = Router::new()
-        // Health & info
+    let mut app = Router::new()
+        // Liveness and readiness (unready while draining)
         .route("/health", get(health_check))
-        .route("/api/models", get(list_models))
+        .route("/ready", get(shutdown::ready_handler))
         
-        // Agent API
-        .route("/api/chat", post(chat_handler))
-        .route("/api/chat/stream", get(chat_stream_handler))
+        .nest(openapi::API_PREFIX, api.clone())
+        .nest("/api", api)
+        .merge(openai_api)
         
         // Payments
-        .route("/api/checkout", post(create_checkout))
-        .route("/api/license/verify", post(verify_license))
-        .route("/webhook/stripe", post(stripe_webhook))
+        .route("/webhook/stripe", post(stripe_webhook));
+    
+    // Prometheus scrape endpoint
+    if config.telemetry.metrics {
+        app = app.route("/metrics", get(telemetry::metrics_handler));
+    }
+    
+    let app = app
+        // Request metrics by route (static files are not tracked)
+        .route_layer(middleware::from_fn_with_state(state.clone(), telemetry::track_requests))
         
         // Static files (WASM frontend)
-        .nest_service("/", tower_http::services::ServeDir::new("static"))
+        .nest_service("/", tower_http::services::ServeDir::new(&config.server.static_dir))
         
         .layer(cors)
+        .layer(middleware::from_fn_with_state(state.clone(), shutdown::track_in_flight))
         .layer(TraceLayer::new_for_http())
         .with_state(state);
-
-    // Start server
-    let addr = std::env::var("BIND_ADDR").unwrap_or_else(|_| "0.0.0.0:3000".into());
+    
+    // Start server (HTTPS when TLS is configured)
+    let addr = config.server.bind_addr.clone();
     let listener = tokio::net::TcpListener::bind(&addr).await?;
+    let tls = config.server.tls.as_ref().map(tls::acceptor).transpose()?;
+    let scheme = if tls.is_some() { "https" } else { "http" };
+    
+    config::reload_on_sighup(args, config.clone(), cascade_alias, settings);
     
     tracing::info!("══════════════════════════════════════════════════");
-    tracing::info!("🚀 rust-agent server running on http://{}", addr);
+    tracing::info!("🚀 rust-agent server running on {}://{}", scheme, addr);
     tracing::info!("══════════════════════════════════════════════════");
     tracing::info!("");
-    tracing::info!("Endpoints:");
+    tracing::info!("Endpoints (REST API under /api/v1, also served at /api):");
     tracing::info!("  GET  /health          - Health check");
-    tracing::info!("  GET  /api/models      - List available models");
-    tracing::info!("  POST /api/chat        - Send message");
-    tracing::info!("  GET  /api/chat/stream - WebSocket streaming");
-    tracing::info!("  POST /api/checkout    - Create Stripe checkout");
-    tracing::info!("  POST /api/license/verify - Verify license key");
+    tracing::info!("  GET  /ready           - Readiness (503 while shutting down)");
+    if config.telemetry.metrics {
+        tracing::info!("  GET  /metrics         - Prometheus metrics");
+    }
+    tracing::info!("  GET  /api/v1/openapi.json - OpenAPI document");
+    tracing::info!("  GET  /api/v1/models   - List available models");
+    tracing::info!("  POST /api/v1/auth/register - Create an account");
+    tracing::info!("  POST /api/v1/auth/login    - Sign in (session cookie + token)");
+    tracing::info!("  POST /api/v1/auth/logout   - Sign out");
+    tracing::info!("  GET  /api/v1/auth/me       - Account, API keys and license");
+    tracing::info!("  POST /api/v1/auth/keys     - Create an API key");
+    tracing::info!("  POST /api/v1/auth/keys/revoke - Revoke an API key");
+    tracing::info!("  POST /api/v1/chat        - Send message");
+    tracing::info!("  GET  /api/v1/chat/stream - WebSocket streaming");
+    tracing::info!("  POST /api/v1/chat/stream - Server-Sent Events streaming");
+    tracing::info!("  POST /api/v1/sessions/list   - List conversations (by tag)");
+    tracing::info!("  POST /api/v1/sessions/export - Export a conversation");
+    tracing::info!("  POST /api/v1/sessions/import - Import a conversation");
+    tracing::info!("  POST /api/v1/sessions/share  - Share a conversation with your organization");
+    tracing::info!("  POST /api/v1/users/export    - Export all of a user's data");
+    tracing::info!("  GET  /api/v1/portfolios  - List / POST create portfolios (?org= for shared ones)");
+    tracing::info!("  GET  /api/v1/portfolios/{{name}} - View a portfolio");
+    tracing::info!("  POST /api/v1/portfolios/{{name}}/positions - Add (DELETE .../{{symbol}} remove) a position");
+    tracing::info!("  GET  /api/v1/portfolios/{{name}}/transactions - List / POST record transactions");
+    tracing::info!("  POST /api/v1/portfolios/{{name}}/revalue - Reprice at market");
+    tracing::info!("  GET  /api/v1/dca/jobs    - List / POST schedule DCA purchases");
+    tracing::info!("  GET  /api/v1/dca/jobs/{{id}} - View (DELETE cancel) a DCA job");
+    tracing::info!("  GET  /api/v1/alerts      - List / POST set price and portfolio alerts");
+    tracing::info!("  GET  /api/v1/alerts/{{id}} - View (DELETE) an alert");
+    tracing::info!("  GET  /api/v1/notifications - Inbox (POST .../{{id}}/read, .../read-all)");
+    tracing::info!("  GET  /api/v1/notifications/preferences - View / PUT channels and opt-outs");
+    tracing::info!("  POST /api/v1/orgs        - Create an organization (POST /orgs/join to join one)");
+    tracing::info!("  GET  /api/v1/orgs/{{id}}   - View (DELETE) an organization, its members and seats");
+    tracing::info!("  POST /api/v1/orgs/{{id}}/invites - Invite a member (DELETE .../{{token}} revoke)");
+    tracing::info!("  PUT  /api/v1/orgs/{{id}}/members/{{user_id}} - Change a role (DELETE remove, or leave)");
+    tracing::info!("  GET  /api/v1/orgs/{{id}}/usage - Usage of the organization's license by member");
+    tracing::info!("  POST /api/v1/checkout    - Create Stripe checkout");
+    tracing::info!("  POST /api/v1/license/verify - Verify license key");
+    tracing::info!("  POST /api/v1/license/usage  - Usage statement");
+    tracing::info!("  POST /v1/chat/completions - OpenAI-compatible chat");
+    tracing::info!("  GET  /v1/models           - OpenAI-compatible model list");
     tracing::info!("");
     
-    axum::serve(listener, app).await?;
+    // Graceful shutdown: drain on SIGTERM/Ctrl-C, then wait for running requests
+    let drain_delay = Duration::from_secs(config.server.drain_delay_secs);
+    let grace = Duration::from_secs(config.server.shutdown_grace_secs);
+    shutdown::drain_on_signal(shutdown.clone(), drain_delay);
+    let closed = shutdown::listener_closed(shutdown.clone(), drain_delay);
+    let server = async {
+        match tls {
+            Some(acceptor) => {
+                axum::serve(tls::TlsListener::new(listener, acceptor)?, app).with_graceful_shutdown(closed).await
+            }
+            None => axum::serve(listener, app).with_graceful_shutdown(closed).await,
+        }
+    };
+    shutdown.run(server, drain_delay, grace).await?;
     
+    // Flush stores and export the last spans
+    shutdown::flush(&flush_state);
+    tracing::info!("Shutdown complete");
+    telemetry.shutdown();
     Ok(())
 }
+
+/// The REST API's routes (the OpenAPI document lists the same paths)
+fn api_routes(state: &AppState) -> Routes<AppState> {
+    // Routes that need a signed-in user (API key, bearer token or session cookie)
+    let protected = Routes::new()
+        // Agent API
+        .route(
+            "/chat",
+            post(chat_handler).route_layer(middleware::from_fn_with_state(state.clone(), quota::enforce)),
+        )
+        // SSE (POST, admitted like /chat) and WebSocket (GET, admits each message);
+        // the route layer only wraps the methods added before it
+        .route(
+            "/chat/stream",
+            post(stream::chat_sse_handler)
+                .route_layer(middleware::from_fn_with_state(state.clone(), quota::enforce))
+                .get(chat_stream_handler),
+        )
+        
+        // Conversation export/import and GDPR data access
+        .route("/sessions/list", post(list_sessions))
+        .route("/sessions/export", post(export_session))
+        .route("/sessions/import", post(import_session))
+        .route("/sessions/share", post(share_session))
+        .route("/users/export", post(export_user_data))
+        
+        // Portfolios
+        .route("/portfolios", get(portfolios::list_portfolios).post(portfolios::create_portfolio))
+        .route("/portfolios/{name}", get(portfolios::get_portfolio))
+        .route("/portfolios/{name}/positions", post(portfolios::add_position))
+        .route("/portfolios/{name}/positions/{symbol}", delete(portfolios::remove_position))
+        .route(
+            "/portfolios/{name}/transactions",
+            get(portfolios::list_transactions).post(portfolios::record_transaction),
+        )
+        .route("/portfolios/{name}/revalue", post(portfolios::revalue_portfolio))
+        
+        // Scheduled DCA purchases
+        .route("/dca/jobs", get(dca::list_jobs).post(dca::create_job))
+        .route("/dca/jobs/{id}", get(dca::get_job).delete(dca::cancel_job))
+        
+        // Price and portfolio alerts
+        .route("/alerts", get(alerts::list_alerts).post(alerts::create_alert))
+        .route("/alerts/{id}", get(alerts::get_alert).delete(alerts::delete_alert))
+        
+        // Notifications
+        .route("/notifications", get(notifications::list_notifications))
+        .route("/notifications/{id}/read", post(notifications::mark_read))
+        .route("/notifications/read-all", post(notifications::mark_all_read))
+        .route(
+            "/notifications/preferences",
+            get(notifications::get_preferences).put(notifications::set_preferences),
+        )
+        
+        // Organizations (Team plan seats, shared portfolios and conversations)
+        .route("/orgs", post(orgs::create_org))
+        .route("/orgs/join", post(orgs::join_org))
+        .route("/orgs/{id}", get(orgs::get_org).delete(orgs::delete_org))
+        .route("/orgs/{id}/invites", post(orgs::create_invite))
+        .route("/orgs/{id}/invites/{token}", delete(orgs::revoke_invite))
+        .route("/orgs/{id}/members/{user_id}", put(orgs::set_role).delete(orgs::remove_member))
+        .route("/orgs/{id}/usage", get(orgs::org_usage))
+        
+        // Account
+        .route("/auth/me", get(auth::account))
+        .route("/auth/keys", post(auth::create_api_key))
+        .route("/auth/keys/revoke", post(auth::revoke_api_key))
+        
+        // Payments
+        .route("/checkout", post(create_checkout))
+        .route("/license/verify", post(verify_license))
+        .route("/license/usage", post(usage_statement))
+        .map(|router| router.route_layer(middleware::from_fn_with_state(state.clone(), auth::require_user)));
+    
+    Routes::new()
+        // Info
+        .route("/models", get(list_models))
+        .route("/openapi.json", get(openapi::openapi_json))
+        
+        // Sign-up and login
+        .route("/auth/register", post(auth::register))
+        .route("/auth/login", post(auth::login))
+        .route("/auth/logout", post(auth::logout))
+        
+        .merge(protected)
+}
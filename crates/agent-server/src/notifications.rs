@@ -0,0 +1,318 @@
+//! Notifications API
+//!
+//! Users read their in-app inbox and choose how they're notified: channels
+//! per kind of notification (an empty list opts out), an email address and a
+//! webhook URL. Webhook payloads are signed with a secret the server issues
+//! when the URL is set; it is shown only in that response.
+//!
+//! Also sends the reminders nobody asks for: licenses about to expire
+//! (`notifications.license_notice_days`) and failed subscription payments.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::time::Duration;
+
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use agent_notify::{check_webhook_url, Channel, InboxItem, Notification, NotificationKind, Preferences, WebhookEndpoint};
+use agent_payments::{LicenseStore, WebhookEvent};
+
+use crate::auth::UserId;
+use crate::error::{ApiError, ApiJson, ApiPath, ApiQuery, ErrorCode};
+use crate::openapi::{ChannelName, NotificationKindName};
+use crate::state::AppState;
+
+/// Default and largest page of the inbox
+const DEFAULT_LIMIT: usize = 50;
+const MAX_LIMIT: usize = 200;
+
+// ============================================================================
+// Requests and Responses
+// ============================================================================
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct NotificationListQuery {
+    /// Only unread notifications
+    #[serde(default)]
+    pub unread: bool,
+    
+    /// Most notifications returned (default 50, at most 200)
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct NotificationView {
+    pub id: String,
+    #[schemars(with = "NotificationKindName")]
+    pub kind: NotificationKind,
+    pub subject: String,
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    pub read: bool,
+}
+
+impl From<InboxItem> for NotificationView {
+    fn from(item: InboxItem) -> Self {
+        Self {
+            id: item.id.to_string(),
+            kind: item.kind,
+            subject: item.subject,
+            body: item.body,
+            created_at: item.created_at,
+            read: item.read,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct NotificationListResponse {
+    /// Newest first
+    pub notifications: Vec<NotificationView>,
+    pub unread_count: usize,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct MarkReadResponse {
+    /// Notifications that were unread
+    pub marked: usize,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct PreferencesRequest {
+    /// Address for email notifications
+    #[serde(default)]
+    pub email: Option<String>,
+    
+    /// Endpoint for webhook notifications
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    
+    /// Issue a new webhook signing secret (one is issued whenever the URL changes)
+    #[serde(default)]
+    pub rotate_webhook_secret: bool,
+    
+    /// Channels for every kind without an override (default: in_app)
+    #[serde(default = "default_channels")]
+    #[schemars(with = "Vec<ChannelName>")]
+    pub channels: BTreeSet<Channel>,
+    
+    /// Per-kind channels; an empty list opts out of that kind
+    #[serde(default)]
+    #[schemars(with = "BTreeMap<String, Vec<ChannelName>>")]
+    pub kinds: BTreeMap<NotificationKind, BTreeSet<Channel>>,
+}
+
+fn default_channels() -> BTreeSet<Channel> {
+    Preferences::default().channels
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PreferencesView {
+    pub email: Option<String>,
+    pub webhook_url: Option<String>,
+    
+    /// The webhook signing secret; only returned when it was just issued
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub webhook_secret: Option<String>,
+    
+    #[schemars(with = "Vec<ChannelName>")]
+    pub channels: BTreeSet<Channel>,
+    
+    #[schemars(with = "BTreeMap<String, Vec<ChannelName>>")]
+    pub kinds: BTreeMap<NotificationKind, BTreeSet<Channel>>,
+    
+    /// Channels this server can deliver on
+    #[schemars(with = "Vec<ChannelName>")]
+    pub available_channels: Vec<Channel>,
+}
+
+impl PreferencesView {
+    fn new(state: &AppState, preferences: Preferences, webhook_secret: Option<String>) -> Self {
+        Self {
+            email: preferences.email,
+            webhook_url: preferences.webhook.map(|webhook| webhook.url),
+            webhook_secret,
+            channels: preferences.channels,
+            kinds: preferences.kinds,
+            available_channels: Channel::ALL.iter().copied().filter(|&channel| state.notifications.supports(channel)).collect(),
+        }
+    }
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+/// The caller's inbox, newest first
+pub async fn list_notifications(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiQuery(query): ApiQuery<NotificationListQuery>,
+) -> Result<Json<NotificationListResponse>, ApiError> {
+    let limit = query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT);
+    let items = state.inbox.list(user_id.as_str(), query.unread, limit)?;
+    Ok(Json(NotificationListResponse {
+        notifications: items.into_iter().map(NotificationView::from).collect(),
+        unread_count: state.inbox.unread_count(user_id.as_str())?,
+    }))
+}
+
+/// Mark one notification read
+pub async fn mark_read(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiPath(id): ApiPath<String>,
+) -> Result<StatusCode, ApiError> {
+    let not_found = || ApiError::not_found(ErrorCode::NotificationNotFound, format!("Notification not found: {}", id));
+    let uuid = Uuid::parse_str(&id).map_err(|_| not_found())?;
+    if state.inbox.mark_read(user_id.as_str(), uuid)? {
+        Ok(StatusCode::NO_CONTENT)
+    } else {
+        Err(not_found())
+    }
+}
+
+/// Mark the whole inbox read
+pub async fn mark_all_read(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+) -> Result<Json<MarkReadResponse>, ApiError> {
+    let marked = state.inbox.mark_all_read(user_id.as_str())?;
+    Ok(Json(MarkReadResponse { marked }))
+}
+
+/// The caller's notification preferences
+pub async fn get_preferences(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+) -> Result<Json<PreferencesView>, ApiError> {
+    let preferences = state.notifications.preferences().get(user_id.as_str())?;
+    Ok(Json(PreferencesView::new(&state, preferences, None)))
+}
+
+/// Replace the caller's notification preferences
+pub async fn set_preferences(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiJson(payload): ApiJson<PreferencesRequest>,
+) -> Result<Json<PreferencesView>, ApiError> {
+    let store = state.notifications.preferences();
+    let current = store.get(user_id.as_str())?;
+    
+    let mut issued = None;
+    let webhook = match payload.webhook_url.map(|url| url.trim().to_string()).filter(|url| !url.is_empty()) {
+        Some(url) => {
+            let allow_http = state.settings.current().allow_http_webhooks;
+            let secure = url.starts_with("https://") || (allow_http && url.starts_with("http://"));
+            if !secure {
+                return Err(ApiError::bad_request(ErrorCode::InvalidPreferences, "webhook_url must be an https:// URL"));
+            }
+            // Webhooks are delivered from inside our network; they must not point back into it
+            check_webhook_url(&url)
+                .await
+                .map_err(|e| ApiError::bad_request(ErrorCode::InvalidPreferences, format!("webhook_url rejected: {}", e)))?;
+            match current.webhook {
+                Some(endpoint) if endpoint.url == url && !payload.rotate_webhook_secret => Some(endpoint),
+                _ => {
+                    let secret = format!("whsec_{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple());
+                    issued = Some(secret.clone());
+                    Some(WebhookEndpoint { url, secret })
+                }
+            }
+        }
+        None => None,
+    };
+    
+    let preferences = Preferences {
+        email: payload.email.map(|email| email.trim().to_string()).filter(|email| !email.is_empty()),
+        webhook,
+        channels: payload.channels,
+        kinds: payload.kinds,
+    };
+    preferences.validate()?;
+    
+    let enabled = preferences.channels.iter().chain(preferences.kinds.values().flatten());
+    if let Some(channel) = enabled.copied().find(|&channel| !state.notifications.supports(channel)) {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidPreferences,
+            format!("{} notifications are not available on this server", channel),
+        ));
+    }
+    
+    store.set(user_id.as_str(), preferences.clone())?;
+    Ok(Json(PreferencesView::new(&state, preferences, issued)))
+}
+
+// ============================================================================
+// Billing Notices
+// ============================================================================
+
+/// Tell the license owner a subscription payment failed
+pub fn payment_failed(state: &AppState, event: &WebhookEvent) {
+    let WebhookEvent::PaymentFailed { subscription_id: Some(subscription_id), .. } = event else {
+        return;
+    };
+    let license = match state.license_store.get_by_subscription(subscription_id) {
+        Ok(Some(license)) => license,
+        Ok(None) => return,
+        Err(e) => {
+            tracing::warn!("⚠ License of {} not found for a payment notice: {}", subscription_id, e);
+            return;
+        }
+    };
+    let Some(user_id) = &license.user_id else {
+        tracing::info!(subscription_id = %subscription_id, "Payment failed for a license without an account");
+        return;
+    };
+    
+    // Stripe retries failed invoices; one notice a day is enough
+    let notification = Notification::new(user_id, NotificationKind::PaymentFailed)
+        .with("plan", license.plan.as_str())
+        .dedup(format!("payment-failed:{}:{}", subscription_id, Utc::now().date_naive()));
+    state.notifications.dispatch(notification);
+}
+
+/// Remind owners of licenses expiring within `notice_days`, every `interval`
+pub fn spawn_license_reminders(state: AppState, interval: Duration, notice_days: u32) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(interval);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                () = state.shutdown.draining() => break,
+            }
+            remind_expiring(&state, Utc::now(), notice_days);
+        }
+    });
+}
+
+fn remind_expiring(state: &AppState, now: DateTime<Utc>, notice_days: u32) {
+    let licenses = match state.license_store.expiring(now, now + chrono::Duration::days(i64::from(notice_days))) {
+        Ok(licenses) => licenses,
+        Err(e) => {
+            tracing::warn!("⚠ Expiring licenses not checked: {}", e);
+            return;
+        }
+    };
+    for license in licenses {
+        let (Some(user_id), Some(expires_at)) = (&license.user_id, license.expires_at) else {
+            continue;
+        };
+        let days = (expires_at - now).num_days().max(1);
+        // Once per license and expiry date
+        let notification = Notification::new(user_id, NotificationKind::LicenseExpiring)
+            .with("plan", license.plan.as_str())
+            .with("days", days)
+            .with("expires_at", expires_at.format("%Y-%m-%d"))
+            .dedup(format!("license-expiring:{}:{}", license.key, expires_at.date_naive()));
+        state.notifications.dispatch(notification);
+    }
+}
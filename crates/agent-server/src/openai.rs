@@ -0,0 +1,557 @@
+//! OpenAI-Compatible API
+//!
+//! `/v1/chat/completions` and `/v1/models` for OpenAI SDK clients and tools
+//! such as Continue or Open WebUI. A completion is an agent run with the
+//! server's tools (the crypto tools included, as far as the plan allows);
+//! tools sent by the client are ignored. Conversations aren't stored - the
+//! client sends the history with every request.
+//!
+//! Clients authenticate with their license key as the API key
+//! (`Authorization: Bearer XXXX-XXXX-XXXX-XXXX`); account API keys and
+//! session tokens work too. Errors use OpenAI's `{"error": {...}}` shape.
+
+use std::convert::Infallible;
+use std::future::Future;
+
+use axum::{
+    extract::{rejection::JsonRejection, Extension, FromRequest, Request, State},
+    http::{header, StatusCode},
+    middleware::Next,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
+    Json,
+};
+use chrono::Utc;
+use futures::{stream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tokio::sync::mpsc;
+
+use agent_core::{
+    prompt::PromptVars,
+    provider::{GenerationOptions, TokenUsage},
+    reasoning::{Agent, AgentConfig, AgentRun, RunEvent},
+    message::Conversation,
+    safety::Stage,
+    Message,
+};
+use agent_payments::{LicenseKey, LicenseStore};
+use crypto_advisor::CRYPTO_ADVISOR_TEMPLATE_NAME;
+
+use crate::auth::{self, UserId};
+use crate::error::ApiError;
+use crate::handlers::{charge_usage, user_tools};
+use crate::quota::{resolve, Admission, Entitlement, QuotaError};
+use crate::state::AppState;
+
+/// Interval of keep-alive comments while a streamed answer is being worked out
+const KEEP_ALIVE: std::time::Duration = std::time::Duration::from_secs(15);
+
+/// Run events buffered for a client that reads slower than the model writes
+const STREAM_BUFFER: usize = 64;
+
+// ============================================================================
+// Request/Response Types
+// ============================================================================
+
+#[derive(Debug, Deserialize)]
+pub struct ChatCompletionRequest {
+    #[serde(default)]
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(default)]
+    pub stream: bool,
+    #[serde(default)]
+    pub stream_options: Option<StreamOptions>,
+    #[serde(default)]
+    pub temperature: Option<f32>,
+    #[serde(default)]
+    pub top_p: Option<f32>,
+    #[serde(default, alias = "max_completion_tokens")]
+    pub max_tokens: Option<u32>,
+    #[serde(default)]
+    pub stop: Option<StopSequences>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct StreamOptions {
+    #[serde(default)]
+    pub include_usage: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum StopSequences {
+    One(String),
+    Many(Vec<String>),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    #[serde(default)]
+    pub content: Option<MessageContent>,
+    #[serde(default)]
+    pub tool_call_id: Option<String>,
+}
+
+/// Plain text, or content parts (only text parts are used)
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    Text(String),
+    Parts(Vec<ContentPart>),
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ContentPart {
+    #[serde(default)]
+    pub text: Option<String>,
+}
+
+impl MessageContent {
+    fn text(&self) -> String {
+        match self {
+            MessageContent::Text(text) => text.clone(),
+            MessageContent::Parts(parts) => parts.iter().filter_map(|p| p.text.as_deref()).collect::<Vec<_>>().join("\n"),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ChatCompletion {
+    pub id: String,
+    pub object: &'static str,
+    pub created: i64,
+    pub model: String,
+    pub choices: Vec<Choice>,
+    pub usage: TokenUsage,
+}
+
+#[derive(Serialize)]
+pub struct Choice {
+    pub index: u32,
+    pub message: AssistantMessage,
+    pub finish_reason: &'static str,
+}
+
+#[derive(Serialize)]
+pub struct AssistantMessage {
+    pub role: &'static str,
+    pub content: String,
+}
+
+#[derive(Serialize)]
+pub struct ModelList {
+    pub object: &'static str,
+    pub data: Vec<ModelObject>,
+}
+
+#[derive(Serialize)]
+pub struct ModelObject {
+    pub id: String,
+    pub object: &'static str,
+    pub created: i64,
+    pub owned_by: &'static str,
+}
+
+/// An OpenAI-style error response
+fn api_error(status: StatusCode, message: impl Into<String>, code: &str) -> Response {
+    let kind = match status {
+        StatusCode::BAD_REQUEST | StatusCode::UNPROCESSABLE_ENTITY => "invalid_request_error",
+        StatusCode::UNAUTHORIZED => "authentication_error",
+        StatusCode::FORBIDDEN => "permission_error",
+        StatusCode::TOO_MANY_REQUESTS => "rate_limit_error",
+        _ => "server_error",
+    };
+    let body = serde_json::json!({
+        "error": {"message": message.into(), "type": kind, "param": null, "code": code},
+    });
+    (status, Json(body)).into_response()
+}
+
+/// `Json` whose rejections (malformed body, wrong content type) use OpenAI's error shape
+#[derive(FromRequest)]
+#[from_request(via(Json), rejection(JsonError))]
+pub struct OpenAiJson<T>(pub T);
+
+/// A rejected request body
+pub struct JsonError(JsonRejection);
+
+impl From<JsonRejection> for JsonError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self(rejection)
+    }
+}
+
+impl IntoResponse for JsonError {
+    fn into_response(self) -> Response {
+        api_error(self.0.status(), self.0.body_text(), "invalid_json")
+    }
+}
+
+fn quota_error(e: QuotaError) -> Response {
+    let mut error = ApiError::from(e);
+    let mut response = api_error(error.status(), error.message(), &error.code().to_string());
+    response.headers_mut().extend(error.headers_mut().drain());
+    response
+}
+
+// ============================================================================
+// Authentication
+// ============================================================================
+
+/// The caller of a license nobody claimed, named by the key's hash
+///
+/// The id ends up in usage records, session owners and logs, so it must not give the key away.
+fn license_user(key: &LicenseKey) -> UserId {
+    UserId::from_string(format!("license:{}", hex::encode(Sha256::digest(key.as_str().as_bytes()))))
+}
+
+/// Middleware resolving the caller's plan from a bearer license key (or account credentials)
+pub async fn require_license(State(state): State<AppState>, mut request: Request, next: Next) -> Response {
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(|token| LicenseKey::from_string(token.trim()));
+    let license = match bearer.map(|key| state.license_store.get(&key)).transpose() {
+        Ok(license) => license.flatten(),
+        Err(e) => {
+            tracing::error!("License store error: {}", e);
+            return quota_error(QuotaError::Unavailable);
+        }
+    };
+    
+    let resolved = match license {
+        // A license nobody claimed stands for its own caller
+        Some(license) => {
+            let user_id = license.user_id.clone().map_or_else(|| license_user(&license.key), UserId::from_string);
+            resolve(&state, &user_id, Some(license.key.as_str()))
+        }
+        None => match auth::authenticate(&state, request.headers()) {
+            Some(user_id) => resolve(&state, &user_id, None),
+            None => {
+                let mut response = api_error(StatusCode::UNAUTHORIZED, "Invalid license key", "invalid_api_key");
+                response.headers_mut().insert(header::WWW_AUTHENTICATE, header::HeaderValue::from_static("Bearer"));
+                return response;
+            }
+        },
+    };
+    
+    match resolved {
+        Ok(entitlement) => {
+            request.extensions_mut().insert(entitlement);
+            next.run(request).await
+        }
+        Err(e) => quota_error(e),
+    }
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+/// Models the caller's plan may use
+pub async fn list_models(State(state): State<AppState>, Extension(entitlement): Extension<Entitlement>) -> Response {
+    let models = match state.provider.list_models().await {
+        Ok(models) => models,
+        Err(e) => return api_error(StatusCode::SERVICE_UNAVAILABLE, format!("Ollama unavailable: {}", e), "model_unavailable"),
+    };
+    
+    let mut ids: Vec<String> = models.into_iter().map(|m| m.id).collect();
+    let default_model = state.settings.current().default_model.clone();
+    if !ids.contains(&default_model) {
+        ids.insert(0, default_model);
+    }
+    let data = ids
+        .into_iter()
+        .filter(|id| entitlement.limits.allows_model(id))
+        .map(|id| ModelObject { id, object: "model", created: 0, owned_by: "rust-agent" })
+        .collect();
+    
+    Json(ModelList { object: "list", data }).into_response()
+}
+
+/// OpenAI chat completion, answered by the tool-using advisor agent
+pub async fn chat_completions(
+    State(state): State<AppState>,
+    Extension(entitlement): Extension<Entitlement>,
+    OpenAiJson(request): OpenAiJson<ChatCompletionRequest>,
+) -> Response {
+    let model = Some(request.model.trim()).filter(|m| !m.is_empty()).map_or_else(|| state.settings.current().default_model.clone(), String::from);
+    let admission = match entitlement.check_model(&model).and_then(|()| entitlement.admit(&state)) {
+        Ok(admission) => admission,
+        Err(e) => return quota_error(e),
+    };
+    
+    let (agent, mut conversation) = match completion_agent(&state, &entitlement, &request, &model) {
+        Ok(prepared) => prepared,
+        Err((status, message, code)) => return api_error(status, message, code),
+    };
+    
+    let id = format!("chatcmpl-{}", uuid::Uuid::new_v4().simple());
+    let created = Utc::now().timestamp();
+    let quota = admission.quota;
+    
+    let mut response = if request.stream {
+        let include_usage = request.stream_options.as_ref().is_some_and(|o| o.include_usage);
+        let (events, received) = mpsc::channel(STREAM_BUFFER);
+        let run = run_agent(state, entitlement, admission, agent, conversation, events);
+        Sse::new(completion_chunks(progress(run, received), id, created, model, include_usage))
+            .keep_alive(KeepAlive::new().interval(KEEP_ALIVE))
+            .into_response()
+    } else {
+        let _admission = admission;
+        match agent.run_detailed(&mut conversation).await {
+            Ok(run) => {
+                if let Some(key) = entitlement.license {
+                    charge_usage(&state, &entitlement.user_id, key, &run.model, run.usage);
+                }
+                Json(ChatCompletion {
+                    id,
+                    object: "chat.completion",
+                    created,
+                    model: run.model,
+                    choices: vec![Choice {
+                        index: 0,
+                        message: AssistantMessage { role: "assistant", content: run.content },
+                        finish_reason: "stop",
+                    }],
+                    usage: run.usage,
+                })
+                .into_response()
+            }
+            Err(e) => {
+                tracing::error!("Agent error: {}", e);
+                api_error(StatusCode::INTERNAL_SERVER_ERROR, e.user_message(), "agent_error")
+            }
+        }
+    };
+    
+    quota.apply(response.headers_mut());
+    response
+}
+
+/// The agent and conversation for a completion request
+///
+/// System (and `developer`) messages are added to the advisor's system prompt;
+/// the rest become the conversation, which must end with a user message.
+fn completion_agent(
+    state: &AppState,
+    entitlement: &Entitlement,
+    request: &ChatCompletionRequest,
+    model: &str,
+) -> Result<(Agent, Conversation), (StatusCode, String, &'static str)> {
+    let mut instructions = Vec::new();
+    let mut conversation = Conversation::new();
+    for message in &request.messages {
+        let content = message.content.as_ref().map(MessageContent::text).unwrap_or_default();
+        match message.role.as_str() {
+            "system" | "developer" => instructions.push(content),
+            "user" => conversation.push(Message::user(content)),
+            "assistant" => conversation.push(Message::assistant(content)),
+            "tool" => conversation.push(Message::tool(content, message.tool_call_id.clone())),
+            role => {
+                return Err((StatusCode::BAD_REQUEST, format!("Unsupported message role '{}'", role), "invalid_role"));
+            }
+        }
+    }
+    if conversation.last().map(|m| &m.role) != Some(&agent_core::Role::User) {
+        return Err((StatusCode::BAD_REQUEST, "The last message must be from the user".into(), "invalid_messages"));
+    }
+    
+    // Redact secrets before the model sees them
+    let screened = state
+        .safety
+        .screen_messages(conversation.messages(), Stage::Request, Some(entitlement.user_id.as_str()))
+        .map_err(|e| (StatusCode::UNPROCESSABLE_ENTITY, e.user_message(), "content_blocked"))?;
+    *conversation.messages_mut() = screened;
+    
+    let tools = user_tools(state, entitlement);
+    let prompt = state
+        .prompts
+        .render(CRYPTO_ADVISOR_TEMPLATE_NAME, None, Some(entitlement.user_id.as_str()), &PromptVars::new().tools(&tools))
+        .map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string(), "prompt_error"))?;
+    let system_prompt = std::iter::once(prompt.text).chain(instructions).collect::<Vec<_>>().join("\n\n");
+    
+    let defaults = GenerationOptions::default();
+    let config = AgentConfig {
+        system_prompt,
+        generation: GenerationOptions {
+            model: model.to_string(),
+            temperature: request.temperature.unwrap_or(defaults.temperature),
+            top_p: request.top_p.unwrap_or(defaults.top_p),
+            max_tokens: request.max_tokens.map_or(entitlement.limits.max_tokens, |n| n.min(entitlement.limits.max_tokens)),
+            stop_sequences: match &request.stop {
+                Some(StopSequences::One(stop)) => vec![stop.clone()],
+                Some(StopSequences::Many(stops)) => stops.clone(),
+                None => Vec::new(),
+            },
+            ..defaults
+        },
+        ..Default::default()
+    };
+    
+    let agent = Agent::new(state.provider.clone(), tools, config).with_metrics(state.metrics.agent.clone());
+    Ok((agent, conversation))
+}
+
+/// Run the agent for a streamed completion, holding the concurrency slot until it finishes
+///
+/// The answer's text and tool calls go to `events` as they happen.
+async fn run_agent(
+    state: AppState,
+    entitlement: Entitlement,
+    admission: Admission,
+    agent: Agent,
+    mut conversation: Conversation,
+    events: mpsc::Sender<RunEvent>,
+) -> agent_core::Result<AgentRun> {
+    let _admission = admission;
+    let run = agent.run_streaming(&mut conversation, &events).await?;
+    if let Some(key) = entitlement.license {
+        charge_usage(&state, &entitlement.user_id, key, &run.model, run.usage);
+    }
+    Ok(run)
+}
+
+/// What a streamed run has produced so far
+enum Progress {
+    Event(RunEvent),
+    Done(agent_core::Result<AgentRun>),
+}
+
+/// The events of `run` as they arrive, then its result
+///
+/// `run` owns the sender, so the channel closes once it's done and every
+/// event comes out before the result.
+fn progress(
+    run: impl Future<Output = agent_core::Result<AgentRun>> + Send + 'static,
+    events: mpsc::Receiver<RunEvent>,
+) -> impl Stream<Item = Progress> {
+    stream::unfold((Some(Box::pin(run)), None, events), |(mut run, mut result, mut events)| async move {
+        if let Some(running) = run.as_mut() {
+            let next = tokio::select! {
+                biased;
+                Some(event) = events.recv() => Ok(event),
+                done = running => Err(done),
+            };
+            match next {
+                Ok(event) => return Some((Progress::Event(event), (run, result, events))),
+                Err(done) => {
+                    run = None;
+                    result = Some(done);
+                }
+            }
+        }
+        match events.recv().await {
+            Some(event) => Some((Progress::Event(event), (run, result, events))),
+            None => result.take().map(|done| (Progress::Done(done), (run, None, events))),
+        }
+    })
+}
+
+/// `chat.completion.chunk` events for an agent run, then `[DONE]`
+///
+/// Tool calls run server-side; the answer's text is sent as the model writes
+/// it, and dropping the stream (client gone) cancels the run.
+fn completion_chunks(
+    progress: impl Stream<Item = Progress>,
+    id: String,
+    created: i64,
+    model: String,
+    include_usage: bool,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    let mut started = false;
+    progress.flat_map(move |progress| {
+        let chunk = |delta: serde_json::Value, finish_reason: Option<&str>, model: &str| {
+            serde_json::json!({
+                "id": id,
+                "object": "chat.completion.chunk",
+                "created": created,
+                "model": model,
+                "choices": [{"index": 0, "delta": delta, "finish_reason": finish_reason}],
+            })
+        };
+        let mut events = Vec::new();
+        let done = match progress {
+            Progress::Event(RunEvent::Delta(content)) if started => {
+                events.push(chunk(serde_json::json!({"content": content}), None, &model));
+                false
+            }
+            // The first chunk carries the role
+            Progress::Event(RunEvent::Delta(content)) => {
+                started = true;
+                events.push(chunk(serde_json::json!({"role": "assistant", "content": content}), None, &model));
+                false
+            }
+            Progress::Event(RunEvent::ToolCall(_) | RunEvent::ToolResult(_)) => false,
+            Progress::Done(Ok(run)) => {
+                if !started {
+                    events.push(chunk(serde_json::json!({"role": "assistant", "content": ""}), None, &run.model));
+                }
+                events.push(chunk(serde_json::json!({}), Some("stop"), &run.model));
+                if include_usage {
+                    events.push(serde_json::json!({
+                        "id": id,
+                        "object": "chat.completion.chunk",
+                        "created": created,
+                        "model": run.model,
+                        "choices": [],
+                        "usage": run.usage,
+                    }));
+                }
+                true
+            }
+            Progress::Done(Err(e)) => {
+                tracing::error!("Agent error: {}", e);
+                events.push(serde_json::json!({
+                    "error": {"message": e.user_message(), "type": "server_error", "param": null, "code": "agent_error"},
+                }));
+                true
+            }
+        };
+        
+        let events: Vec<Result<Event, Infallible>> = events
+            .into_iter()
+            .map(|event| Ok(Event::default().data(event.to_string())))
+            .chain(done.then(|| Ok(Event::default().data("[DONE]"))))
+            .collect();
+        stream::iter(events)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_license_user_hides_key() {
+        let key = LicenseKey::from_string("lic_secret");
+        let user = license_user(&key);
+        assert!(user.as_str().starts_with("license:"));
+        assert!(!user.as_str().contains("lic_secret"));
+        assert_eq!(user, license_user(&key));
+    }
+
+    #[tokio::test]
+    async fn test_progress_sends_deltas_before_result() {
+        let (events, received) = mpsc::channel(STREAM_BUFFER);
+        let run = async move {
+            for delta in ["It's ", "4."] {
+                events.send(RunEvent::Delta(delta.to_string())).await.unwrap();
+            }
+            Err(agent_core::AgentError::Other("done".into()))
+        };
+        
+        let progress: Vec<_> = progress(run, received).collect().await;
+        assert_eq!(progress.len(), 3);
+        assert!(matches!(&progress[0], Progress::Event(RunEvent::Delta(d)) if d == "It's "));
+        assert!(matches!(&progress[1], Progress::Event(RunEvent::Delta(d)) if d == "4."));
+        assert!(matches!(progress[2], Progress::Done(Err(_))));
+    }
+}
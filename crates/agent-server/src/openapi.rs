@@ -0,0 +1,644 @@
+//! OpenAPI Document
+//!
+//! `GET /api/openapi.json` describes the versioned REST API (`/api/v1`) as
+//! OpenAPI 3.0, so integrators can generate clients. Schemas are generated
+//! from the handlers' request and response types with `schemars`; the
+//! operations are listed in `document`, which a test checks against the
+//! routes of `main.rs` (`Routes` keeps their paths).
+//!
+//! Types of other crates that don't derive `JsonSchema` are described by the
+//! mirrors below, which only exist for the document.
+
+use std::sync::OnceLock;
+
+use axum::{routing::MethodRouter, Json, Router};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use schemars::{
+    generate::{SchemaGenerator, SchemaSettings},
+    JsonSchema, Schema,
+};
+use serde_json::{json, Map, Value};
+
+use crate::alerts::{AlertListResponse, AlertView, CreateAlertRequest};
+use crate::auth::{AccountResponse, ApiKeyRequest, ApiKeyResponse, CredentialsRequest, LoginResponse, RevokeApiKeyRequest, SESSION_COOKIE};
+use crate::dca::{CreateDcaJobRequest, DcaJobListResponse, DcaJobView};
+use crate::error::ErrorResponse;
+use crate::notifications::{MarkReadResponse, NotificationListResponse, PreferencesRequest, PreferencesView};
+use crate::orgs::{CreateOrgRequest, InviteRequest, InviteView, JoinRequest, OrgView, RoleRequest};
+use crate::portfolios::{
+    AddPositionRequest, CreatePortfolioRequest, PortfolioListResponse, PortfolioView, RevalueResponse, TransactionListResponse,
+    TransactionRequest, TransactionResponse,
+};
+use crate::handlers::{
+    ChatRequest, ChatResponse, CheckoutRequest, CheckoutResponse, ModelInfo, SessionExportRequest, SessionImportResponse,
+    SessionListRequest, SessionListResponse, SessionShareRequest, StreamEvent, UsageStatementRequest, UserExportRequest,
+    VerifyLicenseRequest,
+};
+
+/// Prefix of the versioned REST API
+pub const API_PREFIX: &str = "/api/v1";
+
+// ============================================================================
+// Routes
+// ============================================================================
+
+/// A router that keeps its paths, so they can be checked against the document
+pub struct Routes<S> {
+    pub router: Router<S>,
+    pub paths: Vec<&'static str>,
+}
+
+impl<S: Clone + Send + Sync + 'static> Routes<S> {
+    pub fn new() -> Self {
+        Self { router: Router::new(), paths: Vec::new() }
+    }
+    
+    pub fn route(mut self, path: &'static str, method_router: MethodRouter<S>) -> Self {
+        self.router = self.router.route(path, method_router);
+        self.paths.push(path);
+        self
+    }
+    
+    pub fn merge(mut self, other: Self) -> Self {
+        self.router = self.router.merge(other.router);
+        self.paths.extend(other.paths);
+        self
+    }
+    
+    /// Change the router (add a route layer, say), keeping the paths
+    pub fn map(mut self, f: impl FnOnce(Router<S>) -> Router<S>) -> Self {
+        self.router = f(self.router);
+        self
+    }
+}
+
+// ============================================================================
+// Schema Mirrors
+// ============================================================================
+
+/// Tokens used by a request
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+pub struct Usage {
+    pub prompt_tokens: u32,
+    pub completion_tokens: u32,
+    pub total_tokens: u32,
+}
+
+/// Subscription plan
+#[derive(JsonSchema)]
+#[serde(rename = "Plan", rename_all = "lowercase")]
+#[allow(dead_code)]
+pub enum PlanName {
+    Free,
+    Pro,
+    Team,
+}
+
+/// A member's role in an organization
+#[derive(JsonSchema)]
+#[serde(rename = "Role", rename_all = "lowercase")]
+#[allow(dead_code)]
+pub enum RoleName {
+    /// Uses the license, reads shared resources
+    Member,
+    /// Also invites and removes members and changes shared portfolios
+    Admin,
+    /// Holds the license; also manages admins and deletes the organization
+    Owner,
+}
+
+/// Side of a trade
+#[derive(JsonSchema)]
+#[serde(rename = "TradeSide", rename_all = "lowercase")]
+#[allow(dead_code)]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+/// Where a notification is delivered
+#[derive(JsonSchema)]
+#[serde(rename = "Channel", rename_all = "snake_case")]
+#[allow(dead_code)]
+pub enum ChannelName {
+    InApp,
+    Email,
+    Webhook,
+}
+
+/// What a notification is about
+#[derive(JsonSchema)]
+#[serde(rename = "NotificationKind", rename_all = "snake_case")]
+#[allow(dead_code)]
+pub enum NotificationKindName {
+    DcaPurchase,
+    DcaPaused,
+    PriceAlert,
+    PaymentFailed,
+    LicenseExpiring,
+}
+
+/// What an alert watches
+#[derive(JsonSchema)]
+#[serde(rename = "AlertCondition", tag = "type", rename_all = "snake_case")]
+#[allow(dead_code)]
+pub enum Condition {
+    /// Price at or above `price`
+    PriceAbove { symbol: String, price: Decimal },
+    /// Price at or below `price`
+    PriceBelow { symbol: String, price: Decimal },
+    /// Price moved at least `percent` either way within `window_minutes` (at most 10080)
+    PercentMove { symbol: String, percent: Decimal, window_minutes: u32 },
+    /// Portfolio value at least `percent` below its highest value since the alert was set
+    Drawdown { portfolio: String, percent: Decimal },
+    /// The asset's share of the portfolio more than `band_percent` points from `target_percent`
+    AllocationDrift { portfolio: String, symbol: String, target_percent: Decimal, band_percent: Decimal },
+}
+
+/// Where an alert is in its cycle
+#[derive(JsonSchema)]
+#[serde(rename = "AlertStatus", rename_all = "lowercase")]
+#[allow(dead_code)]
+pub enum AlertStatusName {
+    /// Waiting for the condition
+    Armed,
+    /// Fired; re-arms once the condition has cleared (repeating alerts)
+    Triggered,
+    /// Fired once and finished
+    Done,
+}
+
+/// Whether a license is valid, and its requests left today
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct LicenseVerification {
+    valid: bool,
+    plan: Option<PlanName>,
+    remaining_requests: Option<u32>,
+    message: Option<String>,
+}
+
+/// Usage of one model within a statement
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct ModelUsage {
+    model: String,
+    requests: u64,
+    prompt_tokens: u64,
+    completion_tokens: u64,
+    /// Cost in millionths of a US dollar
+    cost_micros: i64,
+}
+
+/// Usage of one account within a statement
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct MemberUsage {
+    /// Null for requests recorded without an account
+    user_id: Option<String>,
+    requests: u64,
+    total_tokens: u64,
+    /// Cost in millionths of a US dollar
+    cost_micros: i64,
+}
+
+/// Usage statement of a license over a period
+#[derive(JsonSchema)]
+#[allow(dead_code)]
+struct UsageStatement {
+    license_key: String,
+    plan: PlanName,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    models: Vec<ModelUsage>,
+    /// Usage by account, most expensive first
+    members: Vec<MemberUsage>,
+    total_tokens: u64,
+    total_cost_micros: i64,
+}
+
+// ============================================================================
+// Document
+// ============================================================================
+
+/// The OpenAPI document (built on first request)
+pub async fn openapi_json() -> Json<&'static Value> {
+    static DOCUMENT: OnceLock<Value> = OnceLock::new();
+    Json(DOCUMENT.get_or_init(document))
+}
+
+fn document() -> Value {
+    let mut spec = Spec::new();
+    
+    spec.op("get", "/models", "Models", "List available models").public().returns::<Vec<ModelInfo>>(200).add();
+    
+    spec.op("post", "/auth/register", "Accounts", "Create an account and sign in")
+        .public()
+        .body::<CredentialsRequest>()
+        .returns::<LoginResponse>(201)
+        .add();
+    spec.op("post", "/auth/login", "Accounts", "Sign in (sets the session cookie)")
+        .public()
+        .body::<CredentialsRequest>()
+        .returns::<LoginResponse>(200)
+        .add();
+    spec.op("post", "/auth/logout", "Accounts", "Clear the session cookie").public().no_content().add();
+    spec.op("get", "/auth/me", "Accounts", "The signed-in account, its API keys and license").returns::<AccountResponse>(200).add();
+    spec.op("post", "/auth/keys", "Accounts", "Create an API key (shown only once)")
+        .body::<ApiKeyRequest>()
+        .returns::<ApiKeyResponse>(201)
+        .add();
+    spec.op("post", "/auth/keys/revoke", "Accounts", "Revoke an API key").body::<RevokeApiKeyRequest>().no_content().add();
+    
+    spec.op("post", "/chat", "Chat", "Send a message and get the answer")
+        .body::<ChatRequest>()
+        .returns::<ChatResponse>(200)
+        .add();
+    spec.op("post", "/chat/stream", "Chat", "Stream the answer as Server-Sent Events (`chunk`, `tool_call`, `tool_result`, `done` and `error` events)")
+        .body::<ChatRequest>()
+        .header("Last-Event-ID", "Resume a stream after this event id, replaying the events after it")
+        .event_stream::<StreamEvent>()
+        .add();
+    spec.op("get", "/chat/stream", "Chat", "Stream answers over a WebSocket: send `ChatRequest` messages, receive `StreamEvent` messages")
+        .switching_protocols()
+        .add();
+    
+    spec.op("post", "/sessions/list", "Conversations", "List conversations, newest first")
+        .body::<SessionListRequest>()
+        .returns::<SessionListResponse>(200)
+        .add();
+    spec.op("post", "/sessions/export", "Conversations", "Export a conversation (JSON, Markdown or OpenAI JSONL)")
+        .body::<SessionExportRequest>()
+        .download(&["application/json", "text/markdown", "application/jsonl"])
+        .add();
+    spec.op("post", "/sessions/import", "Conversations", "Import a conversation exported as JSON")
+        .raw_body("application/json", "A conversation exported with format `json`")
+        .returns::<SessionImportResponse>(200)
+        .add();
+    spec.op("post", "/sessions/share", "Conversations", "Share a conversation with your organization, or take a shared one back")
+        .body::<SessionShareRequest>()
+        .no_content()
+        .add();
+    spec.op("post", "/users/export", "Conversations", "Export every conversation of the signed-in user")
+        .body::<UserExportRequest>()
+        .download(&["application/json", "application/jsonl"])
+        .add();
+    
+    spec.op("get", "/portfolios", "Portfolios", "List your portfolios").shared().returns::<PortfolioListResponse>(200).add();
+    spec.op("post", "/portfolios", "Portfolios", "Create a portfolio")
+        .shared()
+        .body::<CreatePortfolioRequest>()
+        .returns::<PortfolioView>(201)
+        .add();
+    spec.op("get", "/portfolios/{name}", "Portfolios", "A portfolio with its positions and totals")
+        .path_param("name")
+        .shared()
+        .returns::<PortfolioView>(200)
+        .add();
+    spec.op("post", "/portfolios/{name}/positions", "Portfolios", "Add a position (replaces a position in the same asset)")
+        .path_param("name")
+        .shared()
+        .body::<AddPositionRequest>()
+        .returns::<PortfolioView>(200)
+        .add();
+    spec.op("delete", "/portfolios/{name}/positions/{symbol}", "Portfolios", "Remove a position")
+        .path_param("name")
+        .path_param("symbol")
+        .shared()
+        .returns::<PortfolioView>(200)
+        .add();
+    spec.op("get", "/portfolios/{name}/transactions", "Portfolios", "Transactions, most recent first")
+        .path_param("name")
+        .shared()
+        .query_param("limit", "integer", "Most transactions to return (default 100, at most 1000)")
+        .returns::<TransactionListResponse>(200)
+        .add();
+    spec.op("post", "/portfolios/{name}/transactions", "Portfolios", "Record a buy or sell and apply it to the positions")
+        .path_param("name")
+        .shared()
+        .body::<TransactionRequest>()
+        .returns::<TransactionResponse>(201)
+        .add();
+    spec.op("post", "/portfolios/{name}/revalue", "Portfolios", "Update the positions with current market prices")
+        .path_param("name")
+        .shared()
+        .returns::<RevalueResponse>(200)
+        .add();
+    
+    spec.op("get", "/dca/jobs", "DCA jobs", "List your scheduled DCA plans").returns::<DcaJobListResponse>(200).add();
+    spec.op("post", "/dca/jobs", "DCA jobs", "Schedule a DCA plan (paper purchases as entries come due)")
+        .body::<CreateDcaJobRequest>()
+        .header("Idempotency-Key", "Retry safely: a repeated key returns the job created first (200)")
+        .returns::<DcaJobView>(201)
+        .add();
+    spec.op("get", "/dca/jobs/{id}", "DCA jobs", "A DCA plan with its schedule and purchases")
+        .path_param("id")
+        .returns::<DcaJobView>(200)
+        .add();
+    spec.op("delete", "/dca/jobs/{id}", "DCA jobs", "Cancel a DCA plan (purchases made are kept)")
+        .path_param("id")
+        .returns::<DcaJobView>(200)
+        .add();
+    
+    spec.op("get", "/alerts", "Alerts", "List your price and portfolio alerts").returns::<AlertListResponse>(200).add();
+    spec.op("post", "/alerts", "Alerts", "Set an alert (notified as price_alert when it fires)")
+        .body::<CreateAlertRequest>()
+        .returns::<AlertView>(201)
+        .add();
+    spec.op("get", "/alerts/{id}", "Alerts", "An alert and its status")
+        .path_param("id")
+        .returns::<AlertView>(200)
+        .add();
+    spec.op("delete", "/alerts/{id}", "Alerts", "Delete an alert")
+        .path_param("id")
+        .no_content()
+        .add();
+    
+    spec.op("get", "/notifications", "Notifications", "Your inbox, newest first")
+        .query_param("unread", "boolean", "Only unread notifications")
+        .query_param("limit", "integer", "Most notifications to return (default 50, at most 200)")
+        .returns::<NotificationListResponse>(200)
+        .add();
+    spec.op("post", "/notifications/{id}/read", "Notifications", "Mark a notification read")
+        .path_param("id")
+        .no_content()
+        .add();
+    spec.op("post", "/notifications/read-all", "Notifications", "Mark every notification read")
+        .returns::<MarkReadResponse>(200)
+        .add();
+    spec.op("get", "/notifications/preferences", "Notifications", "Your channels, addresses and opt-outs")
+        .returns::<PreferencesView>(200)
+        .add();
+    spec.op("put", "/notifications/preferences", "Notifications", "Replace your notification preferences (issues a webhook secret when the URL changes)")
+        .body::<PreferencesRequest>()
+        .returns::<PreferencesView>(200)
+        .add();
+    
+    spec.op("post", "/orgs", "Organizations", "Create an organization for your Team license (you become its owner)")
+        .body::<CreateOrgRequest>()
+        .returns::<OrgView>(201)
+        .add();
+    spec.op("post", "/orgs/join", "Organizations", "Join an organization with an invite token")
+        .body::<JoinRequest>()
+        .returns::<OrgView>(200)
+        .add();
+    spec.op("get", "/orgs/{id}", "Organizations", "An organization with its members, seats and pending invites")
+        .path_param("id")
+        .returns::<OrgView>(200)
+        .add();
+    spec.op("delete", "/orgs/{id}", "Organizations", "Delete an organization (owner; shared conversations go to the owner)")
+        .path_param("id")
+        .no_content()
+        .add();
+    spec.op("post", "/orgs/{id}/invites", "Organizations", "Invite a member (takes a seat until accepted or expired)")
+        .path_param("id")
+        .body::<InviteRequest>()
+        .returns::<InviteView>(201)
+        .add();
+    spec.op("delete", "/orgs/{id}/invites/{token}", "Organizations", "Revoke a pending invite")
+        .path_param("id")
+        .path_param("token")
+        .no_content()
+        .add();
+    spec.op("put", "/orgs/{id}/members/{user_id}", "Organizations", "Change a member's role (owner; `owner` hands over ownership)")
+        .path_param("id")
+        .path_param("user_id")
+        .body::<RoleRequest>()
+        .returns::<OrgView>(200)
+        .add();
+    spec.op("delete", "/orgs/{id}/members/{user_id}", "Organizations", "Remove a member, or leave (your own user id)")
+        .path_param("id")
+        .path_param("user_id")
+        .no_content()
+        .add();
+    spec.op("get", "/orgs/{id}/usage", "Organizations", "Usage of the organization's license by member and model (admins and the owner)")
+        .path_param("id")
+        .query_param("from", "string", "Period start (default: start of the current month)")
+        .query_param("to", "string", "Period end (default: now)")
+        .returns::<UsageStatement>(200)
+        .add();
+    
+    spec.op("post", "/checkout", "Payments", "Create a Stripe checkout session")
+        .body::<CheckoutRequest>()
+        .returns::<CheckoutResponse>(200)
+        .add();
+    spec.op("post", "/license/verify", "Payments", "Verify a license key")
+        .body::<VerifyLicenseRequest>()
+        .returns::<LicenseVerification>(200)
+        .add();
+    spec.op("post", "/license/usage", "Payments", "Per-model token and cost statement of a license")
+        .body::<UsageStatementRequest>()
+        .returns::<UsageStatement>(200)
+        .add();
+    
+    spec.op("get", "/openapi.json", "Meta", "This document").public().raw_response("application/json").add();
+    
+    spec.finish()
+}
+
+/// Paths and schemas collected so far
+struct Spec {
+    /// Request bodies are described as deserialized, responses as serialized
+    requests: SchemaGenerator,
+    responses: SchemaGenerator,
+    paths: Map<String, Value>,
+}
+
+impl Spec {
+    fn new() -> Self {
+        Self {
+            requests: SchemaSettings::openapi3().for_deserialize().into_generator(),
+            responses: SchemaSettings::openapi3().for_serialize().into_generator(),
+            paths: Map::new(),
+        }
+    }
+    
+    fn op<'a>(&'a mut self, method: &'static str, path: &'static str, tag: &str, summary: &str) -> Op<'a> {
+        let operation = json!({
+            "tags": [tag],
+            "summary": summary,
+            "responses": {"default": {"$ref": "#/components/responses/Error"}},
+        });
+        Op { spec: self, method, path, operation }
+    }
+    
+    fn finish(mut self) -> Value {
+        let error = subschema::<ErrorResponse>(&mut self.responses);
+        let mut schemas = self.requests.take_definitions(true);
+        for (name, schema) in self.responses.take_definitions(true) {
+            schemas.entry(name).or_insert(schema);
+        }
+        
+        json!({
+            "openapi": "3.0.3",
+            "info": {
+                "title": "rust-agent API",
+                "version": env!("CARGO_PKG_VERSION"),
+                "description": "Errors are JSON objects with a message (`error`) and a stable `code`.",
+            },
+            "servers": [{"url": API_PREFIX}],
+            "security": [{"bearer": []}, {"apiKey": []}, {"session": []}],
+            "paths": self.paths,
+            "components": {
+                "schemas": schemas,
+                "responses": {
+                    "Error": {
+                        "description": "Error",
+                        "content": {"application/json": {"schema": error}},
+                    },
+                },
+                "securitySchemes": {
+                    "bearer": {"type": "http", "scheme": "bearer", "description": "API key (`ak_...`) or session token"},
+                    "apiKey": {"type": "apiKey", "in": "header", "name": "X-API-Key"},
+                    "session": {"type": "apiKey", "in": "cookie", "name": SESSION_COOKIE},
+                },
+            },
+        })
+    }
+}
+
+/// A schema for `T`, with its definitions added to the generator
+fn subschema<T: JsonSchema>(generator: &mut SchemaGenerator) -> Value {
+    let mut schema: Schema = generator.subschema_for::<T>();
+    for transform in generator.transforms_mut() {
+        transform.transform(&mut schema);
+    }
+    schema.to_value()
+}
+
+/// An operation being described
+struct Op<'a> {
+    spec: &'a mut Spec,
+    method: &'static str,
+    path: &'static str,
+    operation: Value,
+}
+
+impl Op<'_> {
+    /// No authentication required
+    fn public(mut self) -> Self {
+        self.operation["security"] = json!([]);
+        self
+    }
+    
+    fn body<T: JsonSchema>(mut self) -> Self {
+        let schema = subschema::<T>(&mut self.spec.requests);
+        self.operation["requestBody"] = json!({"required": true, "content": {"application/json": {"schema": schema}}});
+        self
+    }
+    
+    fn raw_body(mut self, content_type: &str, description: &str) -> Self {
+        self.operation["requestBody"] = json!({"required": true, "description": description, "content": {content_type: {"schema": {}}}});
+        self
+    }
+    
+    fn header(self, name: &str, description: &str) -> Self {
+        self.parameter(json!({"name": name, "in": "header", "required": false, "description": description, "schema": {"type": "string"}}))
+    }
+    
+    fn path_param(self, name: &str) -> Self {
+        self.parameter(json!({"name": name, "in": "path", "required": true, "schema": {"type": "string"}}))
+    }
+    
+    fn query_param(self, name: &str, kind: &str, description: &str) -> Self {
+        self.parameter(json!({"name": name, "in": "query", "required": false, "description": description, "schema": {"type": kind}}))
+    }
+    
+    /// `?org=` selecting an organization's shared resources
+    fn shared(self) -> Self {
+        self.query_param("org", "string", "Use this organization's shared portfolios instead of your own")
+    }
+    
+    fn parameter(mut self, parameter: Value) -> Self {
+        match self.operation["parameters"].as_array_mut() {
+            Some(parameters) => parameters.push(parameter),
+            None => self.operation["parameters"] = json!([parameter]),
+        }
+        self
+    }
+    
+    fn returns<T: JsonSchema>(self, status: u16) -> Self {
+        let schema = subschema::<T>(&mut self.spec.responses);
+        self.response(status, json!({"description": "Success", "content": {"application/json": {"schema": schema}}}))
+    }
+    
+    fn raw_response(self, content_type: &str) -> Self {
+        self.response(200, json!({"description": "Success", "content": {content_type: {"schema": {}}}}))
+    }
+    
+    fn no_content(self) -> Self {
+        self.response(204, json!({"description": "Done"}))
+    }
+    
+    fn download(self, content_types: &[&str]) -> Self {
+        let content: Map<String, Value> = content_types.iter().map(|t| (t.to_string(), json!({"schema": {"type": "string"}}))).collect();
+        self.response(200, json!({"description": "Export file (attachment)", "content": content}))
+    }
+    
+    /// `text/event-stream` whose events' data are `T`
+    fn event_stream<T: JsonSchema>(self) -> Self {
+        let schema = subschema::<T>(&mut self.spec.responses);
+        self.response(200, json!({"description": "Event stream; each event's data is JSON", "content": {"text/event-stream": {"schema": schema}}}))
+    }
+    
+    fn switching_protocols(self) -> Self {
+        self.response(101, json!({"description": "WebSocket upgrade"}))
+    }
+    
+    fn response(mut self, status: u16, response: Value) -> Self {
+        self.operation["responses"][status.to_string()] = response;
+        self
+    }
+    
+    fn add(self) {
+        let path = self.spec.paths.entry(self.path).or_insert_with(|| json!({}));
+        path[self.method] = self.operation;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::{header, Method, Request, StatusCode}};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::state::AppState;
+
+    #[tokio::test]
+    async fn test_document_matches_routes() {
+        let dir = std::env::temp_dir().join(format!("openapi-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let state = AppState::for_tests(&dir);
+        let user = state.users.register("alice", "correct horse").unwrap();
+        let (token, _) = state.tokens.issue(&user.id);
+        let routes = crate::api_routes(&state);
+        let router = routes.router.with_state(state);
+        
+        let document = document();
+        let operations = document["paths"].as_object().unwrap();
+        let mut documented: Vec<&str> = operations.keys().map(String::as_str).collect();
+        let mut routed = routes.paths.clone();
+        documented.sort_unstable();
+        routed.sort_unstable();
+        assert_eq!(routed, documented);
+        
+        // Every documented method is routed, and no other (405 is the router's answer to the rest)
+        for (path, methods) in operations {
+            let uri = path.split('/').map(|s| if s.starts_with('{') { "x" } else { s }).collect::<Vec<_>>().join("/");
+            for method in [Method::GET, Method::POST, Method::PUT, Method::PATCH, Method::DELETE] {
+                let request = Request::builder()
+                    .method(method.clone())
+                    .uri(&uri)
+                    .header(header::AUTHORIZATION, format!("Bearer {}", token))
+                    .body(Body::empty())
+                    .unwrap();
+                let response = router.clone().oneshot(request).await.unwrap();
+                let documented = methods.get(method.as_str().to_lowercase()).is_some();
+                assert_eq!(response.status() != StatusCode::METHOD_NOT_ALLOWED, documented, "{} {}", method, path);
+            }
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,514 @@
+//! Organization API
+//!
+//! A Team license is shared through an organization: the license holder
+//! creates one and becomes its owner, admins invite colleagues with a token
+//! that can be redeemed for `INVITE_TTL_DAYS` days, and every member's
+//! requests are charged to the organization's license while they hold one
+//! of its seats (see `quota::request_license`). Members and pending invites
+//! together never exceed the plan's seats (`Plan::seats`, the owner included).
+//!
+//! Members share portfolios and conversations: the portfolio routes take
+//! `?org=<id>`, chat and the conversation list take `org_id`. Every member
+//! reads the shared portfolios and continues the shared conversations; admins
+//! and the owner change shared portfolios. Members share their own
+//! conversations with `/sessions/share`, and admins take them back out.
+//!
+//! Organizations are kept in a JSON file (`storage.orgs_path`). Organizations
+//! the caller is not a member of are reported as not found; changes their
+//! role doesn't allow are forbidden.
+
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use agent_payments::{Invite, LicenseStore, Organization, PaymentError, Plan, Role, UsageStatement, MAX_ORG_NAME_CHARS};
+
+use crate::auth::UserId;
+use crate::error::{ApiError, ApiJson, ApiPath, ApiQuery, ErrorCode};
+use crate::handlers::{session_error, statement_period};
+use crate::openapi::{PlanName, RoleName};
+use crate::state::AppState;
+
+// ============================================================================
+// Requests and Responses
+// ============================================================================
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CreateOrgRequest {
+    pub name: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct InviteRequest {
+    /// Role the new member gets (default member; only the owner invites admins)
+    #[serde(default)]
+    #[schemars(with = "Option<RoleName>")]
+    pub role: Option<Role>,
+    
+    /// Who the invite is for, as a reminder (e.g. an email address)
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct JoinRequest {
+    /// Invite token (`inv_...`)
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct RoleRequest {
+    /// `owner` hands over ownership (the current owner becomes an admin)
+    #[schemars(with = "RoleName")]
+    pub role: Role,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct OrgUsageQuery {
+    /// Period start (default: start of the current month)
+    #[serde(default)]
+    pub from: Option<DateTime<Utc>>,
+    /// Period end (default: now)
+    #[serde(default)]
+    pub to: Option<DateTime<Utc>>,
+}
+
+/// Selects a team's shared resources instead of the caller's own
+#[derive(Debug, Default, Deserialize, JsonSchema)]
+pub struct SharedQuery {
+    /// Organization whose shared resources to use
+    #[serde(default)]
+    pub org: Option<String>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct MemberView {
+    pub user_id: String,
+    pub username: Option<String>,
+    #[schemars(with = "RoleName")]
+    pub role: Role,
+    pub joined_at: DateTime<Utc>,
+    
+    /// Whether the member holds one of the plan's seats (after a downgrade,
+    /// the members who joined last don't)
+    pub seated: bool,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct InviteView {
+    /// Give this to the invitee; it joins with `POST /orgs/join`
+    pub token: String,
+    #[schemars(with = "RoleName")]
+    pub role: Role,
+    pub label: Option<String>,
+    pub invited_by: String,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl From<Invite> for InviteView {
+    fn from(invite: Invite) -> Self {
+        Self {
+            token: invite.token,
+            role: invite.role,
+            label: invite.label,
+            invited_by: invite.invited_by,
+            created_at: invite.created_at,
+            expires_at: invite.expires_at,
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct OrgView {
+    pub id: String,
+    pub name: String,
+    
+    /// Plan of the organization's license (None once it has lapsed)
+    #[schemars(with = "Option<PlanName>")]
+    pub plan: Option<Plan>,
+    
+    /// Seats of the plan, the owner's included (0 once the license has lapsed)
+    pub seats: u32,
+    
+    /// Members and pending invites
+    pub seats_used: u32,
+    
+    /// The caller's role
+    #[schemars(with = "RoleName")]
+    pub role: Role,
+    
+    /// In the order they joined
+    pub members: Vec<MemberView>,
+    
+    /// Pending invites (shown to admins and the owner)
+    pub invites: Vec<InviteView>,
+    
+    pub created_at: DateTime<Utc>,
+}
+
+/// Organization storage failures get their own code; the rest map as usual
+fn org_error(e: PaymentError) -> ApiError {
+    match e {
+        PaymentError::Storage(cause) => {
+            tracing::error!(code = %ErrorCode::OrgsUnavailable, "{}", cause);
+            ApiError::new(StatusCode::SERVICE_UNAVAILABLE, ErrorCode::OrgsUnavailable, "Organization storage unavailable")
+        }
+        e => e.into(),
+    }
+}
+
+fn lapsed() -> ApiError {
+    ApiError::new(StatusCode::FORBIDDEN, ErrorCode::InvalidLicense, "The organization's license is not active")
+}
+
+/// Seats of an organization's license, or `lapsed` if it is no longer valid
+fn seats(state: &AppState, org: &Organization) -> Result<u32, ApiError> {
+    match state.license_store.get(&org.license_key)? {
+        Some(license) if license.is_valid() => Ok(license.plan.seats()),
+        _ => Err(lapsed()),
+    }
+}
+
+/// An organization as shown to one of its members
+fn view(state: &AppState, org: Organization, user_id: &UserId) -> OrgView {
+    let plan = state
+        .license_store
+        .get(&org.license_key)
+        .ok()
+        .flatten()
+        .filter(|license| license.is_valid())
+        .map(|license| license.plan);
+    let seats = plan.as_ref().map_or(0, Plan::seats);
+    let role = org.role_of(user_id.as_str()).unwrap_or(Role::Member);
+    
+    let members = org
+        .members
+        .iter()
+        .map(|member| MemberView {
+            user_id: member.user_id.clone(),
+            username: state.users.get(&UserId::from_string(&member.user_id)).map(|user| user.username),
+            role: member.role,
+            joined_at: member.joined_at,
+            seated: org.seated(&member.user_id, seats),
+        })
+        .collect();
+    let now = Utc::now();
+    let invites = if role.can_manage() {
+        org.invites.iter().filter(|i| i.is_pending(now)).cloned().map(InviteView::from).collect()
+    } else {
+        Vec::new()
+    };
+    
+    OrgView {
+        seats_used: org.seats_used(now),
+        id: org.id,
+        name: org.name,
+        plan,
+        seats,
+        role,
+        members,
+        invites,
+        created_at: org.created_at,
+    }
+}
+
+/// An organization the caller is a member of (others are reported as not found)
+fn member_org(state: &AppState, user_id: &str, id: &str) -> Result<Organization, ApiError> {
+    state
+        .orgs
+        .get(id)
+        .map_err(org_error)?
+        .filter(|org| org.member(user_id).is_some())
+        .ok_or_else(|| PaymentError::OrgNotFound(id.to_string()).into())
+}
+
+// ============================================================================
+// Shared Resources
+// ============================================================================
+
+/// Whose portfolios or conversations a request works on
+pub struct Scope {
+    owner: String,
+    
+    /// The caller's role, when working on a team's resources
+    role: Option<Role>,
+}
+
+impl Scope {
+    pub fn owner(&self) -> Option<&str> {
+        Some(&self.owner)
+    }
+    
+    /// Fails unless the caller may change the resources: their own, or a team's as an admin or the owner
+    pub fn editable(self) -> Result<Self, ApiError> {
+        match self.role {
+            Some(role) if !role.can_manage() => Err(PaymentError::NotPermitted(
+                "members read shared resources; admins and the owner change them".into(),
+            )
+            .into()),
+            _ => Ok(self),
+        }
+    }
+}
+
+/// The caller's own resources, or those of an organization they are a member of
+pub fn scope(state: &AppState, user_id: &str, org_id: Option<&str>) -> Result<Scope, ApiError> {
+    let Some(org_id) = org_id else {
+        return Ok(Scope { owner: user_id.to_string(), role: None });
+    };
+    let org = member_org(state, user_id, org_id)?;
+    Ok(Scope { role: org.role_of(user_id), owner: org.scope() })
+}
+
+/// Whether resources kept under `owner` are shared with a user through their organization
+pub fn shared_with(state: &AppState, owner: &str, user_id: &str) -> bool {
+    Organization::id_of_scope(owner).is_some_and(|id| {
+        state.orgs.for_user(user_id).ok().flatten().is_some_and(|org| org.id == id)
+    })
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+/// Create an organization for the caller's Team license
+pub async fn create_org(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiJson(payload): ApiJson<CreateOrgRequest>,
+) -> Result<(StatusCode, Json<OrgView>), ApiError> {
+    let name = payload.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_ORG_NAME_CHARS {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            format!("Organization name must be 1-{} characters", MAX_ORG_NAME_CHARS),
+        ));
+    }
+    let license = state
+        .license_store
+        .get_by_user(user_id.as_str())?
+        .filter(|license| license.plan.seats() > 1)
+        .ok_or_else(|| ApiError::new(StatusCode::FORBIDDEN, ErrorCode::InvalidLicense, "Organizations need an active Team license"))?;
+    
+    let org = Organization::new(name, user_id.as_str(), license.key);
+    state.orgs.create(&org).map_err(org_error)?;
+    tracing::info!(user_id = %user_id, org_id = %org.id, "Organization created");
+    Ok((StatusCode::CREATED, Json(view(&state, org, &user_id))))
+}
+
+/// An organization of the caller's, with its members and seats
+pub async fn get_org(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiPath(id): ApiPath<String>,
+) -> Result<Json<OrgView>, ApiError> {
+    let org = member_org(&state, user_id.as_str(), &id)?;
+    Ok(Json(view(&state, org, &user_id)))
+}
+
+/// Delete an organization (owner only); its shared conversations go to the owner
+pub async fn delete_org(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiPath(id): ApiPath<String>,
+) -> Result<StatusCode, ApiError> {
+    let org = member_org(&state, user_id.as_str(), &id)?;
+    if org.role_of(user_id.as_str()) != Some(Role::Owner) {
+        return Err(PaymentError::NotPermitted("only the owner can delete the organization".into()).into());
+    }
+    
+    let sessions = state.sessions.list(Some(&org.scope()), usize::MAX).map_err(session_error)?;
+    for mut session in sessions {
+        session.metadata.user_id = Some(user_id.to_string());
+        state.sessions.save(&session).map_err(session_error)?;
+    }
+    state.orgs.delete(&id).map_err(org_error)?;
+    tracing::info!(user_id = %user_id, org_id = %id, "Organization deleted");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Invite someone, taking a seat until the invite is accepted or expires
+pub async fn create_invite(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiPath(id): ApiPath<String>,
+    ApiJson(payload): ApiJson<InviteRequest>,
+) -> Result<(StatusCode, Json<InviteView>), ApiError> {
+    let org = member_org(&state, user_id.as_str(), &id)?;
+    let seats = seats(&state, &org)?;
+    let role = payload.role.unwrap_or(Role::Member);
+    let label = payload.label.map(|l| l.trim().to_string()).filter(|l| !l.is_empty());
+    
+    let mut invite = None;
+    state
+        .orgs
+        .update(&id, &mut |org| {
+            invite = Some(org.invite(user_id.as_str(), role, label.clone(), seats, Utc::now())?);
+            Ok(())
+        })
+        .map_err(org_error)?;
+    let invite = invite.ok_or_else(|| ApiError::internal(ErrorCode::InternalError, "Invite not created"))?;
+    
+    tracing::info!(user_id = %user_id, org_id = %id, role = invite.role.as_str(), "Invite created");
+    Ok((StatusCode::CREATED, Json(InviteView::from(invite))))
+}
+
+/// Withdraw a pending invite, freeing its seat
+pub async fn revoke_invite(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiPath((id, token)): ApiPath<(String, String)>,
+) -> Result<StatusCode, ApiError> {
+    member_org(&state, user_id.as_str(), &id)?;
+    state.orgs.update(&id, &mut |org| org.revoke_invite(user_id.as_str(), &token)).map_err(org_error)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Join an organization with an invite token
+pub async fn join_org(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiJson(payload): ApiJson<JoinRequest>,
+) -> Result<Json<OrgView>, ApiError> {
+    let token = payload.token.trim();
+    let org = state
+        .orgs
+        .by_invite(token)
+        .map_err(org_error)?
+        .ok_or_else(|| ApiError::from(PaymentError::InvalidInvite("unknown or expired invite".into())))?;
+    let seats = seats(&state, &org)?;
+    
+    let org = state
+        .orgs
+        .update(&org.id, &mut |org| org.accept(token, user_id.as_str(), seats, Utc::now()).map(|_| ()))
+        .map_err(org_error)?;
+    tracing::info!(user_id = %user_id, org_id = %org.id, "Joined organization");
+    Ok(Json(view(&state, org, &user_id)))
+}
+
+/// Change a member's role (owner only)
+pub async fn set_role(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiPath((id, member_id)): ApiPath<(String, String)>,
+    ApiJson(payload): ApiJson<RoleRequest>,
+) -> Result<Json<OrgView>, ApiError> {
+    member_org(&state, user_id.as_str(), &id)?;
+    let org = state
+        .orgs
+        .update(&id, &mut |org| org.set_role(user_id.as_str(), &member_id, payload.role).map(|_| ()))
+        .map_err(org_error)?;
+    tracing::info!(user_id = %user_id, org_id = %id, member = %member_id, role = payload.role.as_str(), "Role changed");
+    Ok(Json(view(&state, org, &user_id)))
+}
+
+/// Remove a member, or leave the organization (your own user id)
+pub async fn remove_member(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiPath((id, member_id)): ApiPath<(String, String)>,
+) -> Result<StatusCode, ApiError> {
+    member_org(&state, user_id.as_str(), &id)?;
+    state
+        .orgs
+        .update(&id, &mut |org| org.remove(user_id.as_str(), &member_id).map(|_| ()))
+        .map_err(org_error)?;
+    tracing::info!(user_id = %user_id, org_id = %id, member = %member_id, "Member removed");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Usage of the organization's license by member (admins and the owner)
+pub async fn org_usage(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiPath(id): ApiPath<String>,
+    ApiQuery(query): ApiQuery<OrgUsageQuery>,
+) -> Result<Json<UsageStatement>, ApiError> {
+    let org = member_org(&state, user_id.as_str(), &id)?;
+    if !org.role_of(user_id.as_str()).is_some_and(Role::can_manage) {
+        return Err(PaymentError::NotPermitted("admins and the owner see the organization's usage".into()).into());
+    }
+    let license = state.license_store.get(&org.license_key)?.ok_or_else(lapsed)?;
+    
+    let (from, to) = statement_period(query.from, query.to);
+    let records = state.license_store.usage_records(&license.key, from, to).map_err(|e| {
+        tracing::error!("Usage lookup error: {}", e);
+        ApiError::internal(ErrorCode::UsageError, e.user_message())
+    })?;
+    Ok(Json(UsageStatement::build(&license, &records, from, to)))
+}
+
+#[cfg(test)]
+mod tests {
+    use agent_payments::LicenseKey;
+    use crypto_advisor::Portfolio;
+
+    use super::*;
+    use crate::portfolios;
+
+    /// An organization of `ana`'s with `bo` as a member and a shared "team" portfolio
+    fn team(state: &AppState) -> Organization {
+        let now = Utc::now();
+        let mut org = Organization::new("Desk", "ana", LicenseKey::from_string("TEAM-0000-0000-0000"));
+        let invite = org.invite("ana", Role::Member, None, 5, now).unwrap();
+        org.accept(&invite.token, "bo", 5, now).unwrap();
+        state.orgs.create(&org).unwrap();
+        
+        let mut portfolio = Portfolio::new("team");
+        portfolio.owner_id = Some(org.scope());
+        state.portfolios.create(&portfolio).unwrap();
+        org
+    }
+
+    async fn get_portfolio(state: &AppState, user_id: &str, org: &Organization) -> Result<Json<portfolios::PortfolioView>, ApiError> {
+        portfolios::get_portfolio(
+            State(state.clone()),
+            Extension(UserId::from_string(user_id)),
+            ApiPath("team".to_string()),
+            ApiQuery(SharedQuery { org: Some(org.id.clone()) }),
+        )
+        .await
+    }
+
+    #[tokio::test]
+    async fn test_non_member_rejected() {
+        let dir = std::env::temp_dir().join(format!("orgs-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let state = AppState::for_tests(&dir);
+        let org = team(&state);
+        
+        // Told the organization doesn't exist, rather than that they aren't in it
+        let error = scope(&state, "cy", Some(&org.id)).err().unwrap();
+        assert_eq!(error.status(), StatusCode::NOT_FOUND);
+        assert_eq!(get_portfolio(&state, "cy", &org).await.err().unwrap().status(), StatusCode::NOT_FOUND);
+        assert!(!shared_with(&state, &org.scope(), "cy"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_member_reads_team_resources() {
+        let dir = std::env::temp_dir().join(format!("orgs-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let state = AppState::for_tests(&dir);
+        let org = team(&state);
+        
+        let shared = scope(&state, "bo", Some(&org.id)).unwrap();
+        assert_eq!(shared.owner(), Some(org.scope().as_str()));
+        assert_eq!(get_portfolio(&state, "bo", &org).await.unwrap().0.name, "team");
+        assert!(shared_with(&state, &org.scope(), "bo"));
+        
+        // Members read; only admins and the owner change
+        assert_eq!(shared.editable().err().unwrap().status(), StatusCode::FORBIDDEN);
+        assert!(scope(&state, "ana", Some(&org.id)).unwrap().editable().is_ok());
+        
+        // A member's own resources aren't shared with the team
+        assert!(!shared_with(&state, "ana", "bo"));
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,358 @@
+//! Portfolio API
+//!
+//! REST access to the portfolios the `portfolio_tracker` tool keeps, backed
+//! by the same `PortfolioStore`. Every route is scoped to the signed-in user
+//! (`Portfolio.owner_id`): portfolios of other users are reported as not
+//! found, never as forbidden. With `?org=<id>` the routes work on an
+//! organization's shared portfolios instead: all its members read them, and
+//! admins and the owner change them.
+//!
+//! Amounts are decimals, sent as strings (`"0.25"`); requests also accept
+//! plain JSON numbers.
+
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{Extension, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use crypto_advisor::{store, AdvisorError, Portfolio, Position, TradeSide, Transaction};
+
+use crate::auth::UserId;
+use crate::error::{ApiError, ApiJson, ApiPath, ApiQuery, ErrorCode};
+use crate::openapi::Side;
+use crate::orgs::{self, SharedQuery};
+use crate::state::AppState;
+
+/// Longest portfolio name
+const MAX_NAME_CHARS: usize = 64;
+
+// ============================================================================
+// Requests and Responses
+// ============================================================================
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct CreatePortfolioRequest {
+    pub name: String,
+    
+    /// Starting cash in USD (default: 0)
+    #[serde(default)]
+    pub cash_balance: Option<Decimal>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct AddPositionRequest {
+    pub symbol: String,
+    pub quantity: Decimal,
+    
+    /// Average cost per unit in USD
+    pub cost_basis: Decimal,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TransactionRequest {
+    pub symbol: String,
+    #[schemars(with = "Side")]
+    pub side: TradeSide,
+    pub quantity: Decimal,
+    
+    /// Price per unit in USD (default: the current market price)
+    #[serde(default)]
+    pub price: Option<Decimal>,
+    
+    /// Fee in USD (default: 0)
+    #[serde(default)]
+    pub fee: Option<Decimal>,
+    
+    /// When the trade happened (default: now)
+    #[serde(default)]
+    pub executed_at: Option<DateTime<Utc>>,
+    
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+#[derive(Debug, Deserialize, JsonSchema)]
+pub struct TransactionListQuery {
+    /// Most transactions to return (default 100, at most 1000)
+    #[serde(default)]
+    pub limit: Option<usize>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PositionView {
+    pub symbol: String,
+    pub quantity: Decimal,
+    pub cost_basis: Decimal,
+    pub current_value: Decimal,
+    pub unrealized_pnl: Decimal,
+    pub unrealized_pnl_percent: Decimal,
+    pub opened_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl From<&Position> for PositionView {
+    fn from(position: &Position) -> Self {
+        Self {
+            symbol: position.symbol.clone(),
+            quantity: position.quantity,
+            cost_basis: position.cost_basis,
+            current_value: position.current_value,
+            unrealized_pnl: position.unrealized_pnl,
+            unrealized_pnl_percent: position.unrealized_pnl_percent,
+            opened_at: position.opened_at,
+            updated_at: position.updated_at,
+        }
+    }
+}
+
+/// A portfolio with its totals, as of its last revaluation
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PortfolioView {
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+    pub cash_balance: Decimal,
+    pub total_cost: Decimal,
+    pub total_value: Decimal,
+    pub total_pnl: Decimal,
+    
+    /// Positions by symbol
+    pub positions: Vec<PositionView>,
+    
+    /// Percent of the total value per symbol (and `CASH`)
+    pub allocations: BTreeMap<String, Decimal>,
+}
+
+impl From<&Portfolio> for PortfolioView {
+    fn from(portfolio: &Portfolio) -> Self {
+        let mut positions: Vec<PositionView> = portfolio.positions.values().map(PositionView::from).collect();
+        positions.sort_by(|a, b| a.symbol.cmp(&b.symbol));
+        
+        Self {
+            name: portfolio.name.clone(),
+            created_at: portfolio.created_at,
+            cash_balance: portfolio.cash_balance,
+            total_cost: portfolio.positions.values().map(Position::total_cost).sum(),
+            total_value: portfolio.total_value(),
+            total_pnl: portfolio.total_pnl(),
+            positions,
+            allocations: portfolio.allocations().into_iter().map(|(s, p)| (s, p.round_dp(2))).collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct PortfolioListResponse {
+    pub portfolios: Vec<PortfolioView>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TransactionView {
+    pub id: String,
+    pub symbol: String,
+    #[schemars(with = "Side")]
+    pub side: TradeSide,
+    pub quantity: Decimal,
+    pub price: Decimal,
+    pub fee: Decimal,
+    pub executed_at: DateTime<Utc>,
+    pub note: Option<String>,
+}
+
+impl From<&Transaction> for TransactionView {
+    fn from(transaction: &Transaction) -> Self {
+        Self {
+            id: transaction.id.to_string(),
+            symbol: transaction.symbol.clone(),
+            side: transaction.side,
+            quantity: transaction.quantity,
+            price: transaction.price,
+            fee: transaction.fee,
+            executed_at: transaction.executed_at,
+            note: transaction.note.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TransactionListResponse {
+    /// Most recent first
+    pub transactions: Vec<TransactionView>,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct TransactionResponse {
+    pub transaction: TransactionView,
+    
+    /// The portfolio after the transaction
+    pub portfolio: PortfolioView,
+}
+
+#[derive(Debug, Serialize, JsonSchema)]
+pub struct RevalueResponse {
+    pub portfolio: PortfolioView,
+    
+    /// Positions whose price was updated (the others keep their last value)
+    pub updated: usize,
+}
+
+// ============================================================================
+// Handlers
+// ============================================================================
+
+/// List the caller's (or their team's) portfolios, oldest first
+pub async fn list_portfolios(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiQuery(shared): ApiQuery<SharedQuery>,
+) -> Result<Json<PortfolioListResponse>, ApiError> {
+    let scope = orgs::scope(&state, user_id.as_str(), shared.org.as_deref())?;
+    let portfolios = state.portfolios.list(scope.owner())?;
+    Ok(Json(PortfolioListResponse { portfolios: portfolios.iter().map(PortfolioView::from).collect() }))
+}
+
+/// Create an empty portfolio
+pub async fn create_portfolio(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiQuery(shared): ApiQuery<SharedQuery>,
+    ApiJson(payload): ApiJson<CreatePortfolioRequest>,
+) -> Result<(StatusCode, Json<PortfolioView>), ApiError> {
+    let scope = orgs::scope(&state, user_id.as_str(), shared.org.as_deref())?.editable()?;
+    let name = payload.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_NAME_CHARS || name.contains('/') {
+        return Err(ApiError::bad_request(
+            ErrorCode::InvalidRequest,
+            format!("Portfolio name must be 1-{} characters, without '/'", MAX_NAME_CHARS),
+        ));
+    }
+    let cash_balance = payload.cash_balance.unwrap_or(Decimal::ZERO);
+    if cash_balance < Decimal::ZERO {
+        return Err(ApiError::bad_request(ErrorCode::InvalidRequest, "Cash balance cannot be negative"));
+    }
+    
+    let mut portfolio = Portfolio::new(name);
+    portfolio.owner_id = scope.owner().map(String::from);
+    portfolio.cash_balance = cash_balance;
+    state.portfolios.create(&portfolio)?;
+    
+    Ok((StatusCode::CREATED, Json(PortfolioView::from(&portfolio))))
+}
+
+/// One of the caller's (or their team's) portfolios
+pub async fn get_portfolio(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiPath(name): ApiPath<String>,
+    ApiQuery(shared): ApiQuery<SharedQuery>,
+) -> Result<Json<PortfolioView>, ApiError> {
+    let scope = orgs::scope(&state, user_id.as_str(), shared.org.as_deref())?;
+    let portfolio = state
+        .portfolios
+        .get(scope.owner(), &name)?
+        .ok_or(AdvisorError::PortfolioNotFound(name))?;
+    Ok(Json(PortfolioView::from(&portfolio)))
+}
+
+/// Add a position (replacing any position in the same asset), priced at the market
+pub async fn add_position(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiPath(name): ApiPath<String>,
+    ApiQuery(shared): ApiQuery<SharedQuery>,
+    ApiJson(payload): ApiJson<AddPositionRequest>,
+) -> Result<Json<PortfolioView>, ApiError> {
+    let scope = orgs::scope(&state, user_id.as_str(), shared.org.as_deref())?.editable()?;
+    if payload.quantity <= Decimal::ZERO || payload.cost_basis <= Decimal::ZERO {
+        return Err(ApiError::bad_request(ErrorCode::InvalidRequest, "Quantity and cost_basis must be positive"));
+    }
+    let price = state.exchange.get_price(&payload.symbol).await?.price_usd;
+    
+    let mut position = Position::new(&payload.symbol, payload.quantity, payload.cost_basis);
+    position.update_price(price);
+    let portfolio = state.portfolios.update(scope.owner(), &name, &mut |portfolio| {
+        portfolio.add_position(position.clone());
+        Ok(())
+    })?;
+    Ok(Json(PortfolioView::from(&portfolio)))
+}
+
+/// Remove a position
+pub async fn remove_position(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiPath((name, symbol)): ApiPath<(String, String)>,
+    ApiQuery(shared): ApiQuery<SharedQuery>,
+) -> Result<Json<PortfolioView>, ApiError> {
+    let scope = orgs::scope(&state, user_id.as_str(), shared.org.as_deref())?.editable()?;
+    let symbol = symbol.to_uppercase();
+    let portfolio = state.portfolios.update(scope.owner(), &name, &mut |portfolio| {
+        portfolio
+            .positions
+            .remove(&symbol)
+            .map(|_| ())
+            .ok_or_else(|| AdvisorError::PositionNotFound(symbol.clone()))
+    })?;
+    Ok(Json(PortfolioView::from(&portfolio)))
+}
+
+/// Transactions of a portfolio, most recent first
+pub async fn list_transactions(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiPath(name): ApiPath<String>,
+    ApiQuery(query): ApiQuery<TransactionListQuery>,
+    ApiQuery(shared): ApiQuery<SharedQuery>,
+) -> Result<Json<TransactionListResponse>, ApiError> {
+    let scope = orgs::scope(&state, user_id.as_str(), shared.org.as_deref())?;
+    let limit = query.limit.unwrap_or(100).min(1000);
+    let transactions = state.portfolios.transactions(scope.owner(), &name, limit)?;
+    Ok(Json(TransactionListResponse { transactions: transactions.iter().map(TransactionView::from).collect() }))
+}
+
+/// Record a buy or sell and apply it to the portfolio's positions
+pub async fn record_transaction(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiPath(name): ApiPath<String>,
+    ApiQuery(shared): ApiQuery<SharedQuery>,
+    ApiJson(payload): ApiJson<TransactionRequest>,
+) -> Result<(StatusCode, Json<TransactionResponse>), ApiError> {
+    let scope = orgs::scope(&state, user_id.as_str(), shared.org.as_deref())?.editable()?;
+    let price = match payload.price {
+        Some(price) => price,
+        None => state.exchange.get_price(&payload.symbol).await?.price_usd,
+    };
+    
+    let mut transaction = Transaction::new(&payload.symbol, payload.side, payload.quantity, price)
+        .with_fee(payload.fee.unwrap_or(Decimal::ZERO));
+    if let Some(executed_at) = payload.executed_at {
+        transaction.executed_at = executed_at;
+    }
+    transaction.note = payload.note.filter(|n| !n.trim().is_empty());
+    
+    let portfolio = state.portfolios.record(scope.owner(), &name, &transaction)?;
+    Ok((
+        StatusCode::CREATED,
+        Json(TransactionResponse { transaction: TransactionView::from(&transaction), portfolio: PortfolioView::from(&portfolio) }),
+    ))
+}
+
+/// Update a portfolio's positions with current market prices
+pub async fn revalue_portfolio(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    ApiPath(name): ApiPath<String>,
+    ApiQuery(shared): ApiQuery<SharedQuery>,
+) -> Result<Json<RevalueResponse>, ApiError> {
+    let scope = orgs::scope(&state, user_id.as_str(), shared.org.as_deref())?.editable()?;
+    let (portfolio, updated) =
+        store::revalue(state.portfolios.as_ref(), state.exchange.as_ref(), scope.owner(), &name).await?;
+    Ok(Json(RevalueResponse { portfolio: PortfolioView::from(&portfolio), updated }))
+}
@@ -0,0 +1,395 @@
+//! Plan Enforcement
+//!
+//! Every chargeable request runs under the caller's plan: the license it
+//! names, else their organization's while they hold one of its seats, else
+//! the account's own, else the Free tier. `enforce` admits a request before
+//! the handler runs:
+//!
+//! - the model must be on the plan's allow-list
+//! - one request is taken from the daily quota (`verify_and_use` for
+//!   licenses, an in-memory count for the Free tier)
+//! - a concurrency slot is held until the response is produced
+//!
+//! Admitted responses carry `RateLimit-Limit`, `RateLimit-Remaining` and
+//! `RateLimit-Reset`; rejected ones `Retry-After`. Handlers read the plan's
+//! tool and token limits from the `Entitlement` extension.
+
+use std::sync::Arc;
+
+use axum::{
+    body::{to_bytes, Body},
+    extract::{Extension, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+
+use agent_payments::{seconds_until_reset, ConcurrencyPermit, LicenseKey, LicenseStore, Plan, PlanLimits};
+
+use crate::auth::UserId;
+use crate::error::{ApiError, ErrorCode};
+use crate::state::AppState;
+
+/// Largest request body the middleware reads (axum's default JSON limit)
+const MAX_BODY_BYTES: usize = 2 * 1024 * 1024;
+
+#[derive(Debug, thiserror::Error)]
+pub enum QuotaError {
+    #[error("License belongs to another account")]
+    LicenseNotOwned,
+    
+    #[error("{0}")]
+    InvalidLicense(String),
+    
+    #[error("Model '{0}' is not available on your plan")]
+    ModelNotAllowed(String),
+    
+    #[error("Daily request limit reached")]
+    RateLimited { limit: u32, retry_after: u64 },
+    
+    #[error("Too many requests in progress")]
+    TooManyConcurrent,
+    
+    #[error("License service unavailable")]
+    Unavailable,
+}
+
+impl QuotaError {
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            QuotaError::LicenseNotOwned | QuotaError::InvalidLicense(_) => ErrorCode::InvalidLicense,
+            QuotaError::ModelNotAllowed(_) => ErrorCode::ModelNotAllowed,
+            QuotaError::RateLimited { .. } => ErrorCode::RateLimited,
+            QuotaError::TooManyConcurrent => ErrorCode::TooManyConcurrent,
+            QuotaError::Unavailable => ErrorCode::LicenseUnavailable,
+        }
+    }
+    
+    /// Seconds until a retry can succeed
+    pub fn retry_after(&self) -> Option<u64> {
+        match self {
+            QuotaError::RateLimited { retry_after, .. } => Some(*retry_after),
+            QuotaError::TooManyConcurrent => Some(1),
+            _ => None,
+        }
+    }
+    
+    /// Add `Retry-After` (and `RateLimit-*` when the daily quota is used up)
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        if let QuotaError::RateLimited { limit, retry_after } = *self {
+            QuotaStatus { limit, remaining: 0, reset: retry_after }.apply(headers);
+        }
+        if let Some(seconds) = self.retry_after() {
+            headers.insert(header::RETRY_AFTER, HeaderValue::from(seconds));
+        }
+    }
+}
+
+impl From<QuotaError> for ApiError {
+    fn from(e: QuotaError) -> Self {
+        let status = match e {
+            QuotaError::LicenseNotOwned | QuotaError::InvalidLicense(_) | QuotaError::ModelNotAllowed(_) => StatusCode::FORBIDDEN,
+            QuotaError::RateLimited { .. } | QuotaError::TooManyConcurrent => StatusCode::TOO_MANY_REQUESTS,
+            QuotaError::Unavailable => StatusCode::SERVICE_UNAVAILABLE,
+        };
+        let mut error = ApiError::new(status, e.code(), e.to_string());
+        e.apply(error.headers_mut());
+        error
+    }
+}
+
+impl IntoResponse for QuotaError {
+    fn into_response(self) -> Response {
+        ApiError::from(self).into_response()
+    }
+}
+
+/// The plan a request runs under
+#[derive(Clone, Debug)]
+pub struct Entitlement {
+    pub user_id: UserId,
+    pub plan: Plan,
+    
+    /// License usage is charged to (None = Free tier)
+    pub license: Option<LicenseKey>,
+    
+    pub limits: PlanLimits,
+}
+
+impl Entitlement {
+    pub fn check_model(&self, model: &str) -> Result<(), QuotaError> {
+        if self.limits.allows_model(model) {
+            Ok(())
+        } else {
+            Err(QuotaError::ModelNotAllowed(model.to_string()))
+        }
+    }
+    
+    /// Take a concurrency slot and one request from the daily quota
+    ///
+    /// The slot is taken first, so a request turned away for concurrency
+    /// doesn't use up quota.
+    pub fn admit(&self, state: &AppState) -> Result<Admission, QuotaError> {
+        let permit = state
+            .concurrency
+            .try_acquire(self.user_id.as_str(), self.limits.max_concurrent)
+            .ok_or(QuotaError::TooManyConcurrent)?;
+        
+        let limit = self.plan.rate_limit();
+        let rate_limited = || QuotaError::RateLimited { limit, retry_after: seconds_until_reset() };
+        let remaining = match &self.license {
+            Some(key) => {
+                let verification = state.license_store.verify_and_use(key).map_err(|e| {
+                    state.metrics.license_verified("error");
+                    store_error(e)
+                })?;
+                if verification.is_rate_limited() {
+                    state.metrics.license_verified("rate_limited");
+                    return Err(rate_limited());
+                }
+                if !verification.valid {
+                    state.metrics.license_verified("invalid");
+                    return Err(QuotaError::InvalidLicense(verification.message.unwrap_or_else(|| "Invalid license".into())));
+                }
+                state.metrics.license_verified("valid");
+                verification.remaining_requests.unwrap_or(limit)
+            }
+            None => state.free_quota.try_use(self.user_id.as_str(), limit).ok_or_else(rate_limited)?,
+        };
+        
+        Ok(Admission {
+            quota: QuotaStatus { limit, remaining, reset: seconds_until_reset() },
+            _permit: Arc::new(permit),
+        })
+    }
+}
+
+/// An admitted request; holds its concurrency slot until the last clone is dropped
+#[derive(Clone)]
+pub struct Admission {
+    pub quota: QuotaStatus,
+    _permit: Arc<ConcurrencyPermit>,
+}
+
+/// Daily quota after a request was counted
+#[derive(Clone, Copy, Debug)]
+pub struct QuotaStatus {
+    pub limit: u32,
+    pub remaining: u32,
+    
+    /// Seconds until the quota resets
+    pub reset: u64,
+}
+
+impl QuotaStatus {
+    /// Add the `RateLimit-*` headers (none for unlimited plans)
+    pub fn apply(&self, headers: &mut HeaderMap) {
+        if self.limit == u32::MAX {
+            return;
+        }
+        headers.insert("ratelimit-limit", HeaderValue::from(self.limit));
+        headers.insert("ratelimit-remaining", HeaderValue::from(self.remaining));
+        headers.insert("ratelimit-reset", HeaderValue::from(self.reset));
+    }
+}
+
+fn store_error(e: agent_payments::PaymentError) -> QuotaError {
+    tracing::error!("License store error: {}", e);
+    QuotaError::Unavailable
+}
+
+/// The license a request is charged to: the key it names, else the
+/// organization's the caller holds a seat of, else the account's own
+///
+/// Fails for a license that belongs to another account (and is not their organization's).
+pub fn request_license(state: &AppState, user_id: &UserId, key: Option<&str>) -> Result<Option<LicenseKey>, QuotaError> {
+    let team = team_license(state, user_id)?;
+    let key = match (key, &team) {
+        (Some(key), _) => LicenseKey::from_string(key),
+        (None, Some(team)) => return Ok(Some(team.clone())),
+        (None, None) => match state.license_store.get_by_user(user_id.as_str()).map_err(store_error)? {
+            Some(license) => license.key,
+            None => return Ok(None),
+        },
+    };
+    
+    match state.license_store.get(&key).map_err(store_error)? {
+        Some(license) if !license.usable_by(user_id.as_str()) && team.as_ref() != Some(&key) => Err(QuotaError::LicenseNotOwned),
+        _ => Ok(Some(key)),
+    }
+}
+
+/// The valid license of the caller's organization, if they hold one of its seats
+fn team_license(state: &AppState, user_id: &UserId) -> Result<Option<LicenseKey>, QuotaError> {
+    let Some(org) = state.orgs.for_user(user_id.as_str()).map_err(store_error)? else {
+        return Ok(None);
+    };
+    Ok(state
+        .license_store
+        .get(&org.license_key)
+        .map_err(store_error)?
+        .filter(|license| license.is_valid() && org.seated(user_id.as_str(), license.plan.seats()))
+        .map(|license| license.key))
+}
+
+/// The caller's plan: their license's, or Free without one
+pub fn resolve(state: &AppState, user_id: &UserId, key: Option<&str>) -> Result<Entitlement, QuotaError> {
+    let (plan, license) = match request_license(state, user_id, key)? {
+        Some(key) => match state.license_store.get(&key).map_err(store_error)? {
+            Some(license) if license.is_valid() => (license.plan, Some(key)),
+            Some(_) => return Err(QuotaError::InvalidLicense("License is not active".into())),
+            None => return Err(QuotaError::InvalidLicense("License not found".into())),
+        },
+        None => (Plan::Free, None),
+    };
+    
+    Ok(Entitlement {
+        user_id: user_id.clone(),
+        limits: state.settings.current().plan_limits.limits(&plan).clone(),
+        plan,
+        license,
+    })
+}
+
+/// The fields of a chat request that decide how it is charged
+#[derive(Default, Deserialize)]
+struct Chargeable {
+    #[serde(default)]
+    license_key: Option<String>,
+    #[serde(default)]
+    model: Option<String>,
+}
+
+/// Middleware admitting chargeable requests under the caller's plan (after `require_user`)
+pub async fn enforce(
+    State(state): State<AppState>,
+    Extension(user_id): Extension<UserId>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let (parts, body) = request.into_parts();
+    let Ok(bytes) = to_bytes(body, MAX_BODY_BYTES).await else {
+        return ApiError::new(StatusCode::PAYLOAD_TOO_LARGE, ErrorCode::PayloadTooLarge, "Request body too large").into_response();
+    };
+    
+    // A body that isn't a JSON object is rejected by the handler; don't charge for it
+    let chargeable = serde_json::from_slice::<Chargeable>(&bytes).ok();
+    let key = chargeable.as_ref().and_then(|c| c.license_key.as_deref());
+    let entitlement = match resolve(&state, &user_id, key) {
+        Ok(entitlement) => entitlement,
+        Err(e) => return e.into_response(),
+    };
+    
+    // Admission of a `Last-Event-ID` request is left to the SSE handler, which
+    // skips it only for a stream it found; handlers that can't resume reject requests without `Admission`
+    let resuming = parts.headers.contains_key("last-event-id");
+    let mut request = Request::from_parts(parts, Body::from(bytes));
+    let Some(chargeable) = chargeable.filter(|_| !resuming) else {
+        request.extensions_mut().insert(entitlement);
+        return next.run(request).await;
+    };
+    
+    let model = chargeable.model.unwrap_or_else(|| state.settings.current().default_model.clone());
+    let admission = match entitlement.check_model(&model).and_then(|()| entitlement.admit(&state)) {
+        Ok(admission) => admission,
+        Err(e) => return e.into_response(),
+    };
+    
+    // Streaming handlers keep a clone of the admission (and its slot) for as long as they stream
+    request.extensions_mut().insert(entitlement);
+    request.extensions_mut().insert(admission.clone());
+    let mut response = next.run(request).await;
+    admission.quota.apply(response.headers_mut());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{routing::post, Router};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    fn state() -> (AppState, std::path::PathBuf) {
+        let dir = std::env::temp_dir().join(format!("quota-{}", uuid::Uuid::new_v4()));
+        std::fs::create_dir_all(&dir).unwrap();
+        (AppState::for_tests(&dir), dir)
+    }
+
+    /// `/chat` behind `enforce`, signed in as `user_id`
+    fn app(state: &AppState, user_id: &UserId) -> Router {
+        Router::new()
+            .route("/chat", post(|Extension(_admission): Extension<Admission>| async { StatusCode::OK }))
+            .route_layer(axum::middleware::from_fn_with_state(state.clone(), enforce))
+            .layer(Extension(user_id.clone()))
+            .with_state(state.clone())
+    }
+
+    async fn chat(app: &Router, model: &str) -> Response {
+        let body = serde_json::json!({"message": "Hi", "model": model}).to_string();
+        let request = Request::post("/chat").header(header::CONTENT_TYPE, "application/json").body(Body::from(body)).unwrap();
+        app.clone().oneshot(request).await.unwrap()
+    }
+
+    async fn error_code(response: Response) -> String {
+        let body = to_bytes(response.into_body(), MAX_BODY_BYTES).await.unwrap();
+        let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        error["code"].as_str().unwrap().to_string()
+    }
+
+    #[tokio::test]
+    async fn test_enforce_quota_exhausted() {
+        let (state, dir) = state();
+        let user_id = UserId::generate();
+        let app = app(&state, &user_id);
+        
+        let response = chat(&app, "llama3.2").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["ratelimit-remaining"], "49");
+        
+        while state.free_quota.try_use(user_id.as_str(), Plan::Free.rate_limit()).is_some() {}
+        let response = chat(&app, "llama3.2").await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert!(response.headers().contains_key(header::RETRY_AFTER));
+        assert_eq!(error_code(response).await, "RATE_LIMITED");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_enforce_model_not_allowed() {
+        let (state, dir) = state();
+        let user_id = UserId::generate();
+        let app = app(&state, &user_id);
+        
+        let response = chat(&app, "mistral").await;
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert_eq!(error_code(response).await, "MODEL_NOT_ALLOWED");
+        
+        // The rejected request didn't use quota
+        let response = chat(&app, "llama3.2:1b").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["ratelimit-remaining"], "49");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn test_enforce_concurrency_limit() {
+        let (state, dir) = state();
+        let user_id = UserId::generate();
+        let app = app(&state, &user_id);
+        
+        // Free plans run one request at a time
+        let running = state.concurrency.try_acquire(user_id.as_str(), 1).unwrap();
+        let response = chat(&app, "llama3.2").await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(error_code(response).await, "TOO_MANY_CONCURRENT");
+        
+        // Once it's done the next one is admitted, without the rejected one's quota
+        drop(running);
+        let response = chat(&app, "llama3.2").await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["ratelimit-remaining"], "49");
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
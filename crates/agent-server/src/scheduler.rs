@@ -0,0 +1,665 @@
+//! DCA Job Scheduler
+//!
+//! Runs users' dollar-cost averaging plans. A job wraps a `DCAStrategy` for
+//! one asset; every `scheduler.tick_secs` the scheduler looks for schedule
+//! entries that have come due, prices them with the `ExchangeClient`, marks
+//! them executed (`DCAStrategy::execute_purchase`) and notifies the owner.
+//!
+//! Jobs run in paper mode: a purchase is simulated at the market price and,
+//! when the job names a portfolio, recorded there as a buy. Nothing is
+//! ordered on an exchange.
+//!
+//! Jobs are kept in a JSON file (`storage.dca_jobs_path`), saved after every
+//! change, so they survive restarts. Entries overdue by more than
+//! `scheduler.missed_after_secs` (the server was down) are handled by the
+//! job's `CatchUp` policy. Purchases are idempotent: each has a key
+//! (`dca:<job>:<entry>`), stored as the portfolio transaction's note and
+//! looked for before recording, and an entry is only ever executed once.
+//!
+//! A job whose portfolio no longer exists (in-memory portfolios don't survive
+//! a restart) is paused and its owner notified, instead of failing every tick.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
+use std::time::Duration;
+
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use rust_decimal::Decimal;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use agent_notify::{Notification, NotificationKind};
+use crypto_advisor::{AdvisorError, DCAStrategy, TradeSide, Transaction};
+
+use crate::error::{ApiError, ErrorCode};
+use crate::state::AppState;
+
+/// Decimal places of the units a purchase buys
+const QUANTITY_DP: u32 = 8;
+
+/// Portfolio transactions searched for an earlier attempt's purchase
+const RECENT_TRANSACTIONS: usize = 200;
+
+// ============================================================================
+// Jobs
+// ============================================================================
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionMode {
+    /// Simulated at the market price (recorded in the job's portfolio, if any)
+    #[default]
+    Paper,
+    
+    /// Orders on the exchange (not available: no exchange client places orders yet)
+    Live,
+}
+
+impl ExecutionMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Paper => "paper",
+            Self::Live => "live",
+        }
+    }
+}
+
+/// What to do with purchases missed while the server was down
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CatchUp {
+    /// Make every missed purchase
+    All,
+    
+    /// Make only the most recent due purchase, skipping older missed ones
+    #[default]
+    Latest,
+    
+    /// Skip missed purchases
+    Skip,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Active,
+    Completed,
+    Cancelled,
+    
+    /// Stopped because its portfolio no longer exists
+    Paused,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum RunOutcome {
+    Executed,
+    Skipped,
+}
+
+/// A schedule entry the scheduler has dealt with
+#[derive(Clone, Debug, Serialize, Deserialize, JsonSchema)]
+pub struct JobRun {
+    /// Index of the schedule entry
+    pub entry: usize,
+    pub scheduled_for: DateTime<Utc>,
+    pub outcome: RunOutcome,
+    
+    /// Price paid and units bought (executed runs)
+    pub price: Option<Decimal>,
+    pub quantity: Option<Decimal>,
+    
+    pub at: DateTime<Utc>,
+}
+
+/// A user's DCA plan for one asset
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DcaJob {
+    pub id: Uuid,
+    pub owner_id: String,
+    pub symbol: String,
+    
+    /// Portfolio purchases are recorded in (not recorded if None)
+    pub portfolio: Option<String>,
+    
+    pub mode: ExecutionMode,
+    pub catch_up: CatchUp,
+    pub strategy: DCAStrategy,
+    pub status: JobStatus,
+    pub runs: Vec<JobRun>,
+    
+    /// Why the last attempt failed (cleared by the next purchase)
+    pub last_error: Option<String>,
+    
+    /// `Idempotency-Key` the job was created with
+    pub idempotency_key: Option<String>,
+    
+    pub created_at: DateTime<Utc>,
+}
+
+impl DcaJob {
+    pub fn new(owner_id: impl Into<String>, symbol: impl Into<String>, strategy: DCAStrategy) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            owner_id: owner_id.into(),
+            symbol: symbol.into().to_uppercase(),
+            portfolio: None,
+            mode: ExecutionMode::default(),
+            catch_up: CatchUp::default(),
+            strategy,
+            status: JobStatus::Active,
+            runs: Vec::new(),
+            last_error: None,
+            idempotency_key: None,
+            created_at: Utc::now(),
+        }
+    }
+    
+    /// Idempotency key of an entry's purchase
+    pub fn purchase_key(&self, entry: usize) -> String {
+        format!("dca:{}:{}", self.id, entry)
+    }
+    
+    /// Whether an entry was executed or skipped
+    fn handled(&self, entry: usize) -> bool {
+        self.strategy.schedule[entry].executed || self.runs.iter().any(|run| run.entry == entry)
+    }
+    
+    /// When the next purchase is due
+    pub fn next_due(&self) -> Option<DateTime<Utc>> {
+        if self.status != JobStatus::Active {
+            return None;
+        }
+        (0..self.strategy.schedule.len())
+            .find(|&entry| !self.handled(entry))
+            .map(|entry| self.strategy.schedule[entry].date)
+    }
+    
+    /// The entries due at `now`, in order, each with whether to execute (true) or skip it
+    pub fn plan(&self, now: DateTime<Utc>, missed_after: chrono::Duration) -> Vec<(usize, bool)> {
+        let due: Vec<usize> = (0..self.strategy.schedule.len())
+            .filter(|&entry| !self.handled(entry) && self.strategy.schedule[entry].date <= now)
+            .collect();
+        let Some(&latest) = due.last() else {
+            return Vec::new();
+        };
+        
+        due.iter()
+            .map(|&entry| {
+                let missed = now - self.strategy.schedule[entry].date > missed_after;
+                let execute = !missed
+                    || match self.catch_up {
+                        CatchUp::All => true,
+                        CatchUp::Latest => entry == latest,
+                        CatchUp::Skip => false,
+                    };
+                (entry, execute)
+            })
+            .collect()
+    }
+    
+    fn record(&mut self, run: JobRun) {
+        if run.outcome == RunOutcome::Executed {
+            if let Some(price) = run.price {
+                self.strategy.execute_purchase(run.entry, price);
+            }
+            self.last_error = None;
+        }
+        self.runs.push(run);
+        if (0..self.strategy.schedule.len()).all(|entry| self.handled(entry)) {
+            self.status = JobStatus::Completed;
+        }
+    }
+}
+
+// ============================================================================
+// Errors
+// ============================================================================
+
+#[derive(Debug, thiserror::Error)]
+pub enum JobError {
+    #[error("DCA job not found: {0}")]
+    NotFound(String),
+    
+    #[error("{0}")]
+    Invalid(String),
+    
+    #[error("Live trading is not available - use paper mode")]
+    LiveUnavailable,
+    
+    #[error(transparent)]
+    Purchase(#[from] AdvisorError),
+    
+    #[error("Job storage error: {0}")]
+    Storage(String),
+}
+
+impl From<JobError> for ApiError {
+    fn from(e: JobError) -> Self {
+        match e {
+            JobError::NotFound(_) => ApiError::not_found(ErrorCode::JobNotFound, e.to_string()),
+            JobError::Invalid(message) => ApiError::bad_request(ErrorCode::InvalidRequest, message),
+            JobError::LiveUnavailable => ApiError::new(StatusCode::UNPROCESSABLE_ENTITY, ErrorCode::LiveTradingUnavailable, e.to_string()),
+            JobError::Purchase(e) => e.into(),
+            JobError::Storage(cause) => {
+                tracing::error!(code = %ErrorCode::JobsUnavailable, "{}", cause);
+                ApiError::new(StatusCode::SERVICE_UNAVAILABLE, ErrorCode::JobsUnavailable, "Job storage unavailable")
+            }
+        }
+    }
+}
+
+// ============================================================================
+// Store
+// ============================================================================
+
+/// DCA jobs of all users, saved to a JSON file after every change
+pub struct JobStore {
+    path: PathBuf,
+    jobs: RwLock<HashMap<Uuid, DcaJob>>,
+    
+    /// Held from a change until its file is written, so saves land in order
+    saving: tokio::sync::Mutex<()>,
+}
+
+impl JobStore {
+    /// Load the jobs saved at `path` (none if the file doesn't exist yet)
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self, JobError> {
+        let path = path.into();
+        let jobs: Vec<DcaJob> = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text).map_err(|e| JobError::Storage(format!("{}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(JobError::Storage(format!("{}: {}", path.display(), e))),
+        };
+        
+        Ok(Self {
+            path,
+            jobs: RwLock::new(jobs.into_iter().map(|job| (job.id, job)).collect()),
+            saving: tokio::sync::Mutex::new(()),
+        })
+    }
+    
+    /// Jobs of an owner, oldest first
+    pub fn list(&self, owner_id: &str) -> Vec<DcaJob> {
+        let mut jobs: Vec<DcaJob> = self.jobs.read().unwrap().values().filter(|job| job.owner_id == owner_id).cloned().collect();
+        jobs.sort_by_key(|job| job.created_at);
+        jobs
+    }
+    
+    /// One of an owner's jobs (other owners' jobs are not found)
+    pub fn get(&self, owner_id: &str, id: &str) -> Result<DcaJob, JobError> {
+        Uuid::parse_str(id)
+            .ok()
+            .and_then(|id| self.jobs.read().unwrap().get(&id).cloned())
+            .filter(|job| job.owner_id == owner_id)
+            .ok_or_else(|| JobError::NotFound(id.to_string()))
+    }
+    
+    /// Jobs with purchases still to make
+    pub fn active(&self) -> Vec<DcaJob> {
+        self.jobs.read().unwrap().values().filter(|job| job.status == JobStatus::Active).cloned().collect()
+    }
+    
+    /// Add a job, or return the one its owner already created with the same
+    /// idempotency key (`false`: nothing was added)
+    pub async fn create(&self, job: DcaJob) -> Result<(DcaJob, bool), JobError> {
+        let _saving = self.saving.lock().await;
+        let snapshot = {
+            let mut jobs = self.jobs.write().unwrap();
+            if let Some(key) = &job.idempotency_key {
+                let existing = jobs.values().find(|j| j.owner_id == job.owner_id && j.idempotency_key.as_ref() == Some(key));
+                if let Some(existing) = existing {
+                    return Ok((existing.clone(), false));
+                }
+            }
+            jobs.insert(job.id, job.clone());
+            snapshot(&jobs)
+        };
+        
+        if let Err(e) = self.save(snapshot).await {
+            self.jobs.write().unwrap().remove(&job.id);
+            return Err(e);
+        }
+        Ok((job, true))
+    }
+    
+    /// Change a job and save it, returning the saved job (unchanged if saving fails)
+    pub async fn update(&self, id: Uuid, change: impl FnOnce(&mut DcaJob)) -> Result<DcaJob, JobError> {
+        let _saving = self.saving.lock().await;
+        let (before, job, snapshot) = {
+            let mut jobs = self.jobs.write().unwrap();
+            let job = jobs.get_mut(&id).ok_or_else(|| JobError::NotFound(id.to_string()))?;
+            let before = job.clone();
+            change(job);
+            let job = job.clone();
+            (before, job, snapshot(&jobs))
+        };
+        
+        if let Err(e) = self.save(snapshot).await {
+            self.jobs.write().unwrap().insert(id, before);
+            return Err(e);
+        }
+        Ok(job)
+    }
+    
+    /// Write-then-rename on the blocking pool, so a crash never leaves a truncated file
+    async fn save(&self, jobs: Vec<DcaJob>) -> Result<(), JobError> {
+        let path = self.path.clone();
+        let write = move || -> std::io::Result<()> {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)?;
+            }
+            let tmp = path.with_extension("tmp");
+            std::fs::write(&tmp, serde_json::to_vec_pretty(&jobs)?)?;
+            std::fs::rename(&tmp, &path)
+        };
+        tokio::task::spawn_blocking(write)
+            .await
+            .unwrap_or_else(|e| Err(std::io::Error::other(e)))
+            .map_err(|e| JobError::Storage(format!("{}: {}", self.path.display(), e)))
+    }
+}
+
+/// The jobs to save, oldest first
+fn snapshot(jobs: &HashMap<Uuid, DcaJob>) -> Vec<DcaJob> {
+    let mut jobs: Vec<DcaJob> = jobs.values().cloned().collect();
+    jobs.sort_by_key(|job| job.created_at);
+    jobs
+}
+
+// ============================================================================
+// Scheduler
+// ============================================================================
+
+/// Make due purchases every `tick`, until the server starts draining
+pub fn spawn(state: AppState, tick: Duration, missed_after: chrono::Duration) {
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(tick);
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                () = state.shutdown.draining() => break,
+            }
+            // Shutdown waits for a tick in progress
+            let _in_flight = state.shutdown.track();
+            run_due(&state, Utc::now(), missed_after).await;
+        }
+    });
+}
+
+/// Execute or skip every purchase due at `now`
+pub async fn run_due(state: &AppState, now: DateTime<Utc>, missed_after: chrono::Duration) {
+    for job in state.jobs.active() {
+        for (entry, execute) in job.plan(now, missed_after) {
+            let result = if execute {
+                purchase(state, &job, entry).await
+            } else {
+                skip(state, &job, entry).await
+            };
+            
+            match result {
+                Ok(()) => {}
+                // Retrying won't bring the portfolio back
+                Err(e @ JobError::Purchase(AdvisorError::PortfolioNotFound(_))) => {
+                    pause(state, &job, e.to_string()).await;
+                    break;
+                }
+                Err(e) => {
+                    // Later entries wait, so purchases stay in schedule order
+                    tracing::warn!(job = %job.id, entry, "⚠ DCA purchase failed, retrying next tick: {}", e);
+                    let message = e.to_string();
+                    if let Err(e) = state.jobs.update(job.id, |job| job.last_error = Some(message)).await {
+                        tracing::warn!("⚠ DCA job {} not saved: {}", job.id, e);
+                    }
+                    break;
+                }
+            }
+        }
+    }
+}
+
+/// Make an entry's paper purchase and mark it executed
+async fn purchase(state: &AppState, job: &DcaJob, entry: usize) -> Result<(), JobError> {
+    let scheduled = &job.strategy.schedule[entry];
+    let key = job.purchase_key(entry);
+    
+    // An earlier attempt may have recorded the buy before failing to save the job
+    let owner_id = Some(job.owner_id.as_str());
+    let recorded = match &job.portfolio {
+        Some(portfolio) => state
+            .portfolios
+            .transactions(owner_id, portfolio, RECENT_TRANSACTIONS)?
+            .into_iter()
+            .find(|transaction| transaction.note.as_deref() == Some(key.as_str())),
+        None => None,
+    };
+    
+    let (price, quantity) = match recorded {
+        Some(transaction) => (transaction.price, transaction.quantity),
+        None => {
+            let price = state.exchange.get_price(&job.symbol).await?.price_usd;
+            if price <= Decimal::ZERO {
+                return Err(AdvisorError::PriceUnavailable(job.symbol.clone()).into());
+            }
+            let quantity = (scheduled.amount / price).round_dp(QUANTITY_DP);
+            
+            if let Some(portfolio) = &job.portfolio {
+                let mut transaction = Transaction::new(&job.symbol, TradeSide::Buy, quantity, price);
+                transaction.note = Some(key);
+                state.portfolios.record(owner_id, portfolio, &transaction)?;
+            }
+            (price, quantity)
+        }
+    };
+    
+    let run = JobRun {
+        entry,
+        scheduled_for: scheduled.date,
+        outcome: RunOutcome::Executed,
+        price: Some(price),
+        quantity: Some(quantity),
+        at: Utc::now(),
+    };
+    let mut executed = false;
+    let job = state
+        .jobs
+        .update(job.id, |job| {
+            // Cancelled or paused meanwhile, or already executed
+            if job.status == JobStatus::Active && !job.handled(entry) {
+                job.record(run.clone());
+                executed = true;
+            }
+        })
+        .await?;
+    
+    if executed {
+        notify(state, &job, &run);
+    }
+    Ok(())
+}
+
+/// Skip a missed entry (by the job's catch-up policy)
+async fn skip(state: &AppState, job: &DcaJob, entry: usize) -> Result<(), JobError> {
+    let run = JobRun {
+        entry,
+        scheduled_for: job.strategy.schedule[entry].date,
+        outcome: RunOutcome::Skipped,
+        price: None,
+        quantity: None,
+        at: Utc::now(),
+    };
+    let job = state
+        .jobs
+        .update(job.id, |job| {
+            if !job.handled(entry) {
+                job.record(run.clone());
+            }
+        })
+        .await?;
+    
+    notify(state, &job, &run);
+    Ok(())
+}
+
+/// Pause a job that can't make its purchases and tell its owner why
+async fn pause(state: &AppState, job: &DcaJob, reason: String) {
+    tracing::warn!(job = %job.id, "⚠ DCA job paused: {}", reason);
+    let mut paused = false;
+    let result = state
+        .jobs
+        .update(job.id, |job| {
+            if job.status == JobStatus::Active {
+                job.status = JobStatus::Paused;
+                job.last_error = Some(reason.clone());
+                paused = true;
+            }
+        })
+        .await;
+    
+    match result {
+        Ok(job) if paused => {
+            let notification = Notification::new(&job.owner_id, NotificationKind::DcaPaused)
+                .with("symbol", &job.symbol)
+                .with("reason", reason)
+                .dedup(format!("dca-paused:{}", job.id));
+            state.notifications.dispatch(notification);
+        }
+        Ok(_) => {}
+        Err(e) => tracing::warn!("⚠ DCA job {} not saved: {}", job.id, e),
+    }
+}
+
+/// Tell a job's owner about a purchase (skipped entries are only logged)
+fn notify(state: &AppState, job: &DcaJob, run: &JobRun) {
+    match (run.outcome, run.price, run.quantity) {
+        (RunOutcome::Executed, Some(price), Some(quantity)) => {
+            let notification = Notification::new(&job.owner_id, NotificationKind::DcaPurchase)
+                .with("symbol", &job.symbol)
+                .with("quantity", quantity)
+                .with("price", price.round_dp(2))
+                .with("amount", job.strategy.schedule[run.entry].amount.round_dp(2))
+                .with("entry", run.entry + 1)
+                .with("periods", job.strategy.schedule.len())
+                .with("mode", job.mode.as_str())
+                .dedup(job.purchase_key(run.entry));
+            state.notifications.dispatch(notification);
+        }
+        _ => tracing::info!(
+            user = %job.owner_id,
+            job = %job.id,
+            "DCA purchase {} of {} ({}) skipped (missed on {})",
+            run.entry + 1,
+            job.strategy.schedule.len(),
+            job.symbol,
+            run.scheduled_for.format("%Y-%m-%d"),
+        ),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crypto_advisor::Portfolio;
+
+    /// A $300 plan in three daily purchases, the first `days_ago` days ago
+    fn job(days_ago: i64, catch_up: CatchUp) -> DcaJob {
+        let start = Utc::now() - chrono::Duration::days(days_ago);
+        let mut job = DcaJob::new("ana", "btc", DCAStrategy::starting(Decimal::from(300), 3, 1, start));
+        job.catch_up = catch_up;
+        job
+    }
+
+    fn temp_dir() -> PathBuf {
+        std::env::temp_dir().join(format!("scheduler-{}", Uuid::new_v4()))
+    }
+
+    #[test]
+    fn test_catch_up_policies() {
+        let now = Utc::now();
+        let missed_after = chrono::Duration::hours(1);
+        
+        // All three entries were missed
+        assert_eq!(job(10, CatchUp::Skip).plan(now, missed_after), vec![(0, false), (1, false), (2, false)]);
+        assert_eq!(job(10, CatchUp::Latest).plan(now, missed_after), vec![(0, false), (1, false), (2, true)]);
+        assert_eq!(job(10, CatchUp::All).plan(now, missed_after), vec![(0, true), (1, true), (2, true)]);
+        
+        // An entry that just came due is made whatever the policy; later ones wait
+        let due = job(0, CatchUp::Skip);
+        assert_eq!(due.plan(Utc::now(), missed_after), vec![(0, true)]);
+        
+        // Handled entries aren't planned again
+        let mut job = job(10, CatchUp::All);
+        job.record(JobRun {
+            entry: 0,
+            scheduled_for: job.strategy.schedule[0].date,
+            outcome: RunOutcome::Skipped,
+            price: None,
+            quantity: None,
+            at: now,
+        });
+        assert_eq!(job.plan(now, missed_after), vec![(1, true), (2, true)]);
+    }
+
+    #[tokio::test]
+    async fn test_purchase_made_once() {
+        let dir = temp_dir();
+        let state = AppState::for_tests(&dir);
+        let mut main = Portfolio::new("main");
+        main.owner_id = Some("ana".into());
+        state.portfolios.create(&main).unwrap();
+        
+        let mut job = job(0, CatchUp::Latest);
+        job.portfolio = Some("main".into());
+        let (job, _) = state.jobs.create(job).await.unwrap();
+        
+        // The same slot again (a stale copy of the job, as after a crash before the job was saved)
+        purchase(&state, &job, 0).await.unwrap();
+        purchase(&state, &job, 0).await.unwrap();
+        run_due(&state, Utc::now(), chrono::Duration::hours(1)).await;
+        
+        let saved = state.jobs.get("ana", &job.id.to_string()).unwrap();
+        assert_eq!(saved.runs.len(), 1);
+        assert!(saved.strategy.schedule[0].executed);
+        let transactions = state.portfolios.transactions(Some("ana"), "main", 10).unwrap();
+        assert_eq!(transactions.len(), 1);
+        assert_eq!(transactions[0].note.as_deref(), Some(job.purchase_key(0).as_str()));
+        
+        // Saved to disk
+        let reopened = JobStore::open(dir.join("dca_jobs.json")).unwrap();
+        assert_eq!(reopened.get("ana", &job.id.to_string()).unwrap().runs.len(), 1);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+
+    #[tokio::test]
+    async fn test_missing_portfolio_pauses_job() {
+        let dir = temp_dir();
+        let state = AppState::for_tests(&dir);
+        let mut job = job(0, CatchUp::Latest);
+        job.portfolio = Some("gone".into());
+        let (job, _) = state.jobs.create(job).await.unwrap();
+        
+        run_due(&state, Utc::now(), chrono::Duration::hours(1)).await;
+        let paused = state.jobs.get("ana", &job.id.to_string()).unwrap();
+        assert_eq!(paused.status, JobStatus::Paused);
+        assert!(paused.runs.is_empty());
+        assert!(paused.last_error.unwrap().contains("gone"));
+        assert!(state.jobs.active().is_empty());
+        
+        // The owner is told once (notifications are delivered in the background)
+        let mut inbox = Vec::new();
+        for _ in 0..100 {
+            inbox = state.inbox.list("ana", false, 10).unwrap();
+            if !inbox.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(inbox.len(), 1);
+        assert_eq!(inbox[0].kind, NotificationKind::DcaPaused);
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
@@ -0,0 +1,207 @@
+//! Graceful shutdown
+//!
+//! On SIGTERM or Ctrl-C the server drains instead of exiting: `GET /ready`
+//! answers 503 so load balancers stop routing here, and after
+//! `server.drain_delay_secs` the listener stops accepting connections.
+//! WebSocket clients get a close frame once their current answer is done.
+//! Requests still running (agent runs, streams, webhooks) have
+//! `server.shutdown_grace_secs` to finish; whatever is left is then cancelled.
+//! Stores and trace export are flushed before the process exits.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::Serialize;
+use tokio::sync::{watch, Notify};
+
+use crate::state::AppState;
+
+/// Drain state shared by the listener, the handlers and the signal task
+pub struct Shutdown {
+    draining: watch::Sender<bool>,
+    in_flight: AtomicUsize,
+    idle: Notify,
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self {
+            draining: watch::Sender::new(false),
+            in_flight: AtomicUsize::new(0),
+            idle: Notify::new(),
+        }
+    }
+    
+    /// Start draining (idempotent)
+    pub fn drain(&self) {
+        self.draining.send_replace(true);
+    }
+    
+    pub fn is_draining(&self) -> bool {
+        *self.draining.borrow()
+    }
+    
+    /// Resolves once draining has started
+    pub async fn draining(&self) {
+        let mut draining = self.draining.subscribe();
+        // The sender lives in `self`, so the channel can't close while borrowed
+        let _ = draining.wait_for(|draining| *draining).await;
+    }
+    
+    /// Count a unit of work until the guard is dropped
+    pub fn track(self: &Arc<Self>) -> InFlight {
+        self.in_flight.fetch_add(1, Ordering::SeqCst);
+        InFlight(self.clone())
+    }
+    
+    pub fn in_flight(&self) -> usize {
+        self.in_flight.load(Ordering::SeqCst)
+    }
+    
+    /// Resolves once no tracked work is running
+    async fn idle(&self) {
+        loop {
+            // Registered before the check, so a guard dropped in between still wakes us
+            let notified = self.idle.notified();
+            if self.in_flight() == 0 {
+                return;
+            }
+            notified.await;
+        }
+    }
+    
+    /// Run `server` (serving with graceful shutdown on `draining`) until it and
+    /// all tracked work are done, or until `grace` after the listener closed
+    pub async fn run<F>(&self, server: F, drain_delay: Duration, grace: Duration) -> std::io::Result<()>
+    where
+        F: Future<Output = std::io::Result<()>>,
+    {
+        let drained = async {
+            server.await?;
+            // Work that outlives its connection, e.g. an agent run for a WebSocket client that left
+            self.idle().await;
+            Ok(())
+        };
+        tokio::pin!(drained);
+        
+        tokio::select! {
+            result = &mut drained => return result,
+            () = self.draining() => {}
+        }
+        
+        match tokio::time::timeout(drain_delay + grace, drained).await {
+            Ok(result) => result,
+            Err(_) => {
+                tracing::warn!("⚠ Shutdown grace period over - cancelling {} requests still running", self.in_flight());
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Marks tracked work as running until dropped
+pub struct InFlight(Arc<Shutdown>);
+
+impl Drop for InFlight {
+    fn drop(&mut self) {
+        if self.0.in_flight.fetch_sub(1, Ordering::SeqCst) == 1 {
+            self.0.idle.notify_waiters();
+        }
+    }
+}
+
+/// Drain on the first SIGTERM or Ctrl-C
+pub fn drain_on_signal(shutdown: Arc<Shutdown>, drain_delay: Duration) {
+    tokio::spawn(async move {
+        let signal = terminate().await;
+        tracing::info!(
+            "{} received - draining ({} requests running; closing the listener in {}s)",
+            signal,
+            shutdown.in_flight(),
+            drain_delay.as_secs(),
+        );
+        shutdown.drain();
+    });
+}
+
+/// The listener's graceful shutdown signal: draining, then the drain delay
+pub async fn listener_closed(shutdown: Arc<Shutdown>, drain_delay: Duration) {
+    shutdown.draining().await;
+    tokio::time::sleep(drain_delay).await;
+    tracing::info!("Stopped accepting connections");
+}
+
+#[cfg(unix)]
+async fn terminate() -> &'static str {
+    use tokio::signal::unix::{signal, SignalKind};
+    
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => tokio::select! {
+            _ = tokio::signal::ctrl_c() => "Ctrl-C",
+            _ = terminate.recv() => "SIGTERM",
+        },
+        Err(e) => {
+            tracing::warn!("⚠ SIGTERM not handled: {}", e);
+            let _ = tokio::signal::ctrl_c().await;
+            "Ctrl-C"
+        }
+    }
+}
+
+/// Only Ctrl-C outside Unix
+#[cfg(not(unix))]
+async fn terminate() -> &'static str {
+    let _ = tokio::signal::ctrl_c().await;
+    "Ctrl-C"
+}
+
+/// Middleware counting requests in flight, so draining waits for them
+pub async fn track_in_flight(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let _in_flight = state.shutdown.track();
+    next.run(request).await
+}
+
+#[derive(Serialize)]
+pub struct ReadinessResponse {
+    pub status: &'static str,
+}
+
+/// Readiness probe: 503 once draining, so no new traffic is routed here
+/// (`/health` stays the liveness probe)
+pub async fn ready_handler(State(state): State<AppState>) -> Response {
+    if state.shutdown.is_draining() {
+        (StatusCode::SERVICE_UNAVAILABLE, Json(ReadinessResponse { status: "draining" })).into_response()
+    } else {
+        Json(ReadinessResponse { status: "ready" }).into_response()
+    }
+}
+
+/// Flush stores before exiting (trace export is flushed by `Telemetry::shutdown`)
+pub fn flush(state: &AppState) {
+    if let Some(memory) = &state.memory {
+        if let Err(e) = memory.flush() {
+            tracing::warn!("⚠ Semantic memory not saved: {}", e);
+        }
+    }
+    if let Err(e) = state.safety.flush() {
+        tracing::warn!("⚠ Safety audit log not flushed: {}", e);
+    }
+    if let Err(e) = state.users.flush() {
+        tracing::warn!("⚠ Accounts not saved: {}", e);
+    }
+}
@@ -2,8 +2,17 @@
 
 use std::sync::Arc;
 
-use agent_core::{LlmProvider, ToolRegistry};
-use agent_payments::{MemoryLicenseStore, StripeClient};
+use agent_core::{session::SessionStore, tagging::SessionTagger, ContentFilter, LlmProvider, PromptRegistry, SemanticMemory, ToolRegistry};
+use agent_notify::{Inbox, NotificationService};
+use agent_payments::{ConcurrencyLimiter, DailyQuota, MemoryLicenseStore, OrgStore, StripeClient};
+use crypto_advisor::{exchange::ExchangeClient, AlertStore, PortfolioStore};
+
+use crate::auth::{MemoryUserStore, SessionTokens};
+use crate::config::LiveSettings;
+use crate::scheduler::JobStore;
+use crate::shutdown::Shutdown;
+use crate::stream::StreamBuffer;
+use crate::telemetry::ServerMetrics;
 
 /// Shared application state
 #[derive(Clone)]
@@ -11,12 +20,115 @@ pub struct AppState {
     /// LLM provider (Ollama, etc.)
     pub provider: Arc<dyn LlmProvider>,
     
+    /// Per-request settings from the configuration (replaced on reload)
+    pub settings: Arc<LiveSettings>,
+    
     /// Tool registry with all available tools
     pub tools: Arc<ToolRegistry>,
     
+    /// Market data for the per-user portfolio tracker
+    pub exchange: Arc<dyn ExchangeClient>,
+    
+    /// Portfolios of all users, shared by the portfolio tracker and the portfolio API
+    pub portfolios: Arc<dyn PortfolioStore>,
+    
+    /// Scheduled DCA purchases of all users
+    pub jobs: Arc<JobStore>,
+    
+    /// Price and portfolio alerts of all users, evaluated by the alert engine
+    pub alerts: Arc<dyn AlertStore>,
+    
+    /// Versioned system prompt templates (hot-reloaded from PROMPTS_DIR)
+    pub prompts: Arc<PromptRegistry>,
+    
+    /// Secret/PII filter applied to user input (the provider is wrapped with it too)
+    pub safety: Arc<ContentFilter>,
+    
+    /// Conversation history (screened by the safety filter before saving)
+    pub sessions: Arc<dyn SessionStore>,
+    
+    /// Titles and tags new conversations in the background (None = disabled)
+    pub tagger: Option<Arc<SessionTagger>>,
+    
+    /// Recent SSE events, for resuming streams after a disconnect
+    pub streams: Arc<StreamBuffer>,
+    
+    /// Per-user semantic memory (optional - None if the index failed to load)
+    pub memory: Option<Arc<SemanticMemory>>,
+    
+    /// User accounts and API keys
+    pub users: Arc<MemoryUserStore>,
+    
+    /// Signs and checks login session tokens
+    pub tokens: Arc<SessionTokens>,
+    
     /// License store for subscription management
     pub license_store: Arc<MemoryLicenseStore>,
     
+    /// Organizations sharing Team licenses, their members and invites
+    pub orgs: Arc<dyn OrgStore>,
+    
+    /// Daily request counts of users without a license
+    pub free_quota: Arc<DailyQuota>,
+    
+    /// Requests each user has in flight
+    pub concurrency: ConcurrencyLimiter,
+    
     /// Stripe client (optional - None if not configured)
     pub stripe: Option<Arc<StripeClient>>,
+    
+    /// Renders notifications and delivers them on each user's channels
+    pub notifications: Arc<NotificationService>,
+    
+    /// In-app notifications of all users
+    pub inbox: Arc<dyn Inbox>,
+    
+    /// Prometheus metrics, served at /metrics
+    pub metrics: Arc<ServerMetrics>,
+    
+    /// Drain state and in-flight work, for graceful shutdown
+    pub shutdown: Arc<Shutdown>,
+}
+
+#[cfg(test)]
+impl AppState {
+    /// In-memory state for handler and middleware tests (DCA jobs are saved under `dir`)
+    pub fn for_tests(dir: &std::path::Path) -> Self {
+        use agent_core::{safety::SafetyPolicy, session::MemorySessionStore};
+        use agent_notify::{InboxNotifier, MemoryInbox, MemoryPreferenceStore, Templates};
+        use agent_payments::MemoryOrgStore;
+        use crypto_advisor::{exchange::MockExchangeClient, MemoryAlertStore, MemoryPortfolioStore};
+        
+        use crate::config::{Config, Settings};
+        
+        let inbox: Arc<dyn Inbox> = Arc::new(MemoryInbox::default());
+        let notifications = NotificationService::new(Templates::builtin(), Arc::new(MemoryPreferenceStore::new()))
+            .with_notifier(Arc::new(InboxNotifier::new(inbox.clone())));
+        Self {
+            provider: Arc::new(agent_runtime::OllamaProvider::new("localhost", 11434)),
+            settings: Arc::new(LiveSettings::new(Settings::from_config(&Config::default(), None).unwrap())),
+            tools: Arc::new(ToolRegistry::new()),
+            exchange: Arc::new(MockExchangeClient::new()),
+            portfolios: Arc::new(MemoryPortfolioStore::new()),
+            jobs: Arc::new(JobStore::open(dir.join("dca_jobs.json")).unwrap()),
+            alerts: Arc::new(MemoryAlertStore::new()),
+            prompts: Arc::new(PromptRegistry::new()),
+            safety: Arc::new(ContentFilter::new(SafetyPolicy::default())),
+            sessions: Arc::new(MemorySessionStore::new()),
+            tagger: None,
+            streams: Arc::new(StreamBuffer::new()),
+            memory: None,
+            users: Arc::new(MemoryUserStore::new()),
+            tokens: Arc::new(SessionTokens::ephemeral(chrono::Duration::hours(1))),
+            license_store: Arc::new(MemoryLicenseStore::new()),
+            orgs: Arc::new(MemoryOrgStore::new()),
+            free_quota: Arc::new(DailyQuota::new()),
+            concurrency: ConcurrencyLimiter::new(),
+            stripe: None,
+            notifications: Arc::new(notifications),
+            inbox,
+            metrics: Arc::new(ServerMetrics::new(None)),
+            shutdown: Arc::new(Shutdown::new()),
+        }
+    }
 }
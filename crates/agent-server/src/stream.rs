@@ -0,0 +1,252 @@
+//! Server-Sent Events Streaming
+//!
+//! `POST /api/chat/stream` streams the WebSocket's events (`chunk`, `tool_call`,
+//! `tool_result`, `done`, `error`) as `text/event-stream`, for clients that
+//! can't hold a WebSocket open.
+//!
+//! - every event has the id `<stream>:<seq>` and is kept in a short buffer;
+//!   sending the request again with `Last-Event-ID` replays the events after
+//!   that id, then follows the answer if it is still being generated
+//! - heartbeat comments keep proxies from closing quiet connections
+//! - once no client has been connected for `RECONNECT_GRACE`, generation is
+//!   cancelled (the provider stream is dropped)
+
+use std::collections::{HashMap, VecDeque};
+use std::convert::Infallible;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{Extension, State},
+    http::{HeaderMap, StatusCode},
+    response::sse::{Event, KeepAlive, Sse},
+};
+use futures::Stream;
+use tokio::sync::{mpsc, watch};
+
+use crate::auth::UserId;
+use crate::error::{ApiError, ApiJson, ErrorCode};
+use crate::handlers::{chat_events, ChatRequest, StreamEvent};
+use crate::quota::{Admission, Entitlement};
+use crate::state::AppState;
+
+/// Interval of heartbeat comments
+const HEARTBEAT: Duration = Duration::from_secs(15);
+
+/// How long an answer keeps generating with no client connected
+const RECONNECT_GRACE: Duration = Duration::from_secs(10);
+
+/// How long a finished stream can still be resumed
+const RETAIN_FINISHED: Duration = Duration::from_secs(60);
+
+/// Events kept per stream (older ones can't be resumed from)
+const MAX_BUFFERED_EVENTS: usize = 4096;
+
+struct Buffered {
+    owner: UserId,
+    events: VecDeque<(u64, StreamEvent)>,
+    next_seq: u64,
+    finished: Option<Instant>,
+    
+    /// Bumped on every new event; subscribers wait on it
+    changed: watch::Sender<u64>,
+}
+
+enum Read {
+    Event(u64, StreamEvent),
+    Pending,
+    Finished,
+    
+    /// The stream expired, or the event was evicted from the buffer
+    Gone,
+}
+
+/// Recent events of SSE streams, for `Last-Event-ID` resume
+#[derive(Default)]
+pub struct StreamBuffer {
+    streams: Mutex<HashMap<String, Buffered>>,
+}
+
+impl StreamBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Buffer a new stream's events and subscribe to it from the start
+    pub fn publish(self: &Arc<Self>, owner: UserId, events: mpsc::Receiver<StreamEvent>) -> Subscription {
+        let id = uuid::Uuid::new_v4().simple().to_string();
+        let (changed, _) = watch::channel(0);
+        {
+            let mut streams = self.streams.lock().unwrap();
+            streams.retain(|_, s| s.finished.is_none_or(|at| at.elapsed() < RETAIN_FINISHED));
+            streams.insert(
+                id.clone(),
+                Buffered {
+                    owner,
+                    events: VecDeque::new(),
+                    next_seq: 0,
+                    finished: None,
+                    changed,
+                },
+            );
+        }
+        
+        let subscription = self.subscribe(&id, 0);
+        tokio::spawn(self.clone().pump(id, events));
+        subscription
+    }
+    
+    /// Subscribe to a stream after a `Last-Event-ID` (`<stream>:<seq>`) of its owner
+    pub fn resume(self: &Arc<Self>, owner: &UserId, last_event_id: &str) -> Option<Subscription> {
+        let (id, seq) = last_event_id.split_once(':')?;
+        let seq: u64 = seq.parse().ok()?;
+        let streams = self.streams.lock().unwrap();
+        let stream = streams.get(id).filter(|s| &s.owner == owner)?;
+        if stream.finished.is_some_and(|at| at.elapsed() >= RETAIN_FINISHED) {
+            return None;
+        }
+        drop(streams);
+        Some(self.subscribe(id, seq.saturating_add(1)))
+    }
+    
+    fn subscribe(self: &Arc<Self>, id: &str, next_seq: u64) -> Subscription {
+        let changed = self.streams.lock().unwrap().get(id).map(|s| s.changed.subscribe());
+        Subscription {
+            buffer: self.clone(),
+            id: id.to_string(),
+            next_seq,
+            changed,
+        }
+    }
+    
+    /// Move a stream's events into the buffer until it ends or is abandoned
+    async fn pump(self: Arc<Self>, id: String, mut events: mpsc::Receiver<StreamEvent>) {
+        let mut check = tokio::time::interval(Duration::from_secs(1));
+        let mut unwatched_since: Option<Instant> = None;
+        loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Some(event) => self.push(&id, event),
+                    None => break,
+                },
+                _ = check.tick() => {
+                    let watched = self.streams.lock().unwrap().get(&id).is_some_and(|s| s.changed.receiver_count() > 0);
+                    if watched {
+                        unwatched_since = None;
+                    } else if unwatched_since.get_or_insert_with(Instant::now).elapsed() >= RECONNECT_GRACE {
+                        tracing::debug!("SSE stream {} abandoned, cancelling generation", id);
+                        break;
+                    }
+                }
+            }
+        }
+        
+        // Dropping `events` stops the generating task
+        if let Some(stream) = self.streams.lock().unwrap().get_mut(&id) {
+            stream.finished = Some(Instant::now());
+            stream.changed.send_modify(|v| *v += 1);
+        }
+    }
+    
+    fn push(&self, id: &str, event: StreamEvent) {
+        if let Some(stream) = self.streams.lock().unwrap().get_mut(id) {
+            stream.events.push_back((stream.next_seq, event));
+            stream.next_seq += 1;
+            if stream.events.len() > MAX_BUFFERED_EVENTS {
+                stream.events.pop_front();
+            }
+            stream.changed.send_modify(|v| *v += 1);
+        }
+    }
+    
+    fn read(&self, id: &str, seq: u64) -> Read {
+        let streams = self.streams.lock().unwrap();
+        let Some(stream) = streams.get(id) else {
+            return Read::Gone;
+        };
+        match stream.events.front() {
+            Some((first, _)) if seq < *first => Read::Gone,
+            Some((first, _)) if seq < stream.next_seq => {
+                let (seq, event) = &stream.events[usize::try_from(seq - first).unwrap_or(usize::MAX)];
+                Read::Event(*seq, event.clone())
+            }
+            _ if stream.finished.is_some() => Read::Finished,
+            _ => Read::Pending,
+        }
+    }
+}
+
+/// A client's position in a buffered stream
+pub struct Subscription {
+    buffer: Arc<StreamBuffer>,
+    id: String,
+    next_seq: u64,
+    changed: Option<watch::Receiver<u64>>,
+}
+
+impl Subscription {
+    async fn next(&mut self) -> Option<Event> {
+        let changed = self.changed.as_mut()?;
+        loop {
+            // Mark the current version seen before reading, so no event is missed
+            changed.borrow_and_update();
+            match self.buffer.read(&self.id, self.next_seq) {
+                Read::Event(seq, event) => {
+                    self.next_seq = seq + 1;
+                    return Some(sse_event(Some(format!("{}:{}", self.id, seq)), event));
+                }
+                Read::Finished => return None,
+                Read::Gone => {
+                    self.changed = None;
+                    let error = ApiError::new(StatusCode::GONE, ErrorCode::ResumeUnavailable, "Stream can no longer be resumed");
+                    return Some(sse_event(None, error.into()));
+                }
+                Read::Pending => changed.changed().await.ok()?,
+            }
+        }
+    }
+    
+    fn into_stream(self) -> impl Stream<Item = Result<Event, Infallible>> {
+        futures::stream::unfold(self, |mut subscription| async move {
+            subscription.next().await.map(|event| (Ok(event), subscription))
+        })
+    }
+}
+
+/// An SSE event named after the event's `type`
+fn sse_event(id: Option<String>, event: StreamEvent) -> Event {
+    let sse = Event::default().event(event.name()).data(serde_json::to_string(&event).unwrap_or_default());
+    match id {
+        Some(id) => sse.id(id),
+        None => sse,
+    }
+}
+
+/// Streaming chat over Server-Sent Events
+pub async fn chat_sse_handler(
+    State(state): State<AppState>,
+    Extension(entitlement): Extension<Entitlement>,
+    admission: Option<Extension<Admission>>,
+    headers: HeaderMap,
+    ApiJson(request): ApiJson<ChatRequest>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, ApiError> {
+    // Only a stream that is found replays without admission (it was admitted when it started)
+    let subscription = match (headers.get("last-event-id"), admission) {
+        (Some(last_event_id), _) => last_event_id
+            .to_str()
+            .ok()
+            .and_then(|id| state.streams.resume(&entitlement.user_id, id))
+            .ok_or_else(|| ApiError::not_found(ErrorCode::StreamNotFound, "Stream not found or expired"))?,
+        (None, Some(Extension(admission))) => {
+            let owner = entitlement.user_id.clone();
+            let events = chat_events(state.clone(), request, entitlement, admission);
+            state.streams.publish(owner, events)
+        }
+        (None, None) => {
+            tracing::error!("SSE chat request reached the handler without admission");
+            return Err(ApiError::internal(ErrorCode::InternalError, "Request was not admitted"));
+        }
+    };
+    
+    Ok(Sse::new(subscription.into_stream()).keep_alive(KeepAlive::new().interval(HEARTBEAT)))
+}
@@ -0,0 +1,191 @@
+//! Telemetry
+//!
+//! Logging, trace export and Prometheus metrics.
+//!
+//! Spans nest per request: tower-http's `request` span, then `agent.run`,
+//! `llm.complete` / `llm.stream` and `tool.call` (see `agent_core::metrics`).
+//! Built with the `otlp` feature and given `telemetry.otlp_endpoint`, they
+//! are exported over OTLP/gRPC as well as logged.
+//!
+//! `GET /metrics` serves every metric in the Prometheus text format: HTTP
+//! request latency by route, the agent metrics (iterations, tool calls,
+//! provider latency, time to first token, tokens), license verifications by
+//! outcome and Stripe webhook events by type.
+
+use std::time::Instant;
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use sha2::{Digest, Sha256};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+use agent_core::metrics::{AgentMetrics, Counter, Histogram, Metrics, LATENCY_BUCKETS};
+
+use crate::config::TelemetryConfig;
+use crate::state::AppState;
+
+/// Content type of the Prometheus text format
+const PROMETHEUS_CONTENT_TYPE: &str = "text/plain; version=0.0.4; charset=utf-8";
+
+// ============================================================================
+// Tracing
+// ============================================================================
+
+/// Installed tracing; `shutdown` flushes spans not yet exported
+#[derive(Default)]
+pub struct Telemetry {
+    #[cfg(feature = "otlp")]
+    tracer_provider: Option<opentelemetry_sdk::trace::TracerProvider>,
+}
+
+impl Telemetry {
+    pub fn shutdown(self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = self.tracer_provider {
+            if let Err(e) = provider.shutdown() {
+                tracing::warn!("⚠ Trace export shutdown failed: {}", e);
+            }
+        }
+    }
+}
+
+/// Install the global subscriber: `RUST_LOG` filter, log output and OTLP export
+#[cfg_attr(not(feature = "otlp"), allow(unused_variables))]
+pub fn init(config: &TelemetryConfig) -> anyhow::Result<Telemetry> {
+    let registry = tracing_subscriber::registry()
+        .with(tracing_subscriber::EnvFilter::new(
+            std::env::var("RUST_LOG").unwrap_or_else(|_| "info,tower_http=debug".into()),
+        ))
+        .with(tracing_subscriber::fmt::layer());
+    
+    #[cfg(feature = "otlp")]
+    if let Some(endpoint) = &config.otlp_endpoint {
+        use opentelemetry::trace::TracerProvider as _;
+        
+        let provider = otlp_tracer_provider(endpoint, &config.service_name)?;
+        registry
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("agent-server")))
+            .init();
+        tracing::info!("✓ Exporting traces to {}", endpoint);
+        return Ok(Telemetry { tracer_provider: Some(provider) });
+    }
+    
+    registry.init();
+    Ok(Telemetry::default())
+}
+
+#[cfg(feature = "otlp")]
+fn otlp_tracer_provider(endpoint: &str, service_name: &str) -> anyhow::Result<opentelemetry_sdk::trace::TracerProvider> {
+    use opentelemetry_otlp::WithExportConfig;
+    
+    let exporter = opentelemetry_otlp::SpanExporter::builder()
+        .with_tonic()
+        .with_endpoint(endpoint)
+        .build()?;
+    let resource = opentelemetry_sdk::Resource::new([opentelemetry::KeyValue::new("service.name", service_name.to_string())]);
+    
+    Ok(opentelemetry_sdk::trace::TracerProvider::builder()
+        .with_batch_exporter(exporter, opentelemetry_sdk::runtime::Tokio)
+        .with_resource(resource)
+        .build())
+}
+
+// ============================================================================
+// Metrics
+// ============================================================================
+
+/// The server's metric registry and the families it records
+pub struct ServerMetrics {
+    pub registry: Metrics,
+    
+    /// Recorded by agents, the provider stack and tools
+    pub agent: AgentMetrics,
+    
+    http_requests: Counter,
+    http_duration: Histogram,
+    license_verifications: Counter,
+    webhook_events: Counter,
+    
+    /// Bearer token required to scrape (open if None)
+    token: Option<String>,
+}
+
+impl ServerMetrics {
+    pub fn new(token: Option<String>) -> Self {
+        let registry = Metrics::new();
+        Self {
+            agent: AgentMetrics::register(&registry),
+            http_requests: registry.counter(
+                "http_requests_total",
+                "HTTP requests by route and status",
+                &["method", "route", "status"],
+            ),
+            http_duration: registry.histogram(
+                "http_request_duration_seconds",
+                "HTTP request latency (until the response head; streams continue after it)",
+                &["method", "route"],
+                LATENCY_BUCKETS,
+            ),
+            license_verifications: registry.counter(
+                "license_verifications_total",
+                "License key checks by outcome",
+                &["outcome"],
+            ),
+            webhook_events: registry.counter(
+                "webhook_events_total",
+                "Stripe webhook events by type and outcome",
+                &["type", "outcome"],
+            ),
+            registry,
+            token,
+        }
+    }
+    
+    /// Count a license check: `valid`, `invalid`, `rate_limited` or `error`
+    pub fn license_verified(&self, outcome: &str) {
+        self.license_verifications.inc(&[outcome]);
+    }
+    
+    /// Count a webhook event: `processed`, `failed` or `rejected` (bad signature)
+    pub fn webhook_event(&self, event_type: &str, outcome: &str) {
+        self.webhook_events.inc(&[event_type, outcome]);
+    }
+}
+
+/// Middleware timing requests by matched route (a route layer, so unmatched paths don't add series)
+pub async fn track_requests(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let method = request.method().to_string();
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| "unmatched".to_string(), |path| path.as_str().to_string());
+    
+    let started = Instant::now();
+    let response = next.run(request).await;
+    
+    let metrics = &state.metrics;
+    metrics.http_duration.observe(&[&method, &route], started.elapsed().as_secs_f64());
+    metrics.http_requests.inc(&[&method, &route, response.status().as_str()]);
+    response
+}
+
+/// Prometheus scrape endpoint (bearer `telemetry.metrics_token` when configured)
+pub async fn metrics_handler(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Some(token) = &state.metrics.token {
+        let presented = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        // Compare digests, so the time taken doesn't depend on the token's contents
+        if Sha256::digest(presented) != Sha256::digest(token) {
+            return (StatusCode::UNAUTHORIZED, [(header::WWW_AUTHENTICATE, "Bearer")]).into_response();
+        }
+    }
+    
+    ([(header::CONTENT_TYPE, PROMETHEUS_CONTENT_TYPE)], state.metrics.registry.render()).into_response()
+}
@@ -0,0 +1,101 @@
+//! HTTPS
+//!
+//! A `TlsListener` accepts TCP connections and completes their TLS
+//! handshakes in the background, so `axum::serve` only ever sees connections
+//! that are ready. A client that stalls its handshake is dropped after
+//! `HANDSHAKE_TIMEOUT` without holding up anyone else.
+
+use std::io;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::mpsc;
+use tokio_rustls::{rustls, server::TlsStream, TlsAcceptor};
+
+use crate::config::TlsConfig;
+
+/// Longest a client may take to complete the TLS handshake
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Handshaken connections waiting to be served
+const READY_CAPACITY: usize = 64;
+
+/// Build the TLS acceptor from PEM certificate and key files
+pub fn acceptor(config: &TlsConfig) -> anyhow::Result<TlsAcceptor> {
+    let certs = rustls_pemfile::certs(&mut open(&config.cert_path)?).collect::<io::Result<Vec<_>>>()?;
+    if certs.is_empty() {
+        anyhow::bail!("No certificates in {}", config.cert_path.display());
+    }
+    let key = rustls_pemfile::private_key(&mut open(&config.key_path)?)?
+        .ok_or_else(|| anyhow::anyhow!("No private key in {}", config.key_path.display()))?;
+    
+    let mut server = rustls::ServerConfig::builder().with_no_client_auth().with_single_cert(certs, key)?;
+    server.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(TlsAcceptor::from(Arc::new(server)))
+}
+
+fn open(path: &Path) -> anyhow::Result<io::BufReader<std::fs::File>> {
+    let file = std::fs::File::open(path).map_err(|e| anyhow::anyhow!("Cannot open {}: {}", path.display(), e))?;
+    Ok(io::BufReader::new(file))
+}
+
+/// A listener yielding TLS connections (for `axum::serve`)
+pub struct TlsListener {
+    ready: mpsc::Receiver<(TlsStream<TcpStream>, SocketAddr)>,
+    local_addr: SocketAddr,
+}
+
+impl TlsListener {
+    pub fn new(listener: TcpListener, acceptor: TlsAcceptor) -> io::Result<Self> {
+        let local_addr = listener.local_addr()?;
+        let (tx, ready) = mpsc::channel(READY_CAPACITY);
+        
+        tokio::spawn(async move {
+            loop {
+                let (stream, addr) = match listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        // Out of file descriptors and the like: back off instead of spinning
+                        tracing::warn!("Accept failed: {}", e);
+                        tokio::time::sleep(Duration::from_secs(1)).await;
+                        continue;
+                    }
+                };
+                
+                let acceptor = acceptor.clone();
+                let tx = tx.clone();
+                tokio::spawn(async move {
+                    match tokio::time::timeout(HANDSHAKE_TIMEOUT, acceptor.accept(stream)).await {
+                        Ok(Ok(stream)) => {
+                            let _ = tx.send((stream, addr)).await;
+                        }
+                        Ok(Err(e)) => tracing::debug!("TLS handshake with {} failed: {}", addr, e),
+                        Err(_) => tracing::debug!("TLS handshake with {} timed out", addr),
+                    }
+                });
+            }
+        });
+        
+        Ok(Self { ready, local_addr })
+    }
+}
+
+impl axum::serve::Listener for TlsListener {
+    type Io = TlsStream<TcpStream>;
+    type Addr = SocketAddr;
+    
+    async fn accept(&mut self) -> (Self::Io, Self::Addr) {
+        match self.ready.recv().await {
+            Some(connection) => connection,
+            // The accept loop never ends while the listener exists
+            None => std::future::pending().await,
+        }
+    }
+    
+    fn local_addr(&self) -> io::Result<Self::Addr> {
+        Ok(self.local_addr)
+    }
+}
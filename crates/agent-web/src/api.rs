@@ -2,6 +2,9 @@
 
 use serde::{Deserialize, Serialize};
 
+/// Prefix of the versioned REST API
+const API_BASE: &str = "/api/v1";
+
 /// Chat message for display
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct ChatMessage {
@@ -22,7 +25,7 @@ pub async fn send_chat(message: &str, license_key: Option<&str>) -> Result<Strin
     }
 
     let response = client
-        .post("/api/chat")
+        .post(format!("{}/chat", API_BASE))
         .json(&body)
         .send()
         .await
@@ -37,6 +40,32 @@ pub async fn send_chat(message: &str, license_key: Option<&str>) -> Result<Strin
     }
 }
 
+/// Sign in, or create an account first when `register` is set
+///
+/// The server answers with a session cookie that authenticates later requests.
+pub async fn login(username: &str, password: &str, register: bool) -> Result<String, String> {
+    let client = reqwest::Client::new();
+    let path = format!("{}/auth/{}", API_BASE, if register { "register" } else { "login" });
+    
+    let body = serde_json::json!({
+        "username": username,
+        "password": password,
+    });
+
+    let response = client
+        .post(&path)
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let data: serde_json::Value = response.json().await.unwrap_or_default();
+    match data["username"].as_str() {
+        Some(username) => Ok(username.to_string()),
+        None => Err(data["error"].as_str().unwrap_or("Sign-in failed").to_string()),
+    }
+}
+
 /// Create a Stripe checkout session
 pub async fn create_checkout(plan: &str) -> Result<String, String> {
     let client = reqwest::Client::new();
@@ -53,7 +82,7 @@ pub async fn create_checkout(plan: &str) -> Result<String, String> {
     });
 
     let response = client
-        .post("/api/checkout")
+        .post(format!("{}/checkout", API_BASE))
         .json(&body)
         .send()
         .await
@@ -3,7 +3,7 @@
 use leptos::prelude::*;
 use leptos_router::{components::*, path};
 
-use crate::pages::{ChatPage, HomePage, PricingPage};
+use crate::pages::{ChatPage, HomePage, LoginPage, PricingPage};
 
 /// Root application component
 #[component]
@@ -15,6 +15,7 @@ pub fn App() -> impl IntoView {
                     <Route path=path!("/") view=HomePage />
                     <Route path=path!("/chat") view=ChatPage />
                     <Route path=path!("/pricing") view=PricingPage />
+                    <Route path=path!("/login") view=LoginPage />
                 </Routes>
             </main>
         </Router>
@@ -56,6 +56,7 @@ pub fn ChatPage() -> impl IntoView {
         <div class="chat">
             <aside class="sidebar">
                 <h2>"Settings"</h2>
+                <p><a href="/login">"Sign in"</a></p>
                 <div class="field">
                     <label>"License Key"</label>
                     <input
@@ -0,0 +1,69 @@
+//! Login Page
+
+use leptos::prelude::*;
+use crate::api;
+
+#[component]
+pub fn LoginPage() -> impl IntoView {
+    let (username, set_username) = signal(String::new());
+    let (password, set_password) = signal(String::new());
+    let (error, set_error) = signal(None::<String>);
+    let (loading, set_loading) = signal(false);
+
+    let submit = move |register: bool| {
+        if loading.get() {
+            return;
+        }
+        set_loading.set(true);
+        set_error.set(None);
+
+        let (name, pass) = (username.get(), password.get());
+        leptos::task::spawn_local(async move {
+            match api::login(&name, &pass, register).await {
+                Ok(_) => {
+                    if let Some(window) = web_sys::window() {
+                        let _ = window.location().set_href("/chat");
+                    }
+                }
+                Err(e) => set_error.set(Some(e)),
+            }
+            set_loading.set(false);
+        });
+    };
+
+    view! {
+        <div class="login">
+            <h1>"Sign in"</h1>
+
+            <Show when=move || error.get().is_some()>
+                <p class="error">{move || error.get().unwrap_or_default()}</p>
+            </Show>
+
+            <div class="field">
+                <label>"Username"</label>
+                <input
+                    type="text"
+                    prop:value=move || username.get()
+                    on:input=move |ev| set_username.set(event_target_value(&ev))
+                />
+            </div>
+            <div class="field">
+                <label>"Password"</label>
+                <input
+                    type="password"
+                    prop:value=move || password.get()
+                    on:input=move |ev| set_password.set(event_target_value(&ev))
+                />
+            </div>
+
+            <div class="actions">
+                <button class="btn btn-primary" on:click=move |_| submit(false) disabled=move || loading.get()>
+                    "Sign in"
+                </button>
+                <button class="btn" on:click=move |_| submit(true) disabled=move || loading.get()>
+                    "Create account"
+                </button>
+            </div>
+        </div>
+    }
+}
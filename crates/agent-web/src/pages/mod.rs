@@ -3,7 +3,9 @@
 mod home;
 mod chat;
 mod pricing;
+mod login;
 
 pub use home::HomePage;
 pub use chat::ChatPage;
 pub use pricing::PricingPage;
+pub use login::LoginPage;
@@ -0,0 +1,524 @@
+//! Alert evaluation
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use super::{window, Alert, AlertCondition, AlertStore, Reading, MAX_WINDOW_MINUTES};
+use crate::error::{AdvisorError, Result};
+use crate::exchange::ExchangeClient;
+use crate::model::Portfolio;
+use crate::store::PortfolioStore;
+
+/// An alert that fired
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct AlertEvent {
+    pub alert_id: Uuid,
+    pub owner_id: Option<String>,
+    
+    /// e.g. "Price alert: ETH"
+    pub title: String,
+    
+    /// What happened, e.g. "ETH fell to $2990.50"
+    pub message: String,
+    
+    /// The alert's condition, e.g. "ETH at or below $3000"
+    pub condition: String,
+    
+    /// The measured value that fired the alert
+    pub value: Decimal,
+    
+    /// Unique per alert and firing
+    pub dedup_key: String,
+    
+    pub fired_at: DateTime<Utc>,
+}
+
+/// Prices seen by the engine, kept for the longest `percent_move` window
+#[derive(Default)]
+struct PriceHistory {
+    samples: HashMap<String, VecDeque<(DateTime<Utc>, Decimal)>>,
+}
+
+impl PriceHistory {
+    fn record(&mut self, symbol: &str, at: DateTime<Utc>, price: Decimal) {
+        let samples = self.samples.entry(symbol.to_string()).or_default();
+        samples.push_back((at, price));
+        
+        // Keep the newest sample older than the window, as the reference for it
+        let cutoff = at - Duration::minutes(i64::from(MAX_WINDOW_MINUTES));
+        while samples.len() > 1 && samples[1].0 <= cutoff {
+            samples.pop_front();
+        }
+    }
+    
+    /// The latest price at or before `at`
+    fn at_or_before(&self, symbol: &str, at: DateTime<Utc>) -> Option<Decimal> {
+        let samples = self.samples.get(symbol)?;
+        samples.iter().rev().find(|(seen, _)| *seen <= at).map(|(_, price)| *price)
+    }
+    
+    /// Forget symbols no alert watches anymore
+    fn retain(&mut self, watched: &HashSet<String>) {
+        self.samples.retain(|symbol, _| watched.contains(symbol));
+    }
+}
+
+/// Checks active alerts against current prices
+///
+/// Call `evaluate` periodically; `percent_move` alerts compare against the
+/// prices seen on earlier calls, so they need a few ticks of history first.
+pub struct AlertEngine {
+    exchange: Arc<dyn ExchangeClient>,
+    portfolios: Arc<dyn PortfolioStore>,
+    alerts: Arc<dyn AlertStore>,
+    history: Mutex<PriceHistory>,
+}
+
+impl AlertEngine {
+    pub fn new(
+        exchange: Arc<dyn ExchangeClient>,
+        portfolios: Arc<dyn PortfolioStore>,
+        alerts: Arc<dyn AlertStore>,
+    ) -> Self {
+        Self {
+            exchange,
+            portfolios,
+            alerts,
+            history: Mutex::new(PriceHistory::default()),
+        }
+    }
+    
+    /// Evaluate every active alert at `now`, returning those that fired
+    ///
+    /// Each price is fetched once per call; alerts whose prices or portfolio
+    /// are unavailable are left as they are until the next call.
+    pub async fn evaluate(&self, now: DateTime<Utc>) -> Result<Vec<AlertEvent>> {
+        let alerts = self.alerts.active()?;
+        
+        let mut portfolios: HashMap<(Option<String>, String), Portfolio> = HashMap::new();
+        let mut symbols: HashSet<String> = HashSet::new();
+        for alert in &alerts {
+            if let Some(symbol) = alert.condition.symbol() {
+                symbols.insert(symbol.to_string());
+            }
+            if let Some(name) = alert.condition.portfolio() {
+                let key = (alert.owner_id.clone(), name.to_string());
+                if portfolios.contains_key(&key) {
+                    continue;
+                }
+                match self.portfolios.get(alert.owner_id.as_deref(), name) {
+                    Ok(Some(portfolio)) => {
+                        symbols.extend(portfolio.positions.keys().cloned());
+                        portfolios.insert(key, portfolio);
+                    }
+                    Ok(None) => tracing::debug!(alert_id = %alert.id, "Portfolio '{}' of an alert not found", name),
+                    Err(e) => tracing::warn!(alert_id = %alert.id, "⚠ Portfolio '{}' not loaded: {}", name, e),
+                }
+            }
+        }
+        
+        let mut prices = HashMap::new();
+        for symbol in &symbols {
+            match self.exchange.get_price(symbol).await {
+                Ok(asset) => {
+                    prices.insert(symbol.clone(), asset.price_usd);
+                }
+                Err(e) => tracing::warn!("⚠ {} price unavailable for alerts: {}", symbol, e),
+            }
+        }
+        for portfolio in portfolios.values_mut() {
+            portfolio.update_prices(&prices);
+        }
+        
+        // Evaluate under the history lock; save the changes after releasing it
+        let mut changed = Vec::new();
+        {
+            let mut history = self.history.lock().unwrap();
+            for (symbol, price) in &prices {
+                history.record(symbol, now, *price);
+            }
+            history.retain(&symbols);
+            
+            for original in alerts {
+                let mut alert = original.clone();
+                let portfolio = alert
+                    .condition
+                    .portfolio()
+                    .and_then(|name| portfolios.get(&(alert.owner_id.clone(), name.to_string())));
+                let Some(reading) = read(&mut alert, &prices, &history, portfolio, now) else {
+                    continue;
+                };
+                
+                let fired = alert.observe(&reading, now);
+                if alert != original {
+                    changed.push((alert, reading, fired));
+                }
+            }
+        }
+        
+        let mut events = Vec::new();
+        for (alert, reading, fired) in changed {
+            // The owner may have deleted the alert meanwhile
+            match self.alerts.update(alert.id, &mut |stored| {
+                *stored = alert.clone();
+                Ok(())
+            }).await {
+                Ok(_) => {}
+                Err(AdvisorError::AlertNotFound(_)) => continue,
+                Err(e) => {
+                    tracing::warn!(alert_id = %alert.id, "⚠ Alert not saved: {}", e);
+                    continue;
+                }
+            }
+            
+            if fired {
+                events.push(AlertEvent {
+                    alert_id: alert.id,
+                    owner_id: alert.owner_id.clone(),
+                    title: title(&alert.condition),
+                    message: reading.message,
+                    condition: alert.condition.describe(),
+                    value: reading.value,
+                    dedup_key: alert.dedup_key(),
+                    fired_at: now,
+                });
+            }
+        }
+        Ok(events)
+    }
+}
+
+fn title(condition: &AlertCondition) -> String {
+    match (condition.symbol(), condition.portfolio()) {
+        (Some(symbol), _) => format!("Price alert: {}", symbol),
+        (None, Some(portfolio)) => format!("Portfolio alert: {}", portfolio),
+        (None, None) => "Alert".to_string(),
+    }
+}
+
+/// Evaluate an alert's condition, or None without the prices or history it needs
+///
+/// Updates the tracked peak of `drawdown` alerts.
+fn read(
+    alert: &mut Alert,
+    prices: &HashMap<String, Decimal>,
+    history: &PriceHistory,
+    portfolio: Option<&Portfolio>,
+    now: DateTime<Utc>,
+) -> Option<Reading> {
+    let hundred = Decimal::ONE_HUNDRED;
+    let h = alert.hysteresis_percent;
+    let reading = match &alert.condition {
+        AlertCondition::PriceAbove { symbol, price } => {
+            let current = *prices.get(symbol)?;
+            Reading::at_least(
+                current,
+                *price,
+                *price * h / hundred,
+                format!("{} rose to ${}", symbol, current.round_dp(2)),
+            )
+        }
+        AlertCondition::PriceBelow { symbol, price } => {
+            let current = *prices.get(symbol)?;
+            Reading::at_most(
+                current,
+                *price,
+                *price * h / hundred,
+                format!("{} fell to ${}", symbol, current.round_dp(2)),
+            )
+        }
+        AlertCondition::PercentMove { symbol, percent, window_minutes } => {
+            let current = *prices.get(symbol)?;
+            let since = now - Duration::minutes(i64::from(*window_minutes));
+            let past = history.at_or_before(symbol, since).filter(|p| !p.is_zero())?;
+            let change = (current - past) / past * hundred;
+            let sign = if change > Decimal::ZERO { "+" } else { "" };
+            Reading::at_least(
+                change.abs(),
+                *percent,
+                h,
+                format!(
+                    "{} moved {}{}% in {}, from ${} to ${}",
+                    symbol,
+                    sign,
+                    change.round_dp(2),
+                    window(*window_minutes),
+                    past.round_dp(2),
+                    current.round_dp(2)
+                ),
+            )
+        }
+        AlertCondition::Drawdown { portfolio: name, percent } => {
+            let value = portfolio?.total_value();
+            let peak = alert.peak_value.map_or(value, |peak| peak.max(value));
+            alert.peak_value = Some(peak);
+            if peak <= Decimal::ZERO {
+                return None;
+            }
+            let drawdown = (peak - value) / peak * hundred;
+            Reading::at_least(
+                drawdown,
+                *percent,
+                h,
+                format!(
+                    "Portfolio '{}' is down {}% from its high of ${} (now ${})",
+                    name,
+                    drawdown.round_dp(2),
+                    peak.round_dp(2),
+                    value.round_dp(2)
+                ),
+            )
+        }
+        AlertCondition::AllocationDrift { portfolio: name, symbol, target_percent, band_percent } => {
+            let portfolio = portfolio?;
+            if portfolio.total_value() <= Decimal::ZERO {
+                return None;
+            }
+            let share = portfolio.allocations().get(symbol).copied().unwrap_or_default();
+            Reading::at_least(
+                (share - target_percent).abs(),
+                *band_percent,
+                h,
+                format!("{} is {}% of portfolio '{}'", symbol, share.round_dp(2), name),
+            )
+        }
+    };
+    Some(reading)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::{AlertStatus, MemoryAlertStore};
+    use crate::model::{Asset, TradeSide, Transaction};
+    use crate::store::MemoryPortfolioStore;
+    use async_trait::async_trait;
+    use rust_decimal_macros::dec;
+
+    /// Exchange whose prices the test sets
+    #[derive(Default)]
+    struct TestExchange {
+        prices: Mutex<HashMap<String, Decimal>>,
+    }
+
+    impl TestExchange {
+        fn set(&self, symbol: &str, price: Decimal) {
+            self.prices.lock().unwrap().insert(symbol.to_string(), price);
+        }
+    }
+
+    #[async_trait]
+    impl ExchangeClient for TestExchange {
+        async fn get_price(&self, symbol: &str) -> Result<Asset> {
+            let price = self.prices.lock().unwrap().get(symbol).copied();
+            price
+                .map(|price| Asset::new(symbol, symbol, price))
+                .ok_or_else(|| AdvisorError::PriceUnavailable(symbol.to_string()))
+        }
+        
+        async fn get_volume(&self, _symbol: &str) -> Result<Decimal> {
+            Ok(Decimal::ZERO)
+        }
+        
+        async fn health_check(&self) -> bool {
+            true
+        }
+        
+        fn name(&self) -> &str {
+            "TestExchange"
+        }
+    }
+
+    fn engine() -> (Arc<TestExchange>, Arc<MemoryPortfolioStore>, Arc<MemoryAlertStore>, AlertEngine) {
+        let exchange = Arc::new(TestExchange::default());
+        let portfolios = Arc::new(MemoryPortfolioStore::new());
+        let alerts = Arc::new(MemoryAlertStore::new());
+        let engine = AlertEngine::new(exchange.clone(), portfolios.clone(), alerts.clone());
+        (exchange, portfolios, alerts, engine)
+    }
+
+    #[tokio::test]
+    async fn test_price_alert_does_not_flap() {
+        let (exchange, _, alerts, engine) = engine();
+        let mut alert = Alert::new(Some("ana"), AlertCondition::PriceBelow { symbol: "ETH".into(), price: dec!(3000) });
+        alert.repeat = true;
+        alert.cooldown_minutes = 0;
+        alerts.create(&alert).await.unwrap();
+        let start = Utc::now();
+        
+        exchange.set("ETH", dec!(3100));
+        assert!(engine.evaluate(start).await.unwrap().is_empty());
+        
+        exchange.set("ETH", dec!(2990));
+        let events = engine.evaluate(start + Duration::minutes(1)).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].title, "Price alert: ETH");
+        assert_eq!(events[0].message, "ETH fell to $2990");
+        assert_eq!(events[0].condition, "ETH at or below $3000");
+        assert_eq!(events[0].dedup_key, format!("alert:{}:1", alert.id));
+        
+        // Bouncing around the threshold, within the 1% hysteresis, fires nothing
+        for (minute, price) in [(2, dec!(3010)), (3, dec!(2995)), (4, dec!(3020)), (5, dec!(2999))] {
+            exchange.set("ETH", price);
+            assert!(engine.evaluate(start + Duration::minutes(minute)).await.unwrap().is_empty());
+        }
+        
+        // Recovering past 3030 re-arms it
+        exchange.set("ETH", dec!(3050));
+        assert!(engine.evaluate(start + Duration::minutes(6)).await.unwrap().is_empty());
+        assert_eq!(alerts.get(Some("ana"), alert.id).unwrap().unwrap().status, AlertStatus::Armed);
+        exchange.set("ETH", dec!(2900));
+        let events = engine.evaluate(start + Duration::minutes(7)).await.unwrap();
+        assert_eq!(events[0].dedup_key, format!("alert:{}:2", alert.id));
+    }
+
+    #[tokio::test]
+    async fn test_percent_move_over_window() {
+        let (exchange, _, alerts, engine) = engine();
+        let condition = AlertCondition::PercentMove { symbol: "SOL".into(), percent: dec!(10), window_minutes: 60 };
+        alerts.create(&Alert::new(None, condition)).await.unwrap();
+        let start = Utc::now();
+        
+        exchange.set("SOL", dec!(200));
+        // No price an hour back yet
+        assert!(engine.evaluate(start).await.unwrap().is_empty());
+        exchange.set("SOL", dec!(170));
+        assert!(engine.evaluate(start + Duration::minutes(30)).await.unwrap().is_empty());
+        
+        exchange.set("SOL", dec!(178));
+        let events = engine.evaluate(start + Duration::minutes(60)).await.unwrap();
+        assert_eq!(events.len(), 1);
+        assert_eq!(events[0].value, dec!(11));
+        assert!(events[0].message.starts_with("SOL moved -11"));
+        assert!(alerts.active().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_portfolio_drawdown_and_drift() {
+        let (exchange, portfolios, alerts, engine) = engine();
+        let mut portfolio = Portfolio::new("main");
+        portfolio.owner_id = Some("ana".into());
+        portfolios.create(&portfolio).unwrap();
+        portfolios.record(Some("ana"), "main", &Transaction::new("BTC", TradeSide::Buy, dec!(1), dec!(50000))).unwrap();
+        portfolios.record(Some("ana"), "main", &Transaction::new("ETH", TradeSide::Buy, dec!(10), dec!(5000))).unwrap();
+        
+        let drawdown = Alert::new(Some("ana"), AlertCondition::Drawdown { portfolio: "main".into(), percent: dec!(20) });
+        let drift = Alert::new(Some("ana"), AlertCondition::AllocationDrift {
+            portfolio: "main".into(),
+            symbol: "BTC".into(),
+            target_percent: dec!(50),
+            band_percent: dec!(10),
+        });
+        alerts.create(&drawdown).await.unwrap();
+        alerts.create(&drift).await.unwrap();
+        let start = Utc::now();
+        
+        exchange.set("BTC", dec!(50000));
+        exchange.set("ETH", dec!(5000));
+        assert!(engine.evaluate(start).await.unwrap().is_empty());
+        assert_eq!(alerts.get(Some("ana"), drawdown.id).unwrap().unwrap().peak_value, Some(dec!(100000)));
+        
+        // ETH halves: value 75000 (25% down), BTC now 2/3 of the portfolio
+        exchange.set("ETH", dec!(2500));
+        let events = engine.evaluate(start + Duration::minutes(1)).await.unwrap();
+        assert_eq!(events.len(), 2);
+        let titles: HashSet<_> = events.iter().map(|e| e.title.as_str()).collect();
+        assert_eq!(titles, HashSet::from(["Portfolio alert: main"]));
+        assert!(events.iter().any(|e| e.value == dec!(25)));
+        
+        // Another user's portfolio of the same name isn't theirs to watch
+        let other = Alert::new(Some("bob"), AlertCondition::Drawdown { portfolio: "main".into(), percent: dec!(1) });
+        alerts.create(&other).await.unwrap();
+        assert!(engine.evaluate(start + Duration::minutes(2)).await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_store_limits_and_scoping() {
+        let store = MemoryAlertStore::new();
+        let condition = AlertCondition::PriceAbove { symbol: "BTC".into(), price: dec!(100000) };
+        for _ in 0..crate::alerts::MAX_ALERTS_PER_OWNER {
+            store.create(&Alert::new(Some("ana"), condition.clone())).await.unwrap();
+        }
+        assert!(matches!(store.create(&Alert::new(Some("ana"), condition.clone())).await, Err(AdvisorError::InvalidAlert(_))));
+        let theirs = Alert::new(Some("bob"), condition);
+        store.create(&theirs).await.unwrap();
+        
+        assert!(store.get(Some("ana"), theirs.id).unwrap().is_none());
+        assert!(!store.delete(Some("ana"), theirs.id).await.unwrap());
+        assert!(store.delete(Some("bob"), theirs.id).await.unwrap());
+        assert!(store.list(Some("bob")).unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_store_update_and_delete_saved() {
+        let path = std::env::temp_dir().join(format!("alerts-{}.json", uuid::Uuid::new_v4()));
+        let store = MemoryAlertStore::open(&path).unwrap();
+        let alert = Alert::new(Some("ana"), AlertCondition::PriceAbove { symbol: "BTC".into(), price: dec!(100000) });
+        store.create(&alert).await.unwrap();
+        
+        let updated = store.update(alert.id, &mut |stored| {
+            stored.repeat = true;
+            Ok(())
+        }).await.unwrap();
+        assert!(updated.repeat);
+        assert!(MemoryAlertStore::open(&path).unwrap().get(Some("ana"), alert.id).unwrap().unwrap().repeat);
+        
+        // A failed change leaves the alert as it was
+        let failed = store.update(alert.id, &mut |stored| {
+            stored.repeat = false;
+            Err(AdvisorError::InvalidAlert("no".into()))
+        }).await;
+        assert!(matches!(failed, Err(AdvisorError::InvalidAlert(_))));
+        assert!(store.get(Some("ana"), alert.id).unwrap().unwrap().repeat);
+        
+        assert!(store.delete(Some("ana"), alert.id).await.unwrap());
+        assert!(MemoryAlertStore::open(&path).unwrap().list(Some("ana")).unwrap().is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_update_of_deleted_alert_not_found() {
+        let path = std::env::temp_dir().join(format!("alerts-{}.json", uuid::Uuid::new_v4()));
+        let store = MemoryAlertStore::open(&path).unwrap();
+        store.create(&Alert::new(Some("ana"), AlertCondition::PriceAbove { symbol: "BTC".into(), price: dec!(100000) })).await.unwrap();
+        
+        // The engine read the alert, then its owner deleted it before the engine saved it
+        let mut evaluated = store.active().unwrap().remove(0);
+        evaluated.status = AlertStatus::Done;
+        assert!(store.delete(Some("ana"), evaluated.id).await.unwrap());
+        let saved = store.update(evaluated.id, &mut |stored| {
+            *stored = evaluated.clone();
+            Ok(())
+        }).await;
+        
+        assert!(matches!(saved, Err(AdvisorError::AlertNotFound(_))));
+        assert!(store.get(Some("ana"), evaluated.id).unwrap().is_none());
+        assert!(MemoryAlertStore::open(&path).unwrap().list(Some("ana")).unwrap().is_empty());
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_store_change_undone_when_save_fails() {
+        let dir = std::env::temp_dir().join(format!("alerts-{}", uuid::Uuid::new_v4()));
+        let store = MemoryAlertStore::open(dir.join("alerts.json")).unwrap();
+        let alert = Alert::new(None, AlertCondition::PriceAbove { symbol: "BTC".into(), price: dec!(100000) });
+        store.create(&alert).await.unwrap();
+        
+        // A file where the directory was, so nothing can be saved
+        std::fs::remove_dir_all(&dir).unwrap();
+        std::fs::write(&dir, "").unwrap();
+        let updated = store.update(alert.id, &mut |stored| {
+            stored.repeat = true;
+            Ok(())
+        }).await;
+        assert!(matches!(updated, Err(AdvisorError::Storage(_))));
+        assert!(matches!(store.delete(None, alert.id).await, Err(AdvisorError::Storage(_))));
+        assert!(matches!(store.create(&Alert::new(None, alert.condition.clone())).await, Err(AdvisorError::Storage(_))));
+        assert_eq!(store.list(None).unwrap(), vec![alert]);
+        let _ = std::fs::remove_file(&dir);
+    }
+}
@@ -0,0 +1,343 @@
+//! Price Alerts
+//!
+//! User-defined conditions on prices and portfolios, checked periodically by
+//! the `AlertEngine` against an `ExchangeClient`:
+//!
+//! - a price crossing a threshold (`price_above`, `price_below`)
+//! - a move of at least X% within a window (`percent_move`)
+//! - a portfolio falling X% from its highest value (`drawdown`)
+//! - an asset's share of a portfolio leaving a band around its target
+//!   (`allocation_drift`)
+//!
+//! Alerts don't flap: once fired, an alert stays `triggered` until its
+//! condition has cleared by `hysteresis_percent` and `cooldown_minutes` have
+//! passed, and only then re-arms (repeating alerts) - one-shot alerts are
+//! `done` after firing. Each firing has its own dedup key, so delivering it
+//! twice can be detected downstream.
+
+mod engine;
+mod store;
+
+pub use engine::{AlertEngine, AlertEvent};
+pub use store::{AlertStore, MemoryAlertStore};
+
+use chrono::{DateTime, Duration, Utc};
+use rust_decimal::Decimal;
+use rust_decimal_macros::dec;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::{AdvisorError, Result};
+
+/// Longest window of a `percent_move` condition (7 days)
+pub const MAX_WINDOW_MINUTES: u32 = 7 * 24 * 60;
+
+/// Most alerts one owner can have
+pub const MAX_ALERTS_PER_OWNER: usize = 50;
+
+/// What an alert watches
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AlertCondition {
+    /// Price at or above `price`
+    PriceAbove { symbol: String, price: Decimal },
+    
+    /// Price at or below `price`
+    PriceBelow { symbol: String, price: Decimal },
+    
+    /// Price moved at least `percent` (either way) within the last `window_minutes`
+    PercentMove { symbol: String, percent: Decimal, window_minutes: u32 },
+    
+    /// Portfolio value at least `percent` below its highest value since the alert was created
+    Drawdown { portfolio: String, percent: Decimal },
+    
+    /// The asset's share of the portfolio more than `band_percent` points away from `target_percent`
+    AllocationDrift {
+        portfolio: String,
+        symbol: String,
+        target_percent: Decimal,
+        band_percent: Decimal,
+    },
+}
+
+impl AlertCondition {
+    /// The asset priced, for single-asset conditions
+    pub fn symbol(&self) -> Option<&str> {
+        match self {
+            Self::PriceAbove { symbol, .. } | Self::PriceBelow { symbol, .. } | Self::PercentMove { symbol, .. } => Some(symbol),
+            Self::Drawdown { .. } | Self::AllocationDrift { .. } => None,
+        }
+    }
+    
+    /// The portfolio valued, for portfolio conditions
+    pub fn portfolio(&self) -> Option<&str> {
+        match self {
+            Self::Drawdown { portfolio, .. } | Self::AllocationDrift { portfolio, .. } => Some(portfolio),
+            _ => None,
+        }
+    }
+    
+    /// Upper-case symbols, and reject values that can never or always trigger
+    pub fn normalized(mut self) -> Result<Self> {
+        let invalid = |message: &str| Err(AdvisorError::InvalidAlert(message.into()));
+        match &mut self {
+            Self::PriceAbove { symbol, price } | Self::PriceBelow { symbol, price } => {
+                *symbol = symbol.trim().to_uppercase();
+                if *price <= Decimal::ZERO {
+                    return invalid("price must be positive");
+                }
+            }
+            Self::PercentMove { symbol, percent, window_minutes } => {
+                *symbol = symbol.trim().to_uppercase();
+                if *percent <= Decimal::ZERO {
+                    return invalid("percent must be positive");
+                }
+                if !(1..=MAX_WINDOW_MINUTES).contains(window_minutes) {
+                    return Err(AdvisorError::InvalidAlert(format!("window_minutes must be 1-{}", MAX_WINDOW_MINUTES)));
+                }
+            }
+            Self::Drawdown { percent, .. } => {
+                if *percent <= Decimal::ZERO || *percent >= dec!(100) {
+                    return invalid("percent must be between 0 and 100");
+                }
+            }
+            Self::AllocationDrift { symbol, target_percent, band_percent, .. } => {
+                *symbol = symbol.trim().to_uppercase();
+                if *target_percent < Decimal::ZERO || *target_percent > dec!(100) {
+                    return invalid("target_percent must be 0-100");
+                }
+                if *band_percent <= Decimal::ZERO {
+                    return invalid("band_percent must be positive");
+                }
+            }
+        }
+        if self.symbol().is_some_and(str::is_empty) || self.portfolio().is_some_and(|p| p.trim().is_empty()) {
+            return invalid("symbol and portfolio must not be empty");
+        }
+        Ok(self)
+    }
+    
+    /// Human description, e.g. "ETH at or below $3000"
+    pub fn describe(&self) -> String {
+        match self {
+            Self::PriceAbove { symbol, price } => format!("{} at or above ${}", symbol, price),
+            Self::PriceBelow { symbol, price } => format!("{} at or below ${}", symbol, price),
+            Self::PercentMove { symbol, percent, window_minutes } => {
+                format!("{} moves {}% within {}", symbol, percent, window(*window_minutes))
+            }
+            Self::Drawdown { portfolio, percent } => format!("portfolio '{}' down {}% from its high", portfolio, percent),
+            Self::AllocationDrift { portfolio, symbol, target_percent, band_percent } => format!(
+                "{} in portfolio '{}' drifts more than {} points from {}%",
+                symbol, portfolio, band_percent, target_percent
+            ),
+        }
+    }
+}
+
+fn window(minutes: u32) -> String {
+    match minutes {
+        m if m % 1440 == 0 => format!("{}d", m / 1440),
+        m if m % 60 == 0 => format!("{}h", m / 60),
+        m => format!("{}m", m),
+    }
+}
+
+/// Where an alert is in its cycle
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlertStatus {
+    /// Waiting for the condition
+    #[default]
+    Armed,
+    /// Fired; re-arms once the condition has cleared (repeating alerts)
+    Triggered,
+    /// Fired once and finished (one-shot alerts)
+    Done,
+}
+
+/// A user's alert
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Alert {
+    pub id: Uuid,
+    
+    /// Who is notified (None = only logged)
+    pub owner_id: Option<String>,
+    
+    pub condition: AlertCondition,
+    
+    /// Fire again after re-arming, instead of once
+    #[serde(default)]
+    pub repeat: bool,
+    
+    /// How far (percent of the threshold, or percentage points) the condition
+    /// must clear before a repeating alert re-arms
+    pub hysteresis_percent: Decimal,
+    
+    /// Least time between two firings of a repeating alert
+    pub cooldown_minutes: u32,
+    
+    pub status: AlertStatus,
+    
+    /// Highest portfolio value seen (`drawdown` only)
+    #[serde(default)]
+    pub peak_value: Option<Decimal>,
+    
+    /// Times fired
+    #[serde(default)]
+    pub fire_count: u32,
+    
+    #[serde(default)]
+    pub last_fired_at: Option<DateTime<Utc>>,
+    
+    pub created_at: DateTime<Utc>,
+}
+
+impl Alert {
+    /// A one-shot alert with 1% hysteresis and a one-hour cooldown
+    pub fn new(owner_id: Option<&str>, condition: AlertCondition) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            owner_id: owner_id.map(String::from),
+            condition,
+            repeat: false,
+            hysteresis_percent: Decimal::ONE,
+            cooldown_minutes: 60,
+            status: AlertStatus::Armed,
+            peak_value: None,
+            fire_count: 0,
+            last_fired_at: None,
+            created_at: Utc::now(),
+        }
+    }
+    
+    /// Still watched by the engine
+    pub fn is_active(&self) -> bool {
+        self.status != AlertStatus::Done
+    }
+    
+    /// Key of the latest firing, e.g. `alert:<id>:3`
+    pub fn dedup_key(&self) -> String {
+        format!("alert:{}:{}", self.id, self.fire_count)
+    }
+    
+    /// Check the options (and normalize the condition)
+    pub fn validated(mut self) -> Result<Self> {
+        self.condition = self.condition.normalized()?;
+        if self.hysteresis_percent < Decimal::ZERO {
+            return Err(AdvisorError::InvalidAlert("hysteresis_percent must not be negative".into()));
+        }
+        Ok(self)
+    }
+    
+    /// Move through the cycle given the condition's latest reading
+    ///
+    /// Returns true when the alert fires.
+    pub fn observe(&mut self, reading: &Reading, now: DateTime<Utc>) -> bool {
+        match self.status {
+            AlertStatus::Armed if reading.triggered => {
+                self.status = if self.repeat { AlertStatus::Triggered } else { AlertStatus::Done };
+                self.fire_count += 1;
+                self.last_fired_at = Some(now);
+                true
+            }
+            AlertStatus::Triggered if reading.cleared => {
+                let cooled = self
+                    .last_fired_at
+                    .is_none_or(|at| now - at >= Duration::minutes(i64::from(self.cooldown_minutes)));
+                if cooled {
+                    self.status = AlertStatus::Armed;
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A condition evaluated once
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Reading {
+    /// The measured value (price, percent or percentage points)
+    pub value: Decimal,
+    
+    /// The condition holds
+    pub triggered: bool,
+    
+    /// The condition is off by more than the hysteresis (a fired alert may re-arm)
+    pub cleared: bool,
+    
+    /// What happened, for the notification
+    pub message: String,
+}
+
+impl Reading {
+    /// `value` against a lower bound: triggered at or above `threshold`,
+    /// cleared below it by `margin`
+    fn at_least(value: Decimal, threshold: Decimal, margin: Decimal, message: String) -> Self {
+        Self { value, triggered: value >= threshold, cleared: value < threshold - margin, message }
+    }
+    
+    /// `value` against an upper bound: triggered at or below `threshold`,
+    /// cleared above it by `margin`
+    fn at_most(value: Decimal, threshold: Decimal, margin: Decimal, message: String) -> Self {
+        Self { value, triggered: value <= threshold, cleared: value > threshold + margin, message }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reading(triggered: bool, cleared: bool) -> Reading {
+        Reading { value: Decimal::ZERO, triggered, cleared, message: String::new() }
+    }
+
+    #[test]
+    fn test_condition_normalized() {
+        let condition = AlertCondition::PriceBelow { symbol: " eth ".into(), price: dec!(3000) }.normalized().unwrap();
+        assert_eq!(condition.symbol(), Some("ETH"));
+        assert_eq!(condition.describe(), "ETH at or below $3000");
+        
+        assert!(AlertCondition::PriceAbove { symbol: "BTC".into(), price: Decimal::ZERO }.normalized().is_err());
+        let too_long = AlertCondition::PercentMove { symbol: "BTC".into(), percent: dec!(5), window_minutes: MAX_WINDOW_MINUTES + 1 };
+        assert!(too_long.normalized().is_err());
+        assert!(AlertCondition::Drawdown { portfolio: "main".into(), percent: dec!(100) }.normalized().is_err());
+    }
+
+    #[test]
+    fn test_observe_hysteresis_and_cooldown() {
+        let start = Utc::now();
+        let condition = AlertCondition::PriceAbove { symbol: "BTC".into(), price: dec!(100000) };
+        
+        // One-shot: fires once, then done
+        let mut once = Alert::new(Some("ana"), condition.clone());
+        assert!(once.observe(&reading(true, false), start));
+        assert_eq!(once.status, AlertStatus::Done);
+        assert!(!once.observe(&reading(true, false), start));
+        
+        // Repeating: stays triggered until cleared and cooled down
+        let mut alert = Alert::new(Some("ana"), condition);
+        alert.repeat = true;
+        assert!(alert.observe(&reading(true, false), start));
+        assert_eq!(alert.dedup_key(), format!("alert:{}:1", alert.id));
+        // Dipping under the threshold but within the hysteresis doesn't re-arm
+        assert!(!alert.observe(&reading(false, false), start + Duration::hours(2)));
+        assert!(!alert.observe(&reading(true, false), start + Duration::hours(2)));
+        // Cleared too soon
+        alert.observe(&reading(false, true), start + Duration::minutes(10));
+        assert_eq!(alert.status, AlertStatus::Triggered);
+        alert.observe(&reading(false, true), start + Duration::hours(2));
+        assert_eq!(alert.status, AlertStatus::Armed);
+        assert!(alert.observe(&reading(true, false), start + Duration::hours(3)));
+        assert_eq!(alert.fire_count, 2);
+    }
+
+    #[test]
+    fn test_reading_bounds() {
+        let above = Reading::at_least(dec!(99500), dec!(100000), dec!(1000), String::new());
+        assert!(!above.triggered && !above.cleared);
+        assert!(Reading::at_least(dec!(98000), dec!(100000), dec!(1000), String::new()).cleared);
+        assert!(Reading::at_most(dec!(2999), dec!(3000), dec!(30), String::new()).triggered);
+        assert!(Reading::at_most(dec!(3031), dec!(3000), dec!(30), String::new()).cleared);
+    }
+}
@@ -0,0 +1,190 @@
+//! Alert storage
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::RwLock;
+
+use async_trait::async_trait;
+use uuid::Uuid;
+
+use super::{Alert, MAX_ALERTS_PER_OWNER};
+use crate::error::{AdvisorError, Result};
+
+/// Alert storage backend
+///
+/// Alerts are scoped to an owner like portfolios; `None` is the shared
+/// (unauthenticated) scope. Changes are async so stores can save them off
+/// the async threads.
+#[async_trait]
+pub trait AlertStore: Send + Sync {
+    /// Alerts of an owner, oldest first
+    fn list(&self, owner_id: Option<&str>) -> Result<Vec<Alert>>;
+    
+    /// An owner's alert
+    fn get(&self, owner_id: Option<&str>, id: Uuid) -> Result<Option<Alert>>;
+    
+    /// Add an alert (fails with `InvalidAlert` past `MAX_ALERTS_PER_OWNER` active alerts)
+    async fn create(&self, alert: &Alert) -> Result<()>;
+    
+    /// Apply `change` to an alert and save it, returning the updated alert
+    ///
+    /// Fails with `AlertNotFound` once the alert is deleted.
+    async fn update(&self, id: Uuid, change: &mut (dyn for<'a> FnMut(&'a mut Alert) -> Result<()> + Send)) -> Result<Alert>;
+    
+    /// Remove an owner's alert; false if there was none
+    async fn delete(&self, owner_id: Option<&str>, id: Uuid) -> Result<bool>;
+    
+    /// Alerts of all owners still watched by the engine
+    fn active(&self) -> Result<Vec<Alert>>;
+}
+
+/// In-memory alert store, optionally saved to a JSON file after every change
+pub struct MemoryAlertStore {
+    path: Option<PathBuf>,
+    alerts: RwLock<HashMap<Uuid, Alert>>,
+    /// Held from a change until it is saved, so saves land in order
+    saving: tokio::sync::Mutex<()>,
+}
+
+impl Default for MemoryAlertStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryAlertStore {
+    pub fn new() -> Self {
+        Self {
+            path: None,
+            alerts: RwLock::new(HashMap::new()),
+            saving: tokio::sync::Mutex::new(()),
+        }
+    }
+    
+    /// Load the alerts saved at `path` (none if the file doesn't exist yet)
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let alerts: Vec<Alert> = match std::fs::read_to_string(&path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| AdvisorError::Storage(format!("{}: {}", path.display(), e)))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(AdvisorError::Storage(format!("{}: {}", path.display(), e))),
+        };
+        
+        Ok(Self {
+            path: Some(path),
+            alerts: RwLock::new(alerts.into_iter().map(|alert| (alert.id, alert)).collect()),
+            saving: tokio::sync::Mutex::new(()),
+        })
+    }
+    
+    /// The alerts to save, oldest first (None when kept in memory only)
+    fn snapshot(&self, alerts: &HashMap<Uuid, Alert>) -> Option<Vec<Alert>> {
+        self.path.as_ref().map(|_| Self::sorted(alerts.values().cloned()))
+    }
+    
+    /// Write-then-rename on the blocking pool, so a crash never leaves a truncated file
+    async fn save(&self, snapshot: Option<Vec<Alert>>) -> Result<()> {
+        let (Some(path), Some(alerts)) = (self.path.clone(), snapshot) else {
+            return Ok(());
+        };
+        let shown = path.display().to_string();
+        let write = move || -> std::io::Result<()> {
+            if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                std::fs::create_dir_all(parent)?;
+            }
+            let tmp = path.with_extension("tmp");
+            std::fs::write(&tmp, serde_json::to_vec_pretty(&alerts)?)?;
+            std::fs::rename(&tmp, &path)
+        };
+        tokio::task::spawn_blocking(write)
+            .await
+            .unwrap_or_else(|e| Err(std::io::Error::other(e)))
+            .map_err(|e| AdvisorError::Storage(format!("{}: {}", shown, e)))
+    }
+    
+    fn sorted(alerts: impl Iterator<Item = Alert>) -> Vec<Alert> {
+        let mut alerts: Vec<Alert> = alerts.collect();
+        alerts.sort_by_key(|alert| alert.created_at);
+        alerts
+    }
+}
+
+#[async_trait]
+impl AlertStore for MemoryAlertStore {
+    fn list(&self, owner_id: Option<&str>) -> Result<Vec<Alert>> {
+        let alerts = self.alerts.read().unwrap();
+        Ok(Self::sorted(alerts.values().filter(|a| a.owner_id.as_deref() == owner_id).cloned()))
+    }
+    
+    fn get(&self, owner_id: Option<&str>, id: Uuid) -> Result<Option<Alert>> {
+        let alerts = self.alerts.read().unwrap();
+        Ok(alerts.get(&id).filter(|a| a.owner_id.as_deref() == owner_id).cloned())
+    }
+    
+    async fn create(&self, alert: &Alert) -> Result<()> {
+        let _saving = self.saving.lock().await;
+        let snapshot = {
+            let mut alerts = self.alerts.write().unwrap();
+            let owned = alerts
+                .values()
+                .filter(|a| a.owner_id == alert.owner_id && a.is_active())
+                .count();
+            if owned >= MAX_ALERTS_PER_OWNER {
+                return Err(AdvisorError::InvalidAlert(format!(
+                    "at most {} active alerts; delete one first",
+                    MAX_ALERTS_PER_OWNER
+                )));
+            }
+            alerts.insert(alert.id, alert.clone());
+            self.snapshot(&alerts)
+        };
+        
+        if let Err(e) = self.save(snapshot).await {
+            self.alerts.write().unwrap().remove(&alert.id);
+            return Err(e);
+        }
+        Ok(())
+    }
+    
+    async fn update(&self, id: Uuid, change: &mut (dyn for<'a> FnMut(&'a mut Alert) -> Result<()> + Send)) -> Result<Alert> {
+        let _saving = self.saving.lock().await;
+        let (before, alert, snapshot) = {
+            let mut alerts = self.alerts.write().unwrap();
+            let stored = alerts.get_mut(&id).ok_or_else(|| AdvisorError::AlertNotFound(id.to_string()))?;
+            let before = stored.clone();
+            let mut alert = before.clone();
+            change(&mut alert)?;
+            *stored = alert.clone();
+            (before, alert, self.snapshot(&alerts))
+        };
+        
+        if let Err(e) = self.save(snapshot).await {
+            self.alerts.write().unwrap().insert(id, before);
+            return Err(e);
+        }
+        Ok(alert)
+    }
+    
+    async fn delete(&self, owner_id: Option<&str>, id: Uuid) -> Result<bool> {
+        let _saving = self.saving.lock().await;
+        let (removed, snapshot) = {
+            let mut alerts = self.alerts.write().unwrap();
+            if alerts.get(&id).is_none_or(|a| a.owner_id.as_deref() != owner_id) {
+                return Ok(false);
+            }
+            (alerts.remove(&id), self.snapshot(&alerts))
+        };
+        
+        if let Err(e) = self.save(snapshot).await {
+            self.alerts.write().unwrap().extend(removed.map(|removed| (id, removed)));
+            return Err(e);
+        }
+        Ok(true)
+    }
+    
+    fn active(&self) -> Result<Vec<Alert>> {
+        let alerts = self.alerts.read().unwrap();
+        Ok(Self::sorted(alerts.values().filter(|a| a.is_active()).cloned()))
+    }
+}
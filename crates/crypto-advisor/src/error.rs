@@ -34,9 +34,36 @@ pub enum AdvisorError {
     #[error("Asset not supported: {0}")]
     UnsupportedAsset(String),
     
+    #[error("Portfolio not found: {0}")]
+    PortfolioNotFound(String),
+    
+    #[error("Portfolio already exists: {0}")]
+    PortfolioExists(String),
+    
+    #[error("Position not found: {0}")]
+    PositionNotFound(String),
+    
+    #[error("Invalid transaction: {0}")]
+    InvalidTransaction(String),
+    
+    #[error("Alert not found: {0}")]
+    AlertNotFound(String),
+    
+    #[error("Invalid alert: {0}")]
+    InvalidAlert(String),
+    
+    #[error("Portfolio storage error: {0}")]
+    Storage(String),
+    
     #[error("Configuration error: {0}")]
     Config(String),
     
+    #[error("Knowledge base error: {0}")]
+    Knowledge(String),
+    
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+    
     #[error("Network error: {0}")]
     Network(#[from] reqwest::Error),
     
@@ -0,0 +1,211 @@
+//! Document Ingestion
+//!
+//! Loads Markdown, plain text (e.g. PDF-extracted) and HTML files and splits
+//! them into heading-aware chunks small enough to quote in a tool result.
+
+use std::path::Path;
+
+use super::Chunk;
+use crate::error::Result;
+
+/// Target chunk size in characters
+const CHUNK_CHARS: usize = 1200;
+
+/// Supported source formats
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DocumentFormat {
+    Markdown,
+    Text,
+    Html,
+}
+
+impl DocumentFormat {
+    /// Detect format from a file extension
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "md" | "markdown" => Some(Self::Markdown),
+            "txt" => Some(Self::Text),
+            "html" | "htm" => Some(Self::Html),
+            _ => None,
+        }
+    }
+}
+
+/// Recursively load every supported document under `dir` into chunks
+///
+/// Chunk sources are paths relative to `dir`, so an index stays valid when
+/// the document directory is moved.
+pub fn load_dir(dir: &Path) -> Result<Vec<Chunk>> {
+    let mut files = Vec::new();
+    collect_files(dir, &mut files)?;
+    files.sort();
+    
+    let mut chunks = Vec::new();
+    for path in files {
+        let Some(format) = DocumentFormat::from_path(&path) else {
+            continue;
+        };
+        let source = path
+            .strip_prefix(dir)
+            .unwrap_or(&path)
+            .to_string_lossy()
+            .replace('\\', "/");
+        let content = std::fs::read_to_string(&path)?;
+        chunks.extend(chunk_document(&source, &content, format));
+    }
+    
+    Ok(chunks)
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<std::path::PathBuf>) -> Result<()> {
+    for entry in std::fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            collect_files(&path, files)?;
+        } else {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Split one document into chunks
+pub fn chunk_document(source: &str, content: &str, format: DocumentFormat) -> Vec<Chunk> {
+    let text = match format {
+        DocumentFormat::Html => html_to_text(content),
+        DocumentFormat::Markdown | DocumentFormat::Text => content.to_string(),
+    };
+    
+    // Sections keyed by the nearest Markdown heading (HTML headings are
+    // converted to Markdown by `html_to_text`)
+    let mut sections: Vec<(Option<String>, String)> = vec![(None, String::new())];
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with('#') && format != DocumentFormat::Text {
+            let heading = trimmed.trim_start_matches('#').trim();
+            sections.push((Some(heading.to_string()), String::new()));
+        } else {
+            let body = &mut sections.last_mut().expect("at least one section").1;
+            body.push_str(line);
+            body.push('\n');
+        }
+    }
+    
+    let mut chunks = Vec::new();
+    for (heading, body) in sections {
+        for text in split_paragraphs(&body, CHUNK_CHARS) {
+            chunks.push(Chunk {
+                id: format!("{}#{}", source, chunks.len()),
+                source: source.to_string(),
+                heading: heading.clone(),
+                text,
+                embedding: None,
+            });
+        }
+    }
+    
+    chunks
+}
+
+/// Pack paragraphs into pieces of at most `max_chars` (a single longer
+/// paragraph is split on sentence boundaries)
+fn split_paragraphs(body: &str, max_chars: usize) -> Vec<String> {
+    let mut pieces = Vec::new();
+    let mut current = String::new();
+    
+    let paragraphs = body
+        .split("\n\n")
+        .map(|p| p.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|p| !p.is_empty());
+    
+    for paragraph in paragraphs {
+        let units: Vec<String> = if paragraph.chars().count() > max_chars {
+            paragraph.split_inclusive(". ").map(str::to_string).collect()
+        } else {
+            vec![paragraph]
+        };
+        
+        for unit in units {
+            if !current.is_empty() && current.chars().count() + unit.chars().count() > max_chars {
+                pieces.push(std::mem::take(&mut current).trim().to_string());
+            }
+            if !current.is_empty() {
+                current.push(' ');
+            }
+            current.push_str(&unit);
+        }
+    }
+    
+    if !current.trim().is_empty() {
+        pieces.push(current.trim().to_string());
+    }
+    pieces
+}
+
+/// Strip tags from HTML, keeping headings and paragraph breaks
+fn html_to_text(html: &str) -> String {
+    let mut out = String::new();
+    let mut rest = html;
+    
+    while let Some(start) = rest.find('<') {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = rest[start + 1..start + end].trim().to_lowercase();
+        rest = &rest[start + end + 1..];
+        
+        let name = tag.trim_start_matches('/').split_whitespace().next().unwrap_or("");
+        match name {
+            // Drop non-content elements entirely
+            "script" | "style" if !tag.starts_with('/') => {
+                let close = format!("</{}", name);
+                rest = match rest.to_ascii_lowercase().find(&close) {
+                    Some(i) => rest[i..].find('>').map_or("", |j| &rest[i + j + 1..]),
+                    None => "",
+                };
+            }
+            "h1" | "h2" | "h3" | "h4" | "h5" | "h6" if !tag.starts_with('/') => {
+                let level = name[1..].parse().unwrap_or(1);
+                out.push_str("\n\n");
+                out.push_str(&"#".repeat(level));
+                out.push(' ');
+            }
+            "p" | "div" | "br" | "li" | "tr" | "h1" | "h2" | "h3" | "h4" | "h5" | "h6" => {
+                out.push_str("\n\n");
+            }
+            _ => {}
+        }
+    }
+    out.push_str(rest);
+    
+    out.replace("&nbsp;", " ")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&#39;", "'")
+        .replace("&amp;", "&")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_markdown_chunks_by_heading() {
+        let doc = "# DCA Explained\n\nBuy a fixed amount on a schedule.\n\n## Risks\n\nPrices can still fall.\n";
+        let chunks = chunk_document("dca.md", doc, DocumentFormat::Markdown);
+        assert_eq!(chunks.len(), 2);
+        assert_eq!(chunks[1].heading.as_deref(), Some("Risks"));
+        assert_eq!(chunks[1].text, "Prices can still fall.");
+    }
+
+    #[test]
+    fn test_html_to_text() {
+        let html = "<html><style>p{}</style><h2>Fees</h2><p>Fees &amp; spreads apply.</p></html>";
+        let chunks = chunk_document("fees.html", html, DocumentFormat::Html);
+        assert_eq!(chunks.len(), 1);
+        assert_eq!(chunks[0].heading.as_deref(), Some("Fees"));
+        assert_eq!(chunks[0].text, "Fees & spreads apply.");
+    }
+}
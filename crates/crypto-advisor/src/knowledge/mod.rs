@@ -0,0 +1,329 @@
+//! Knowledge Base
+//!
+//! Retrieval over the firm's own educational material (DCA explainers, risk
+//! disclosures, asset fact sheets) so the advisor can cite sources instead of
+//! inventing claims.
+//!
+//! Documents are chunked by `ingest`, ranked with BM25, and — when the index
+//! was built with an embedding model — re-scored with cosine similarity for a
+//! hybrid lexical/semantic ranking.
+
+pub mod ingest;
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use agent_core::embedding::{cosine_similarity, EmbeddingProvider};
+
+use crate::error::{AdvisorError, Result};
+
+/// BM25 term-frequency saturation
+const BM25_K1: f32 = 1.2;
+
+/// BM25 length normalization
+const BM25_B: f32 = 0.75;
+
+/// Weight of the semantic score in hybrid ranking
+const SEMANTIC_WEIGHT: f32 = 0.5;
+
+/// Texts embedded per request when building an index
+const EMBED_BATCH: usize = 32;
+
+/// A passage of a source document
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Chunk {
+    /// Stable ID (`<source>#<n>`)
+    pub id: String,
+    
+    /// Document path relative to the knowledge directory
+    pub source: String,
+    
+    /// Nearest section heading
+    pub heading: Option<String>,
+    
+    /// Passage text
+    pub text: String,
+    
+    /// Embedding vector (present when indexed with an embedding model)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
+}
+
+/// A ranked search result
+#[derive(Clone, Debug, Serialize)]
+pub struct Passage {
+    pub chunk_id: String,
+    pub source: String,
+    pub heading: Option<String>,
+    pub text: String,
+    pub score: f32,
+}
+
+#[derive(Serialize, Deserialize)]
+struct IndexFile {
+    version: u32,
+    built_at: DateTime<Utc>,
+    embedding_model: Option<String>,
+    chunks: Vec<Chunk>,
+}
+
+/// Searchable collection of document chunks
+#[derive(Debug, Default)]
+pub struct KnowledgeBase {
+    chunks: Vec<Chunk>,
+    embedding_model: Option<String>,
+    built_at: Option<DateTime<Utc>>,
+    
+    // BM25 statistics, derived from `chunks`
+    term_freqs: Vec<HashMap<String, u32>>,
+    doc_lens: Vec<usize>,
+    doc_freqs: HashMap<String, u32>,
+    avg_len: f32,
+}
+
+impl KnowledgeBase {
+    /// Build from chunks
+    pub fn from_chunks(chunks: Vec<Chunk>) -> Self {
+        let mut kb = Self {
+            chunks,
+            built_at: Some(Utc::now()),
+            ..Default::default()
+        };
+        kb.rebuild_stats();
+        kb
+    }
+    
+    /// Ingest every supported document under `dir`
+    pub fn from_dir(dir: impl AsRef<Path>) -> Result<Self> {
+        Ok(Self::from_chunks(ingest::load_dir(dir.as_ref())?))
+    }
+    
+    /// Load a saved index
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let file: IndexFile = serde_json::from_slice(&std::fs::read(path)?)?;
+        if file.version != 1 {
+            return Err(AdvisorError::Knowledge(format!(
+                "Unsupported index version {}",
+                file.version
+            )));
+        }
+        
+        let mut kb = Self {
+            chunks: file.chunks,
+            embedding_model: file.embedding_model,
+            built_at: Some(file.built_at),
+            ..Default::default()
+        };
+        kb.rebuild_stats();
+        Ok(kb)
+    }
+    
+    /// Save the index
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        
+        let file = IndexFile {
+            version: 1,
+            built_at: self.built_at.unwrap_or_else(Utc::now),
+            embedding_model: self.embedding_model.clone(),
+            chunks: self.chunks.clone(),
+        };
+        std::fs::write(path, serde_json::to_vec(&file)?)?;
+        Ok(())
+    }
+    
+    /// Compute embeddings for every chunk
+    pub async fn embed(&mut self, embedder: &dyn EmbeddingProvider) -> Result<()> {
+        for batch in self.chunks.chunks_mut(EMBED_BATCH) {
+            let texts: Vec<String> = batch.iter().map(Chunk::embedding_text).collect();
+            let vectors = embedder
+                .embed(&texts)
+                .await
+                .map_err(|e| AdvisorError::Knowledge(e.to_string()))?;
+            
+            for (chunk, vector) in batch.iter_mut().zip(vectors) {
+                chunk.embedding = Some(vector);
+            }
+        }
+        
+        self.embedding_model = Some(embedder.model().to_string());
+        Ok(())
+    }
+    
+    /// Embedding model the index was built with, if any
+    pub fn embedding_model(&self) -> Option<&str> {
+        self.embedding_model.as_deref()
+    }
+    
+    /// Number of chunks
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+    
+    /// Check if empty
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+    
+    /// Number of distinct source documents
+    pub fn source_count(&self) -> usize {
+        let mut sources: Vec<&str> = self.chunks.iter().map(|c| c.source.as_str()).collect();
+        sources.sort_unstable();
+        sources.dedup();
+        sources.len()
+    }
+    
+    /// Rank passages by BM25, blended with semantic similarity when a query
+    /// embedding is given and the index has embeddings
+    pub fn search(&self, query: &str, query_embedding: Option<&[f32]>, limit: usize) -> Vec<Passage> {
+        let lexical = self.bm25_scores(query);
+        let max_lexical = lexical.iter().copied().fold(0.0f32, f32::max);
+        
+        let mut scored: Vec<(usize, f32)> = self
+            .chunks
+            .iter()
+            .enumerate()
+            .map(|(i, chunk)| {
+                let lexical = if max_lexical > 0.0 { lexical[i] / max_lexical } else { 0.0 };
+                let score = match (query_embedding, &chunk.embedding) {
+                    (Some(query), Some(embedding)) => {
+                        let semantic = cosine_similarity(query, embedding).max(0.0);
+                        (1.0 - SEMANTIC_WEIGHT) * lexical + SEMANTIC_WEIGHT * semantic
+                    }
+                    _ => lexical,
+                };
+                (i, score)
+            })
+            .filter(|(_, score)| *score > 0.0)
+            .collect();
+        
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+        scored.truncate(limit);
+        
+        scored
+            .into_iter()
+            .map(|(i, score)| {
+                let chunk = &self.chunks[i];
+                Passage {
+                    chunk_id: chunk.id.clone(),
+                    source: chunk.source.clone(),
+                    heading: chunk.heading.clone(),
+                    text: chunk.text.clone(),
+                    score,
+                }
+            })
+            .collect()
+    }
+    
+    fn bm25_scores(&self, query: &str) -> Vec<f32> {
+        let n = self.chunks.len() as f32;
+        let mut terms = tokenize(query);
+        terms.sort_unstable();
+        terms.dedup();
+        
+        self.term_freqs
+            .iter()
+            .zip(&self.doc_lens)
+            .map(|(freqs, &len)| {
+                terms
+                    .iter()
+                    .filter_map(|term| {
+                        let tf = *freqs.get(term)? as f32;
+                        let df = self.doc_freqs.get(term).copied().unwrap_or(0) as f32;
+                        let idf = ((n - df + 0.5) / (df + 0.5) + 1.0).ln();
+                        let norm = 1.0 - BM25_B + BM25_B * len as f32 / self.avg_len.max(1.0);
+                        Some(idf * tf * (BM25_K1 + 1.0) / (tf + BM25_K1 * norm))
+                    })
+                    .sum()
+            })
+            .collect()
+    }
+    
+    fn rebuild_stats(&mut self) {
+        self.term_freqs.clear();
+        self.doc_lens.clear();
+        self.doc_freqs.clear();
+        
+        for chunk in &self.chunks {
+            let tokens = tokenize(&chunk.embedding_text());
+            let mut freqs: HashMap<String, u32> = HashMap::new();
+            for token in &tokens {
+                *freqs.entry(token.clone()).or_default() += 1;
+            }
+            for term in freqs.keys() {
+                *self.doc_freqs.entry(term.clone()).or_default() += 1;
+            }
+            self.doc_lens.push(tokens.len());
+            self.term_freqs.push(freqs);
+        }
+        
+        let total: usize = self.doc_lens.iter().sum();
+        self.avg_len = if self.chunks.is_empty() { 0.0 } else { total as f32 / self.chunks.len() as f32 };
+    }
+}
+
+impl Chunk {
+    /// Text used for indexing: heading plus passage
+    fn embedding_text(&self) -> String {
+        match &self.heading {
+            Some(heading) => format!("{}\n{}", heading, self.text),
+            None => self.text.clone(),
+        }
+    }
+}
+
+/// Lowercased alphanumeric tokens
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ingest::{chunk_document, DocumentFormat};
+
+    fn sample() -> KnowledgeBase {
+        let mut chunks = chunk_document(
+            "dca.md",
+            "# Dollar-Cost Averaging\n\nDCA invests a fixed amount on a regular schedule.\n\n## Risk Disclosure\n\nCrypto assets are volatile and can lose all value.\n",
+            DocumentFormat::Markdown,
+        );
+        chunks.extend(chunk_document(
+            "btc.md",
+            "# Bitcoin Fact Sheet\n\nBitcoin has a fixed supply of 21 million coins.\n",
+            DocumentFormat::Markdown,
+        ));
+        KnowledgeBase::from_chunks(chunks)
+    }
+
+    #[test]
+    fn test_bm25_ranking() {
+        let kb = sample();
+        let hits = kb.search("how volatile is crypto, can I lose value?", None, 2);
+        assert_eq!(hits[0].heading.as_deref(), Some("Risk Disclosure"));
+        assert!(kb.search("ethereum staking", None, 3).is_empty());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join(format!("knowledge-{}.json", uuid::Uuid::new_v4()));
+        let kb = sample();
+        kb.save(&path).unwrap();
+        
+        let loaded = KnowledgeBase::load(&path).unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded.source_count(), 2);
+        assert_eq!(loaded.search("bitcoin supply", None, 1)[0].source, "btc.md");
+        
+        std::fs::remove_file(&path).ok();
+    }
+}
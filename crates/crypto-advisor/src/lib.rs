@@ -39,11 +39,21 @@ pub mod svckit;
 pub mod strategy;
 pub mod exchange;
 pub mod model;
+pub mod knowledge;
+pub mod team;
+pub mod policy;
+pub mod store;
+pub mod alerts;
 pub mod error;
 
+use agent_core::prompt::{PromptRegistry, PromptTemplate, BUILTIN_VERSION};
+
 pub use error::{AdvisorError, Result};
-pub use model::{Asset, Portfolio, Position, RiskProfile, Allocation};
+pub use knowledge::KnowledgeBase;
+pub use model::{Asset, Portfolio, Position, RiskProfile, Allocation, TradeSide, Transaction};
 pub use strategy::{DCAStrategy, DiversificationStrategy, AllocationPlan};
+pub use store::{MemoryPortfolioStore, PortfolioStore};
+pub use alerts::{Alert, AlertCondition, AlertEngine, AlertEvent, AlertStatus, AlertStore, MemoryAlertStore};
 
 /// Re-export tools for easy registration
 pub mod tools {
@@ -52,13 +62,18 @@ pub mod tools {
         DCACalculatorTool,
         RiskAnalyzerTool,
         PortfolioTrackerTool,
+        KnowledgeSearchTool,
+        PriceAlertTool,
     };
 }
 
-/// System prompt for the crypto advisor agent
-pub const CRYPTO_ADVISOR_PROMPT: &str = r#"You are a conservative cryptocurrency investment advisor focused on risk management and capital preservation.
-
-## Investment Philosophy
+/// Advisor prompt text, with `$context` spliced in after the opening line
+macro_rules! advisor_prompt {
+    ($context:literal) => {
+        concat!(
+            "You are a conservative cryptocurrency investment advisor focused on risk management and capital preservation.\n\n",
+            $context,
+            r#"## Investment Philosophy
 
 1. **Diversification over concentration** - Always spread risk across multiple assets
 2. **Dollar-cost averaging (DCA)** - Spread purchases over time, never lump-sum into volatile assets
@@ -92,5 +107,33 @@ Always explain:
 - `dca_calculator` - Compute diversified allocations
 - `risk_analyzer` - Assess volatility and risk metrics
 - `portfolio_tracker` - Track positions and P&L
+- `knowledge_search` - Look up our educational material and risk disclosures
+- `price_alert` - Notify the user when a price crosses a level or moves sharply, a portfolio draws down, or an allocation drifts
+
+When explaining concepts or risks, prefer `knowledge_search` and cite passages as [n] with their source. Never cite a source the tool did not return.
+
+Never make investment decisions without using these tools first."#
+        )
+    };
+}
 
-Never make investment decisions without using these tools first."#;
+/// System prompt for the crypto advisor agent
+pub const CRYPTO_ADVISOR_PROMPT: &str = advisor_prompt!("");
+
+/// Name of the crypto advisor prompt template
+pub const CRYPTO_ADVISOR_TEMPLATE_NAME: &str = "crypto_advisor";
+
+/// Built-in system prompt template for the crypto advisor agent
+pub const CRYPTO_ADVISOR_TEMPLATE: &str = advisor_prompt!("{{> user_context}}\n\n");
+
+/// Tag vocabulary for automatic conversation tagging (`agent_core::tagging`)
+pub const TAGGING_INSTRUCTIONS: &str = r#"Tags, in this order:
+- each crypto asset discussed, as its lowercase ticker ("btc", "eth", "sol")
+- the user's intent, one of: "dca-plan", "portfolio-review", "price-check", "risk-analysis", "education"
+- the risk level discussed, if any: "risk-conservative", "risk-moderate" or "risk-aggressive""#;
+
+/// Register the advisor's built-in prompt templates
+pub fn register_prompts(registry: &PromptRegistry) {
+    registry.register(PromptTemplate::new(CRYPTO_ADVISOR_TEMPLATE_NAME, BUILTIN_VERSION, CRYPTO_ADVISOR_TEMPLATE));
+    team::register_prompts(registry);
+}
@@ -7,6 +7,9 @@ use chrono::{DateTime, Utc};
 use rust_decimal::Decimal;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use uuid::Uuid;
+
+use crate::error::{AdvisorError, Result};
 
 /// A cryptocurrency asset
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -191,6 +194,43 @@ impl Portfolio {
         self.positions.insert(position.symbol.clone(), position);
     }
     
+    /// Apply a trade to the positions
+    ///
+    /// Buys raise the quantity and average the cost basis (fees included);
+    /// sells lower the quantity and close the position when nothing is left.
+    /// The trade price becomes the position's current price until the next
+    /// revaluation. Cash is not touched: trades are funded from outside.
+    pub fn apply(&mut self, transaction: &Transaction) -> Result<()> {
+        transaction.validate()?;
+        let symbol = transaction.symbol.to_uppercase();
+        
+        match transaction.side {
+            TradeSide::Buy => {
+                let cost = transaction.quantity * transaction.price + transaction.fee;
+                let position = self.positions
+                    .entry(symbol.clone())
+                    .or_insert_with(|| Position::new(symbol, Decimal::ZERO, Decimal::ZERO));
+                let quantity = position.quantity + transaction.quantity;
+                position.cost_basis = (position.total_cost() + cost) / quantity;
+                position.quantity = quantity;
+                position.update_price(transaction.price);
+            }
+            TradeSide::Sell => {
+                let held = self.positions.get(&symbol).map_or(Decimal::ZERO, |p| p.quantity);
+                if transaction.quantity > held {
+                    return Err(AdvisorError::InsufficientFunds { needed: transaction.quantity, available: held });
+                }
+                if transaction.quantity == held {
+                    self.positions.remove(&symbol);
+                } else if let Some(position) = self.positions.get_mut(&symbol) {
+                    position.quantity -= transaction.quantity;
+                    position.update_price(transaction.price);
+                }
+            }
+        }
+        Ok(())
+    }
+    
     /// Update all positions with current prices
     pub fn update_prices(&mut self, prices: &HashMap<String, Decimal>) {
         for (symbol, position) in &mut self.positions {
@@ -201,6 +241,74 @@ impl Portfolio {
     }
 }
 
+/// Side of a trade
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TradeSide {
+    Buy,
+    Sell,
+}
+
+/// A buy or sell recorded against a portfolio
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Transaction {
+    pub id: Uuid,
+    
+    /// Asset symbol
+    pub symbol: String,
+    
+    pub side: TradeSide,
+    
+    /// Units bought or sold
+    pub quantity: Decimal,
+    
+    /// Price per unit in USD
+    pub price: Decimal,
+    
+    /// Fee paid in USD
+    pub fee: Decimal,
+    
+    /// When the trade happened
+    pub executed_at: DateTime<Utc>,
+    
+    /// Free-form note
+    pub note: Option<String>,
+}
+
+impl Transaction {
+    pub fn new(symbol: impl Into<String>, side: TradeSide, quantity: Decimal, price: Decimal) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            symbol: symbol.into().to_uppercase(),
+            side,
+            quantity,
+            price,
+            fee: Decimal::ZERO,
+            executed_at: Utc::now(),
+            note: None,
+        }
+    }
+    
+    pub fn with_fee(mut self, fee: Decimal) -> Self {
+        self.fee = fee;
+        self
+    }
+    
+    /// Check the amounts are usable (positive quantity and price, no negative fee)
+    pub fn validate(&self) -> Result<()> {
+        if self.symbol.trim().is_empty() {
+            return Err(AdvisorError::InvalidTransaction("Symbol is required".into()));
+        }
+        if self.quantity <= Decimal::ZERO || self.price <= Decimal::ZERO {
+            return Err(AdvisorError::InvalidTransaction("Quantity and price must be positive".into()));
+        }
+        if self.fee < Decimal::ZERO {
+            return Err(AdvisorError::InvalidTransaction("Fee cannot be negative".into()));
+        }
+        Ok(())
+    }
+}
+
 /// Risk profile for a user or portfolio
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct RiskProfile {
@@ -266,6 +374,16 @@ impl RiskProfile {
         }
     }
     
+    /// Parse a profile name ("conservative", "moderate", "aggressive") or tolerance level ("1"-"5")
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "conservative" => Some(Self::conservative()),
+            "moderate" => Some(Self::moderate()),
+            "aggressive" => Some(Self::aggressive()),
+            level => level.parse().ok().filter(|l| (1..=5).contains(l)).map(Self::from_tolerance),
+        }
+    }
+    
     /// Create from tolerance level (1-5)
     pub fn from_tolerance(level: u8) -> Self {
         match level {
@@ -372,4 +490,26 @@ mod tests {
         assert!(allocs.contains_key("BTC"));
         assert!(allocs.contains_key("CASH"));
     }
+
+    #[test]
+    fn test_apply_transactions() {
+        let mut portfolio = Portfolio::new("Test");
+        portfolio.apply(&Transaction::new("btc", TradeSide::Buy, dec!(1), dec!(40000))).unwrap();
+        portfolio.apply(&Transaction::new("BTC", TradeSide::Buy, dec!(1), dec!(50000)).with_fee(dec!(100))).unwrap();
+        
+        let btc = &portfolio.positions["BTC"];
+        assert_eq!(btc.quantity, dec!(2));
+        assert_eq!(btc.cost_basis, dec!(45050));
+        assert_eq!(btc.current_value, dec!(100000));
+        
+        let oversold = portfolio.apply(&Transaction::new("BTC", TradeSide::Sell, dec!(3), dec!(50000)));
+        assert!(matches!(oversold, Err(AdvisorError::InsufficientFunds { .. })));
+        
+        portfolio.apply(&Transaction::new("BTC", TradeSide::Sell, dec!(0.5), dec!(60000))).unwrap();
+        assert_eq!(portfolio.positions["BTC"].quantity, dec!(1.5));
+        assert_eq!(portfolio.positions["BTC"].cost_basis, dec!(45050));
+        
+        portfolio.apply(&Transaction::new("BTC", TradeSide::Sell, dec!(1.5), dec!(60000))).unwrap();
+        assert!(portfolio.positions.is_empty());
+    }
 }
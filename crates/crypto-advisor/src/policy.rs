@@ -0,0 +1,211 @@
+//! Advisor Policy Guardrails
+//!
+//! Rule-based checks for the promises the advisor prompt makes:
+//!
+//! - **Concentration** - no single asset above `RiskProfile.max_single_allocation`
+//!   unless the user explicitly asked to override it
+//! - **Tool evidence** - no recommendation without using tools first
+//! - **Risk disclosure** - every recommendation explains the risks
+//!
+//! The checks are lexical heuristics over the answer text, tuned to avoid
+//! flagging price moves ("SOL fell 40%") as allocations.
+
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+
+use agent_core::{
+    guardrail::{Guardrail, GuardrailCheck, GuardrailInput, Guardrails, Violation},
+    Result as CoreResult,
+};
+
+use crate::model::RiskProfile;
+
+/// Words that mark a line as recommending an investment
+const RECOMMENDATION_WORDS: &[&str] = &["invest", "allocat", "buy", "purchase", "put ", "dca", "weight"];
+
+/// Words that mark a percentage as a price move or statistic, not an allocation
+const MOVEMENT_WORDS: &[&str] = &[
+    "drop", "fell", "fall", "declin", "crash", "drawdown", "volatil", "down", "gain", "rose", "rise", "up ",
+    "change", "loss", "lose", "return", "apy", "yield",
+];
+
+/// Phrases by which a user explicitly overrides the position limit
+const OVERRIDE_PHRASES: &[&str] = &["override", "all in", "all-in", "everything in", "only one", "single asset"];
+
+/// Words that count as a risk disclosure
+const DISCLOSURE_WORDS: &[&str] = &["risk", "volatil", "lose", "loss", "past performance", "not financial advice"];
+
+/// Tokens that look like tickers but aren't assets
+const NOT_TICKERS: &[&str] = &["DCA", "USD", "ETF", "APY", "API", "FOMO", "ATH", "P&L", "PNL", "CEO", "OK"];
+
+/// Single-asset allocation above the profile's limit
+pub struct ConcentrationRule {
+    profile: RiskProfile,
+}
+
+impl ConcentrationRule {
+    pub fn new(profile: RiskProfile) -> Self {
+        Self { profile }
+    }
+}
+
+#[async_trait]
+impl Guardrail for ConcentrationRule {
+    fn name(&self) -> &str {
+        "concentration"
+    }
+    
+    async fn check(&self, input: &GuardrailInput<'_>) -> CoreResult<GuardrailCheck> {
+        let question = input.question.to_lowercase();
+        if OVERRIDE_PHRASES.iter().any(|p| question.contains(p)) {
+            return Ok(GuardrailCheck::default());
+        }
+        
+        let limit = self.profile.max_single_allocation;
+        let mut flagged: Vec<String> = Vec::new();
+        let mut violations = Vec::new();
+        for (symbol, percent) in allocations(input.answer) {
+            if percent > limit && !flagged.contains(&symbol) {
+                flagged.push(symbol.clone());
+                violations.push(Violation::new(
+                    "concentration",
+                    format!(
+                        "{} is allocated {}%, above the {}% single-asset limit for this risk profile. Reduce it or spread the amount across more assets.",
+                        symbol, percent, limit
+                    ),
+                ));
+            }
+        }
+        
+        Ok(violations.into())
+    }
+}
+
+/// Recommendations made without using any tool
+pub struct ToolEvidenceRule;
+
+#[async_trait]
+impl Guardrail for ToolEvidenceRule {
+    fn name(&self) -> &str {
+        "tool_evidence"
+    }
+    
+    async fn check(&self, input: &GuardrailInput<'_>) -> CoreResult<GuardrailCheck> {
+        if recommends(input.answer) && !input.has_tool_evidence() {
+            return Ok(vec![Violation::new(
+                "tool_evidence",
+                "The recommendation is not based on any tool result. Look up current prices and risk metrics with the tools before recommending.",
+            )]
+            .into());
+        }
+        Ok(GuardrailCheck::default())
+    }
+}
+
+/// Recommendations without a risk disclosure
+pub struct RiskDisclosureRule;
+
+#[async_trait]
+impl Guardrail for RiskDisclosureRule {
+    fn name(&self) -> &str {
+        "risk_disclosure"
+    }
+    
+    async fn check(&self, input: &GuardrailInput<'_>) -> CoreResult<GuardrailCheck> {
+        let answer = input.answer.to_lowercase();
+        if recommends(input.answer) && !DISCLOSURE_WORDS.iter().any(|w| answer.contains(w)) {
+            return Ok(vec![Violation::new(
+                "risk_disclosure",
+                "The recommendation has no risk disclosure. Explain volatility and that crypto assets can lose most or all of their value.",
+            )]
+            .into());
+        }
+        Ok(GuardrailCheck::default())
+    }
+}
+
+/// The advisor's rule-based guardrails for a risk profile
+pub fn advisor_guardrails(profile: RiskProfile) -> Guardrails {
+    Guardrails::new()
+        .rule(ConcentrationRule::new(profile))
+        .rule(ToolEvidenceRule)
+        .rule(RiskDisclosureRule)
+}
+
+/// Whether an answer recommends investing (an amount or percentage plus a recommendation word)
+fn recommends(answer: &str) -> bool {
+    let lower = answer.to_lowercase();
+    (lower.contains('%') || lower.contains('$')) && RECOMMENDATION_WORDS.iter().any(|w| lower.contains(w))
+}
+
+/// `(symbol, percent)` pairs for allocation-like percentages
+///
+/// Each percentage is attributed to the nearest ticker on the same line. On a
+/// tie the following ticker wins after "in"/"into" ("20% in BTC"), otherwise
+/// the preceding one ("BTC: 20%"). Lines describing price moves are skipped.
+fn allocations(answer: &str) -> Vec<(String, Decimal)> {
+    let mut found = Vec::new();
+    
+    for line in answer.lines() {
+        let lower = line.to_lowercase();
+        if MOVEMENT_WORDS.iter().any(|w| lower.contains(w)) {
+            continue;
+        }
+        
+        let tokens: Vec<&str> = line
+            .split(|c: char| c.is_whitespace() || matches!(c, ',' | ':' | '(' | ')' | '|' | '*' | '-' | '/'))
+            .filter(|t| !t.is_empty())
+            .collect();
+        let tickers: Vec<(usize, &str)> = tokens.iter().enumerate().filter(|(_, t)| is_ticker(t)).map(|(i, t)| (i, *t)).collect();
+        
+        for (i, token) in tokens.iter().enumerate() {
+            let Some(Ok(percent)) = token.trim_end_matches('.').strip_suffix('%').map(str::parse::<Decimal>) else {
+                continue;
+            };
+            let prefer_following = tokens.get(i + 1).is_some_and(|next| matches!(*next, "in" | "into" | "to"));
+            if let Some((_, symbol)) = tickers.iter().min_by_key(|(j, _)| (i.abs_diff(*j), (*j > i) != prefer_following)) {
+                found.push((symbol.to_string(), percent));
+            }
+        }
+    }
+    
+    found
+}
+
+fn is_ticker(token: &str) -> bool {
+    (2..=6).contains(&token.len())
+        && token.chars().all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+        && token.chars().next().is_some_and(|c| c.is_ascii_uppercase())
+        && !NOT_TICKERS.contains(&token)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use agent_core::ToolResult;
+
+    async fn violations(question: &str, answer: &str, tool_results: &[ToolResult]) -> Vec<String> {
+        let input = GuardrailInput { question, answer, tool_results };
+        advisor_guardrails(RiskProfile::conservative())
+            .check(&input)
+            .await
+            .violations
+            .into_iter()
+            .map(|v| v.rule)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_policy_rules() {
+        let prices = [ToolResult::success("price_lookup", "BTC: $60,000")];
+        let compliant = "Allocate your $1000 as:\n- BTC: 20% ($200)\n- ETH: 20% ($200)\n- SOL: 10% ($100)\nCrypto is volatile; you could lose money. SOL fell 40% last year.";
+        assert!(violations("Invest $1000", compliant, &prices).await.is_empty());
+        
+        let concentrated = "Put 60% of it in SOL and 20% in BTC, then buy more SOL.";
+        assert_eq!(violations("Invest $1000", concentrated, &[]).await, vec!["concentration", "tool_evidence", "risk_disclosure"]);
+        
+        // An explicit override lifts the position limit
+        let all_in = "Allocation: SOL 100% ($1000). This is very risky.";
+        assert!(violations("Override the limit, I want all-in SOL", all_in, &prices).await.is_empty());
+    }
+}
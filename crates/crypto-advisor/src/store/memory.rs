@@ -0,0 +1,100 @@
+//! In-memory portfolio store
+
+use std::collections::HashMap;
+use std::sync::RwLock;
+
+use super::PortfolioStore;
+use crate::error::{AdvisorError, Result};
+use crate::model::{Portfolio, Transaction};
+
+/// `(owner_id, name)`; portfolios without an owner use an empty owner
+type Key = (String, String);
+
+fn key(owner_id: Option<&str>, name: &str) -> Key {
+    (owner_id.unwrap_or_default().to_string(), name.to_string())
+}
+
+/// In-memory portfolio store (for development/testing)
+pub struct MemoryPortfolioStore {
+    portfolios: RwLock<HashMap<Key, Portfolio>>,
+    transactions: RwLock<HashMap<Key, Vec<Transaction>>>,
+}
+
+impl Default for MemoryPortfolioStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MemoryPortfolioStore {
+    pub fn new() -> Self {
+        Self {
+            portfolios: RwLock::new(HashMap::new()),
+            transactions: RwLock::new(HashMap::new()),
+        }
+    }
+}
+
+impl PortfolioStore for MemoryPortfolioStore {
+    fn list(&self, owner_id: Option<&str>) -> Result<Vec<Portfolio>> {
+        let portfolios = self.portfolios.read().unwrap();
+        let mut result: Vec<_> = portfolios
+            .values()
+            .filter(|p| p.owner_id.as_deref() == owner_id)
+            .cloned()
+            .collect();
+        result.sort_by(|a, b| a.created_at.cmp(&b.created_at).then_with(|| a.name.cmp(&b.name)));
+        Ok(result)
+    }
+    
+    fn get(&self, owner_id: Option<&str>, name: &str) -> Result<Option<Portfolio>> {
+        let portfolios = self.portfolios.read().unwrap();
+        Ok(portfolios.get(&key(owner_id, name)).cloned())
+    }
+    
+    fn create(&self, portfolio: &Portfolio) -> Result<()> {
+        let mut portfolios = self.portfolios.write().unwrap();
+        let key = key(portfolio.owner_id.as_deref(), &portfolio.name);
+        if portfolios.contains_key(&key) {
+            return Err(AdvisorError::PortfolioExists(portfolio.name.clone()));
+        }
+        portfolios.insert(key, portfolio.clone());
+        Ok(())
+    }
+    
+    fn update(
+        &self,
+        owner_id: Option<&str>,
+        name: &str,
+        change: &mut dyn FnMut(&mut Portfolio) -> Result<()>,
+    ) -> Result<Portfolio> {
+        let mut portfolios = self.portfolios.write().unwrap();
+        let stored = portfolios
+            .get_mut(&key(owner_id, name))
+            .ok_or_else(|| AdvisorError::PortfolioNotFound(name.to_string()))?;
+        
+        let mut portfolio = stored.clone();
+        change(&mut portfolio)?;
+        *stored = portfolio.clone();
+        Ok(portfolio)
+    }
+    
+    fn record(&self, owner_id: Option<&str>, name: &str, transaction: &Transaction) -> Result<Portfolio> {
+        // Both locks are held so history and positions never disagree
+        let mut transactions = self.transactions.write().unwrap();
+        let portfolio = self.update(owner_id, name, &mut |portfolio| portfolio.apply(transaction))?;
+        transactions.entry(key(owner_id, name)).or_default().push(transaction.clone());
+        Ok(portfolio)
+    }
+    
+    fn transactions(&self, owner_id: Option<&str>, name: &str, limit: usize) -> Result<Vec<Transaction>> {
+        if self.get(owner_id, name)?.is_none() {
+            return Err(AdvisorError::PortfolioNotFound(name.to_string()));
+        }
+        let transactions = self.transactions.read().unwrap();
+        let mut result = transactions.get(&key(owner_id, name)).cloned().unwrap_or_default();
+        result.sort_by(|a, b| b.executed_at.cmp(&a.executed_at));
+        result.truncate(limit);
+        Ok(result)
+    }
+}
@@ -0,0 +1,108 @@
+//! Portfolio Storage
+//!
+//! Portfolios are stored per owner (`Portfolio.owner_id`, `None` for
+//! portfolios without one) and named uniquely within it. The portfolio
+//! tracker tool and the REST API share one store, so what the agent records
+//! is what the API shows and the other way round.
+
+mod memory;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub use memory::MemoryPortfolioStore;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqlitePortfolioStore;
+
+use std::collections::HashMap;
+
+use crate::error::{AdvisorError, Result};
+use crate::exchange::ExchangeClient;
+use crate::model::{Portfolio, Transaction};
+
+/// Portfolio storage trait
+pub trait PortfolioStore: Send + Sync {
+    /// Portfolios of an owner, oldest first
+    fn list(&self, owner_id: Option<&str>) -> Result<Vec<Portfolio>>;
+    
+    /// Get a portfolio by name
+    fn get(&self, owner_id: Option<&str>, name: &str) -> Result<Option<Portfolio>>;
+    
+    /// Add a new portfolio (fails with `PortfolioExists` if the name is taken)
+    fn create(&self, portfolio: &Portfolio) -> Result<()>;
+    
+    /// Change a portfolio and save it, returning the saved portfolio
+    ///
+    /// Nothing is saved when `change` fails.
+    fn update(
+        &self,
+        owner_id: Option<&str>,
+        name: &str,
+        change: &mut dyn FnMut(&mut Portfolio) -> Result<()>,
+    ) -> Result<Portfolio>;
+    
+    /// Apply a transaction to a portfolio and add it to its history (atomic)
+    fn record(&self, owner_id: Option<&str>, name: &str, transaction: &Transaction) -> Result<Portfolio>;
+    
+    /// Transactions of a portfolio, most recent first
+    fn transactions(&self, owner_id: Option<&str>, name: &str, limit: usize) -> Result<Vec<Transaction>>;
+}
+
+/// Update a portfolio's positions with current exchange prices
+///
+/// Returns the saved portfolio and how many positions were repriced;
+/// positions whose price can't be fetched keep their last value.
+pub async fn revalue(
+    store: &dyn PortfolioStore,
+    exchange: &dyn ExchangeClient,
+    owner_id: Option<&str>,
+    name: &str,
+) -> Result<(Portfolio, usize)> {
+    let portfolio = store.get(owner_id, name)?.ok_or_else(|| AdvisorError::PortfolioNotFound(name.to_string()))?;
+    
+    let mut prices = HashMap::new();
+    for symbol in portfolio.positions.keys() {
+        if let Ok(asset) = exchange.get_price(symbol).await {
+            prices.insert(symbol.clone(), asset.price_usd);
+        }
+    }
+    
+    // Prices are fetched without holding the store; positions sold meanwhile are skipped
+    let mut updated = 0;
+    let portfolio = store.update(owner_id, name, &mut |portfolio| {
+        updated = prices.keys().filter(|s| portfolio.positions.contains_key(*s)).count();
+        portfolio.update_prices(&prices);
+        Ok(())
+    })?;
+    Ok((portfolio, updated))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::MockExchangeClient;
+    use crate::model::TradeSide;
+    use rust_decimal_macros::dec;
+
+    #[tokio::test]
+    async fn test_memory_store() {
+        let store = MemoryPortfolioStore::new();
+        let mut portfolio = Portfolio::new("long-term");
+        portfolio.owner_id = Some("alice".into());
+        store.create(&portfolio).unwrap();
+        assert!(matches!(store.create(&portfolio), Err(AdvisorError::PortfolioExists(_))));
+        assert!(store.get(Some("bob"), "long-term").unwrap().is_none());
+        
+        let buy = Transaction::new("BTC", TradeSide::Buy, dec!(0.5), dec!(40000));
+        store.record(Some("alice"), "long-term", &buy).unwrap();
+        let oversell = Transaction::new("BTC", TradeSide::Sell, dec!(1), dec!(40000));
+        assert!(store.record(Some("alice"), "long-term", &oversell).is_err());
+        assert_eq!(store.transactions(Some("alice"), "long-term", 10).unwrap().len(), 1);
+        
+        let exchange = MockExchangeClient::new();
+        let (portfolio, updated) = revalue(&store, &exchange, Some("alice"), "long-term").await.unwrap();
+        assert_eq!(updated, 1);
+        assert_eq!(portfolio.positions["BTC"].quantity, dec!(0.5));
+        assert_eq!(store.list(Some("alice")).unwrap().len(), 1);
+        assert!(store.list(None).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,303 @@
+//! SQLite portfolio store
+//!
+//! Each portfolio is one row holding its JSON, keyed by owner and name;
+//! transactions are kept in their own table. Updates run in a SQLite
+//! transaction, so a failed change or a crash leaves the previous state.
+//!
+//! A file database is served by a small pool of connections (WAL mode, so
+//! readers don't wait for a writer). Calls made on a multi-threaded Tokio
+//! runtime run with `block_in_place`, so the worker's other tasks move to
+//! another thread while SQLite does I/O.
+
+use std::path::Path;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use chrono::SecondsFormat;
+use rusqlite::{params, Connection, OptionalExtension};
+
+use super::PortfolioStore;
+use crate::error::{AdvisorError, Result};
+use crate::model::{Portfolio, Transaction};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS portfolios (
+        owner_id   TEXT NOT NULL,
+        name       TEXT NOT NULL,
+        data       TEXT NOT NULL,
+        created_at TEXT NOT NULL,
+        PRIMARY KEY (owner_id, name)
+    );
+    CREATE TABLE IF NOT EXISTS transactions (
+        id          TEXT PRIMARY KEY,
+        owner_id    TEXT NOT NULL,
+        portfolio   TEXT NOT NULL,
+        data        TEXT NOT NULL,
+        executed_at TEXT NOT NULL
+    );
+    CREATE INDEX IF NOT EXISTS transactions_by_portfolio
+        ON transactions (owner_id, portfolio, executed_at);
+";
+
+/// Connections opened to a database file
+const POOL_SIZE: usize = 4;
+
+/// How long a connection waits for another one's write to finish
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Portfolio store backed by a SQLite database file
+pub struct SqlitePortfolioStore {
+    conns: Vec<Mutex<Connection>>,
+    
+    /// Where the next call starts looking for a free connection
+    next: AtomicUsize,
+}
+
+impl SqlitePortfolioStore {
+    /// Open (or create) the database at `path`
+    pub fn open(path: impl AsRef<Path>) -> Result<Self> {
+        let path = path.as_ref();
+        let first = Connection::open(path).map_err(storage)?;
+        first
+            .pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get::<_, String>(0))
+            .map_err(storage)?;
+        first.execute_batch(SCHEMA).map_err(storage)?;
+        
+        let mut conns = vec![first];
+        for _ in 1..POOL_SIZE {
+            conns.push(Connection::open(path).map_err(storage)?);
+        }
+        Self::with_connections(conns)
+    }
+    
+    /// A database that only lives as long as the store (for testing)
+    ///
+    /// One connection: each in-memory connection is a database of its own.
+    pub fn in_memory() -> Result<Self> {
+        let conn = Connection::open_in_memory().map_err(storage)?;
+        conn.execute_batch(SCHEMA).map_err(storage)?;
+        Self::with_connections(vec![conn])
+    }
+    
+    fn with_connections(conns: Vec<Connection>) -> Result<Self> {
+        for conn in &conns {
+            conn.busy_timeout(BUSY_TIMEOUT).map_err(storage)?;
+        }
+        Ok(Self {
+            conns: conns.into_iter().map(Mutex::new).collect(),
+            next: AtomicUsize::new(0),
+        })
+    }
+    
+    /// Run `work` on a free connection (waiting for one if all are busy)
+    fn with_conn<T>(&self, work: impl FnOnce(&mut Connection) -> Result<T>) -> Result<T> {
+        blocking(|| {
+            let start = self.next.fetch_add(1, Ordering::Relaxed);
+            let count = self.conns.len();
+            let mut conn = (0..count)
+                .find_map(|i| self.conns[(start + i) % count].try_lock().ok())
+                .unwrap_or_else(|| self.conns[start % count].lock().unwrap());
+            work(&mut conn)
+        })
+    }
+}
+
+/// Run blocking SQLite work without stalling the other tasks of an async worker
+fn blocking<T>(work: impl FnOnce() -> T) -> T {
+    match tokio::runtime::Handle::try_current() {
+        Ok(handle) if handle.runtime_flavor() == tokio::runtime::RuntimeFlavor::MultiThread => {
+            tokio::task::block_in_place(work)
+        }
+        _ => work(),
+    }
+}
+
+fn storage(e: rusqlite::Error) -> AdvisorError {
+    AdvisorError::Storage(e.to_string())
+}
+
+/// Owner column value (portfolios without an owner use an empty owner)
+fn owner(owner_id: Option<&str>) -> &str {
+    owner_id.unwrap_or_default()
+}
+
+fn timestamp(time: &chrono::DateTime<chrono::Utc>) -> String {
+    time.to_rfc3339_opts(SecondsFormat::Micros, true)
+}
+
+fn load(conn: &Connection, owner_id: Option<&str>, name: &str) -> Result<Option<Portfolio>> {
+    let data: Option<String> = conn
+        .query_row(
+            "SELECT data FROM portfolios WHERE owner_id = ?1 AND name = ?2",
+            params![owner(owner_id), name],
+            |row| row.get(0),
+        )
+        .optional()
+        .map_err(storage)?;
+    Ok(data.map(|data| serde_json::from_str(&data)).transpose()?)
+}
+
+/// Load, change and save a portfolio within one SQLite transaction
+fn change_portfolio(
+    conn: &mut Connection,
+    owner_id: Option<&str>,
+    name: &str,
+    change: &mut dyn FnMut(&mut Portfolio) -> Result<()>,
+    transaction: Option<&Transaction>,
+) -> Result<Portfolio> {
+    let tx = conn.transaction().map_err(storage)?;
+    let mut portfolio = load(&tx, owner_id, name)?.ok_or_else(|| AdvisorError::PortfolioNotFound(name.to_string()))?;
+    change(&mut portfolio)?;
+    
+    tx.execute(
+        "UPDATE portfolios SET data = ?3 WHERE owner_id = ?1 AND name = ?2",
+        params![owner(owner_id), name, serde_json::to_string(&portfolio)?],
+    )
+    .map_err(storage)?;
+    if let Some(transaction) = transaction {
+        tx.execute(
+            "INSERT INTO transactions (id, owner_id, portfolio, data, executed_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                transaction.id.to_string(),
+                owner(owner_id),
+                name,
+                serde_json::to_string(transaction)?,
+                timestamp(&transaction.executed_at),
+            ],
+        )
+        .map_err(storage)?;
+    }
+    tx.commit().map_err(storage)?;
+    Ok(portfolio)
+}
+
+impl PortfolioStore for SqlitePortfolioStore {
+    fn list(&self, owner_id: Option<&str>) -> Result<Vec<Portfolio>> {
+        let rows = self.with_conn(|conn| {
+            let mut stmt = conn
+                .prepare("SELECT data FROM portfolios WHERE owner_id = ?1 ORDER BY created_at, name")
+                .map_err(storage)?;
+            let rows = stmt
+                .query_map(params![owner(owner_id)], |row| row.get::<_, String>(0))
+                .map_err(storage)?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .map_err(storage)?;
+            Ok(rows)
+        })?;
+        rows.iter().map(|data| Ok(serde_json::from_str(data)?)).collect()
+    }
+    
+    fn get(&self, owner_id: Option<&str>, name: &str) -> Result<Option<Portfolio>> {
+        self.with_conn(|conn| load(conn, owner_id, name))
+    }
+    
+    fn create(&self, portfolio: &Portfolio) -> Result<()> {
+        let data = serde_json::to_string(portfolio)?;
+        let inserted = self.with_conn(|conn| {
+            conn.execute(
+                "INSERT OR IGNORE INTO portfolios (owner_id, name, data, created_at) VALUES (?1, ?2, ?3, ?4)",
+                params![owner(portfolio.owner_id.as_deref()), portfolio.name, data, timestamp(&portfolio.created_at)],
+            )
+            .map_err(storage)
+        })?;
+        if inserted == 0 {
+            return Err(AdvisorError::PortfolioExists(portfolio.name.clone()));
+        }
+        Ok(())
+    }
+    
+    fn update(
+        &self,
+        owner_id: Option<&str>,
+        name: &str,
+        change: &mut dyn FnMut(&mut Portfolio) -> Result<()>,
+    ) -> Result<Portfolio> {
+        self.with_conn(|conn| change_portfolio(conn, owner_id, name, change, None))
+    }
+    
+    fn record(&self, owner_id: Option<&str>, name: &str, transaction: &Transaction) -> Result<Portfolio> {
+        let mut apply = |portfolio: &mut Portfolio| portfolio.apply(transaction);
+        self.with_conn(|conn| change_portfolio(conn, owner_id, name, &mut apply, Some(transaction)))
+    }
+    
+    fn transactions(&self, owner_id: Option<&str>, name: &str, limit: usize) -> Result<Vec<Transaction>> {
+        let limit = i64::try_from(limit).unwrap_or(i64::MAX);
+        let rows = self.with_conn(|conn| {
+            if load(conn, owner_id, name)?.is_none() {
+                return Err(AdvisorError::PortfolioNotFound(name.to_string()));
+            }
+            let mut stmt = conn
+                .prepare(
+                    "SELECT data FROM transactions WHERE owner_id = ?1 AND portfolio = ?2 \
+                     ORDER BY executed_at DESC, rowid DESC LIMIT ?3",
+                )
+                .map_err(storage)?;
+            let rows = stmt
+                .query_map(params![owner(owner_id), name, limit], |row| row.get::<_, String>(0))
+                .map_err(storage)?
+                .collect::<rusqlite::Result<Vec<_>>>()
+                .map_err(storage)?;
+            Ok(rows)
+        })?;
+        rows.iter().map(|data| Ok(serde_json::from_str(data)?)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::model::TradeSide;
+    use rust_decimal_macros::dec;
+
+    #[test]
+    fn test_sqlite_store() {
+        let store = SqlitePortfolioStore::in_memory().unwrap();
+        let mut portfolio = Portfolio::new("long-term");
+        portfolio.owner_id = Some("alice".into());
+        store.create(&portfolio).unwrap();
+        assert!(matches!(store.create(&portfolio), Err(AdvisorError::PortfolioExists(_))));
+        
+        let buy = Transaction::new("ETH", TradeSide::Buy, dec!(2), dec!(3000));
+        let saved = store.record(Some("alice"), "long-term", &buy).unwrap();
+        assert_eq!(saved.positions["ETH"].quantity, dec!(2));
+        
+        let oversell = Transaction::new("ETH", TradeSide::Sell, dec!(3), dec!(3000));
+        assert!(store.record(Some("alice"), "long-term", &oversell).is_err());
+        assert_eq!(store.transactions(Some("alice"), "long-term", 10).unwrap().len(), 1);
+        assert_eq!(store.get(Some("alice"), "long-term").unwrap().unwrap().positions["ETH"].quantity, dec!(2));
+        assert!(store.list(Some("bob")).unwrap().is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+    async fn test_sqlite_store_file_pool() {
+        let path = std::env::temp_dir().join(format!("portfolios-{}.db", uuid::Uuid::new_v4()));
+        let store = std::sync::Arc::new(SqlitePortfolioStore::open(&path).unwrap());
+        let mut portfolio = Portfolio::new("main");
+        portfolio.owner_id = Some("alice".into());
+        store.create(&portfolio).unwrap();
+        
+        // Concurrent buys from several tasks all land
+        let buys: Vec<_> = (0..8)
+            .map(|_| {
+                let store = store.clone();
+                tokio::spawn(async move {
+                    let buy = Transaction::new("BTC", TradeSide::Buy, dec!(1), dec!(50000));
+                    store.record(Some("alice"), "main", &buy).unwrap();
+                })
+            })
+            .collect();
+        for buy in buys {
+            buy.await.unwrap();
+        }
+        assert_eq!(store.get(Some("alice"), "main").unwrap().unwrap().positions["BTC"].quantity, dec!(8));
+        assert_eq!(store.transactions(Some("alice"), "main", 100).unwrap().len(), 8);
+        
+        drop(store);
+        let reopened = SqlitePortfolioStore::open(&path).unwrap();
+        assert_eq!(reopened.list(Some("alice")).unwrap().len(), 1);
+        for suffix in ["", "-wal", "-shm"] {
+            std::fs::remove_file(format!("{}{}", path.display(), suffix)).ok();
+        }
+    }
+}
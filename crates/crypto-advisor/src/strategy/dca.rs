@@ -48,10 +48,14 @@ pub struct DCAScheduleEntry {
 }
 
 impl DCAStrategy {
-    /// Create a new DCA strategy
+    /// Create a new DCA strategy, starting now
     pub fn new(total_amount: Decimal, periods: u32, interval_days: u32) -> Self {
+        Self::starting(total_amount, periods, interval_days, Utc::now())
+    }
+    
+    /// Create a DCA strategy whose first purchase is at `start_date`
+    pub fn starting(total_amount: Decimal, periods: u32, interval_days: u32, start_date: DateTime<Utc>) -> Self {
         let amount_per_period = total_amount / Decimal::from(periods);
-        let start_date = Utc::now();
         
         let schedule = (0..periods)
             .map(|i| {
@@ -164,6 +168,15 @@ mod tests {
         assert_eq!(dca.schedule.len(), 10);
     }
 
+    #[test]
+    fn test_dca_starting() {
+        let start = Utc::now() - Duration::days(10);
+        let dca = DCAStrategy::starting(dec!(300), 3, 7, start);
+        let dates: Vec<_> = dca.schedule.iter().map(|e| e.date).collect();
+        assert_eq!(dates, vec![start, start + Duration::days(7), start + Duration::days(14)]);
+        assert!(dca.schedule.iter().all(|e| e.amount == dec!(100)));
+    }
+
     #[test]
     fn test_dca_from_profile() {
         let conservative = RiskProfile::conservative();
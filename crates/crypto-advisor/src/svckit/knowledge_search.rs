@@ -0,0 +1,144 @@
+//! Knowledge Search Tool
+//!
+//! Searches the firm's educational material and returns ranked passages with
+//! source references, so answers can cite them.
+
+use std::sync::Arc;
+use async_trait::async_trait;
+
+use agent_core::{
+    Tool, ToolSchema, ToolCall, ToolResult,
+    embedding::EmbeddingProvider,
+    tool::ParameterSchema,
+    Result as CoreResult,
+};
+
+use crate::knowledge::KnowledgeBase;
+
+/// Tool for retrieving passages from the knowledge base
+pub struct KnowledgeSearchTool {
+    knowledge: Arc<KnowledgeBase>,
+    embedder: Option<Arc<dyn EmbeddingProvider>>,
+}
+
+impl KnowledgeSearchTool {
+    pub fn new(knowledge: Arc<KnowledgeBase>) -> Self {
+        Self { knowledge, embedder: None }
+    }
+    
+    /// Enable hybrid ranking (only effective if the index has embeddings)
+    pub fn with_embedder(mut self, embedder: Arc<dyn EmbeddingProvider>) -> Self {
+        self.embedder = Some(embedder);
+        self
+    }
+}
+
+#[async_trait]
+impl Tool for KnowledgeSearchTool {
+    fn schema(&self) -> ToolSchema {
+        ToolSchema {
+            name: "knowledge_search".into(),
+            description: "Search our educational material (DCA explainers, risk disclosures, asset fact sheets). Returns passages with sources to cite.".into(),
+            parameters: vec![
+                ParameterSchema {
+                    name: "query".into(),
+                    param_type: "string".into(),
+                    description: "What to look up (e.g., 'risks of dollar-cost averaging')".into(),
+                    required: true,
+                    default: None,
+                    enum_values: None,
+                },
+                ParameterSchema {
+                    name: "limit".into(),
+                    param_type: "number".into(),
+                    description: "Maximum passages to return".into(),
+                    required: false,
+                    default: Some(serde_json::json!(3)),
+                    enum_values: None,
+                },
+            ],
+            category: Some("knowledge".into()),
+            has_side_effects: false,
+        }
+    }
+    
+    async fn execute(&self, call: &ToolCall) -> CoreResult<ToolResult> {
+        let query = call.arguments
+            .get("query")
+            .and_then(|v| v.as_str())
+            .unwrap_or("")
+            .trim();
+        
+        if query.is_empty() {
+            return Ok(ToolResult::failure("knowledge_search", "A non-empty 'query' is required"));
+        }
+        
+        let limit = call.arguments
+            .get("limit")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(3)
+            .clamp(1, 10) as usize;
+        
+        // Semantic scoring is best-effort; BM25 alone still gives useful results
+        let query_embedding = match (&self.embedder, self.knowledge.embedding_model()) {
+            (Some(embedder), Some(_)) => match embedder.embed_one(query).await {
+                Ok(vector) => Some(vector),
+                Err(e) => {
+                    tracing::warn!("Knowledge query embedding failed, using BM25 only: {}", e);
+                    None
+                }
+            },
+            _ => None,
+        };
+        
+        let passages = self.knowledge.search(query, query_embedding.as_deref(), limit);
+        
+        if passages.is_empty() {
+            return Ok(ToolResult::success(
+                "knowledge_search",
+                format!("No passages found for '{}'. Do not cite sources for this topic.", query),
+            ).with_data(serde_json::json!({ "passages": [] })));
+        }
+        
+        let mut output = format!("Passages for '{}' (cite as [n]):\n", query);
+        for (i, passage) in passages.iter().enumerate() {
+            let location = match &passage.heading {
+                Some(heading) => format!("{} — {}", passage.source, heading),
+                None => passage.source.clone(),
+            };
+            output.push_str(&format!("\n[{}] {}\n{}\n", i + 1, location, passage.text));
+        }
+        
+        Ok(ToolResult::success("knowledge_search", output.trim_end())
+            .with_data(serde_json::json!({ "passages": passages })))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::knowledge::ingest::{chunk_document, DocumentFormat};
+    use std::collections::HashMap;
+
+    #[tokio::test]
+    async fn test_returns_passages_with_sources() {
+        let chunks = chunk_document(
+            "disclosures/risk.md",
+            "# Risk Disclosure\n\nPast performance does not guarantee future results.\n",
+            DocumentFormat::Markdown,
+        );
+        let tool = KnowledgeSearchTool::new(Arc::new(KnowledgeBase::from_chunks(chunks)));
+        
+        let call = ToolCall {
+            name: "knowledge_search".into(),
+            arguments: HashMap::from([("query".to_string(), serde_json::json!("past performance"))]),
+            id: None,
+        };
+        let result = tool.execute(&call).await.unwrap();
+        
+        assert!(result.success);
+        assert!(result.output.contains("[1] disclosures/risk.md — Risk Disclosure"));
+        let data = result.data.unwrap();
+        assert_eq!(data["passages"][0]["source"], "disclosures/risk.md");
+    }
+}
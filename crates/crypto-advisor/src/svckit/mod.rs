@@ -6,8 +6,12 @@ mod price_lookup;
 mod dca_calculator;
 mod risk_analyzer;
 mod portfolio_tracker;
+mod knowledge_search;
+mod price_alert;
 
 pub use price_lookup::PriceLookupTool;
 pub use dca_calculator::DCACalculatorTool;
 pub use risk_analyzer::RiskAnalyzerTool;
 pub use portfolio_tracker::PortfolioTrackerTool;
+pub use knowledge_search::KnowledgeSearchTool;
+pub use price_alert::PriceAlertTool;
@@ -6,7 +6,6 @@ use std::sync::Arc;
 use async_trait::async_trait;
 use rust_decimal::Decimal;
 use rust_decimal_macros::dec;
-use tokio::sync::RwLock;
 
 use agent_core::{
     Tool, ToolSchema, ToolCall, ToolResult,
@@ -14,32 +13,47 @@ use agent_core::{
     Result as CoreResult,
 };
 
+use crate::error::AdvisorError;
 use crate::exchange::ExchangeClient;
 use crate::model::{Portfolio, Position};
+use crate::store::{self, MemoryPortfolioStore, PortfolioStore};
 
 /// Tool for tracking portfolio positions
 pub struct PortfolioTrackerTool {
     exchange: Arc<dyn ExchangeClient>,
-    portfolios: Arc<RwLock<std::collections::HashMap<String, Portfolio>>>,
+    store: Arc<dyn PortfolioStore>,
+    owner_id: Option<String>,
 }
 
 impl PortfolioTrackerTool {
     pub fn new(exchange: Arc<dyn ExchangeClient>) -> Self {
-        Self {
-            exchange,
-            portfolios: Arc::new(RwLock::new(std::collections::HashMap::new())),
-        }
+        Self::with_store(exchange, Arc::new(MemoryPortfolioStore::new()))
+    }
+    
+    /// Create with a shared portfolio store
+    pub fn with_store(exchange: Arc<dyn ExchangeClient>, store: Arc<dyn PortfolioStore>) -> Self {
+        Self { exchange, store, owner_id: None }
+    }
+    
+    /// Only see and create portfolios of one owner
+    ///
+    /// Tools of different owners can share one store; each sees the
+    /// portfolios whose `owner_id` is its own.
+    pub fn owned_by(mut self, owner_id: impl Into<String>) -> Self {
+        self.owner_id = Some(owner_id.into());
+        self
     }
     
-    /// Create with existing portfolios
-    pub fn with_portfolios(
-        exchange: Arc<dyn ExchangeClient>,
-        portfolios: Arc<RwLock<std::collections::HashMap<String, Portfolio>>>,
-    ) -> Self {
-        Self { exchange, portfolios }
+    fn owner(&self) -> Option<&str> {
+        self.owner_id.as_deref()
     }
 }
 
+/// A store failure as a tool error
+fn store_error(e: AdvisorError) -> agent_core::AgentError {
+    agent_core::AgentError::ToolExecution(e.to_string())
+}
+
 #[async_trait]
 impl Tool for PortfolioTrackerTool {
     fn schema(&self) -> ToolSchema {
@@ -152,9 +166,7 @@ impl Tool for PortfolioTrackerTool {
 
 impl PortfolioTrackerTool {
     async fn view_portfolio(&self, portfolio_id: &str) -> CoreResult<ToolResult> {
-        let portfolios = self.portfolios.read().await;
-        
-        let portfolio = match portfolios.get(portfolio_id) {
+        let portfolio = match self.store.get(self.owner(), portfolio_id).map_err(store_error)? {
             Some(p) => p,
             None => return Ok(ToolResult::success(
                 "portfolio_tracker",
@@ -236,12 +248,6 @@ impl PortfolioTrackerTool {
             ));
         }
         
-        let mut portfolios = self.portfolios.write().await;
-        
-        let portfolio = portfolios
-            .entry(portfolio_id.to_string())
-            .or_insert_with(|| Portfolio::new(portfolio_id));
-        
         // Get current price
         let current_price = match self.exchange.get_price(symbol).await {
             Ok(asset) => asset.price_usd,
@@ -251,7 +257,18 @@ impl PortfolioTrackerTool {
         let mut position = Position::new(symbol, quantity, cost_basis);
         position.update_price(current_price);
         
-        portfolio.add_position(position);
+        let mut portfolio = Portfolio::new(portfolio_id);
+        portfolio.owner_id = self.owner_id.clone();
+        match self.store.create(&portfolio) {
+            Ok(()) | Err(AdvisorError::PortfolioExists(_)) => {}
+            Err(e) => return Err(store_error(e)),
+        }
+        self.store
+            .update(self.owner(), portfolio_id, &mut |portfolio| {
+                portfolio.add_position(position.clone());
+                Ok(())
+            })
+            .map_err(store_error)?;
         
         let total_cost = quantity * cost_basis;
         Ok(ToolResult::success(
@@ -264,49 +281,70 @@ impl PortfolioTrackerTool {
     }
     
     async fn remove_position(&self, portfolio_id: &str, symbol: &str) -> CoreResult<ToolResult> {
-        let mut portfolios = self.portfolios.write().await;
+        let removed = self.store.update(self.owner(), portfolio_id, &mut |portfolio| {
+            portfolio.positions
+                .remove(&symbol.to_uppercase())
+                .map(|_| ())
+                .ok_or_else(|| AdvisorError::PositionNotFound(symbol.to_uppercase()))
+        });
         
-        if let Some(portfolio) = portfolios.get_mut(portfolio_id) {
-            if portfolio.positions.remove(&symbol.to_uppercase()).is_some() {
-                return Ok(ToolResult::success(
-                    "portfolio_tracker",
-                    format!("Removed {} from portfolio '{}'", symbol.to_uppercase(), portfolio_id)
-                ));
-            }
+        match removed {
+            Ok(_) => Ok(ToolResult::success(
+                "portfolio_tracker",
+                format!("Removed {} from portfolio '{}'", symbol.to_uppercase(), portfolio_id)
+            )),
+            Err(AdvisorError::PortfolioNotFound(_) | AdvisorError::PositionNotFound(_)) => Ok(ToolResult::failure(
+                "portfolio_tracker",
+                format!("Position {} not found in portfolio '{}'", symbol, portfolio_id)
+            )),
+            Err(e) => Err(store_error(e)),
         }
-        
-        Ok(ToolResult::failure(
-            "portfolio_tracker",
-            format!("Position {} not found in portfolio '{}'", symbol, portfolio_id)
-        ))
     }
     
     async fn update_prices(&self, portfolio_id: &str) -> CoreResult<ToolResult> {
-        let mut portfolios = self.portfolios.write().await;
-        
-        let portfolio = match portfolios.get_mut(portfolio_id) {
-            Some(p) => p,
-            None => return Ok(ToolResult::failure(
+        let updated = match store::revalue(self.store.as_ref(), self.exchange.as_ref(), self.owner(), portfolio_id).await {
+            Ok((_, updated)) => updated,
+            Err(AdvisorError::PortfolioNotFound(_)) => return Ok(ToolResult::failure(
                 "portfolio_tracker",
                 format!("Portfolio '{}' not found", portfolio_id)
             )),
+            Err(e) => return Err(store_error(e)),
         };
         
-        let mut updated = 0;
-        let symbols: Vec<String> = portfolio.positions.keys().cloned().collect();
-        
-        for symbol in symbols {
-            if let Ok(asset) = self.exchange.get_price(&symbol).await {
-                if let Some(pos) = portfolio.positions.get_mut(&symbol) {
-                    pos.update_price(asset.price_usd);
-                    updated += 1;
-                }
-            }
-        }
-        
         Ok(ToolResult::success(
             "portfolio_tracker",
             format!("Updated prices for {} positions in '{}'", updated, portfolio_id)
         ))
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::exchange::MockExchangeClient;
+    use std::collections::HashMap;
+
+    fn call(args: serde_json::Value) -> ToolCall {
+        let arguments: HashMap<String, serde_json::Value> = serde_json::from_value(args).unwrap();
+        ToolCall { name: "portfolio_tracker".into(), arguments, id: None }
+    }
+
+    #[tokio::test]
+    async fn test_portfolios_are_scoped_by_owner() {
+        let exchange: Arc<dyn ExchangeClient> = Arc::new(MockExchangeClient::new());
+        let store: Arc<dyn PortfolioStore> = Arc::new(MemoryPortfolioStore::new());
+        let alice = PortfolioTrackerTool::with_store(exchange.clone(), store.clone()).owned_by("alice");
+        let bob = PortfolioTrackerTool::with_store(exchange, store.clone()).owned_by("bob");
+        
+        let add = call(serde_json::json!({"action": "add", "symbol": "BTC", "quantity": 0.5, "cost_basis": 40000.0}));
+        assert!(alice.execute(&add).await.unwrap().success);
+        
+        let view = call(serde_json::json!({"action": "view"}));
+        assert!(alice.execute(&view).await.unwrap().output.contains("BTC"));
+        assert!(bob.execute(&view).await.unwrap().output.contains("not found"));
+        
+        let portfolio = store.get(Some("alice"), "default").unwrap().unwrap();
+        assert_eq!(portfolio.owner_id.as_deref(), Some("alice"));
+        assert!(store.get(Some("bob"), "default").unwrap().is_none());
+    }
+}
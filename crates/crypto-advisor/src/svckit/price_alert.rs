@@ -0,0 +1,266 @@
+//! Price Alert Tool
+//!
+//! Creates, lists and deletes alerts ("tell me when ETH drops below 3000").
+//! The server's alert engine evaluates them and notifies the owner.
+
+use std::sync::Arc;
+use async_trait::async_trait;
+use rust_decimal::Decimal;
+use uuid::Uuid;
+
+use agent_core::{
+    Tool, ToolSchema, ToolCall, ToolResult,
+    tool::ParameterSchema,
+    Result as CoreResult,
+};
+
+use crate::alerts::{Alert, AlertCondition, AlertStatus, AlertStore};
+use crate::error::AdvisorError;
+
+/// Tool for managing price and portfolio alerts
+pub struct PriceAlertTool {
+    store: Arc<dyn AlertStore>,
+    owner_id: Option<String>,
+}
+
+impl PriceAlertTool {
+    pub fn new(store: Arc<dyn AlertStore>) -> Self {
+        Self { store, owner_id: None }
+    }
+    
+    /// Only see and create alerts of one owner
+    pub fn owned_by(mut self, owner_id: impl Into<String>) -> Self {
+        self.owner_id = Some(owner_id.into());
+        self
+    }
+    
+    fn owner(&self) -> Option<&str> {
+        self.owner_id.as_deref()
+    }
+}
+
+fn parameter(name: &str, param_type: &str, description: &str) -> ParameterSchema {
+    ParameterSchema {
+        name: name.into(),
+        param_type: param_type.into(),
+        description: description.into(),
+        required: false,
+        default: None,
+        enum_values: None,
+    }
+}
+
+fn string<'a>(call: &'a ToolCall, name: &str) -> Option<&'a str> {
+    call.arguments.get(name).and_then(|v| v.as_str())
+}
+
+fn decimal(call: &ToolCall, name: &str) -> Option<Decimal> {
+    call.arguments
+        .get(name)
+        .and_then(|v| v.as_f64())
+        .and_then(|f| Decimal::try_from(f).ok())
+        .map(|d| d.normalize())
+}
+
+fn missing(name: &str, kind: &str) -> agent_core::AgentError {
+    agent_core::AgentError::ToolValidation(format!("{} required for {} alerts", name, kind))
+}
+
+#[async_trait]
+impl Tool for PriceAlertTool {
+    fn schema(&self) -> ToolSchema {
+        ToolSchema {
+            name: "price_alert".into(),
+            description: "Set, list or delete alerts that notify the user when a price crosses a level, moves by a percentage, \
+                a portfolio draws down, or an allocation drifts from its target.".into(),
+            parameters: vec![
+                ParameterSchema {
+                    name: "action".into(),
+                    param_type: "string".into(),
+                    description: "Action: 'create', 'list', or 'delete'".into(),
+                    required: true,
+                    default: None,
+                    enum_values: Some(vec![
+                        serde_json::json!("create"),
+                        serde_json::json!("list"),
+                        serde_json::json!("delete"),
+                    ]),
+                },
+                ParameterSchema {
+                    name: "type".into(),
+                    param_type: "string".into(),
+                    description: "Alert type (for create)".into(),
+                    required: false,
+                    default: None,
+                    enum_values: Some(vec![
+                        serde_json::json!("price_above"),
+                        serde_json::json!("price_below"),
+                        serde_json::json!("percent_move"),
+                        serde_json::json!("drawdown"),
+                        serde_json::json!("allocation_drift"),
+                    ]),
+                },
+                parameter("symbol", "string", "Asset symbol (price_above, price_below, percent_move, allocation_drift)"),
+                parameter("price", "number", "Price threshold in USD (price_above, price_below)"),
+                parameter("percent", "number", "Move or drawdown in percent (percent_move, drawdown)"),
+                ParameterSchema {
+                    default: Some(serde_json::json!(1440)),
+                    ..parameter("window_minutes", "integer", "Window of the move in minutes, at most 10080 (percent_move)")
+                },
+                ParameterSchema {
+                    default: Some(serde_json::json!("default")),
+                    ..parameter("portfolio_id", "string", "Portfolio watched (drawdown, allocation_drift)")
+                },
+                parameter("target_percent", "number", "Target share of the asset in percent (allocation_drift)"),
+                parameter("band_percent", "number", "Allowed distance from the target in percentage points (allocation_drift)"),
+                ParameterSchema {
+                    default: Some(serde_json::json!(false)),
+                    ..parameter("repeat", "boolean", "Fire again each time the condition recurs, instead of once")
+                },
+                parameter("alert_id", "string", "Alert to delete (for delete)"),
+            ],
+            category: Some("tracking".into()),
+            has_side_effects: true,
+        }
+    }
+    
+    async fn execute(&self, call: &ToolCall) -> CoreResult<ToolResult> {
+        match string(call, "action").unwrap_or("list") {
+            "create" => self.create(call).await,
+            "list" => self.list(),
+            "delete" => {
+                let id = string(call, "alert_id").ok_or_else(|| agent_core::AgentError::ToolValidation(
+                    "alert_id required for delete".into()
+                ))?;
+                self.delete(id).await
+            }
+            _ => Ok(ToolResult::failure("price_alert", "Invalid action")),
+        }
+    }
+}
+
+impl PriceAlertTool {
+    async fn create(&self, call: &ToolCall) -> CoreResult<ToolResult> {
+        let kind = string(call, "type").ok_or_else(|| agent_core::AgentError::ToolValidation(
+            "type required for create".into()
+        ))?;
+        let symbol = || string(call, "symbol").map(String::from).ok_or_else(|| missing("symbol", kind));
+        let number = |name: &str| decimal(call, name).ok_or_else(|| missing(name, kind));
+        let portfolio = string(call, "portfolio_id").unwrap_or("default").to_string();
+        
+        let condition = match kind {
+            "price_above" => AlertCondition::PriceAbove { symbol: symbol()?, price: number("price")? },
+            "price_below" => AlertCondition::PriceBelow { symbol: symbol()?, price: number("price")? },
+            "percent_move" => AlertCondition::PercentMove {
+                symbol: symbol()?,
+                percent: number("percent")?,
+                window_minutes: call.arguments
+                    .get("window_minutes")
+                    .and_then(|v| v.as_u64())
+                    .map_or(1440, |minutes| u32::try_from(minutes).unwrap_or(u32::MAX)),
+            },
+            "drawdown" => AlertCondition::Drawdown { portfolio, percent: number("percent")? },
+            "allocation_drift" => AlertCondition::AllocationDrift {
+                portfolio,
+                symbol: symbol()?,
+                target_percent: number("target_percent")?,
+                band_percent: number("band_percent")?,
+            },
+            _ => return Ok(ToolResult::failure("price_alert", format!("Unknown alert type '{}'", kind))),
+        };
+        
+        let mut alert = Alert::new(self.owner(), condition);
+        alert.repeat = call.arguments.get("repeat").and_then(|v| v.as_bool()).unwrap_or(false);
+        let alert = match alert.validated() {
+            Ok(alert) => alert,
+            Err(AdvisorError::InvalidAlert(message)) => return Ok(ToolResult::failure("price_alert", message)),
+            Err(e) => return Err(store_error(e)),
+        };
+        
+        match self.store.create(&alert).await {
+            Ok(()) => Ok(ToolResult::success(
+                "price_alert",
+                format!(
+                    "Alert set: {} ({}). ID: {}",
+                    alert.condition.describe(),
+                    if alert.repeat { "every time" } else { "once" },
+                    alert.id
+                )
+            )),
+            Err(AdvisorError::InvalidAlert(message)) => Ok(ToolResult::failure("price_alert", message)),
+            Err(e) => Err(store_error(e)),
+        }
+    }
+    
+    fn list(&self) -> CoreResult<ToolResult> {
+        let alerts = self.store.list(self.owner()).map_err(store_error)?;
+        if alerts.is_empty() {
+            return Ok(ToolResult::success("price_alert", "No alerts set."));
+        }
+        
+        let mut output = String::from("Alerts:\n");
+        for alert in alerts {
+            let status = match alert.status {
+                AlertStatus::Armed => "armed",
+                AlertStatus::Triggered => "triggered, waiting to re-arm",
+                AlertStatus::Done => "fired",
+            };
+            output.push_str(&format!("  {} - {} [{}]\n", alert.id, alert.condition.describe(), status));
+        }
+        Ok(ToolResult::success("price_alert", output))
+    }
+    
+    async fn delete(&self, id: &str) -> CoreResult<ToolResult> {
+        let deleted = match Uuid::parse_str(id) {
+            Ok(uuid) => self.store.delete(self.owner(), uuid).await.map_err(store_error)?,
+            Err(_) => false,
+        };
+        if deleted {
+            Ok(ToolResult::success("price_alert", format!("Deleted alert {}", id)))
+        } else {
+            Ok(ToolResult::failure("price_alert", format!("Alert {} not found", id)))
+        }
+    }
+}
+
+/// A store failure as a tool error
+fn store_error(e: AdvisorError) -> agent_core::AgentError {
+    agent_core::AgentError::ToolExecution(e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::alerts::MemoryAlertStore;
+    use std::collections::HashMap;
+
+    fn call(args: serde_json::Value) -> ToolCall {
+        let arguments: HashMap<String, serde_json::Value> = serde_json::from_value(args).unwrap();
+        ToolCall { name: "price_alert".into(), arguments, id: None }
+    }
+
+    #[tokio::test]
+    async fn test_create_list_delete() {
+        let store: Arc<dyn AlertStore> = Arc::new(MemoryAlertStore::new());
+        let alice = PriceAlertTool::new(store.clone()).owned_by("alice");
+        let bob = PriceAlertTool::new(store.clone()).owned_by("bob");
+        
+        let create = call(serde_json::json!({"action": "create", "type": "price_below", "symbol": "eth", "price": 3000}));
+        let result = alice.execute(&create).await.unwrap();
+        assert!(result.success);
+        assert!(result.output.contains("ETH at or below $3000 (once)"));
+        
+        let invalid = call(serde_json::json!({"action": "create", "type": "drawdown", "percent": 150}));
+        assert!(!alice.execute(&invalid).await.unwrap().success);
+        
+        let list = call(serde_json::json!({"action": "list"}));
+        assert!(alice.execute(&list).await.unwrap().output.contains("[armed]"));
+        assert!(bob.execute(&list).await.unwrap().output.contains("No alerts"));
+        
+        let id = store.list(Some("alice")).unwrap()[0].id.to_string();
+        let delete = call(serde_json::json!({"action": "delete", "alert_id": id}));
+        assert!(!bob.execute(&delete).await.unwrap().success);
+        assert!(alice.execute(&delete).await.unwrap().success);
+        assert!(store.list(Some("alice")).unwrap().is_empty());
+    }
+}
@@ -0,0 +1,106 @@
+//! Advisor Team
+//!
+//! Multi-agent variant of the advisor: a planner delegates to market data,
+//! risk and portfolio specialists, each limited to the tools it needs, and
+//! combines their answers.
+
+use std::sync::Arc;
+
+use agent_core::{
+    orchestration::{AgentSpec, Orchestrator},
+    prompt::{PromptRegistry, PromptTemplate, PromptVars, BUILTIN_VERSION},
+    LlmProvider, RenderedPrompt, ToolRegistry, Result as CoreResult,
+};
+
+/// Planner agent (the team's entry point)
+pub const PLANNER: &str = "planner";
+
+/// Market data specialist
+pub const MARKET_DATA: &str = "market_data";
+
+/// Risk specialist
+pub const RISK: &str = "risk";
+
+/// Portfolio specialist
+pub const PORTFOLIO: &str = "portfolio";
+
+const PLANNER_TEMPLATE: &str = r#"You are the lead of a conservative cryptocurrency advisory team focused on risk management and capital preservation.
+
+{{> user_context}}
+
+You do not look up data yourself. Delegate to your specialists with self-contained tasks:
+- `market_data` for current prices and market conditions
+- `risk` for volatility, correlation and worst-case analysis
+- `portfolio` for allocations, DCA schedules and existing positions
+
+Combine their answers into one recommendation. Keep the team's rules: diversify, spread purchases over time, and never put more than 20% in a single asset without the user's explicit override. Always explain the risks."#;
+
+const MARKET_DATA_TEMPLATE: &str = r#"You are the market data specialist of a crypto advisory team. Today's date is {{date}}.
+
+Answer only with facts from your tools: current prices, recent moves and market conditions. Do not give investment advice."#;
+
+const RISK_TEMPLATE: &str = r#"You are the risk specialist of a crypto advisory team. Today's date is {{date}}.{{#risk_profile}} The client's stated risk profile is {{risk_profile}}.{{/risk_profile}}
+
+Use your tools to assess volatility, correlation, worst-case losses and recovery times. Cite risk disclosures from the knowledge base when relevant. Be explicit about what could go wrong."#;
+
+const PORTFOLIO_TEMPLATE: &str = r#"You are the portfolio specialist of a crypto advisory team. Today's date is {{date}}.{{#risk_profile}} The client's stated risk profile is {{risk_profile}}.{{/risk_profile}}
+
+Use your tools to compute diversified allocations and DCA schedules, and to review existing positions. Report exact amounts and percentages."#;
+
+/// Prompt template name of a team member
+fn template_name(agent: &str) -> String {
+    format!("team_{}", agent)
+}
+
+/// Register the team's built-in prompt templates
+pub fn register_prompts(registry: &PromptRegistry) {
+    for (agent, body) in [
+        (PLANNER, PLANNER_TEMPLATE),
+        (MARKET_DATA, MARKET_DATA_TEMPLATE),
+        (RISK, RISK_TEMPLATE),
+        (PORTFOLIO, PORTFOLIO_TEMPLATE),
+    ] {
+        registry.register(PromptTemplate::new(template_name(agent), BUILTIN_VERSION, body));
+    }
+}
+
+/// Build the advisor team over a shared tool pool
+///
+/// Prompts are rendered from `prompts` with `bucket` selecting A/B variants.
+/// Returns the orchestrator and the planner's rendered prompt (for recording
+/// its version); set budgets on the orchestrator before wrapping it in `Arc`.
+pub fn advisor_team(
+    provider: Arc<dyn LlmProvider>,
+    tools: Arc<ToolRegistry>,
+    prompts: &PromptRegistry,
+    bucket: Option<&str>,
+    vars: &PromptVars,
+) -> CoreResult<(Orchestrator, RenderedPrompt)> {
+    let render = |agent: &str| prompts.render(&template_name(agent), None, bucket, vars);
+    let planner = render(PLANNER)?;
+    
+    let orchestrator = Orchestrator::new(provider, tools)
+        .agent(
+            AgentSpec::new(PLANNER, "Plans investments with the specialists", planner.text.clone())
+                .tools(["datetime", "calculate"])
+                .delegates([MARKET_DATA, RISK, PORTFOLIO]),
+        )
+        .agent(
+            AgentSpec::new(MARKET_DATA, "Current prices and market conditions", render(MARKET_DATA)?.text)
+                .tools(["price_lookup"])
+                .max_iterations(4),
+        )
+        .agent(
+            AgentSpec::new(RISK, "Volatility, correlation and worst-case analysis", render(RISK)?.text)
+                .tools(["risk_analyzer", "price_lookup", "knowledge_search"])
+                .max_iterations(5),
+        )
+        .agent(
+            AgentSpec::new(PORTFOLIO, "Allocations, DCA schedules and existing positions", render(PORTFOLIO)?.text)
+                .tools(["dca_calculator", "portfolio_tracker"])
+                .max_iterations(5),
+        )
+        .max_depth(1);
+    
+    Ok((orchestrator, planner))
+}